@@ -0,0 +1,409 @@
+-- Baseline: the schema previously created at startup by ensure_critical_schema.
+-- Every statement is idempotent so it also applies cleanly to existing installs.
+
+-- Extensions
+CREATE EXTENSION IF NOT EXISTS "pgcrypto";
+
+-- Required by db/media.rs similarity() search
+CREATE EXTENSION IF NOT EXISTS "pg_trgm";
+
+-- users
+CREATE TABLE IF NOT EXISTS users (
+    id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    username      TEXT        NOT NULL UNIQUE,
+    email         TEXT        NOT NULL UNIQUE,
+    password_hash TEXT        NOT NULL,
+    role          TEXT        NOT NULL DEFAULT 'user',
+    avatar_url    TEXT,
+    is_active     BOOLEAN     NOT NULL DEFAULT TRUE,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- System user for API key fallback (no JWT)
+INSERT INTO users (id, username, email, password_hash, role, is_active)
+VALUES (
+    '00000000-0000-0000-0000-000000000001',
+    'system-api',
+    'system-api@sokoul.local',
+    'api-key-fallback-user',
+    'user',
+    TRUE
+)
+ON CONFLICT DO NOTHING;
+
+-- media
+CREATE TABLE IF NOT EXISTS media (
+    id              UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    media_type      TEXT        NOT NULL,
+    title           TEXT        NOT NULL,
+    original_title  TEXT,
+    year            INTEGER,
+    tmdb_id         INTEGER,
+    imdb_id         TEXT,
+    overview        TEXT,
+    poster_url      TEXT,
+    backdrop_url    TEXT,
+    genres          TEXT[],
+    rating          DECIMAL(3,1),
+    runtime_minutes INTEGER,
+    status          TEXT        DEFAULT 'unknown',
+    parent_id       UUID        REFERENCES media(id) ON DELETE CASCADE,
+    season_number   INTEGER,
+    episode_number  INTEGER,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (tmdb_id, media_type)
+);
+
+-- episode rows synced by workers/series.rs
+ALTER TABLE media
+    ADD COLUMN IF NOT EXISTS air_date         DATE,
+    ADD COLUMN IF NOT EXISTS last_searched_at TIMESTAMPTZ;
+
+-- media_files (models.rs::MediaFile)
+CREATE TABLE IF NOT EXISTS media_files (
+    id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    media_id      UUID        NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    file_path     TEXT        NOT NULL UNIQUE,
+    file_size     BIGINT,
+    codec_video   TEXT,
+    codec_audio   TEXT,
+    resolution    TEXT,
+    quality_score INTEGER,
+    hash_info     TEXT,
+    source        TEXT,
+    downloaded_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+ALTER TABLE media_files ADD COLUMN IF NOT EXISTS missing_since TIMESTAMPTZ;
+
+-- search_results (models.rs::SearchResult — id is SERIAL i32)
+CREATE TABLE IF NOT EXISTS search_results (
+    id           SERIAL      PRIMARY KEY,
+    media_id     UUID        NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    provider     TEXT        NOT NULL,
+    title        TEXT        NOT NULL,
+    guid         TEXT        NOT NULL,
+    url          TEXT,
+    magnet_link  TEXT,
+    info_hash    TEXT,
+    protocol     TEXT        NOT NULL DEFAULT 'torrent',
+    quality      TEXT,
+    size_bytes   BIGINT      NOT NULL DEFAULT 0,
+    seeders      INTEGER     NOT NULL DEFAULT 0,
+    leechers     INTEGER     NOT NULL DEFAULT 0,
+    score        INTEGER,
+    ai_validated BOOLEAN,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at   TIMESTAMPTZ DEFAULT (NOW() + INTERVAL '24 hours'),
+    UNIQUE (media_id, guid)
+);
+
+-- tasks (models.rs::Task)
+CREATE TABLE IF NOT EXISTS tasks (
+    id           UUID         PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_type    TEXT         NOT NULL,
+    status       TEXT         NOT NULL DEFAULT 'pending',
+    payload      JSONB,
+    result       JSONB,
+    progress     DECIMAL(5,2) DEFAULT 0,
+    error        TEXT,
+    created_at   TIMESTAMPTZ  DEFAULT NOW(),
+    started_at   TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ
+);
+
+-- watch_history
+CREATE TABLE IF NOT EXISTS watch_history (
+    id               UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    media_id         UUID        NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    user_id          UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    watched_at       TIMESTAMPTZ DEFAULT NOW(),
+    progress_seconds INTEGER     DEFAULT 0,
+    total_seconds    INTEGER     DEFAULT 0,
+    completed        BOOLEAN     DEFAULT FALSE,
+    UNIQUE (media_id, user_id)
+);
+
+-- favorites
+CREATE TABLE IF NOT EXISTS favorites (
+    id       UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id  UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    media_id UUID        NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    added_at TIMESTAMPTZ DEFAULT NOW(),
+    UNIQUE (user_id, media_id)
+);
+
+-- watchlist
+CREATE TABLE IF NOT EXISTS watchlist (
+    id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id       UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    media_id      UUID        NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    auto_download BOOLEAN     DEFAULT FALSE,
+    quality_min   TEXT        DEFAULT '1080p',
+    added_at      TIMESTAMPTZ DEFAULT NOW(),
+    UNIQUE (user_id, media_id)
+);
+
+-- watchlist auto-acquisition outcome (workers/watchlist.rs)
+ALTER TABLE watchlist
+    ADD COLUMN IF NOT EXISTS auto_status           TEXT,
+    ADD COLUMN IF NOT EXISTS auto_message          TEXT,
+    ADD COLUMN IF NOT EXISTS last_search_result_id INTEGER,
+    ADD COLUMN IF NOT EXISTS last_searched_at      TIMESTAMPTZ;
+
+-- quality_profiles (db/quality_profiles.rs) — one per user
+CREATE TABLE IF NOT EXISTS quality_profiles (
+    user_id        UUID        PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    min_resolution TEXT        NOT NULL DEFAULT '1080p',
+    max_resolution TEXT,
+    min_seeders    INTEGER     NOT NULL DEFAULT 1,
+    max_size_bytes BIGINT,
+    rejected_terms TEXT[]      NOT NULL DEFAULT ARRAY['cam', 'hdts', 'telesync', 'screener'],
+    updated_at     TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE quality_profiles
+    ADD COLUMN IF NOT EXISTS cutoff_score      INTEGER NOT NULL DEFAULT 70,
+    ADD COLUMN IF NOT EXISTS allow_pre_digital BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS release_region    TEXT;
+
+ALTER TABLE media ADD COLUMN IF NOT EXISTS calendar_synced_at TIMESTAMPTZ;
+
+-- media_release_dates / calendar_feeds (db/calendar.rs)
+CREATE TABLE IF NOT EXISTS media_release_dates (
+    media_id     UUID NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    country      TEXT NOT NULL,
+    release_type TEXT NOT NULL CHECK (release_type IN ('theatrical', 'digital', 'physical')),
+    release_date DATE NOT NULL,
+    PRIMARY KEY (media_id, country, release_type)
+);
+
+CREATE TABLE IF NOT EXISTS calendar_feeds (
+    user_id    UUID        PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    token      TEXT        NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- media_file_upgrades (db/media_files.rs) — history of in-place quality upgrades
+CREATE TABLE IF NOT EXISTS media_file_upgrades (
+    id                UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    media_file_id     UUID        NOT NULL REFERENCES media_files(id) ON DELETE CASCADE,
+    media_id          UUID        NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    old_path          TEXT        NOT NULL,
+    old_quality_score INTEGER,
+    new_path          TEXT        NOT NULL,
+    new_quality_score INTEGER,
+    release_title     TEXT,
+    upgraded_at       TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- library_review_items (db/library.rs) — scanned files awaiting a manual TMDB match
+CREATE TABLE IF NOT EXISTS library_review_items (
+    id             UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    file_path      TEXT        NOT NULL UNIQUE,
+    file_size      BIGINT,
+    parsed_title   TEXT        NOT NULL,
+    parsed_year    INTEGER,
+    season_number  INTEGER,
+    episode_number INTEGER,
+    candidates     JSONB       NOT NULL DEFAULT '[]',
+    status         TEXT        NOT NULL DEFAULT 'pending'
+                   CHECK (status IN ('pending', 'resolved', 'ignored')),
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- subtitles (db/subtitles.rs) — sidecar files and embedded tracks of media_files
+CREATE TABLE IF NOT EXISTS subtitles (
+    id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    media_file_id UUID        NOT NULL REFERENCES media_files(id) ON DELETE CASCADE,
+    source        TEXT        NOT NULL,
+    file_path     TEXT,
+    track_number  INTEGER,
+    format        TEXT        NOT NULL,
+    language      TEXT,
+    title         TEXT,
+    is_forced     BOOLEAN     NOT NULL DEFAULT FALSE,
+    is_sdh        BOOLEAN     NOT NULL DEFAULT FALSE,
+    is_default    BOOLEAN     NOT NULL DEFAULT FALSE,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((file_path IS NULL) <> (track_number IS NULL))
+);
+
+-- monitored_series / monitored_seasons (db/series.rs)
+CREATE TABLE IF NOT EXISTS monitored_series (
+    media_id       UUID        PRIMARY KEY REFERENCES media(id) ON DELETE CASCADE,
+    user_id        UUID        REFERENCES users(id) ON DELETE SET NULL,
+    default_mode   TEXT        NOT NULL DEFAULT 'future'
+                   CHECK (default_mode IN ('all', 'future', 'none')),
+    last_synced_at TIMESTAMPTZ,
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS monitored_seasons (
+    media_id        UUID    NOT NULL REFERENCES monitored_series(media_id) ON DELETE CASCADE,
+    season_number   INTEGER NOT NULL,
+    mode            TEXT    NOT NULL CHECK (mode IN ('all', 'future', 'none')),
+    monitored_since DATE    NOT NULL DEFAULT CURRENT_DATE,
+    PRIMARY KEY (media_id, season_number)
+);
+
+-- collections — thematic (GoT, Breaking Bad…), NOT user playlists
+-- Schema matches db/collections.rs::Collection exactly
+CREATE TABLE IF NOT EXISTS collections (
+    id              UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    name            TEXT        NOT NULL,
+    description     TEXT,
+    category        TEXT        NOT NULL,
+    api_source      TEXT        NOT NULL DEFAULT 'internal',
+    cover_image_url TEXT,
+    backdrop_url    TEXT,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- collection_items — reference external IDs (tmdb_id), not local media UUIDs
+-- Schema matches db/collections.rs::CollectionItem exactly
+CREATE TABLE IF NOT EXISTS collection_items (
+    id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    collection_id UUID        NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+    external_id   TEXT,
+    name          TEXT        NOT NULL,
+    description   TEXT,
+    image_url     TEXT,
+    item_type     TEXT,
+    data_json     JSONB,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- tv_channels
+CREATE TABLE IF NOT EXISTS tv_channels (
+    id         UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    name       TEXT        NOT NULL,
+    code       TEXT        NOT NULL UNIQUE,
+    country    TEXT,
+    logo_url   TEXT,
+    category   TEXT,
+    is_free    BOOLEAN     DEFAULT TRUE,
+    is_active  BOOLEAN     DEFAULT TRUE,
+    stream_url TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- tv_programs (EPG)
+CREATE TABLE IF NOT EXISTS tv_programs (
+    id          UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    channel_id  UUID        NOT NULL REFERENCES tv_channels(id) ON DELETE CASCADE,
+    title       TEXT        NOT NULL,
+    description TEXT,
+    start_time  TIMESTAMPTZ NOT NULL,
+    end_time    TIMESTAMPTZ NOT NULL,
+    genre       TEXT,
+    image_url   TEXT,
+    rating      DECIMAL(3,1),
+    external_id TEXT,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- audit_logs (db/security.rs::AuditLog)
+CREATE TABLE IF NOT EXISTS audit_logs (
+    id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id       UUID        REFERENCES users(id) ON DELETE SET NULL,
+    action        TEXT        NOT NULL,
+    resource_type TEXT,
+    resource_id   TEXT,
+    url           TEXT,
+    ip_address    TEXT,
+    user_agent    TEXT,
+    risk_level    TEXT        NOT NULL DEFAULT 'low',
+    status        TEXT        NOT NULL DEFAULT 'success',
+    metadata      JSONB,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- retention_policies (db/retention.rs) — rules for deleting watched media
+CREATE TABLE IF NOT EXISTS retention_policies (
+    id                     UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    name                   TEXT        NOT NULL,
+    enabled                BOOLEAN     NOT NULL DEFAULT TRUE,
+    media_type             TEXT,
+    watched_by             TEXT        NOT NULL DEFAULT 'all'
+                           CHECK (watched_by IN ('all', 'any')),
+    min_days_since_watched INTEGER     NOT NULL DEFAULT 30 CHECK (min_days_since_watched >= 0),
+    keep_favorites         BOOLEAN     NOT NULL DEFAULT TRUE,
+    keep_watchlisted       BOOLEAN     NOT NULL DEFAULT TRUE,
+    free_space_below_gb    INTEGER     CHECK (free_space_below_gb > 0),
+    created_at             TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at             TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- url_reputation (db/security.rs::UrlReputation)
+CREATE TABLE IF NOT EXISTS url_reputation (
+    id                UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    url               TEXT        NOT NULL UNIQUE,
+    domain            TEXT,
+    risk_level        TEXT        NOT NULL DEFAULT 'unknown',
+    virustotal_result JSONB,
+    urlhaus_result    JSONB,
+    malicious_count   INTEGER     NOT NULL DEFAULT 0,
+    last_checked      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at        TIMESTAMPTZ NOT NULL DEFAULT (NOW() + INTERVAL '24 hours'),
+    created_at        TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- domain_whitelist (db/security.rs::DomainWhitelist)
+CREATE TABLE IF NOT EXISTS domain_whitelist (
+    id         UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    domain     TEXT        NOT NULL UNIQUE,
+    added_by   UUID        REFERENCES users(id) ON DELETE SET NULL,
+    reason     TEXT,
+    is_active  BOOLEAN     NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- domain_blacklist (db/security.rs::DomainBlacklist)
+CREATE TABLE IF NOT EXISTS domain_blacklist (
+    id          UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    domain      TEXT        NOT NULL UNIQUE,
+    risk_level  TEXT        NOT NULL DEFAULT 'high',
+    threat_type TEXT,
+    added_by    UUID        REFERENCES users(id) ON DELETE SET NULL,
+    reason      TEXT,
+    is_active   BOOLEAN     NOT NULL DEFAULT TRUE,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS idx_media_tmdb ON media(tmdb_id, media_type);
+CREATE INDEX IF NOT EXISTS idx_media_type ON media(media_type);
+CREATE INDEX IF NOT EXISTS idx_media_title_trgm ON media USING gin(title gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_media_air_date ON media(air_date);
+CREATE INDEX IF NOT EXISTS idx_media_files_media ON media_files(media_id);
+CREATE INDEX IF NOT EXISTS idx_media_release_dates_date ON media_release_dates(release_date);
+CREATE INDEX IF NOT EXISTS idx_media_file_upgrades_media ON media_file_upgrades(media_id, upgraded_at DESC);
+CREATE INDEX IF NOT EXISTS idx_media_files_size ON media_files(file_size);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_subtitles_file_path ON subtitles(media_file_id, file_path) WHERE file_path IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_subtitles_track ON subtitles(media_file_id, track_number) WHERE track_number IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_library_review_items_status ON library_review_items(status, created_at);
+CREATE INDEX IF NOT EXISTS idx_search_results_media ON search_results(media_id);
+CREATE INDEX IF NOT EXISTS idx_search_results_expires ON search_results(expires_at);
+CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status);
+CREATE INDEX IF NOT EXISTS idx_watch_history_user ON watch_history(user_id);
+CREATE INDEX IF NOT EXISTS idx_watch_history_media ON watch_history(media_id);
+CREATE INDEX IF NOT EXISTS idx_favorites_user ON favorites(user_id);
+CREATE INDEX IF NOT EXISTS idx_watchlist_user ON watchlist(user_id);
+CREATE INDEX IF NOT EXISTS idx_watchlist_auto ON watchlist(auto_download) WHERE auto_download;
+CREATE INDEX IF NOT EXISTS idx_collections_category ON collections(category);
+CREATE INDEX IF NOT EXISTS idx_collection_items_collection ON collection_items(collection_id);
+CREATE INDEX IF NOT EXISTS idx_tv_channels_country ON tv_channels(country);
+CREATE INDEX IF NOT EXISTS idx_tv_channels_code ON tv_channels(code);
+CREATE INDEX IF NOT EXISTS idx_tv_programs_channel ON tv_programs(channel_id);
+CREATE INDEX IF NOT EXISTS idx_tv_programs_start_time ON tv_programs(start_time);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_user ON audit_logs(user_id);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_risk ON audit_logs(risk_level);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_created ON audit_logs(created_at);
+CREATE INDEX IF NOT EXISTS idx_url_reputation_domain ON url_reputation(domain);
+CREATE INDEX IF NOT EXISTS idx_domain_whitelist_domain ON domain_whitelist(domain);
+CREATE INDEX IF NOT EXISTS idx_domain_blacklist_domain ON domain_blacklist(domain);
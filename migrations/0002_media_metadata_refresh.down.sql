@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_media_metadata_refreshed;
+ALTER TABLE media DROP COLUMN IF EXISTS metadata_refreshed_at;
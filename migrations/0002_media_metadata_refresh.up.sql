@@ -0,0 +1,4 @@
+-- Last TMDB metadata refresh of movies and series (workers/metadata.rs)
+ALTER TABLE media ADD COLUMN IF NOT EXISTS metadata_refreshed_at TIMESTAMPTZ;
+CREATE INDEX IF NOT EXISTS idx_media_metadata_refreshed
+    ON media(metadata_refreshed_at NULLS FIRST) WHERE parent_id IS NULL;
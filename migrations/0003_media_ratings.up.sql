@@ -0,0 +1,12 @@
+-- Ratings per source, refreshed with the media metadata (workers/metadata.rs)
+CREATE TABLE IF NOT EXISTS media_ratings (
+    media_id   UUID         NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    source     TEXT         NOT NULL,
+    value      NUMERIC(5,1) NOT NULL,
+    -- Best possible value: 10 for IMDb, TMDB, Trakt and Simkl, 100 for Rotten Tomatoes and Metacritic
+    scale      SMALLINT     NOT NULL,
+    vote_count BIGINT,
+    fetched_at TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (media_id, source)
+);
+CREATE INDEX IF NOT EXISTS idx_media_ratings_source_value ON media_ratings(source, value);
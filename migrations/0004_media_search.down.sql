@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_media_genres;
+DROP INDEX IF EXISTS idx_media_search;
+ALTER TABLE media DROP COLUMN IF EXISTS search_vector;
+DROP FUNCTION IF EXISTS sokoul_unaccent(text);
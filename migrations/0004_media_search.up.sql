@@ -0,0 +1,18 @@
+-- Accent-insensitive full-text search over titles and overviews (db/library_search.rs)
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+-- unaccent() is only STABLE because its dictionary could change; pinning the
+-- dictionary makes it usable in a stored generated column.
+CREATE OR REPLACE FUNCTION sokoul_unaccent(text) RETURNS text
+    LANGUAGE sql IMMUTABLE PARALLEL SAFE STRICT
+    AS $$ SELECT public.unaccent('public.unaccent'::regdictionary, $1) $$;
+
+-- 'simple' configuration: no stemming, so French and English titles match alike.
+ALTER TABLE media ADD COLUMN IF NOT EXISTS search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', sokoul_unaccent(COALESCE(title, ''))), 'A') ||
+    setweight(to_tsvector('simple', sokoul_unaccent(COALESCE(original_title, ''))), 'A') ||
+    setweight(to_tsvector('simple', sokoul_unaccent(COALESCE(overview, ''))), 'C')
+) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_media_search ON media USING gin(search_vector);
+CREATE INDEX IF NOT EXISTS idx_media_genres ON media USING gin(genres);
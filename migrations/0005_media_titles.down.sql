@@ -0,0 +1,2 @@
+ALTER TABLE search_results DROP COLUMN IF EXISTS matched_title;
+DROP TABLE IF EXISTS media_titles;
@@ -0,0 +1,15 @@
+-- Alternative and localized titles per country, from TMDB (workers/metadata.rs)
+CREATE TABLE IF NOT EXISTS media_titles (
+    media_id UUID NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    -- ISO 3166-1 code, '' when TMDB gives none
+    country  TEXT NOT NULL,
+    title    TEXT NOT NULL,
+    -- TMDB's note on the title ("working title", "short title"…)
+    kind     TEXT,
+    PRIMARY KEY (media_id, country, title)
+);
+CREATE INDEX IF NOT EXISTS idx_media_titles_search
+    ON media_titles USING gin(to_tsvector('simple', sokoul_unaccent(title)));
+
+-- Title or alias of the media the release name was matched against (workers/hunter.rs)
+ALTER TABLE search_results ADD COLUMN IF NOT EXISTS matched_title TEXT;
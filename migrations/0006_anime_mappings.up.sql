@@ -0,0 +1,12 @@
+-- MyAnimeList and TheTVDB ids of anime series; TMDB's is on media (db/anime.rs)
+CREATE TABLE IF NOT EXISTS anime_mappings (
+    media_id   UUID        PRIMARY KEY REFERENCES media(id) ON DELETE CASCADE,
+    mal_id     BIGINT      NOT NULL UNIQUE,
+    tvdb_id    INTEGER,
+    -- Romaji title on MyAnimeList, the one fansub releases go by
+    mal_title  TEXT,
+    -- 'auto' (matched through Jikan) or 'manual'; manual mappings are never replaced
+    source     TEXT        NOT NULL DEFAULT 'auto' CHECK (source IN ('auto', 'manual')),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS idx_anime_mappings_tvdb ON anime_mappings(tvdb_id);
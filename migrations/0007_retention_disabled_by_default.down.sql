@@ -0,0 +1 @@
+ALTER TABLE retention_policies ALTER COLUMN enabled SET DEFAULT TRUE;
@@ -0,0 +1,3 @@
+-- New retention policies start disabled so their dry-run report can be
+-- reviewed before anything is deleted.
+ALTER TABLE retention_policies ALTER COLUMN enabled SET DEFAULT FALSE;
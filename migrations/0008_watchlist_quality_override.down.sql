@@ -0,0 +1 @@
+ALTER TABLE watchlist ALTER COLUMN quality_min SET DEFAULT '1080p';
@@ -0,0 +1,4 @@
+-- watchlist.quality_min only overrides the quality profile when set. New
+-- entries leave it unset; the values of existing ones are kept, since a stored
+-- '1080p' may have been chosen.
+ALTER TABLE watchlist ALTER COLUMN quality_min DROP DEFAULT;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_media_ratings_refreshed;
+ALTER TABLE media DROP COLUMN IF EXISTS ratings_refreshed_at;
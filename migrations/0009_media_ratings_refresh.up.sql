@@ -0,0 +1,5 @@
+-- Last refresh of the OMDb, Trakt and Simkl ratings, which run on their own,
+-- much longer schedule than the TMDB details (workers/metadata.rs)
+ALTER TABLE media ADD COLUMN IF NOT EXISTS ratings_refreshed_at TIMESTAMPTZ;
+CREATE INDEX IF NOT EXISTS idx_media_ratings_refreshed
+    ON media(ratings_refreshed_at NULLS FIRST) WHERE parent_id IS NULL;
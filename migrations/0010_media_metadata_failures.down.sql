@@ -0,0 +1,2 @@
+ALTER TABLE media DROP COLUMN IF EXISTS metadata_failed_at;
+ALTER TABLE media DROP COLUMN IF EXISTS metadata_failures;
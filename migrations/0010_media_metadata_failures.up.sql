@@ -0,0 +1,4 @@
+-- Failed metadata refreshes, so failing items back off instead of coming back
+-- every run (workers/metadata.rs)
+ALTER TABLE media ADD COLUMN IF NOT EXISTS metadata_failures INT NOT NULL DEFAULT 0;
+ALTER TABLE media ADD COLUMN IF NOT EXISTS metadata_failed_at TIMESTAMPTZ;
@@ -0,0 +1,3 @@
+UPDATE media_files SET hash_info = fingerprint WHERE fingerprint IS NOT NULL;
+ALTER TABLE media_files DROP COLUMN IF EXISTS file_mtime;
+ALTER TABLE media_files DROP COLUMN IF EXISTS fingerprint;
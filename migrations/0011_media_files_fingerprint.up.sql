@@ -0,0 +1,6 @@
+-- Partial-content hash and modification time that recognize a file after a
+-- move (workers/library_watch.rs). Earlier builds kept the hash in hash_info.
+ALTER TABLE media_files ADD COLUMN IF NOT EXISTS fingerprint TEXT;
+ALTER TABLE media_files ADD COLUMN IF NOT EXISTS file_mtime TIMESTAMPTZ;
+UPDATE media_files SET fingerprint = hash_info, hash_info = NULL
+WHERE hash_info ~ '^[0-9a-f]{64}$';
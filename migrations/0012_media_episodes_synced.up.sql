@@ -0,0 +1,3 @@
+-- Last sync of a series' episode listing; cleared when a file of one of its
+-- episodes is imported or deleted, so completeness re-reads it (api/tracking.rs)
+ALTER TABLE media ADD COLUMN IF NOT EXISTS episodes_synced_at TIMESTAMPTZ;
@@ -0,0 +1 @@
+ALTER TABLE media_files DROP COLUMN IF EXISTS file_inode;
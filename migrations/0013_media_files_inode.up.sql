@@ -0,0 +1,3 @@
+-- Device and inode of a file ("<dev>:<ino>"), so hardlinks to one file are not
+-- reported as duplicates (utils/duplicates.rs).
+ALTER TABLE media_files ADD COLUMN IF NOT EXISTS file_inode TEXT;
@@ -0,0 +1,90 @@
+-- Library scans could create the same episode twice. Keep the oldest row of
+-- each episode, move what referenced its duplicates over to it, then make
+-- episodes unique. Rows a duplicate shares with the kept episode (the same
+-- user's history, favorite or watchlist entry…) are dropped rather than moved.
+CREATE TEMP TABLE episode_duplicates ON COMMIT DROP AS
+SELECT id, keep_id FROM (
+    SELECT id, FIRST_VALUE(id) OVER (
+        PARTITION BY parent_id, season_number, episode_number
+        ORDER BY created_at, id
+    ) AS keep_id
+    FROM media
+    WHERE parent_id IS NOT NULL
+      AND season_number IS NOT NULL AND episode_number IS NOT NULL
+) ranked
+WHERE id <> keep_id;
+
+UPDATE media_files t SET media_id = d.keep_id
+FROM episode_duplicates d WHERE t.media_id = d.id;
+UPDATE media_file_upgrades t SET media_id = d.keep_id
+FROM episode_duplicates d WHERE t.media_id = d.id;
+
+DELETE FROM search_results t USING episode_duplicates d
+WHERE t.media_id = d.id AND EXISTS (
+    SELECT 1 FROM search_results o LEFT JOIN episode_duplicates od ON od.id = o.media_id
+    WHERE COALESCE(od.keep_id, o.media_id) = d.keep_id AND o.guid = t.guid
+      AND (o.media_id = d.keep_id OR o.ctid < t.ctid)
+);
+UPDATE search_results t SET media_id = d.keep_id
+FROM episode_duplicates d WHERE t.media_id = d.id;
+
+DELETE FROM watch_history t USING episode_duplicates d
+WHERE t.media_id = d.id AND EXISTS (
+    SELECT 1 FROM watch_history o LEFT JOIN episode_duplicates od ON od.id = o.media_id
+    WHERE COALESCE(od.keep_id, o.media_id) = d.keep_id AND o.user_id = t.user_id
+      AND (o.media_id = d.keep_id OR o.ctid < t.ctid)
+);
+UPDATE watch_history t SET media_id = d.keep_id
+FROM episode_duplicates d WHERE t.media_id = d.id;
+
+DELETE FROM favorites t USING episode_duplicates d
+WHERE t.media_id = d.id AND EXISTS (
+    SELECT 1 FROM favorites o LEFT JOIN episode_duplicates od ON od.id = o.media_id
+    WHERE COALESCE(od.keep_id, o.media_id) = d.keep_id AND o.user_id = t.user_id
+      AND (o.media_id = d.keep_id OR o.ctid < t.ctid)
+);
+UPDATE favorites t SET media_id = d.keep_id
+FROM episode_duplicates d WHERE t.media_id = d.id;
+
+DELETE FROM watchlist t USING episode_duplicates d
+WHERE t.media_id = d.id AND EXISTS (
+    SELECT 1 FROM watchlist o LEFT JOIN episode_duplicates od ON od.id = o.media_id
+    WHERE COALESCE(od.keep_id, o.media_id) = d.keep_id AND o.user_id = t.user_id
+      AND (o.media_id = d.keep_id OR o.ctid < t.ctid)
+);
+UPDATE watchlist t SET media_id = d.keep_id
+FROM episode_duplicates d WHERE t.media_id = d.id;
+
+DELETE FROM media_release_dates t USING episode_duplicates d
+WHERE t.media_id = d.id AND EXISTS (
+    SELECT 1 FROM media_release_dates o LEFT JOIN episode_duplicates od ON od.id = o.media_id
+    WHERE COALESCE(od.keep_id, o.media_id) = d.keep_id
+      AND o.country = t.country AND o.release_type = t.release_type
+      AND (o.media_id = d.keep_id OR o.ctid < t.ctid)
+);
+UPDATE media_release_dates t SET media_id = d.keep_id
+FROM episode_duplicates d WHERE t.media_id = d.id;
+
+DELETE FROM media_ratings t USING episode_duplicates d
+WHERE t.media_id = d.id AND EXISTS (
+    SELECT 1 FROM media_ratings o LEFT JOIN episode_duplicates od ON od.id = o.media_id
+    WHERE COALESCE(od.keep_id, o.media_id) = d.keep_id AND o.source = t.source
+      AND (o.media_id = d.keep_id OR o.ctid < t.ctid)
+);
+UPDATE media_ratings t SET media_id = d.keep_id
+FROM episode_duplicates d WHERE t.media_id = d.id;
+
+DELETE FROM media_titles t USING episode_duplicates d
+WHERE t.media_id = d.id AND EXISTS (
+    SELECT 1 FROM media_titles o LEFT JOIN episode_duplicates od ON od.id = o.media_id
+    WHERE COALESCE(od.keep_id, o.media_id) = d.keep_id
+      AND o.country = t.country AND o.title = t.title
+      AND (o.media_id = d.keep_id OR o.ctid < t.ctid)
+);
+UPDATE media_titles t SET media_id = d.keep_id
+FROM episode_duplicates d WHERE t.media_id = d.id;
+
+DELETE FROM media m USING episode_duplicates d WHERE m.id = d.id;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_media_episode
+    ON media(parent_id, season_number, episode_number) WHERE parent_id IS NOT NULL;
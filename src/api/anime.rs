@@ -0,0 +1,140 @@
+use crate::{
+    api::error::ApiError,
+    db::{
+        self,
+        anime::{AnimeMapping, NewAnimeMapping},
+    },
+    models::Media,
+    workers, AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct SetAnimeMappingPayload {
+    pub mal_id: i64,
+    pub tvdb_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MalLookupQuery {
+    /// Absolute episode number to convert to season and episode
+    pub absolute: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MalLookupResponse {
+    pub media: Media,
+    pub mapping: AnimeMapping,
+    pub absolute: Option<i32>,
+    pub season: Option<i32>,
+    pub episode: Option<i32>,
+}
+
+/// GET /media/:id/anime - MyAnimeList and TVDB ids of an anime series
+pub async fn get_anime_mapping_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<AnimeMapping>, ApiError> {
+    db::anime::get_mapping(&state.db_pool, id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("Series is not mapped to MyAnimeList".to_string()))
+}
+
+/// PUT /media/:id/anime - Map a series to a MyAnimeList id, overriding automatic matching
+pub async fn set_anime_mapping_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<SetAnimeMappingPayload>,
+) -> Result<Json<AnimeMapping>, ApiError> {
+    if payload.mal_id < 1 {
+        return Err(ApiError::InvalidInput(
+            "mal_id must be positive".to_string(),
+        ));
+    }
+    let media = db::media::get_media_by_id(&state.db_pool, id).await?;
+    if media.media_type != "tv" || media.parent_id.is_some() {
+        return Err(ApiError::InvalidInput(
+            "Only TV series can be mapped to MyAnimeList".to_string(),
+        ));
+    }
+
+    workers::anime::throttle_jikan().await;
+    let mal_title = match state.jikan_client.anime_details(payload.mal_id).await {
+        Ok(anime) => anime.and_then(|a| a.title),
+        Err(e) => {
+            tracing::warn!(
+                "Jikan lookup of MyAnimeList {} failed: {}",
+                payload.mal_id,
+                e
+            );
+            None
+        }
+    };
+    let mapping = db::anime::upsert_mapping(
+        &state.db_pool,
+        &NewAnimeMapping {
+            media_id: id,
+            mal_id: payload.mal_id,
+            tvdb_id: payload.tvdb_id,
+            mal_title: mal_title.as_deref(),
+            source: "manual",
+        },
+    )
+    .await?
+    .ok_or(ApiError::InternalServerError)?;
+    Ok(Json(mapping))
+}
+
+/// DELETE /media/:id/anime - Remove the MyAnimeList mapping of a series
+pub async fn delete_anime_mapping_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    if db::anime::delete_mapping(&state.db_pool, id).await? == 0 {
+        return Err(ApiError::NotFound(
+            "Series is not mapped to MyAnimeList".to_string(),
+        ));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /anime/mal/:mal_id - Series mapped to a MyAnimeList id, optionally resolving an absolute episode
+pub async fn get_by_mal_id_handler(
+    State(state): State<Arc<AppState>>,
+    Path(mal_id): Path<i64>,
+    Query(params): Query<MalLookupQuery>,
+) -> Result<Json<MalLookupResponse>, ApiError> {
+    let media = db::anime::find_by_mal_id(&state.db_pool, mal_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("No series mapped to this MyAnimeList id".to_string()))?;
+    let mapping = db::anime::get_mapping(&state.db_pool, media.id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("No series mapped to this MyAnimeList id".to_string()))?;
+
+    let (season, episode) = match params.absolute {
+        Some(absolute) => db::anime::numbering(&state.db_pool, media.id)
+            .await?
+            .to_season_episode(absolute)
+            .map(|(s, e)| (Some(s), Some(e)))
+            .ok_or_else(|| {
+                ApiError::NotFound(format!("Episode {} is not in the episode list", absolute))
+            })?,
+        None => (None, None),
+    };
+
+    Ok(Json(MalLookupResponse {
+        media,
+        mapping,
+        absolute: params.absolute,
+        season,
+        episode,
+    }))
+}
@@ -0,0 +1,175 @@
+use crate::{
+    api::auth::extract_user_id,
+    api::error::ApiError,
+    config::CONFIG,
+    db::{self, calendar::CalendarEntry},
+    utils::ical::{self, IcsEvent},
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{Days, NaiveDate, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+fn get_user_id(headers: &HeaderMap) -> Uuid {
+    extract_user_id(headers)
+        .unwrap_or_else(|| Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+fn feed_url(token: &str) -> String {
+    format!(
+        "{}/calendar/feed/{}",
+        CONFIG.base_url.trim_end_matches('/'),
+        token
+    )
+}
+
+/// GET /calendar?from=&to= - Upcoming episodes and movie releases (default: next 30 days)
+pub async fn calendar_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<CalendarQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let from = query.from.unwrap_or_else(|| Utc::now().date_naive());
+    let to = query
+        .to
+        .unwrap_or_else(|| from.checked_add_days(Days::new(30)).unwrap_or(from));
+    if to < from {
+        return Err(ApiError::InvalidInput(
+            "'to' must not be before 'from'".to_string(),
+        ));
+    }
+    if (to - from).num_days() > 366 {
+        return Err(ApiError::InvalidInput(
+            "Calendar range is limited to one year".to_string(),
+        ));
+    }
+
+    let user_id = get_user_id(&headers);
+    let region = user_region(&state, user_id).await?;
+    let entries = db::calendar::list_entries(&state.db_pool, user_id, from, to, &region).await?;
+
+    Ok(Json(serde_json::json!({
+        "from": from,
+        "to": to,
+        "region": region,
+        "entries": entries,
+    })))
+}
+
+/// GET /calendar/feed - URL of the user's subscribable .ics feed, if created
+pub async fn get_feed_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let token = db::calendar::get_feed_token(&state.db_pool, get_user_id(&headers)).await?;
+    Ok(Json(serde_json::json!({
+        "enabled": token.is_some(),
+        "url": token.as_deref().map(feed_url),
+    })))
+}
+
+/// POST /calendar/feed - Create or rotate the user's .ics feed token
+pub async fn rotate_feed_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    db::calendar::rotate_feed_token(&state.db_pool, get_user_id(&headers), &token).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "enabled": true,
+            "url": feed_url(&token),
+        })),
+    ))
+}
+
+/// DELETE /calendar/feed - Revoke the user's .ics feed
+pub async fn delete_feed_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiError> {
+    db::calendar::delete_feed_token(&state.db_pool, get_user_id(&headers)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /calendar/feed/:token - iCalendar feed (public, authenticated by the token)
+pub async fn ics_feed_handler(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = db::calendar::find_user_by_feed_token(&state.db_pool, &token)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Unknown calendar feed".to_string()))?;
+
+    let today = Utc::now().date_naive();
+    let from = today.checked_sub_days(Days::new(30)).unwrap_or(today);
+    let to = today.checked_add_days(Days::new(365)).unwrap_or(today);
+    let region = user_region(&state, user_id).await?;
+    let entries = db::calendar::list_entries(&state.db_pool, user_id, from, to, &region).await?;
+
+    let events: Vec<IcsEvent> = entries.iter().map(to_ics_event).collect();
+    let body = ical::render_calendar("Sokoul", &events, Utc::now());
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "inline; filename=\"sokoul.ics\"",
+            ),
+        ],
+        body,
+    ))
+}
+
+/// Country used for movie release dates: the user's profile, else the server default.
+async fn user_region(state: &AppState, user_id: Uuid) -> Result<String, ApiError> {
+    Ok(
+        db::quality_profiles::get_effective_profile(&state.db_pool, user_id)
+            .await?
+            .release_region
+            .unwrap_or_else(|| CONFIG.release_region.clone()),
+    )
+}
+
+fn to_ics_event(entry: &CalendarEntry) -> IcsEvent {
+    let mut summary = match (entry.season_number, entry.episode_number) {
+        (Some(season), Some(episode)) => {
+            let mut s = format!("{} S{:02}E{:02}", entry.title, season, episode);
+            if let Some(ref name) = entry.episode_title {
+                s.push_str(&format!(" - {}", name));
+            }
+            s
+        }
+        _ => format!("{} ({} release)", entry.title, entry.entry_type),
+    };
+    if entry.downloaded {
+        summary.insert_str(0, "✓ ");
+    }
+
+    IcsEvent {
+        uid: format!("{}-{}@sokoul", entry.entry_type, entry.media_id),
+        date: entry.date,
+        summary,
+        description: if entry.downloaded {
+            "Downloaded".to_string()
+        } else {
+            "Not downloaded yet".to_string()
+        },
+    }
+}
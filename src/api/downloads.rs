@@ -2,7 +2,11 @@ use crate::{
     api::error::ApiError,
     db,
     events::{self, DownloadRequestedPayload},
-    security, AppState,
+    models::SearchResult,
+    providers::ProviderRegistry,
+    security,
+    workers::upgrades,
+    AppState,
 };
 use axum::{
     extract::{Path, Query, State},
@@ -32,6 +36,30 @@ pub async fn start_download_handler(
         .find(|r| r.id == payload.search_result_id)
         .ok_or_else(|| ApiError::NotFound("Search result not found".to_string()))?;
 
+    let security_warning = queue_download(&state, None, payload.media_id, result, None).await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "Download started.",
+            "media_id": payload.media_id,
+            "search_result_id": payload.search_result_id,
+            "security_warning": security_warning,
+        })),
+    ))
+}
+
+/// Check a search result's link for malware and hand it to the Hunter queue.
+/// Critical risks are blocked (audited, admin alerted); the reason of an allowed
+/// warning-level risk is returned. `replaces_file_id` marks the download as an
+/// upgrade of that file.
+pub async fn queue_download(
+    state: &Arc<AppState>,
+    user_id: Option<Uuid>,
+    media_id: Uuid,
+    result: &SearchResult,
+    replaces_file_id: Option<Uuid>,
+) -> Result<Option<String>, ApiError> {
     let magnet_or_url = result
         .magnet_link
         .clone()
@@ -41,16 +69,16 @@ pub async fn start_download_handler(
         })?;
 
     // Security Check: Validate URL safety before allowing download
-    let security_check = security::check_url_safety(&state, &magnet_or_url).await;
+    let security_check = security::check_url_safety(state, &magnet_or_url).await;
 
     if security_check.risk_level == "critical" {
         // Block critical risk and log event
         let _ = db::security::insert_audit_log(
             &state.db_pool,
-            None,
+            user_id,
             "download_blocked",
             Some("url"),
-            Some(&payload.search_result_id.to_string()),
+            Some(&result.id.to_string()),
             Some(&magnet_or_url),
             None,
             None,
@@ -86,10 +114,10 @@ pub async fn start_download_handler(
     if security_check.risk_level == "warning" {
         let _ = db::security::insert_audit_log(
             &state.db_pool,
-            None,
+            user_id,
             "download_warning",
             Some("url"),
-            Some(&payload.search_result_id.to_string()),
+            Some(&result.id.to_string()),
             Some(&magnet_or_url),
             None,
             None,
@@ -103,10 +131,11 @@ pub async fn start_download_handler(
     }
 
     let download_event = DownloadRequestedPayload {
-        media_id: payload.media_id,
-        search_result_id: payload.search_result_id,
+        media_id,
+        search_result_id: result.id,
         magnet_or_url,
         title: result.title.clone(),
+        replaces_file_id,
     };
 
     let event_data = serde_json::to_vec(&download_event)
@@ -118,19 +147,7 @@ pub async fn start_download_handler(
         .await
         .map_err(|e| ApiError::MessageBus(e.to_string()))?;
 
-    Ok((
-        StatusCode::ACCEPTED,
-        Json(serde_json::json!({
-            "message": "Download started.",
-            "media_id": payload.media_id,
-            "search_result_id": payload.search_result_id,
-            "security_warning": if security_check.risk_level == "warning" {
-                Some(security_check.reason)
-            } else {
-                None
-            },
-        })),
-    ))
+    Ok((security_check.risk_level == "warning").then_some(security_check.reason))
 }
 
 /// GET /downloads - List current/completed downloads (via tasks)
@@ -221,3 +238,36 @@ pub async fn list_media_files_handler(
     let files = db::media_files::get_files_by_media_id(&state.db_pool, id).await?;
     Ok(Json(files))
 }
+
+/// POST /media/:id/upgrade - Search for a better release of the media's file
+pub async fn search_upgrade_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let files = db::media_files::get_files_by_media_id(&state.db_pool, id).await?;
+    let file = files
+        .iter()
+        .max_by_key(|f| f.quality_score)
+        .ok_or_else(|| ApiError::NotFound("Media has no file to upgrade".to_string()))?;
+
+    let registry = ProviderRegistry::with_indexers(
+        state.redis_client.clone(),
+        state.flaresolverr_client.clone(),
+    );
+    let outcome = upgrades::search_upgrade(&state, &registry, file).await?;
+
+    Ok(Json(serde_json::json!({
+        "media_id": id,
+        "file_id": file.id,
+        "outcome": outcome,
+    })))
+}
+
+/// GET /media/:id/upgrades - Upgrade history of the media's files
+pub async fn list_upgrades_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<db::media_files::MediaFileUpgrade>>, ApiError> {
+    let upgrades = db::media_files::list_upgrades(&state.db_pool, id).await?;
+    Ok(Json(upgrades))
+}
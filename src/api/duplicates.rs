@@ -0,0 +1,208 @@
+use crate::{
+    api::error::ApiError,
+    db,
+    models::MediaFile,
+    utils::{
+        duplicates::{self, DuplicateGroup},
+        file_hash, storage,
+    },
+    AppState,
+};
+use axum::{extract::State, Json};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path, sync::Arc};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// Held by the background pass hashing duplicate candidates.
+static FINGERPRINTING: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateReport {
+    pub groups: Vec<DuplicateGroup>,
+    pub reclaimable_bytes: i64,
+    /// Candidates still being hashed in the background; the report is
+    /// incomplete until this is 0
+    pub pending_fingerprints: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolvePayload {
+    /// File to keep
+    pub keep: Uuid,
+    /// Copies to delete; when absent, only the copies with identical content
+    pub remove: Option<Vec<Uuid>>,
+}
+
+/// Present files without a partial hash that share their size with another
+/// file: only those can have an identical copy, so the rest is never read.
+fn unhashed_candidates(files: &[MediaFile]) -> Vec<MediaFile> {
+    let mut sizes: HashMap<i64, usize> = HashMap::new();
+    for file in files.iter().filter(|f| f.missing_since.is_none()) {
+        if let Some(size) = file.file_size {
+            *sizes.entry(size).or_default() += 1;
+        }
+    }
+    files
+        .iter()
+        .filter(|f| f.missing_since.is_none() && f.fingerprint.is_none())
+        .filter(|f| f.file_size.is_some_and(|size| sizes[&size] > 1))
+        .cloned()
+        .collect()
+}
+
+/// Hash the candidates in the background, unless a pass is already running.
+/// The library watcher fingerprints every file eventually; this only catches up
+/// on those it has not reached yet.
+fn spawn_fingerprinting(state: &Arc<AppState>, candidates: Vec<MediaFile>) {
+    let Ok(guard) = FINGERPRINTING.try_lock() else {
+        return;
+    };
+    let state = state.clone();
+    tokio::spawn(async move {
+        let _guard = guard;
+        for file in candidates {
+            let path = file.file_path.clone();
+            let hashed = tokio::task::spawn_blocking(move || {
+                let path = Path::new(&path);
+                let metadata = std::fs::metadata(path)?;
+                let hash = file_hash::partial_hash(path)?;
+                Ok::<_, std::io::Error>((metadata, hash))
+            })
+            .await;
+            let (metadata, hash) = match hashed {
+                Ok(Ok(hashed)) => hashed,
+                Ok(Err(e)) => {
+                    tracing::warn!("Duplicates: cannot hash {}: {}", file.file_path, e);
+                    continue;
+                }
+                Err(e) => {
+                    tracing::error!("Duplicates: hashing {} failed: {}", file.file_path, e);
+                    continue;
+                }
+            };
+            if let Err(e) = db::media_files::set_fingerprint(
+                &state.db_pool,
+                file.id,
+                metadata.len() as i64,
+                file_hash::modified_at(&metadata),
+                &hash,
+                file_hash::inode(&metadata).as_deref(),
+            )
+            .await
+            {
+                tracing::warn!(
+                    "Duplicates: cannot store the hash of {}: {}",
+                    file.file_path,
+                    e
+                );
+            }
+        }
+    });
+}
+
+/// GET /library/duplicates - Files stored more than once, with the copy to keep
+pub async fn list_duplicates_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<DuplicateReport>, ApiError> {
+    let files = db::media_files::list_all_files(&state.db_pool).await?;
+    let candidates = unhashed_candidates(&files);
+    let pending_fingerprints = candidates.len();
+    if !candidates.is_empty() {
+        spawn_fingerprinting(&state, candidates);
+    }
+
+    let groups = duplicates::find_groups(&files);
+    Ok(Json(DuplicateReport {
+        reclaimable_bytes: groups.iter().map(|g| g.reclaimable_bytes).sum(),
+        groups,
+        pending_fingerprints,
+    }))
+}
+
+/// POST /library/duplicates/resolve - Keep one file and delete its duplicates from disk and library
+pub async fn resolve_duplicates_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ResolvePayload>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let files = db::media_files::list_all_files(&state.db_pool).await?;
+
+    let keep = files
+        .iter()
+        .find(|f| f.id == payload.keep)
+        .ok_or_else(|| ApiError::NotFound(format!("Media file {} not found", payload.keep)))?;
+    let copies: Vec<&MediaFile> = files
+        .iter()
+        .filter(|f| duplicates::are_duplicates(keep, f))
+        .collect();
+
+    let remove: Vec<&MediaFile> = match &payload.remove {
+        None => {
+            let identical = duplicates::identical_copies(keep, &files);
+            if identical.is_empty() && !copies.is_empty() {
+                return Err(ApiError::InvalidInput(format!(
+                    "The copies of {} differ in content; select the ones to remove",
+                    payload.keep
+                )));
+            }
+            identical
+        }
+        Some(ids) => {
+            if ids.is_empty() {
+                return Err(ApiError::InvalidInput(
+                    "Select the duplicates to remove".to_string(),
+                ));
+            }
+            ids.iter()
+                .map(|id| {
+                    copies.iter().copied().find(|f| f.id == *id).ok_or_else(|| {
+                        ApiError::InvalidInput(format!(
+                            "{} is not a duplicate of {}",
+                            id, payload.keep
+                        ))
+                    })
+                })
+                .collect::<Result<_, _>>()?
+        }
+    };
+    if remove.is_empty() {
+        return Err(ApiError::InvalidInput(format!(
+            "{} has no duplicates",
+            payload.keep
+        )));
+    }
+
+    let roots = storage::roots();
+    let mut deleted = Vec::new();
+    let mut freed_bytes = 0i64;
+    let mut errors = Vec::new();
+    for file in remove {
+        let path = storage::absolute(Path::new(&file.file_path));
+        let Some(root) = storage::root_of(&path, &roots) else {
+            errors.push(format!("{}: outside the storage roots", file.file_path));
+            continue;
+        };
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {
+                storage::remove_empty_parents(&path, root);
+                tracing::info!(
+                    "Duplicates: deleted {} (kept {})",
+                    file.file_path,
+                    keep.file_path
+                );
+                freed_bytes += file.file_size.unwrap_or(0);
+                deleted.push(file.id);
+            }
+            Err(e) => errors.push(format!("{}: {}", file.file_path, e)),
+        }
+    }
+    db::media_files::delete_files(&state.db_pool, &deleted).await?;
+
+    Ok(Json(serde_json::json!({
+        "kept": keep.id,
+        "deleted": deleted,
+        "freed_bytes": freed_bytes,
+        "errors": errors,
+    })))
+}
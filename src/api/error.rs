@@ -1,3 +1,4 @@
+use crate::remux::RemuxError;
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
@@ -31,6 +32,9 @@ pub enum ApiError {
 
     #[error("Service unavailable: {0}")]
     ServiceUnavailable(&'static str),
+
+    #[error("Unsupported media: {0}")]
+    UnsupportedMedia(String),
 }
 
 impl From<sqlx::Error> for ApiError {
@@ -44,6 +48,15 @@ impl From<sqlx::Error> for ApiError {
     }
 }
 
+impl From<RemuxError> for ApiError {
+    fn from(err: RemuxError) -> Self {
+        match err {
+            RemuxError::Unsupported(msg) => ApiError::UnsupportedMedia(msg),
+            _ => ApiError::Internal(err.into()),
+        }
+    }
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
@@ -79,6 +92,7 @@ impl IntoResponse for ApiError {
                 )
             }
             ApiError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg.to_string()),
+            ApiError::UnsupportedMedia(msg) => (StatusCode::UNSUPPORTED_MEDIA_TYPE, msg),
         };
 
         let body = Json(json!({ "error": error_message }));
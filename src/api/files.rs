@@ -1,19 +1,42 @@
-use crate::{api::error::ApiError, db, AppState};
+use crate::{
+    api::error::ApiError,
+    db,
+    remux::{
+        mkv::{is_matroska, MkvRemuxer},
+        RemuxError,
+    },
+    utils::http_file,
+    AppState,
+};
 use axum::{
-    body::Body,
-    extract::{Path, State},
-    http::{header, StatusCode},
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, Method},
     response::Response,
     Json,
 };
-use std::sync::Arc;
-use tokio_util::io::ReaderStream;
+use serde::Deserialize;
+use std::{io::BufReader, sync::Arc};
+use tokio::sync::{mpsc, oneshot};
 use uuid::Uuid;
 
-/// GET /files/:file_id/stream - Stream a media file
+/// Fragments buffered ahead of the client while remuxing.
+const REMUX_BUFFER_FRAGMENTS: usize = 4;
+
+#[derive(Debug, Deserialize)]
+pub struct RemuxQuery {
+    /// Start position in seconds; playback begins at the keyframe at or after it.
+    pub start: Option<f64>,
+    /// Matroska audio track number (defaults to the default compatible track).
+    pub audio: Option<u64>,
+}
+
+/// GET /files/:file_id/stream - Stream a media file (byte ranges, conditional requests, HEAD)
 pub async fn stream_file_handler(
     State(state): State<Arc<AppState>>,
     Path(file_id): Path<Uuid>,
+    method: Method,
+    headers: HeaderMap,
 ) -> Result<Response, ApiError> {
     let file = db::media_files::get_file_by_id(&state.db_pool, file_id).await?;
 
@@ -22,37 +45,102 @@ pub async fn stream_file_handler(
         return Err(ApiError::NotFound("File not found on disk".into()));
     }
 
-    let tokio_file = tokio::fs::File::open(path)
-        .await
-        .map_err(|e| ApiError::Internal(anyhow::anyhow!("Unable to open file: {}", e)))?;
-
-    let metadata = tokio_file
-        .metadata()
-        .await
-        .map_err(|e| ApiError::Internal(anyhow::anyhow!("Metadata error: {}", e)))?;
-
-    let file_size = metadata.len();
-    let stream = ReaderStream::new(tokio_file);
-    let body = Body::from_stream(stream);
-
     let filename = path
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("video.mp4");
 
-    let content_type = guess_content_type(filename);
-
-    Ok(Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, content_type)
-        .header(header::CONTENT_LENGTH, file_size)
-        .header(
-            header::CONTENT_DISPOSITION,
-            format!("inline; filename=\"{}\"", filename),
-        )
-        .header(header::ACCEPT_RANGES, "bytes")
+    let mut response = http_file::serve_file(path, &method, &headers, guess_content_type(filename))
+        .await
+        .map_err(|e| ApiError::Internal(anyhow::anyhow!("Unable to read file: {}", e)))?;
+    if let Ok(disposition) = HeaderValue::from_str(&format!("inline; filename=\"{}\"", filename)) {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_DISPOSITION, disposition);
+    }
+    Ok(response)
+}
+
+/// GET /files/:file_id/stream.mp4?start=&audio= - Remux a Matroska file to fragmented MP4 for browsers
+pub async fn stream_mp4_handler(
+    State(state): State<Arc<AppState>>,
+    Path(file_id): Path<Uuid>,
+    Query(query): Query<RemuxQuery>,
+) -> Result<Response, ApiError> {
+    let file = db::media_files::get_file_by_id(&state.db_pool, file_id).await?;
+
+    let path = std::path::PathBuf::from(&file.file_path);
+    if !is_matroska(&file.file_path) {
+        return Err(ApiError::UnsupportedMedia(
+            "Only Matroska (.mkv, .webm) files can be remuxed; use /stream instead".into(),
+        ));
+    }
+    if !path.exists() {
+        return Err(ApiError::NotFound("File not found on disk".into()));
+    }
+    let start = query.start.unwrap_or(0.0);
+    if !start.is_finite() || start < 0.0 {
+        return Err(ApiError::InvalidInput(
+            "start must be a positive number of seconds".into(),
+        ));
+    }
+
+    let (ready_tx, ready_rx) = oneshot::channel();
+    let (tx, mut rx) = mpsc::channel::<std::io::Result<Bytes>>(REMUX_BUFFER_FRAGMENTS);
+    tokio::task::spawn_blocking(move || {
+        let opened = std::fs::File::open(&path)
+            .map_err(RemuxError::from)
+            .and_then(|f| MkvRemuxer::open(BufReader::new(f), query.audio, start));
+        let mut remuxer = match opened {
+            Ok(remuxer) => {
+                let _ = ready_tx.send(Ok(remuxer.start_secs()));
+                remuxer
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+
+        if tx
+            .blocking_send(Ok(Bytes::from(remuxer.init_segment())))
+            .is_err()
+        {
+            return;
+        }
+        loop {
+            let chunk = match remuxer.next_fragment() {
+                Ok(Some(fragment)) => Ok(Bytes::from(fragment)),
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::warn!("Remux of {} aborted: {}", path.display(), e);
+                    Err(std::io::Error::other(e.to_string()))
+                }
+            };
+            let failed = chunk.is_err();
+            // The client hung up or the stream broke.
+            if tx.blocking_send(chunk).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    let start_secs = ready_rx
+        .await
+        .map_err(|_| ApiError::InternalServerError)??;
+
+    let body = Body::from_stream(async_stream::stream! {
+        while let Some(chunk) = rx.recv().await {
+            yield chunk;
+        }
+    });
+    Response::builder()
+        .header(header::CONTENT_TYPE, "video/mp4")
+        .header(header::ACCEPT_RANGES, "none")
+        .header(header::CACHE_CONTROL, "no-store")
+        .header("X-Stream-Start", format!("{:.3}", start_secs))
         .body(body)
-        .unwrap())
+        .map_err(|e| ApiError::Internal(e.into()))
 }
 
 /// GET /files/:file_id/info - Get file info for the player
@@ -78,8 +166,11 @@ pub async fn file_info_handler(
         "codec_audio": file.codec_audio,
         "resolution": file.resolution,
         "exists": exists,
+        "missing_since": file.missing_since,
         "filename": filename,
         "stream_url": format!("/api/files/{}/stream", file.id),
+        "mp4_stream_url": is_matroska(filename).then(|| format!("/api/files/{}/stream.mp4", file.id)),
+        "hls_url": is_matroska(filename).then(|| format!("/api/files/{}/master.m3u8", file.id)),
         "content_type": guess_content_type(filename),
     })))
 }
@@ -0,0 +1,196 @@
+use crate::{
+    api::error::ApiError,
+    db,
+    remux::{
+        cache::{self, SegmentCache},
+        hls::{self, HlsPlan, Rendition},
+        mkv::{is_matroska, SegmentTrack},
+        RemuxError,
+    },
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::PathBuf,
+    sync::Arc,
+};
+use uuid::Uuid;
+
+const PLAYLIST_CONTENT_TYPE: &str = "application/vnd.apple.mpegurl";
+
+/// A library file being packaged, with its disk cache namespace.
+struct HlsSource {
+    path: PathBuf,
+    size: u64,
+    /// Cache directory of this file; changes when the file is replaced.
+    key: String,
+    cache: SegmentCache,
+}
+
+impl HlsSource {
+    async fn load(state: &AppState, file_id: Uuid) -> Result<Self, ApiError> {
+        let file = db::media_files::get_file_by_id(&state.db_pool, file_id).await?;
+        if !is_matroska(&file.file_path) {
+            return Err(ApiError::UnsupportedMedia(
+                "HLS packaging is only available for Matroska (.mkv, .webm) files".into(),
+            ));
+        }
+        let metadata = tokio::fs::metadata(&file.file_path)
+            .await
+            .map_err(|_| ApiError::NotFound("File not found on disk".into()))?;
+
+        Ok(Self {
+            path: PathBuf::from(&file.file_path),
+            size: metadata.len(),
+            key: cache::file_key(file.id, &metadata),
+            cache: SegmentCache::from_config(),
+        })
+    }
+
+    fn reader(&self) -> io::Result<BufReader<File>> {
+        File::open(&self.path).map(BufReader::new)
+    }
+
+    fn cached(
+        &self,
+        name: &str,
+        build: impl FnOnce() -> Result<Vec<u8>, RemuxError>,
+    ) -> Result<Vec<u8>, RemuxError> {
+        let key = format!("{}/{}", self.key, name);
+        if let Some(data) = self.cache.get(&key) {
+            return Ok(data);
+        }
+        let data = build()?;
+        if let Err(e) = self.cache.put(&key, &data) {
+            tracing::warn!("Unable to cache HLS resource {}: {}", key, e);
+        }
+        Ok(data)
+    }
+
+    fn plan(&self) -> Result<HlsPlan, RemuxError> {
+        let data = self.cached("plan.json", || {
+            let plan = hls::plan(&mut self.reader()?)?;
+            serde_json::to_vec(&plan).map_err(|e| io::Error::other(e).into())
+        })?;
+        serde_json::from_slice(&data).map_err(|e| io::Error::other(e).into())
+    }
+
+    /// Content type and body of a rendition resource, `None` when it does not exist.
+    fn resource(
+        &self,
+        rendition: Rendition,
+        name: &str,
+    ) -> Result<Option<(&'static str, Vec<u8>)>, RemuxError> {
+        let plan = self.plan()?;
+        if !plan.has_rendition(rendition) {
+            return Ok(None);
+        }
+        let track = match rendition {
+            Rendition::Video => SegmentTrack::Video,
+            Rendition::Audio(number) => SegmentTrack::Audio(number),
+            Rendition::Subtitles(number) => {
+                return Ok(match name {
+                    "index.m3u8" => {
+                        Some((PLAYLIST_CONTENT_TYPE, plan.subtitle_playlist().into_bytes()))
+                    }
+                    "subtitles.vtt" => {
+                        let vtt = self.cached(&format!("{}/subtitles.vtt", rendition), || {
+                            Ok(hls::subtitles(self.reader()?, number)?.into_bytes())
+                        })?;
+                        Some(("text/vtt; charset=utf-8", vtt))
+                    }
+                    _ => None,
+                });
+            }
+        };
+
+        if name == "index.m3u8" {
+            return Ok(Some((
+                PLAYLIST_CONTENT_TYPE,
+                plan.media_playlist().into_bytes(),
+            )));
+        }
+        if name == "init.mp4" {
+            let init = self.cached(&format!("{}/init.mp4", rendition), || {
+                hls::init_segment(self.reader()?, track)
+            })?;
+            return Ok(Some(("video/mp4", init)));
+        }
+        let Some(index) = name
+            .strip_suffix(".m4s")
+            .and_then(|i| i.parse::<usize>().ok())
+        else {
+            return Ok(None);
+        };
+        if plan.segment_range(index).is_none() {
+            return Ok(None);
+        }
+        let segment = self.cached(&format!("{}/{}.m4s", rendition, index), || {
+            Ok(hls::media_segment(self.reader()?, &plan, track, index)?.unwrap_or_default())
+        })?;
+        Ok(Some(("video/iso.segment", segment)))
+    }
+}
+
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, RemuxError> + Send + 'static,
+) -> Result<T, ApiError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|_| ApiError::InternalServerError)?
+        .map_err(ApiError::from)
+}
+
+/// GET /files/:file_id/master.m3u8 - HLS master playlist with audio and subtitle renditions
+pub async fn master_playlist_handler(
+    State(state): State<Arc<AppState>>,
+    Path(file_id): Path<Uuid>,
+) -> Result<Response, ApiError> {
+    let source = HlsSource::load(&state, file_id).await?;
+    let playlist = blocking(move || Ok(source.plan()?.master_playlist(source.size))).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, PLAYLIST_CONTENT_TYPE),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        playlist,
+    )
+        .into_response())
+}
+
+/// GET /files/:file_id/hls/:rendition/:name - Rendition playlist, init segment, media segment or WebVTT
+pub async fn hls_resource_handler(
+    State(state): State<Arc<AppState>>,
+    Path((file_id, rendition, name)): Path<(Uuid, String, String)>,
+) -> Result<Response, ApiError> {
+    let rendition: Rendition = rendition
+        .parse()
+        .map_err(|_| ApiError::NotFound(format!("Unknown rendition: {}", rendition)))?;
+    let source = HlsSource::load(&state, file_id).await?;
+
+    let is_playlist = name.ends_with(".m3u8");
+    let (content_type, body) = blocking(move || source.resource(rendition, &name))
+        .await?
+        .ok_or_else(|| ApiError::NotFound("HLS resource not found".into()))?;
+    let cache_control = if is_playlist {
+        "no-cache"
+    } else {
+        "private, max-age=86400"
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, cache_control),
+        ],
+        body,
+    )
+        .into_response())
+}
@@ -0,0 +1,90 @@
+use crate::{
+    api::error::ApiError,
+    db,
+    utils::artwork::{self, ArtworkCache, ImageFormat},
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+static CACHE: Lazy<ArtworkCache> = Lazy::new(ArtworkCache::from_config);
+
+/// Width served when none is asked for: the original TMDB poster size.
+const DEFAULT_WIDTH: u32 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct ImageQuery {
+    pub url: Option<String>,
+    /// Target width in pixels, rounded up to a standard size
+    pub w: Option<u32>,
+    /// "webp" or "jpeg"; negotiated from the Accept header when absent
+    pub format: Option<String>,
+}
+
+async fn serve(url: &str, query: &ImageQuery, headers: &HeaderMap) -> Result<Response, ApiError> {
+    if !artwork::is_allowed_source(url) {
+        return Err(ApiError::InvalidInput(
+            "url must be TMDB, Fanart.tv, TheTVDB or TVMaze artwork".into(),
+        ));
+    }
+    let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok());
+    let format = ImageFormat::negotiate(query.format.as_deref(), accept)
+        .ok_or_else(|| ApiError::InvalidInput("format must be webp or jpeg".into()))?;
+    let width = query.w.unwrap_or(DEFAULT_WIDTH);
+
+    let data = CACHE.variant(url, width, format).await.map_err(|e| {
+        tracing::warn!("Artwork unavailable for {}: {}", url, e);
+        ApiError::ServiceUnavailable("Artwork is not cached and its source is unreachable")
+    })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type()),
+            // Variants never change for a given URL.
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+            (header::VARY, "Accept"),
+        ],
+        data,
+    )
+        .into_response())
+}
+
+/// GET /images?url=&w=&format= - Cached, resized copy of external artwork
+pub async fn image_proxy_handler(
+    headers: HeaderMap,
+    Query(query): Query<ImageQuery>,
+) -> Result<Response, ApiError> {
+    let url = query
+        .url
+        .clone()
+        .ok_or_else(|| ApiError::InvalidInput("url is required".into()))?;
+    serve(&url, &query, &headers).await
+}
+
+/// GET /media/:id/artwork/:kind?w=&format= - Poster or backdrop of a media item from the local cache
+pub async fn media_artwork_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((id, kind)): Path<(Uuid, String)>,
+    Query(query): Query<ImageQuery>,
+) -> Result<Response, ApiError> {
+    let media = db::media::get_media_by_id(&state.db_pool, id).await?;
+    let url = match kind.as_str() {
+        "poster" => media.poster_url,
+        "backdrop" => media.backdrop_url,
+        _ => {
+            return Err(ApiError::InvalidInput(
+                "artwork kind must be poster or backdrop".into(),
+            ))
+        }
+    };
+    let url = url.ok_or_else(|| ApiError::NotFound(format!("No {} for this media", kind)))?;
+    serve(&url, &query, &headers).await
+}
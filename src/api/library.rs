@@ -2,7 +2,7 @@ use crate::{
     api::auth::extract_user_id,
     api::error::ApiError,
     api::media_ref::{find_media_id_by_tmdb, resolve_media_id, MediaReferenceInput},
-    db,
+    db::{self, ratings::MediaRating},
     models::{AddFavoritePayload, LibraryStatus},
     AppState,
 };
@@ -14,7 +14,7 @@ use axum::{
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::Row;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 use uuid::Uuid;
 
 // Default user UUID for API key auth (backward compatible)
@@ -24,9 +24,16 @@ fn get_user_id(headers: &HeaderMap) -> Uuid {
 }
 
 #[derive(Debug, Deserialize)]
-pub struct PaginationQuery {
+pub struct LibraryQuery {
     pub page: Option<i64>,
     pub per_page: Option<i64>,
+    /// "added" (default, newest first) or "rating" (best first, unrated last)
+    pub sort: Option<String>,
+    /// Rating used by `sort=rating` and `min_rating`: tmdb (default), imdb,
+    /// rotten_tomatoes, metacritic, trakt or simkl
+    pub rating_source: Option<String>,
+    /// On the source's own scale (10 or 100)
+    pub min_rating: Option<f64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -41,6 +48,7 @@ pub struct FavoriteListItem {
     pub release_date: Option<String>,
     pub overview: Option<String>,
     pub added_at: Option<DateTime<Utc>>,
+    pub ratings: Vec<MediaRating>,
 }
 
 #[derive(Debug, Serialize)]
@@ -115,21 +123,51 @@ pub async fn remove_from_library_by_tmdb_handler(
 pub async fn list_library_handler(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    Query(params): Query<PaginationQuery>,
+    Query(params): Query<LibraryQuery>,
 ) -> Result<Json<PaginatedFavoritesResponse>, ApiError> {
     let user_id = get_user_id(&headers);
     let page = params.page.unwrap_or(1).max(1);
     let per_page = params.per_page.unwrap_or(30).min(100);
     let offset = (page - 1) * per_page;
 
-    let total = db::favorites::count_favorites(&state.db_pool, user_id)
+    let sort = params.sort.as_deref().unwrap_or("added");
+    if !matches!(sort, "added" | "rating") {
+        return Err(ApiError::InvalidInput(
+            "sort must be 'added' or 'rating'".to_string(),
+        ));
+    }
+    let rating_source = params.rating_source.as_deref().unwrap_or("tmdb");
+    if !db::ratings::is_known_source(rating_source) {
+        return Err(ApiError::InvalidInput(format!(
+            "Unknown rating source '{}'",
+            rating_source
+        )));
+    }
+
+    let total = match params.min_rating {
+        None => db::favorites::count_favorites(&state.db_pool, user_id)
+            .await
+            .map_err(ApiError::Database)?,
+        Some(min_rating) => sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM favorites f
+            JOIN media_ratings r ON r.media_id = f.media_id AND r.source = $2
+            WHERE f.user_id = $1 AND r.value >= $3::float8
+            "#,
+        )
+        .bind(user_id)
+        .bind(rating_source)
+        .bind(min_rating)
+        .fetch_one(&state.db_pool)
         .await
-        .map_err(ApiError::Database)?;
+        .map_err(ApiError::Database)?,
+    };
     let rows = sqlx::query(
         r#"
         SELECT
             f.id AS favorite_id,
             f.added_at,
+            m.id AS media_id,
             COALESCE(m.tmdb_id, 0) AS tmdb_id,
             m.media_type,
             m.title,
@@ -140,22 +178,35 @@ pub async fn list_library_handler(
             m.overview
         FROM favorites f
         JOIN media m ON m.id = f.media_id
+        LEFT JOIN media_ratings r ON r.media_id = m.id AND r.source = $4
         WHERE f.user_id = $1
-        ORDER BY f.added_at DESC
+          AND ($5::float8 IS NULL OR r.value >= $5)
+        ORDER BY CASE WHEN $6 = 'rating' THEN r.value END DESC NULLS LAST,
+                 f.added_at DESC
         LIMIT $2 OFFSET $3
         "#,
     )
     .bind(user_id)
     .bind(per_page)
     .bind(offset)
+    .bind(rating_source)
+    .bind(params.min_rating)
+    .bind(sort)
     .fetch_all(&state.db_pool)
     .await
     .map_err(ApiError::Database)?;
 
+    let media_ids: Vec<Uuid> = rows.iter().map(|row| row.get("media_id")).collect();
+    let mut ratings: HashMap<Uuid, Vec<MediaRating>> = HashMap::new();
+    for rating in db::ratings::list_for_media(&state.db_pool, &media_ids).await? {
+        ratings.entry(rating.media_id).or_default().push(rating);
+    }
+
     let items: Vec<FavoriteListItem> = rows
         .into_iter()
         .map(|row| {
             let year: Option<i32> = row.get("year");
+            let media_id: Uuid = row.get("media_id");
             FavoriteListItem {
                 id: row.get("favorite_id"),
                 tmdb_id: row.get("tmdb_id"),
@@ -167,6 +218,7 @@ pub async fn list_library_handler(
                 release_date: year.map(|y| format!("{:04}-01-01", y)),
                 overview: row.get("overview"),
                 added_at: row.get("added_at"),
+                ratings: ratings.remove(&media_id).unwrap_or_default(),
             }
         })
         .collect();
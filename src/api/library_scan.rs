@@ -0,0 +1,199 @@
+use crate::{
+    api::error::ApiError,
+    config::CONFIG,
+    db,
+    models::CreateMediaPayload,
+    utils::release::{self, ParsedRelease},
+    workers::library_scan::{self, ScannedFile},
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use std::{path::PathBuf, sync::Arc};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewQuery {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveReviewPayload {
+    pub tmdb_id: i32,
+    /// "movie" or "tv"
+    pub media_type: String,
+}
+
+/// POST /library/scan - Import existing files from the library roots (incremental)
+pub async fn start_scan_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    if CONFIG.library_roots.is_empty() {
+        return Err(ApiError::InvalidInput(
+            "No library roots configured (LIBRARY_ROOTS)".to_string(),
+        ));
+    }
+
+    let task_id = library_scan::start_scan(&state)
+        .await?
+        .ok_or_else(|| ApiError::InvalidInput("A library scan is already running".to_string()))?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "task_id": task_id,
+            "roots": CONFIG.library_roots,
+        })),
+    ))
+}
+
+/// GET /library/review - Scanned files awaiting a manual match
+pub async fn list_review_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ReviewQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let per_page = query.per_page.unwrap_or(50).clamp(1, 200);
+    let page = query.page.unwrap_or(1).max(1);
+
+    let items =
+        db::library::list_pending_reviews(&state.db_pool, per_page, (page - 1) * per_page).await?;
+    let total = db::library::count_pending_reviews(&state.db_pool).await?;
+
+    Ok(Json(serde_json::json!({
+        "items": items,
+        "total": total,
+        "page": page,
+        "per_page": per_page,
+    })))
+}
+
+/// POST /library/review/:id/resolve - Import a reviewed file as the given TMDB entry
+pub async fn resolve_review_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<ResolveReviewPayload>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let item = db::library::get_review_item(&state.db_pool, id).await?;
+    if item.status != "pending" {
+        return Err(ApiError::InvalidInput(format!(
+            "Review item is already {}",
+            item.status
+        )));
+    }
+
+    let media_payload = match payload.media_type.as_str() {
+        "movie" => {
+            let details = state
+                .tmdb_client
+                .movie_details(payload.tmdb_id)
+                .await
+                .map_err(tmdb_error)?;
+            CreateMediaPayload {
+                title: details.title,
+                media_type: "movie".to_string(),
+                tmdb_id: Some(details.id),
+                year: year_of(details.release_date.as_deref()),
+                overview: details.overview,
+                poster_url: details.poster_path.map(poster_url),
+                genres: Some(details.genres.into_iter().map(|g| g.name).collect()),
+                rating: details.vote_average,
+            }
+        }
+        "tv" => {
+            let details = state
+                .tmdb_client
+                .tv_details(payload.tmdb_id)
+                .await
+                .map_err(tmdb_error)?;
+            CreateMediaPayload {
+                title: details.name,
+                media_type: "tv".to_string(),
+                tmdb_id: Some(details.id),
+                year: year_of(details.first_air_date.as_deref()),
+                overview: details.overview,
+                poster_url: details.poster_path.map(poster_url),
+                genres: Some(details.genres.into_iter().map(|g| g.name).collect()),
+                rating: details.vote_average,
+            }
+        }
+        other => {
+            return Err(ApiError::InvalidInput(format!(
+                "Invalid media type '{}'. Accepted values: movie, tv",
+                other
+            )))
+        }
+    };
+
+    let path = PathBuf::from(&item.file_path);
+    let root = CONFIG
+        .library_roots
+        .iter()
+        .map(PathBuf::from)
+        .find(|root| path.starts_with(root))
+        .unwrap_or_else(|| path.parent().map(PathBuf::from).unwrap_or_default());
+    let file = ScannedFile {
+        path,
+        root,
+        size: item
+            .file_size
+            .and_then(|s| u64::try_from(s).ok())
+            .unwrap_or(0),
+    };
+    let parsed = ParsedRelease {
+        title: item.parsed_title.clone(),
+        year: item.parsed_year,
+        season: item.season_number,
+        episode: item.episode_number,
+        absolute_episode: release::parse_library_path(&file.path, &file.root).absolute_episode,
+    };
+
+    let media = db::media::create_media(&state.db_pool, &media_payload).await?;
+    let media_file = library_scan::attach_file(&state, &media, &parsed, &file)
+        .await?
+        .ok_or_else(|| {
+            ApiError::InvalidInput(format!(
+                "Episode {} is not in the episode list of '{}'",
+                parsed.absolute_episode.unwrap_or_default(),
+                media.title
+            ))
+        })?;
+    db::library::set_review_status(&state.db_pool, id, "resolved").await?;
+
+    Ok(Json(serde_json::json!({
+        "media": media,
+        "file": media_file,
+    })))
+}
+
+/// DELETE /library/review/:id - Dismiss a review item; re-scans will skip the file
+pub async fn ignore_review_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let updated = db::library::set_review_status(&state.db_pool, id, "ignored").await?;
+    if updated == 0 {
+        return Err(ApiError::NotFound("Review item not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn tmdb_error(e: reqwest::Error) -> ApiError {
+    if e.status() == Some(reqwest::StatusCode::NOT_FOUND) {
+        ApiError::NotFound("Unknown TMDB id".to_string())
+    } else {
+        ApiError::Internal(e.into())
+    }
+}
+
+fn year_of(date: Option<&str>) -> Option<i32> {
+    date?.get(..4)?.parse().ok()
+}
+
+fn poster_url(path: String) -> String {
+    format!("https://image.tmdb.org/t/p/w500{}", path)
+}
@@ -0,0 +1,209 @@
+use crate::{
+    api::{auth::extract_user_id, error::ApiError, media::MediaWithRatings},
+    db::{
+        self,
+        library_search::{FacetCount, LibraryFilters, LibrarySort},
+        ratings::MediaRating,
+    },
+    utils::quality,
+    AppState,
+};
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct LibrarySearchQuery {
+    /// Words matched as prefixes against title, original title and overview,
+    /// ignoring case and accents
+    pub q: Option<String>,
+    /// "movie" or "tv"
+    #[serde(rename = "type")]
+    pub media_type: Option<String>,
+    /// Comma-separated; items must have all of them
+    pub genres: Option<String>,
+    pub year_min: Option<i32>,
+    pub year_max: Option<i32>,
+    /// On the rating source's own scale (10 or 100)
+    pub min_rating: Option<f64>,
+    /// tmdb (default), imdb, rotten_tomatoes, metacritic, trakt or simkl
+    pub rating_source: Option<String>,
+    pub runtime_min: Option<i32>,
+    pub runtime_max: Option<i32>,
+    pub has_file: Option<bool>,
+    /// "720p", "1080p", "4k"…
+    pub resolution: Option<String>,
+    pub watched: Option<bool>,
+    pub favorite: Option<bool>,
+    pub watchlist: Option<bool>,
+    /// relevance (default when `q` is set), title, year, rating, added (default
+    /// otherwise) or runtime
+    pub sort: Option<String>,
+    /// "asc" or "desc"; titles default to ascending, the rest to descending
+    pub order: Option<String>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LibraryFacets {
+    pub media_type: Vec<FacetCount>,
+    pub genres: Vec<FacetCount>,
+    pub decades: Vec<FacetCount>,
+    pub resolutions: Vec<FacetCount>,
+    pub has_file: Vec<FacetCount>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LibrarySearchResponse {
+    pub items: Vec<MediaWithRatings>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+    pub total_pages: i64,
+    pub facets: LibraryFacets,
+    pub sort: &'static str,
+    pub order: &'static str,
+    pub sort_options: Vec<&'static str>,
+}
+
+/// GET /library/search - Search and filter local movies and series, with facet counts
+pub async fn library_search_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<LibrarySearchQuery>,
+) -> Result<Json<LibrarySearchResponse>, ApiError> {
+    let user_id = extract_user_id(&headers)
+        .unwrap_or_else(|| Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap());
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(30).clamp(1, 100);
+    let offset = (page - 1) * per_page;
+
+    let tsquery = params
+        .q
+        .as_deref()
+        .and_then(db::library_search::prefix_tsquery);
+
+    let sort_name = match params.sort.as_deref() {
+        Some(sort) => sort,
+        None if tsquery.is_some() => "relevance",
+        None => "added",
+    };
+    let (sort_name, sort) = LibrarySort::ALL
+        .into_iter()
+        .find(|(name, _)| *name == sort_name)
+        .ok_or_else(|| {
+            ApiError::InvalidInput(format!(
+                "sort must be one of: {}",
+                LibrarySort::ALL.map(|(name, _)| name).join(", ")
+            ))
+        })?;
+    let descending = match params.order.as_deref() {
+        None => sort.default_descending(),
+        Some("asc") => false,
+        Some("desc") => true,
+        Some(_) => {
+            return Err(ApiError::InvalidInput(
+                "order must be 'asc' or 'desc'".to_string(),
+            ))
+        }
+    };
+
+    if let Some(media_type) = params.media_type.as_deref() {
+        if !matches!(media_type, "movie" | "tv") {
+            return Err(ApiError::InvalidInput(
+                "type must be 'movie' or 'tv'".to_string(),
+            ));
+        }
+    }
+    let rating_source = params.rating_source.as_deref().unwrap_or("tmdb");
+    if !db::ratings::is_known_source(rating_source) {
+        return Err(ApiError::InvalidInput(format!(
+            "Unknown rating source '{}'",
+            rating_source
+        )));
+    }
+    // Files store the resolution as "<height>p"
+    let resolution = match params.resolution.as_deref() {
+        None => None,
+        Some(value) => Some(
+            quality::parse_resolution(value)
+                .map(|height| format!("{}p", height))
+                .ok_or_else(|| ApiError::InvalidInput(format!("Unknown resolution '{}'", value)))?,
+        ),
+    };
+
+    let filters = LibraryFilters {
+        tsquery,
+        media_type: params.media_type,
+        genres: params
+            .genres
+            .as_deref()
+            .unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|g| !g.is_empty())
+            .map(str::to_string)
+            .collect(),
+        year_min: params.year_min,
+        year_max: params.year_max,
+        rating_source: rating_source.to_string(),
+        rating_min: params.min_rating,
+        runtime_min: params.runtime_min,
+        runtime_max: params.runtime_max,
+        has_file: params.has_file,
+        resolution,
+        watched: params.watched,
+        favorite: params.favorite,
+        watchlist: params.watchlist,
+        user_id,
+    };
+
+    let media =
+        db::library_search::search(&state.db_pool, &filters, sort, descending, per_page, offset)
+            .await?;
+    let facets = db::library_search::facets(&state.db_pool, &filters).await?;
+
+    let media_ids: Vec<Uuid> = media.iter().map(|m| m.id).collect();
+    let mut ratings: HashMap<Uuid, Vec<MediaRating>> = HashMap::new();
+    for rating in db::ratings::list_for_media(&state.db_pool, &media_ids).await? {
+        ratings.entry(rating.media_id).or_default().push(rating);
+    }
+    let items = media
+        .into_iter()
+        .map(|media| MediaWithRatings {
+            ratings: ratings.remove(&media.id).unwrap_or_default(),
+            media,
+        })
+        .collect();
+
+    let total = facets.total;
+    let total_pages = if total == 0 {
+        0
+    } else {
+        (total as f64 / per_page as f64).ceil() as i64
+    };
+
+    Ok(Json(LibrarySearchResponse {
+        items,
+        total,
+        page,
+        per_page,
+        total_pages,
+        facets: LibraryFacets {
+            media_type: facets.media_type.0,
+            genres: facets.genres.0,
+            decades: facets.decades.0,
+            resolutions: facets.resolutions.0,
+            has_file: facets.has_file.0,
+        },
+        sort: sort_name,
+        order: if descending { "desc" } else { "asc" },
+        sort_options: LibrarySort::ALL.iter().map(|(name, _)| *name).collect(),
+    }))
+}
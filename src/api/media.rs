@@ -1,7 +1,9 @@
 use crate::{
     api::error::ApiError,
-    cache, db,
+    cache,
+    db::{self, ratings::MediaRating},
     models::{CreateMediaPayload, Media, UpdateMediaPayload},
+    workers::metadata::{self, RefreshError},
     AppState,
 };
 use axum::{
@@ -91,15 +93,27 @@ pub async fn create_media_handler(
     Ok((StatusCode::CREATED, Json(created_media)))
 }
 
+#[derive(Debug, Serialize)]
+pub struct MediaWithRatings {
+    #[serde(flatten)]
+    pub media: Media,
+    pub ratings: Vec<MediaRating>,
+}
+
+async fn with_ratings(state: &AppState, media: Media) -> Result<MediaWithRatings, ApiError> {
+    let ratings = db::ratings::list_for_media(&state.db_pool, &[media.id]).await?;
+    Ok(MediaWithRatings { media, ratings })
+}
+
 pub async fn get_media_handler(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-) -> Result<Json<Media>, ApiError> {
+) -> Result<Json<MediaWithRatings>, ApiError> {
     let cache_key = format!("media:{}", id);
 
     match cache::get_from_cache::<Media>(&state.redis_client, &cache_key).await {
         Ok(Some(media)) => {
-            return Ok(Json(media));
+            return Ok(Json(with_ratings(&state, media).await?));
         }
         Ok(None) => {}
         Err(e) => {
@@ -113,7 +127,7 @@ pub async fn get_media_handler(
         tracing::warn!("Redis cache error (write): {}", e);
     }
 
-    Ok(Json(media))
+    Ok(Json(with_ratings(&state, media).await?))
 }
 
 pub async fn list_media_handler(
@@ -190,3 +204,28 @@ pub async fn delete_media_handler(
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// POST /media/:id/refresh - Refresh a movie's or series' metadata and ratings now
+pub async fn refresh_metadata_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<MediaWithRatings>, ApiError> {
+    let media = db::media::get_media_by_id(&state.db_pool, id).await?;
+    let Some(tmdb_id) = media.tmdb_id.filter(|_| media.parent_id.is_none()) else {
+        return Err(ApiError::InvalidInput(
+            "Only movies and series with a TMDB id can be refreshed".to_string(),
+        ));
+    };
+
+    match metadata::refresh(&state, &media, tmdb_id).await {
+        Ok(media) => {
+            metadata::refresh_ratings(&state, &media).await?;
+            Ok(Json(with_ratings(&state, media).await?))
+        }
+        Err(RefreshError::RateLimited) => Err(ApiError::ServiceUnavailable(
+            "TMDB rate limit reached, try again shortly",
+        )),
+        Err(RefreshError::Tmdb(e)) => Err(ApiError::Internal(e.into())),
+        Err(RefreshError::Database(e)) => Err(e.into()),
+    }
+}
@@ -1,19 +1,30 @@
+pub mod anime;
 pub mod auth;
+pub mod calendar;
 pub mod collections;
 pub mod downloads;
+pub mod duplicates;
 pub mod enrichment;
 pub mod error;
 pub mod files;
 pub mod health;
+pub mod hls;
+pub mod images;
 pub mod library;
+pub mod library_scan;
+pub mod library_search;
 pub mod media;
 pub mod media_ref;
 pub mod metrics;
+pub mod nfo;
+pub mod quality_profile;
 pub mod recommendations;
+pub mod retention;
 pub mod search;
 pub mod security;
 pub mod storage;
 pub mod streaming;
+pub mod subtitles;
 pub mod tasks;
 pub mod tmdb;
 pub mod tracking;
@@ -0,0 +1,37 @@
+use crate::{api::error::ApiError, db, workers::nfo, AppState};
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, Default)]
+pub struct RegenerateNfoPayload {
+    /// Only this movie or series (with its episodes); the whole library when absent
+    pub media_id: Option<Uuid>,
+    /// Also overwrite NFO files and artwork not written by Sokoul
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// POST /library/nfo - Regenerate NFO files and artwork next to media files (background task)
+pub async fn regenerate_nfo_handler(
+    State(state): State<Arc<AppState>>,
+    payload: Option<Json<RegenerateNfoPayload>>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let Json(payload) = payload.unwrap_or_default();
+    if let Some(media_id) = payload.media_id {
+        // 404 for unknown media rather than an empty task.
+        db::media::get_media_by_id(&state.db_pool, media_id).await?;
+    }
+
+    let task_id = nfo::start_export(&state, payload.media_id, payload.force).await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "task_id": task_id,
+            "media_id": payload.media_id,
+            "force": payload.force,
+        })),
+    ))
+}
@@ -0,0 +1,159 @@
+use crate::{
+    api::auth::extract_user_id,
+    api::error::ApiError,
+    db::{
+        self,
+        quality_profiles::{QualityProfileRow, UpsertQualityProfile},
+    },
+    utils::quality::{parse_resolution, QualityProfile},
+    AppState,
+};
+use axum::{extract::State, http::HeaderMap, Json};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+fn get_user_id(headers: &HeaderMap) -> Uuid {
+    extract_user_id(headers)
+        .unwrap_or_else(|| Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateQualityProfilePayload {
+    pub min_resolution: String,
+    pub max_resolution: Option<String>,
+    #[serde(default = "default_min_seeders")]
+    pub min_seeders: i32,
+    pub max_size_gb: Option<f64>,
+    pub rejected_terms: Option<Vec<String>>,
+    pub cutoff_score: Option<i32>,
+    #[serde(default)]
+    pub allow_pre_digital: bool,
+    /// ISO 3166-1 country code, e.g. "FR"
+    pub release_region: Option<String>,
+}
+
+fn default_min_seeders() -> i32 {
+    1
+}
+
+/// GET /quality-profile - Current user's quality profile (defaults if never saved)
+pub async fn get_quality_profile_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let user_id = get_user_id(&headers);
+    let row = db::quality_profiles::get_profile(&state.db_pool, user_id).await?;
+
+    Ok(Json(match row {
+        Some(row) => profile_json(&row),
+        None => {
+            let defaults = QualityProfile::default();
+            serde_json::json!({
+                "min_resolution": format!("{}p", defaults.min_resolution),
+                "max_resolution": null,
+                "min_seeders": defaults.min_seeders,
+                "max_size_gb": null,
+                "rejected_terms": defaults.rejected_terms,
+                "cutoff_score": defaults.cutoff_score,
+                "allow_pre_digital": defaults.allow_pre_digital,
+                "release_region": null,
+                "is_default": true,
+            })
+        }
+    }))
+}
+
+/// PUT /quality-profile - Create or replace the current user's quality profile
+pub async fn update_quality_profile_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateQualityProfilePayload>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let user_id = get_user_id(&headers);
+
+    let min = parse_resolution(&payload.min_resolution).ok_or_else(|| {
+        ApiError::InvalidInput(format!(
+            "Unknown min_resolution: {}",
+            payload.min_resolution
+        ))
+    })?;
+    if let Some(ref max_resolution) = payload.max_resolution {
+        let max = parse_resolution(max_resolution).ok_or_else(|| {
+            ApiError::InvalidInput(format!("Unknown max_resolution: {}", max_resolution))
+        })?;
+        if max < min {
+            return Err(ApiError::InvalidInput(
+                "max_resolution must not be below min_resolution".to_string(),
+            ));
+        }
+    }
+    if payload.min_seeders < 0 {
+        return Err(ApiError::InvalidInput(
+            "min_seeders must not be negative".to_string(),
+        ));
+    }
+
+    let cutoff_score = payload
+        .cutoff_score
+        .unwrap_or_else(|| QualityProfile::default().cutoff_score);
+    if !(0..=100).contains(&cutoff_score) {
+        return Err(ApiError::InvalidInput(
+            "cutoff_score must be between 0 and 100".to_string(),
+        ));
+    }
+
+    let release_region = payload
+        .release_region
+        .as_deref()
+        .map(|r| r.trim().to_uppercase())
+        .filter(|r| !r.is_empty());
+    if let Some(ref region) = release_region {
+        if region.len() != 2 || !region.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(ApiError::InvalidInput(format!(
+                "release_region must be a two-letter country code, got '{}'",
+                region
+            )));
+        }
+    }
+
+    let max_size_bytes = payload
+        .max_size_gb
+        .map(|gb| (gb * 1024.0 * 1024.0 * 1024.0) as i64);
+    let rejected_terms = payload
+        .rejected_terms
+        .unwrap_or_else(|| QualityProfile::default().rejected_terms);
+
+    let row = db::quality_profiles::upsert_profile(
+        &state.db_pool,
+        user_id,
+        &UpsertQualityProfile {
+            min_resolution: &payload.min_resolution,
+            max_resolution: payload.max_resolution.as_deref(),
+            min_seeders: payload.min_seeders,
+            max_size_bytes,
+            rejected_terms: &rejected_terms,
+            cutoff_score,
+            allow_pre_digital: payload.allow_pre_digital,
+            release_region: release_region.as_deref(),
+        },
+    )
+    .await?;
+
+    Ok(Json(profile_json(&row)))
+}
+
+fn profile_json(row: &QualityProfileRow) -> serde_json::Value {
+    serde_json::json!({
+        "min_resolution": row.min_resolution,
+        "max_resolution": row.max_resolution,
+        "min_seeders": row.min_seeders,
+        "max_size_gb": row.max_size_bytes.map(|b| b as f64 / (1024.0 * 1024.0 * 1024.0)),
+        "rejected_terms": row.rejected_terms,
+        "cutoff_score": row.cutoff_score,
+        "allow_pre_digital": row.allow_pre_digital,
+        "release_region": row.release_region,
+        "is_default": false,
+        "updated_at": row.updated_at,
+    })
+}
@@ -0,0 +1,107 @@
+use crate::{
+    api::{auth::extract_user_id, error::ApiError},
+    db::{
+        self,
+        retention::{RetentionPolicy, RetentionPolicyPayload},
+    },
+    workers::retention::{self, RetentionReport, RetentionRun},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+fn validate(payload: &RetentionPolicyPayload) -> Result<(), ApiError> {
+    if payload.name.trim().is_empty() {
+        return Err(ApiError::InvalidInput(
+            "Policy name is required".to_string(),
+        ));
+    }
+    if let Some(media_type) = &payload.media_type {
+        if !matches!(media_type.as_str(), "movie" | "tv") {
+            return Err(ApiError::InvalidInput(
+                "media_type must be 'movie' or 'tv'".to_string(),
+            ));
+        }
+    }
+    if !matches!(payload.watched_by.as_str(), "all" | "any") {
+        return Err(ApiError::InvalidInput(
+            "watched_by must be 'all' or 'any'".to_string(),
+        ));
+    }
+    if payload.min_days_since_watched < 0 {
+        return Err(ApiError::InvalidInput(
+            "min_days_since_watched cannot be negative".to_string(),
+        ));
+    }
+    if payload.free_space_below_gb.is_some_and(|gb| gb <= 0) {
+        return Err(ApiError::InvalidInput(
+            "free_space_below_gb must be positive".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// GET /retention/policies - List retention policies
+pub async fn list_policies_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<RetentionPolicy>>, ApiError> {
+    Ok(Json(db::retention::list_policies(&state.db_pool).await?))
+}
+
+/// POST /retention/policies - Create a retention policy
+pub async fn create_policy_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RetentionPolicyPayload>,
+) -> Result<(StatusCode, Json<RetentionPolicy>), ApiError> {
+    validate(&payload)?;
+    let policy = db::retention::create_policy(&state.db_pool, &payload).await?;
+    Ok((StatusCode::CREATED, Json(policy)))
+}
+
+/// PUT /retention/policies/:id - Replace a retention policy
+pub async fn update_policy_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<RetentionPolicyPayload>,
+) -> Result<Json<RetentionPolicy>, ApiError> {
+    validate(&payload)?;
+    Ok(Json(
+        db::retention::update_policy(&state.db_pool, id, &payload).await?,
+    ))
+}
+
+/// DELETE /retention/policies/:id - Delete a retention policy
+pub async fn delete_policy_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    if db::retention::delete_policy(&state.db_pool, id).await? == 0 {
+        return Err(ApiError::NotFound(format!(
+            "Retention policy {} not found",
+            id
+        )));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /retention/report - Dry run: files the enabled policies would delete now
+pub async fn report_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<RetentionReport>, ApiError> {
+    Ok(Json(retention::evaluate(&state.db_pool).await?))
+}
+
+/// POST /retention/run - Apply the enabled policies now
+pub async fn run_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<RetentionRun>, ApiError> {
+    let report = retention::evaluate(&state.db_pool).await?;
+    let run = retention::apply(&state.db_pool, &report, extract_user_id(&headers)).await;
+    Ok(Json(run))
+}
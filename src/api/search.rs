@@ -5,7 +5,8 @@ use crate::{
     events::{self, SearchRequestedPayload},
     models::{ApiSearchPayload, SearchResult},
     providers::{
-        jackett::JackettProvider, prowlarr::ProwlarrProvider, ProviderRegistry, SearchProvider,
+        jackett::JackettProvider, prowlarr::ProwlarrProvider, search_cache, ProviderRegistry,
+        SearchProvider,
     },
     AppState,
 };
@@ -47,6 +48,7 @@ pub async fn trigger_search_handler(
         tmdb_id: None,
         season: None,
         episode: None,
+        refresh: payload.refresh,
     };
 
     let payload_bytes = serde_json::to_vec(&event_payload).map_err(|e| {
@@ -80,6 +82,9 @@ pub async fn get_search_results_handler(
 pub struct DirectSearchPayload {
     pub query: String,
     pub media_id: Uuid,
+    /// Bypass the indexer cache and force a fresh search
+    #[serde(default)]
+    pub refresh: bool,
 }
 
 /// POST /search/direct - Search Prowlarr/Jackett directly and return results immediately
@@ -101,26 +106,15 @@ pub async fn direct_search_handler(
     );
 
     // Build provider registry on-the-fly
-    let mut registry = ProviderRegistry::new();
-
-    if !CONFIG.prowlarr_url.is_empty() && !CONFIG.prowlarr_api_key.is_empty() {
-        registry.register(Box::new(ProwlarrProvider::new(
-            CONFIG.prowlarr_api_key.clone(),
-            CONFIG.prowlarr_url.clone(),
-            state.flaresolverr_client.clone(),
-        )));
-    }
-
-    if !CONFIG.jackett_url.is_empty() && !CONFIG.jackett_api_key.is_empty() {
-        registry.register(Box::new(JackettProvider::new(
-            CONFIG.jackett_api_key.clone(),
-            CONFIG.jackett_url.clone(),
-            state.flaresolverr_client.clone(),
-        )));
-    }
+    let registry = ProviderRegistry::with_indexers(
+        state.redis_client.clone(),
+        state.flaresolverr_client.clone(),
+    );
 
     // Search all providers directly
-    let mut sources = registry.search_all(&query, "movie", None).await;
+    let mut sources = registry
+        .search_all(&query, "movie", None, payload.refresh)
+        .await;
 
     // Keep response fast: sort by seeders desc and cap results
     sources.sort_by_key(|s| std::cmp::Reverse(s.seeders.unwrap_or(0)));
@@ -154,6 +148,8 @@ pub async fn direct_search_handler(
 pub struct SseSearchQuery {
     pub query: String,
     pub media_id: Uuid,
+    #[serde(default)]
+    pub refresh: bool,
 }
 
 /// GET /search/stream?query=...&media_id=... — SSE endpoint that streams results as they arrive
@@ -169,6 +165,7 @@ pub async fn stream_search_handler(
     }
 
     let media_id = params.media_id;
+    let refresh = params.refresh;
 
     // Build providers list
     let mut providers: Vec<(String, Box<dyn SearchProvider>)> = Vec::new();
@@ -206,13 +203,16 @@ pub async fn stream_search_handler(
         let mut total_results = 0usize;
 
         for (name, provider) in providers {
-            let search_result = tokio::time::timeout(
+            let search_result = search_cache::cached_search(
+                Some(&state.redis_client),
+                provider.as_ref(),
+                &query,
                 provider_timeout,
-                provider.search(&query),
+                refresh,
             ).await;
 
             match search_result {
-                Ok(Ok(mut results)) => {
+                Ok(mut results) => {
                     results.sort_by_key(|s| std::cmp::Reverse(s.seeders.unwrap_or(0)));
                     results.truncate(100);
                     let count = results.len();
@@ -229,18 +229,12 @@ pub async fn stream_search_handler(
                         }).to_string()
                     ));
                 }
-                Ok(Err(e)) => {
+                Err(e) => {
                     tracing::warn!("SSE search provider '{}' error: {}", name, e);
                     yield Ok(Event::default().event("provider_error").data(
                         json!({"provider": &name, "error": e.to_string()}).to_string()
                     ));
                 }
-                Err(_) => {
-                    tracing::warn!("SSE search provider '{}' timed out", name);
-                    yield Ok(Event::default().event("provider_error").data(
-                        json!({"provider": &name, "error": "timeout"}).to_string()
-                    ));
-                }
             }
         }
 
@@ -1,7 +1,27 @@
-use crate::{api::error::ApiError, config::CONFIG, AppState};
-use axum::{extract::State, Json};
-use serde::Serialize;
-use std::sync::Arc;
+use crate::{
+    api::error::ApiError,
+    config::CONFIG,
+    db::{
+        self,
+        media_files::{MediaUsage, TypeUsage},
+    },
+    models::MediaFile,
+    utils::storage::{self, DirUsage},
+    workers::library_scan,
+    AppState,
+};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use uuid::Uuid;
 
 #[derive(Debug, Serialize)]
 pub struct StorageInfo {
@@ -12,21 +32,87 @@ pub struct StorageInfo {
     pub usage_percent: f64,
     pub files_count: u64,
     pub total_media_size_bytes: u64,
+    /// Download directory and library roots, counted recursively
+    pub roots: Vec<RootUsage>,
+    pub volumes: Vec<VolumeUsage>,
+    pub by_media_type: Vec<TypeUsage>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct RootUsage {
+    pub path: String,
+    #[serde(flatten)]
+    pub usage: DirUsage,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VolumeUsage {
+    pub mount_point: String,
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+    /// Recorded media files stored on this volume
+    pub media_files: u64,
+    pub media_bytes: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrphanFile {
+    pub path: String,
+    pub size_bytes: u64,
+    /// Waiting in the library scan review queue
+    pub pending_review: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrphanRow {
+    pub id: Uuid,
+    pub media_id: Uuid,
+    pub file_path: String,
+    pub file_size: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrphanReport {
+    /// Videos under the storage roots that no `media_files` row points to
+    pub files: Vec<OrphanFile>,
+    /// `media_files` rows whose file is gone from disk
+    pub rows: Vec<OrphanRow>,
+    pub reclaimable_bytes: u64,
+    /// Must be sent back to clean up this exact report
+    pub confirm_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CleanupPayload {
+    pub confirm_token: String,
+    /// Orphan files to delete from disk
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// Orphan rows to delete
+    #[serde(default)]
+    pub rows: Vec<Uuid>,
+}
+
+/// GET /storage - Disk usage of the download directory, library roots, volumes and media types
 pub async fn get_storage_handler(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<StorageInfo>, ApiError> {
     let download_dir = CONFIG.download_dir.clone();
 
     let disks = sysinfo::Disks::new_with_refreshed_list();
-
-    let download_path = std::path::Path::new(&download_dir);
-    let (total, free) = disks
+    let mount_points: Vec<PathBuf> = disks
         .iter()
-        .filter(|d| download_path.starts_with(d.mount_point()))
-        .max_by_key(|d| d.mount_point().as_os_str().len())
-        .map(|d| (d.total_space(), d.available_space()))
+        .map(|d| d.mount_point().to_path_buf())
+        .collect();
+
+    let (total, free) = storage::volume_of(Path::new(&download_dir), &mount_points)
+        .map(|i| (disks[i].total_space(), disks[i].available_space()))
         .unwrap_or((0, 0));
 
     let used = total.saturating_sub(free);
@@ -36,13 +122,51 @@ pub async fn get_storage_handler(
         0.0
     };
 
-    let dir_clone = download_dir.clone();
-    let (files_count, total_media_size) = count_dir_stats(&dir_clone).await;
+    let roots = storage::roots();
+    let roots = tokio::task::spawn_blocking(move || {
+        roots
+            .into_iter()
+            .map(|root| RootUsage {
+                usage: storage::dir_usage(&root),
+                path: root.to_string_lossy().to_string(),
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|_| ApiError::InternalServerError)?;
+    let download_usage = roots.first().map(|r| r.usage).unwrap_or_default();
+
+    let files = db::media_files::list_all_files(&state.db_pool).await?;
+    let mut volumes: Vec<VolumeUsage> = disks
+        .iter()
+        .map(|d| VolumeUsage {
+            mount_point: d.mount_point().to_string_lossy().to_string(),
+            total_bytes: d.total_space(),
+            free_bytes: d.available_space(),
+            media_files: 0,
+            media_bytes: 0,
+        })
+        .collect();
+    for file in &files {
+        if let Some(i) = storage::volume_of(Path::new(&file.file_path), &mount_points) {
+            volumes[i].media_files += 1;
+            volumes[i].media_bytes += file.file_size.unwrap_or(0).max(0) as u64;
+        }
+    }
+    // Only the volumes that hold media or a storage root.
+    let root_volumes: HashSet<usize> = roots
+        .iter()
+        .filter_map(|r| storage::volume_of(Path::new(&r.path), &mount_points))
+        .collect();
+    let volumes = volumes
+        .into_iter()
+        .enumerate()
+        .filter(|(i, v)| v.media_files > 0 || root_volumes.contains(i))
+        .map(|(_, v)| v)
+        .collect();
 
-    let db_total: i64 = sqlx::query_scalar("SELECT COALESCE(SUM(file_size), 0) FROM media_files")
-        .fetch_one(&state.db_pool)
-        .await
-        .unwrap_or(0);
+    let by_media_type = db::media_files::usage_by_media_type(&state.db_pool).await?;
+    let db_total: i64 = by_media_type.iter().map(|t| t.bytes).sum();
 
     Ok(Json(StorageInfo {
         download_dir,
@@ -50,32 +174,176 @@ pub async fn get_storage_handler(
         used_bytes: used,
         free_bytes: free,
         usage_percent,
-        files_count,
-        total_media_size_bytes: total_media_size.max(db_total as u64),
+        files_count: download_usage.files,
+        total_media_size_bytes: download_usage.bytes.max(db_total as u64),
+        roots,
+        volumes,
+        by_media_type,
     }))
 }
 
-async fn count_dir_stats(dir: &str) -> (u64, u64) {
-    let path = std::path::PathBuf::from(dir);
-    if !path.exists() {
-        return (0, 0);
-    }
+/// GET /storage/media - Disk usage per movie or series, largest first
+pub async fn media_usage_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let per_page = query.per_page.unwrap_or(50).clamp(1, 200);
+    let page = query.page.unwrap_or(1).max(1);
+
+    let items: Vec<MediaUsage> =
+        db::media_files::usage_by_media(&state.db_pool, per_page, (page - 1) * per_page).await?;
+    let total = db::media_files::count_media_with_files(&state.db_pool).await?;
+
+    Ok(Json(serde_json::json!({
+        "items": items,
+        "total": total,
+        "page": page,
+        "per_page": per_page,
+    })))
+}
+
+async fn find_orphans(state: &AppState) -> Result<OrphanReport, ApiError> {
+    let files = db::media_files::list_all_files(&state.db_pool).await?;
+    let reviewing = db::library::known_paths(&state.db_pool).await?;
 
-    tokio::task::spawn_blocking(move || {
-        let mut count = 0u64;
-        let mut size = 0u64;
-        if let Ok(entries) = std::fs::read_dir(&path) {
-            for entry in entries.flatten() {
-                if let Ok(meta) = entry.metadata() {
-                    if meta.is_file() {
-                        count += 1;
-                        size += meta.len();
-                    }
+    let roots = storage::roots();
+    // Leave files still being written (downloads, copies) alone.
+    let settle = Duration::from_secs(CONFIG.library_watch_settle_secs.max(60));
+    let (orphan_files, orphan_rows) = tokio::task::spawn_blocking(move || {
+        let recorded: HashSet<PathBuf> = files
+            .iter()
+            .map(|f| storage::absolute(Path::new(&f.file_path)))
+            .collect();
+
+        let mut orphan_files = Vec::new();
+        for root in &roots {
+            for scanned in library_scan::collect_video_files(root) {
+                if recorded.contains(&scanned.path) {
+                    continue;
+                }
+                let settled = std::fs::metadata(&scanned.path)
+                    .and_then(|m| m.modified())
+                    .is_ok_and(|modified| {
+                        SystemTime::now()
+                            .duration_since(modified)
+                            .is_ok_and(|age| age >= settle)
+                    });
+                if !settled {
+                    continue;
                 }
+                let path = scanned.path.to_string_lossy().to_string();
+                orphan_files.push(OrphanFile {
+                    pending_review: reviewing.contains(&path),
+                    size_bytes: scanned.size,
+                    path,
+                });
             }
         }
-        (count, size)
+        orphan_files.sort_by(|a, b| a.path.cmp(&b.path));
+        orphan_files.dedup_by(|a, b| a.path == b.path);
+
+        let orphan_rows: Vec<MediaFile> = files
+            .into_iter()
+            .filter(|f| !Path::new(&f.file_path).exists())
+            .collect();
+        (orphan_files, orphan_rows)
     })
     .await
-    .unwrap_or((0, 0))
+    .map_err(|_| ApiError::InternalServerError)?;
+
+    let confirm_token = storage::confirm_token(
+        orphan_files.iter().map(|f| f.path.as_str()),
+        orphan_rows.iter().map(|r| r.id.to_string()),
+    );
+    Ok(OrphanReport {
+        reclaimable_bytes: orphan_files.iter().map(|f| f.size_bytes).sum(),
+        files: orphan_files,
+        rows: orphan_rows
+            .into_iter()
+            .map(|f| OrphanRow {
+                id: f.id,
+                media_id: f.media_id,
+                file_path: f.file_path,
+                file_size: f.file_size,
+            })
+            .collect(),
+        confirm_token,
+    })
+}
+
+/// GET /storage/orphans - Untracked videos on disk and file rows whose file is gone
+pub async fn orphans_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<OrphanReport>, ApiError> {
+    Ok(Json(find_orphans(&state).await?))
+}
+
+/// POST /storage/orphans/cleanup - Delete selected orphans of the report identified by `confirm_token`
+pub async fn cleanup_orphans_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CleanupPayload>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if payload.files.is_empty() && payload.rows.is_empty() {
+        return Err(ApiError::InvalidInput(
+            "Select the orphan files and/or rows to clean up".to_string(),
+        ));
+    }
+    let report = find_orphans(&state).await?;
+    if report.confirm_token != payload.confirm_token {
+        return Err(ApiError::InvalidInput(
+            "The orphan list changed since it was reviewed; fetch /storage/orphans again"
+                .to_string(),
+        ));
+    }
+
+    // Only what the report lists can be deleted, whatever else the request names.
+    let orphan_files: HashSet<&str> = report.files.iter().map(|f| f.path.as_str()).collect();
+    let orphan_rows: HashSet<Uuid> = report.rows.iter().map(|r| r.id).collect();
+    if let Some(path) = payload
+        .files
+        .iter()
+        .find(|p| !orphan_files.contains(p.as_str()))
+    {
+        return Err(ApiError::InvalidInput(format!(
+            "{} is not an orphan file",
+            path
+        )));
+    }
+    if let Some(id) = payload.rows.iter().find(|id| !orphan_rows.contains(id)) {
+        return Err(ApiError::InvalidInput(format!(
+            "{} is not an orphan row",
+            id
+        )));
+    }
+
+    let roots = storage::roots();
+    let mut deleted_files = 0;
+    let mut freed_bytes = 0u64;
+    let mut errors = Vec::new();
+    for path in &payload.files {
+        let path = Path::new(path);
+        let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        match tokio::fs::remove_file(path).await {
+            Ok(()) => {
+                deleted_files += 1;
+                freed_bytes += size;
+                if let Some(root) = storage::root_of(path, &roots) {
+                    storage::remove_empty_parents(path, root);
+                }
+                tracing::info!("Storage cleanup: deleted orphan file {}", path.display());
+            }
+            Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    let deleted_rows = db::media_files::delete_files(&state.db_pool, &payload.rows).await?;
+    if deleted_rows > 0 {
+        tracing::info!("Storage cleanup: deleted {} orphan file rows", deleted_rows);
+    }
+
+    Ok(Json(serde_json::json!({
+        "deleted_files": deleted_files,
+        "freed_bytes": freed_bytes,
+        "deleted_rows": deleted_rows,
+        "errors": errors,
+    })))
 }
@@ -0,0 +1,137 @@
+use crate::{
+    api::error::ApiError,
+    db::{self, subtitles::Subtitle},
+    remux::{
+        cache::{self, SegmentCache},
+        hls::{self, Rendition},
+    },
+    utils::{
+        language,
+        subtitles::{self, SubtitleFormat},
+        webvtt,
+    },
+    workers, AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use std::{fs::File, io::BufReader, sync::Arc};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct SubtitleQuery {
+    /// Seconds to delay (positive) or advance (negative) the subtitles by.
+    pub offset: Option<f64>,
+}
+
+fn subtitle_json(subtitle: &Subtitle) -> serde_json::Value {
+    serde_json::json!({
+        "id": subtitle.id,
+        "source": subtitle.source,
+        "format": subtitle.format,
+        "language": subtitle.language,
+        "language_name": subtitle.language.as_deref().and_then(language::display_name),
+        "title": subtitle.title,
+        "file_path": subtitle.file_path,
+        "track_number": subtitle.track_number,
+        "is_forced": subtitle.is_forced,
+        "is_sdh": subtitle.is_sdh,
+        "is_default": subtitle.is_default,
+        "convertible": SubtitleFormat::from_name(&subtitle.format).is_some(),
+        "url": format!("/api/files/{}/subtitles/{}", subtitle.media_file_id, subtitle.id),
+    })
+}
+
+/// GET /files/:file_id/subtitles - Sidecar and embedded subtitles of a file (rediscovered on each call)
+pub async fn list_subtitles_handler(
+    State(state): State<Arc<AppState>>,
+    Path(file_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let file = db::media_files::get_file_by_id(&state.db_pool, file_id).await?;
+    let subtitles = workers::subtitles::discover(&state.db_pool, &file).await?;
+
+    Ok(Json(serde_json::json!({
+        "file_id": file.id,
+        "subtitles": subtitles.iter().map(subtitle_json).collect::<Vec<_>>(),
+    })))
+}
+
+/// GET /files/:file_id/subtitles/:subtitle_id?offset= - Serve a subtitle as WebVTT, optionally shifted
+pub async fn subtitle_vtt_handler(
+    State(state): State<Arc<AppState>>,
+    Path((file_id, subtitle_id)): Path<(Uuid, Uuid)>,
+    Query(query): Query<SubtitleQuery>,
+) -> Result<Response, ApiError> {
+    let subtitle = db::subtitles::get_subtitle(&state.db_pool, subtitle_id).await?;
+    if subtitle.media_file_id != file_id {
+        return Err(ApiError::NotFound("Subtitle not found".into()));
+    }
+    let offset = query.offset.unwrap_or(0.0);
+    if !offset.is_finite() {
+        return Err(ApiError::InvalidInput(
+            "offset must be a number of seconds".into(),
+        ));
+    }
+    let offset_ms = (offset * 1000.0).round() as i64;
+
+    let format = SubtitleFormat::from_name(&subtitle.format).ok_or_else(|| {
+        ApiError::UnsupportedMedia(format!(
+            "{} subtitles are images and cannot be converted to WebVTT",
+            subtitle.format.to_uppercase()
+        ))
+    })?;
+
+    let vtt = match (&subtitle.file_path, subtitle.track_number) {
+        (Some(path), _) => {
+            let bytes = tokio::fs::read(path)
+                .await
+                .map_err(|_| ApiError::NotFound("Subtitle file not found on disk".into()))?;
+            subtitles::to_webvtt(format, &bytes, offset_ms)
+        }
+        (None, Some(track)) => {
+            let file = db::media_files::get_file_by_id(&state.db_pool, file_id).await?;
+            let metadata = tokio::fs::metadata(&file.file_path)
+                .await
+                .map_err(|_| ApiError::NotFound("File not found on disk".into()))?;
+            let track = track as u64;
+            // Shares the extraction cached for the HLS subtitle rendition.
+            let key = format!(
+                "{}/{}/subtitles.vtt",
+                cache::file_key(file.id, &metadata),
+                Rendition::Subtitles(track)
+            );
+            let extracted = tokio::task::spawn_blocking(move || {
+                let cache = SegmentCache::from_config();
+                if let Some(data) = cache.get(&key) {
+                    return Ok(String::from_utf8_lossy(&data).into_owned());
+                }
+                let reader = BufReader::new(File::open(&file.file_path)?);
+                let vtt = hls::subtitles(reader, track)?;
+                if let Err(e) = cache.put(&key, vtt.as_bytes()) {
+                    tracing::warn!("Unable to cache subtitles {}: {}", key, e);
+                }
+                Ok::<_, crate::remux::RemuxError>(vtt)
+            })
+            .await
+            .map_err(|_| ApiError::InternalServerError)??;
+
+            let mut cues = subtitles::parse_vtt(&extracted);
+            subtitles::shift(&mut cues, offset_ms);
+            webvtt::render(&cues, &[])
+        }
+        (None, None) => return Err(ApiError::InternalServerError),
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/vtt; charset=utf-8"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        vtt,
+    )
+        .into_response())
+}
@@ -1,13 +1,290 @@
-use axum::{http::StatusCode, response::IntoResponse};
+use crate::{
+    api::auth::extract_user_id,
+    api::error::ApiError,
+    config::CONFIG,
+    db::{self, series::SEASON_MODES},
+    models::CreateTaskPayload,
+    providers::ProviderRegistry,
+    workers::series,
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
 
-pub async fn enable_tracking_handler() -> impl IntoResponse {
-    (StatusCode::OK, "Tracking enabled (placeholder)")
+fn get_user_id(headers: &HeaderMap) -> Uuid {
+    extract_user_id(headers)
+        .unwrap_or_else(|| Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap())
 }
 
-pub async fn disable_tracking_handler() -> impl IntoResponse {
-    (StatusCode::OK, "Tracking disabled (placeholder)")
+#[derive(Debug, Deserialize)]
+pub struct EnableTrackingPayload {
+    #[serde(default = "default_mode")]
+    pub mode: String,
 }
 
-pub async fn get_tracking_handler() -> impl IntoResponse {
-    (StatusCode::OK, "Get tracking status (placeholder)")
+#[derive(Debug, Deserialize)]
+pub struct SeasonModePayload {
+    pub mode: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompletenessQuery {
+    /// Re-read the season and episode listings from TMDB even if they were
+    /// synced recently.
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+fn default_mode() -> String {
+    "future".to_string()
+}
+
+fn validate_mode(mode: &str) -> Result<(), ApiError> {
+    if SEASON_MODES.contains(&mode) {
+        Ok(())
+    } else {
+        Err(ApiError::InvalidInput(format!(
+            "Invalid mode '{}'. Allowed: {}",
+            mode,
+            SEASON_MODES.join(", ")
+        )))
+    }
+}
+
+/// POST /media/:id/tracking - Monitor a series and sync its episodes
+pub async fn enable_tracking_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<EnableTrackingPayload>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    validate_mode(&payload.mode)?;
+
+    let media = db::media::get_media_by_id(&state.db_pool, id).await?;
+    if media.media_type != "tv" {
+        return Err(ApiError::InvalidInput(
+            "Only TV series can be monitored".to_string(),
+        ));
+    }
+
+    db::series::monitor_series(&state.db_pool, id, get_user_id(&headers), &payload.mode).await?;
+
+    // Populate the episode list right away instead of waiting for the next cycle.
+    if let Some(monitored) = db::series::get_monitored_series(&state.db_pool, id).await? {
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = series::sync_series(&state, &monitored).await {
+                tracing::warn!("Initial sync failed for '{}': {}", monitored.title, e);
+            }
+        });
+    }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "media_id": id,
+            "monitored": true,
+            "mode": payload.mode,
+        })),
+    ))
+}
+
+/// DELETE /media/:id/tracking - Stop monitoring a series (episodes are kept)
+pub async fn disable_tracking_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    if db::series::unmonitor_series(&state.db_pool, id).await? == 0 {
+        return Err(ApiError::NotFound("Series is not monitored".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /media/:id/tracking - Monitoring status with per-season modes
+pub async fn get_tracking_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let Some(monitored) = db::series::get_monitored_series(&state.db_pool, id).await? else {
+        return Ok(Json(serde_json::json!({
+            "media_id": id,
+            "monitored": false,
+        })));
+    };
+    let seasons = db::series::list_seasons(&state.db_pool, id).await?;
+
+    Ok(Json(serde_json::json!({
+        "media_id": id,
+        "monitored": true,
+        "default_mode": monitored.default_mode,
+        "last_synced_at": monitored.last_synced_at,
+        "seasons": seasons,
+    })))
+}
+
+/// PUT /media/:id/tracking/seasons/:season - Set a season's monitoring mode
+pub async fn set_season_mode_handler(
+    State(state): State<Arc<AppState>>,
+    Path((id, season)): Path<(Uuid, i32)>,
+    Json(payload): Json<SeasonModePayload>,
+) -> Result<StatusCode, ApiError> {
+    validate_mode(&payload.mode)?;
+    if db::series::get_monitored_series(&state.db_pool, id)
+        .await?
+        .is_none()
+    {
+        return Err(ApiError::NotFound("Series is not monitored".to_string()));
+    }
+
+    db::series::set_season_mode(&state.db_pool, id, season, &payload.mode).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_series(state: &AppState, id: Uuid) -> Result<crate::models::Media, ApiError> {
+    let media = db::media::get_media_by_id(&state.db_pool, id).await?;
+    if media.media_type != "tv" {
+        return Err(ApiError::InvalidInput(
+            "Only TV series have episodes".to_string(),
+        ));
+    }
+    Ok(media)
+}
+
+/// GET /media/:id/completeness - Missing, downloaded and unaired episodes per season
+pub async fn completeness_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<CompletenessQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let media = get_series(&state, id).await?;
+
+    let mut seasons = db::series::season_completeness(&state.db_pool, id).await?;
+    let fresh = db::series::episodes_synced_within(
+        &state.db_pool,
+        id,
+        CONFIG.series_sync_interval_secs as f64,
+    )
+    .await?;
+    // A stale listing is re-synced when possible; an explicit refresh or a
+    // missing listing must be.
+    let must_sync = query.refresh || seasons.is_empty();
+    if must_sync || !fresh {
+        let synced = series::sync_episodes(
+            &state,
+            id,
+            &media.title,
+            media.tmdb_id,
+            media.imdb_id.as_deref(),
+        )
+        .await;
+        match synced {
+            Ok(_) => seasons = db::series::season_completeness(&state.db_pool, id).await?,
+            Err(e) if must_sync => return Err(e.into()),
+            Err(e) => tracing::warn!(
+                "Completeness: keeping the stored episode listing of '{}': {}",
+                media.title,
+                e
+            ),
+        }
+    }
+
+    let total = |f: fn(&db::series::SeasonCompleteness) -> i64| seasons.iter().map(f).sum::<i64>();
+    Ok(Json(serde_json::json!({
+        "media_id": id,
+        "title": media.title,
+        "total": total(|s| s.total),
+        "downloaded": total(|s| s.downloaded),
+        "missing": total(|s| s.missing),
+        "unaired": total(|s| s.unaired),
+        "seasons": seasons,
+    })))
+}
+
+/// POST /media/:id/missing/search - Queue searches for every missing aired episode
+pub async fn search_missing_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let media = get_series(&state, id).await?;
+    let episodes =
+        db::series::list_missing_episodes(&state.db_pool, id, get_user_id(&headers)).await?;
+
+    if episodes.is_empty() {
+        return Ok((
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "media_id": id,
+                "queued": 0,
+                "task_id": null,
+            })),
+        ));
+    }
+
+    let task = db::tasks::create_task(
+        &state.db_pool,
+        &CreateTaskPayload {
+            task_type: "episode_search".to_string(),
+            payload: Some(serde_json::json!({
+                "series_id": id.to_string(),
+                "title": media.title,
+                "episodes": episodes.len(),
+            })),
+        },
+    )
+    .await?;
+
+    let queued = episodes.len();
+    let task_id = task.id;
+    let state = state.clone();
+    tokio::spawn(async move {
+        let _ = db::tasks::update_task_status(&state.db_pool, task_id, "running", None).await;
+        let registry = ProviderRegistry::with_indexers(
+            state.redis_client.clone(),
+            state.flaresolverr_client.clone(),
+        );
+
+        // One episode at a time to stay within indexer rate limits.
+        let mut found = 0;
+        for (i, episode) in episodes.iter().enumerate() {
+            match series::search_episode(&state, &registry, episode).await {
+                Ok(()) => found += 1,
+                Err(e) => tracing::warn!(
+                    "Missing search: '{}' S{:02}E{:02}: {}",
+                    episode.series_title,
+                    episode.season_number,
+                    episode.episode_number,
+                    e
+                ),
+            }
+            let progress = Decimal::from((i + 1) * 100) / Decimal::from(episodes.len());
+            let _ = db::tasks::update_task_progress(&state.db_pool, task_id, progress).await;
+        }
+
+        let _ = db::tasks::complete_task(
+            &state.db_pool,
+            task_id,
+            Some(serde_json::json!({
+                "searched": episodes.len(),
+                "downloads_queued": found,
+            })),
+        )
+        .await;
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "media_id": id,
+            "queued": queued,
+            "task_id": task_id,
+        })),
+    ))
 }
@@ -4,6 +4,7 @@ use crate::{
     api::media_ref::{find_media_id_by_tmdb, resolve_media_id, MediaReferenceInput},
     db,
     models::AddWatchlistPayload,
+    utils::quality::parse_resolution,
     AppState,
 };
 use axum::{
@@ -36,6 +37,32 @@ pub struct WatchlistListItem {
     pub title: Option<String>,
     pub poster_url: Option<String>,
     pub added_at: Option<DateTime<Utc>>,
+    pub auto_download: Option<bool>,
+    pub quality_min: Option<String>,
+    pub auto_status: Option<String>,
+    pub auto_message: Option<String>,
+    pub last_searched_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWatchlistPayload {
+    pub auto_download: bool,
+    /// A resolution, or "none" to use the quality profile's minimum again;
+    /// unchanged when absent
+    pub quality_min: Option<String>,
+}
+
+/// Value of `quality_min` clearing the entry's own minimum.
+const QUALITY_MIN_CLEAR: &str = "none";
+
+fn validate_quality_min(quality_min: &str) -> Result<(), ApiError> {
+    match parse_resolution(quality_min) {
+        Some(_) => Ok(()),
+        None => Err(ApiError::InvalidInput(format!(
+            "Unknown quality_min: {}",
+            quality_min
+        ))),
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -71,13 +98,16 @@ pub async fn add_to_watchlist_handler(
         },
     )
     .await?;
+    if let Some(ref quality_min) = payload.quality_min {
+        validate_quality_min(quality_min)?;
+    }
 
     db::watchlist::add_to_watchlist(
         &state.db_pool,
         user_id,
         media_id,
         payload.auto_download,
-        &payload.quality_min,
+        payload.quality_min.as_deref(),
     )
     .await
     .map_err(ApiError::Database)?;
@@ -97,6 +127,38 @@ pub async fn remove_from_watchlist_handler(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// PATCH /watchlist/:media_id - Toggle automatic acquisition for an entry
+pub async fn update_watchlist_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(media_id): Path<Uuid>,
+    Json(payload): Json<UpdateWatchlistPayload>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = get_user_id(&headers);
+    let quality_min = match payload.quality_min.as_deref() {
+        None => None,
+        Some(QUALITY_MIN_CLEAR) => Some(None),
+        Some(quality_min) => {
+            validate_quality_min(quality_min)?;
+            Some(Some(quality_min))
+        }
+    };
+
+    let updated = db::watchlist::update_auto_download(
+        &state.db_pool,
+        user_id,
+        media_id,
+        payload.auto_download,
+        quality_min,
+    )
+    .await
+    .map_err(ApiError::Database)?;
+    if updated == 0 {
+        return Err(ApiError::NotFound("Watchlist entry not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// DELETE /watchlist/:tmdb_id/:media_type - Remove from watchlist (TMDB compatibility)
 pub async fn remove_from_watchlist_by_tmdb_handler(
     State(state): State<Arc<AppState>>,
@@ -131,6 +193,11 @@ pub async fn list_watchlist_handler(
         SELECT
             w.id AS watchlist_id,
             w.added_at,
+            w.auto_download,
+            w.quality_min,
+            w.auto_status,
+            w.auto_message,
+            w.last_searched_at,
             m.tmdb_id,
             m.media_type AS media_type_wl,
             m.title,
@@ -158,6 +225,11 @@ pub async fn list_watchlist_handler(
             title: row.get("title"),
             poster_url: row.get("poster_url"),
             added_at: row.get("added_at"),
+            auto_download: row.get("auto_download"),
+            quality_min: row.get("quality_min"),
+            auto_status: row.get("auto_status"),
+            auto_message: row.get("auto_message"),
+            last_searched_at: row.get("last_searched_at"),
         })
         .collect();
 
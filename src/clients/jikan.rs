@@ -121,8 +121,17 @@ impl JikanClient {
         query: &str,
         page: u32,
     ) -> Result<JikanResponse<Vec<JikanAnime>>, reqwest::Error> {
-        let url = format!("{}/anime?q={}&page={}&sfw=true", BASE_URL, query, page);
-        let resp = self.client.get(&url).send().await?;
+        let url = format!("{}/anime", BASE_URL);
+        let resp = self
+            .client
+            .get(&url)
+            .query(&[
+                ("q", query.to_string()),
+                ("page", page.to_string()),
+                ("sfw", "true".to_string()),
+            ])
+            .send()
+            .await?;
         if !resp.status().is_success() {
             return Ok(JikanResponse {
                 data: vec![],
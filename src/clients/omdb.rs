@@ -65,6 +65,9 @@ pub struct OmdbResponse {
     pub production: Option<String>,
     #[serde(rename = "Response")]
     pub response: Option<String>,
+    /// Set when `response` is "False", e.g. "Request limit reached!"
+    #[serde(rename = "Error")]
+    pub error: Option<String>,
 }
 
 impl OmdbClient {
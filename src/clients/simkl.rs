@@ -31,6 +31,18 @@ pub struct SimklShowDetails {
     pub runtime: Option<i32>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimklScore {
+    pub rating: Option<f64>,
+    pub votes: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimklRatings {
+    pub id: Option<i64>,
+    pub simkl: Option<SimklScore>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimklStreamSource {
     pub source: Option<String>,
@@ -140,6 +152,28 @@ impl SimklClient {
         }
     }
 
+    /// Simkl community rating of a movie or show, looked up by IMDb id
+    pub async fn ratings(&self, imdb_id: &str) -> Result<Option<SimklRatings>, String> {
+        let url = format!(
+            "{}/ratings?imdb={}&fields=simkl&client_id={}",
+            self.base_url, imdb_id, self.api_key
+        );
+
+        match self.client.get(&url).send().await {
+            Ok(resp) => {
+                if resp.status() == 200 {
+                    match resp.json::<SimklRatings>().await {
+                        Ok(ratings) => Ok(Some(ratings)),
+                        Err(_) => Ok(None),
+                    }
+                } else {
+                    Ok(None)
+                }
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
     pub async fn get_sources(&self, id: i64) -> Result<Vec<SimklStreamSource>, String> {
         let url = format!(
             "{}/movies/{}/watching?client_id={}",
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 const TMDB_API_BASE_URL: &str = "https://api.themoviedb.org/3";
-const TMDB_IMAGE_BASE: &str = "https://image.tmdb.org/t/p";
+pub const TMDB_IMAGE_BASE: &str = "https://image.tmdb.org/t/p";
 
 // ── Search Result (existing, extended) ──
 
@@ -21,6 +21,8 @@ pub struct TmdbSearchResult {
     pub backdrop_path: Option<String>,
     pub vote_average: Option<f64>,
     pub genre_ids: Option<Vec<i32>>,
+    #[serde(default)]
+    pub original_language: Option<String>,
 }
 
 impl TmdbSearchResult {
@@ -87,6 +89,9 @@ pub struct TmdbMovieDetail {
     pub budget: Option<i64>,
     pub revenue: Option<i64>,
     pub belongs_to_collection: Option<BelongsToCollection>,
+    /// Only present when requested with `append_to_response=alternative_titles`
+    #[serde(default)]
+    pub alternative_titles: Option<TmdbAlternativeTitles>,
 }
 
 // ── TV Details ──
@@ -96,6 +101,8 @@ pub struct TmdbTvDetail {
     pub id: i32,
     pub name: String,
     pub original_name: Option<String>,
+    #[serde(default)]
+    pub original_language: Option<String>,
     pub overview: Option<String>,
     pub poster_path: Option<String>,
     pub backdrop_path: Option<String>,
@@ -109,6 +116,34 @@ pub struct TmdbTvDetail {
     pub number_of_episodes: Option<i32>,
     pub status: Option<String>,
     pub seasons: Option<Vec<TmdbSeason>>,
+    /// Only present when requested with `append_to_response=external_ids`
+    #[serde(default)]
+    pub external_ids: Option<TmdbExternalIds>,
+    /// Only present when requested with `append_to_response=alternative_titles`
+    #[serde(default)]
+    pub alternative_titles: Option<TmdbAlternativeTitles>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TmdbExternalIds {
+    pub imdb_id: Option<String>,
+    pub tvdb_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TmdbAlternativeTitles {
+    /// `titles` for movies, `results` for series
+    #[serde(alias = "results", default)]
+    pub titles: Vec<TmdbAlternativeTitle>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TmdbAlternativeTitle {
+    #[serde(default)]
+    pub iso_3166_1: String,
+    pub title: String,
+    #[serde(rename = "type", default)]
+    pub kind: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -142,6 +177,10 @@ pub struct TmdbEpisode {
     pub air_date: Option<String>,
     pub vote_average: Option<f64>,
     pub runtime: Option<i32>,
+    #[serde(default)]
+    pub crew: Vec<TmdbCrewMember>,
+    #[serde(default)]
+    pub guest_stars: Vec<TmdbCastMember>,
 }
 
 // ── Credits ──
@@ -266,6 +305,12 @@ pub struct TmdbCollection {
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TmdbReleaseDateEntry {
     pub certification: String,
+    /// ISO 8601 timestamp, e.g. "2024-03-01T00:00:00.000Z".
+    #[serde(default)]
+    pub release_date: Option<String>,
+    /// 1 premiere, 2 limited theatrical, 3 theatrical, 4 digital, 5 physical, 6 TV.
+    #[serde(rename = "type", default)]
+    pub release_type: i32,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -478,9 +523,11 @@ impl TmdbClient {
 
     pub async fn movie_details(&self, id: i32) -> Result<TmdbMovieDetail, reqwest::Error> {
         let url = format!("{}/movie/{}", TMDB_API_BASE_URL, id);
+        let mut params = self.base_params();
+        params.push(("append_to_response", "alternative_titles".to_string()));
         self.client
             .get(&url)
-            .query(&self.base_params())
+            .query(&params)
             .send()
             .await?
             .error_for_status()?
@@ -492,9 +539,14 @@ impl TmdbClient {
 
     pub async fn tv_details(&self, id: i32) -> Result<TmdbTvDetail, reqwest::Error> {
         let url = format!("{}/tv/{}", TMDB_API_BASE_URL, id);
+        let mut params = self.base_params();
+        params.push((
+            "append_to_response",
+            "external_ids,alternative_titles".to_string(),
+        ));
         self.client
             .get(&url)
-            .query(&self.base_params())
+            .query(&params)
             .send()
             .await?
             .error_for_status()?
@@ -651,8 +703,11 @@ impl TmdbClient {
 
     // ── Certification ──
 
-    /// Fetch the content certification for a movie (prefers FR, falls back to US).
-    pub async fn movie_certification(&self, id: i32) -> Result<Option<String>, reqwest::Error> {
+    /// Fetch a movie's release dates, grouped by country.
+    pub async fn movie_release_dates(
+        &self,
+        id: i32,
+    ) -> Result<Vec<TmdbReleaseDateCountry>, reqwest::Error> {
         let url = format!("{}/movie/{}/release_dates", TMDB_API_BASE_URL, id);
         let resp = self
             .client
@@ -663,6 +718,14 @@ impl TmdbClient {
             .error_for_status()?
             .json::<TmdbReleaseDatesResponse>()
             .await?;
+        Ok(resp.results)
+    }
+
+    /// Fetch the content certification for a movie (prefers FR, falls back to US).
+    pub async fn movie_certification(&self, id: i32) -> Result<Option<String>, reqwest::Error> {
+        let resp = TmdbReleaseDatesResponse {
+            results: self.movie_release_dates(id).await?,
+        };
 
         // Priority: FR → US → any non-empty
         let pick = |code: &str| -> Option<String> {
@@ -6,7 +6,6 @@ pub struct Config {
     pub port: u16,
     #[allow(dead_code)]
     pub node_env: String,
-    #[allow(dead_code)]
     pub base_url: String,
     pub database_url: String,
     pub redis_url: String,
@@ -83,6 +82,58 @@ pub struct Config {
     pub rate_limit_rps: u64,
     // Indexer search timeout (seconds)
     pub indexer_search_timeout_secs: u64,
+    // Indexer response cache TTL (seconds, 0 disables caching)
+    pub indexer_cache_ttl_secs: u64,
+    // Watchlist auto-acquisition
+    pub watchlist_auto_interval_secs: u64,
+    pub watchlist_auto_batch_size: i64,
+    /// Queued downloads not imported after this long are searched again
+    pub watchlist_queued_timeout_secs: i64,
+    /// Entries nothing was found for wait this long before the next search
+    pub watchlist_not_found_retry_secs: i64,
+    // Monitored series
+    pub series_check_interval_secs: u64,
+    pub series_sync_interval_secs: u64,
+    pub episode_search_delay_hours: i32,
+    // Release calendar
+    pub calendar_refresh_interval_secs: u64,
+    pub release_region: String,
+    // Metadata refresh (workers/metadata.rs)
+    /// Airing series and unreleased titles
+    pub metadata_refresh_active_secs: u64,
+    /// Titles from this year or last year
+    pub metadata_refresh_recent_secs: u64,
+    pub metadata_refresh_secs: u64,
+    pub metadata_refresh_batch_size: i64,
+    /// OMDb, Trakt and Simkl ratings
+    pub ratings_refresh_secs: u64,
+    /// OMDb calls the metadata worker may make per day; the free key allows
+    /// 1,000, the rest is left to lookups from the API
+    pub omdb_daily_limit: u32,
+    // Library scanner
+    pub library_roots: Vec<String>,
+    pub library_match_threshold: f64,
+    pub library_watch_settle_secs: u64,
+    pub library_reconcile_interval_secs: u64,
+    // HLS packaging
+    pub hls_cache_dir: String,
+    pub hls_cache_max_mb: u64,
+    // Artwork cache
+    pub artwork_cache_dir: String,
+    pub artwork_cache_max_mb: u64,
+    pub artwork_prewarm_interval_secs: u64,
+    // Kodi / Jellyfin NFO export
+    pub nfo_export_on_import: bool,
+    // Subtitles
+    pub subtitle_languages: Vec<String>,
+    pub opensubtitles_url: String,
+    pub opensubtitles_api_key: String,
+    pub opensubtitles_username: String,
+    pub opensubtitles_password: String,
+    /// Subtitle downloads allowed per 24 hours (OpenSubtitles' free quota)
+    pub subtitle_daily_downloads: i64,
+    /// Pause between two files' subtitle downloads
+    pub subtitle_download_interval_secs: u64,
 }
 
 impl Config {
@@ -167,6 +218,125 @@ impl Config {
                 .unwrap_or_else(|_| "90".to_string())
                 .parse()
                 .unwrap_or(90),
+            indexer_cache_ttl_secs: env::var("INDEXER_CACHE_TTL_SECS")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .unwrap_or(600),
+            watchlist_auto_interval_secs: env::var("WATCHLIST_AUTO_INTERVAL_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .unwrap_or(3600),
+            watchlist_auto_batch_size: env::var("WATCHLIST_AUTO_BATCH_SIZE")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .unwrap_or(20),
+            watchlist_queued_timeout_secs: env::var("WATCHLIST_QUEUED_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "172800".to_string())
+                .parse()
+                .unwrap_or(172800),
+            watchlist_not_found_retry_secs: env::var("WATCHLIST_NOT_FOUND_RETRY_SECS")
+                .unwrap_or_else(|_| "21600".to_string())
+                .parse()
+                .unwrap_or(21600),
+            series_check_interval_secs: env::var("SERIES_CHECK_INTERVAL_SECS")
+                .unwrap_or_else(|_| "900".to_string())
+                .parse()
+                .unwrap_or(900),
+            series_sync_interval_secs: env::var("SERIES_SYNC_INTERVAL_SECS")
+                .unwrap_or_else(|_| "21600".to_string())
+                .parse()
+                .unwrap_or(21600),
+            episode_search_delay_hours: env::var("EPISODE_SEARCH_DELAY_HOURS")
+                .unwrap_or_else(|_| "6".to_string())
+                .parse()
+                .unwrap_or(6),
+            calendar_refresh_interval_secs: env::var("CALENDAR_REFRESH_INTERVAL_SECS")
+                .unwrap_or_else(|_| "43200".to_string())
+                .parse()
+                .unwrap_or(43200),
+            metadata_refresh_active_secs: env::var("METADATA_REFRESH_ACTIVE_SECS")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .unwrap_or(86400),
+            metadata_refresh_recent_secs: env::var("METADATA_REFRESH_RECENT_SECS")
+                .unwrap_or_else(|_| "259200".to_string())
+                .parse()
+                .unwrap_or(259200),
+            metadata_refresh_secs: env::var("METADATA_REFRESH_SECS")
+                .unwrap_or_else(|_| "2592000".to_string())
+                .parse()
+                .unwrap_or(2592000),
+            metadata_refresh_batch_size: env::var("METADATA_REFRESH_BATCH_SIZE")
+                .unwrap_or_else(|_| "40".to_string())
+                .parse()
+                .unwrap_or(40),
+            ratings_refresh_secs: env::var("RATINGS_REFRESH_SECS")
+                .unwrap_or_else(|_| "1209600".to_string())
+                .parse()
+                .unwrap_or(1209600),
+            omdb_daily_limit: env::var("OMDB_DAILY_LIMIT")
+                .unwrap_or_else(|_| "800".to_string())
+                .parse()
+                .unwrap_or(800),
+            release_region: env::var("RELEASE_REGION")
+                .unwrap_or_else(|_| "FR".to_string())
+                .to_uppercase(),
+            library_roots: env::var("LIBRARY_ROOTS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            library_match_threshold: env::var("LIBRARY_MATCH_THRESHOLD")
+                .unwrap_or_else(|_| "0.85".to_string())
+                .parse()
+                .unwrap_or(0.85),
+            library_watch_settle_secs: env::var("LIBRARY_WATCH_SETTLE_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            library_reconcile_interval_secs: env::var("LIBRARY_RECONCILE_INTERVAL_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .unwrap_or(3600),
+            hls_cache_dir: env::var("HLS_CACHE_DIR").unwrap_or_else(|_| "./cache/hls".to_string()),
+            hls_cache_max_mb: env::var("HLS_CACHE_MAX_MB")
+                .unwrap_or_else(|_| "2048".to_string())
+                .parse()
+                .unwrap_or(2048),
+            artwork_cache_dir: env::var("ARTWORK_CACHE_DIR")
+                .unwrap_or_else(|_| "./cache/artwork".to_string()),
+            artwork_cache_max_mb: env::var("ARTWORK_CACHE_MAX_MB")
+                .unwrap_or_else(|_| "2048".to_string())
+                .parse()
+                .unwrap_or(2048),
+            artwork_prewarm_interval_secs: env::var("ARTWORK_PREWARM_INTERVAL_SECS")
+                .unwrap_or_else(|_| "21600".to_string())
+                .parse()
+                .unwrap_or(21600),
+            nfo_export_on_import: env::var("NFO_EXPORT_ON_IMPORT")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            subtitle_languages: env::var("SUBTITLE_LANGUAGES")
+                .unwrap_or_else(|_| "fr,en".to_string())
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            opensubtitles_url: env::var("OPENSUBTITLES_URL")
+                .unwrap_or_else(|_| "https://api.opensubtitles.com/api/v1".to_string()),
+            opensubtitles_api_key: env::var("OPENSUBTITLES_API_KEY").unwrap_or_default(),
+            opensubtitles_username: env::var("OPENSUBTITLES_USERNAME").unwrap_or_default(),
+            opensubtitles_password: env::var("OPENSUBTITLES_PASSWORD").unwrap_or_default(),
+            subtitle_daily_downloads: env::var("SUBTITLE_DAILY_DOWNLOADS")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .unwrap_or(20),
+            subtitle_download_interval_secs: env::var("SUBTITLE_DOWNLOAD_INTERVAL_SECS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
         }
     }
 }
@@ -0,0 +1,134 @@
+use crate::{models::Media, utils::anime::AbsoluteNumbering};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// MyAnimeList and TheTVDB ids of an anime series (its TMDB id is on `media`).
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AnimeMapping {
+    pub media_id: Uuid,
+    pub mal_id: i64,
+    pub tvdb_id: Option<i32>,
+    pub mal_title: Option<String>,
+    /// "auto" or "manual"
+    pub source: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub struct NewAnimeMapping<'a> {
+    pub media_id: Uuid,
+    pub mal_id: i64,
+    pub tvdb_id: Option<i32>,
+    pub mal_title: Option<&'a str>,
+    pub source: &'static str,
+}
+
+pub async fn get_mapping(
+    pool: &PgPool,
+    media_id: Uuid,
+) -> Result<Option<AnimeMapping>, sqlx::Error> {
+    sqlx::query_as::<_, AnimeMapping>("SELECT * FROM anime_mappings WHERE media_id = $1")
+        .bind(media_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// The series mapped to a MyAnimeList id.
+pub async fn find_by_mal_id(pool: &PgPool, mal_id: i64) -> Result<Option<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        "SELECT m.* FROM media m JOIN anime_mappings a ON a.media_id = m.id WHERE a.mal_id = $1",
+    )
+    .bind(mal_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Which of these TMDB series ids are mapped to MyAnimeList.
+pub async fn mapped_tmdb_ids(pool: &PgPool, tmdb_ids: &[i32]) -> Result<Vec<i32>, sqlx::Error> {
+    if tmdb_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    sqlx::query_scalar(
+        r#"
+        SELECT m.tmdb_id FROM anime_mappings a
+        JOIN media m ON m.id = a.media_id
+        WHERE m.media_type = 'tv' AND m.tmdb_id = ANY($1)
+        "#,
+    )
+    .bind(tmdb_ids)
+    .fetch_all(pool)
+    .await
+}
+
+/// Store a mapping. Automatic ones never replace a manual mapping; the
+/// MyAnimeList id moves from any series it was automatically matched to.
+/// Returns None when a manual mapping was kept.
+pub async fn upsert_mapping(
+    pool: &PgPool,
+    mapping: &NewAnimeMapping<'_>,
+) -> Result<Option<AnimeMapping>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"
+        DELETE FROM anime_mappings
+        WHERE mal_id = $1 AND media_id <> $2 AND (source = 'auto' OR $3 = 'manual')
+          AND NOT EXISTS (
+              SELECT 1 FROM anime_mappings
+              WHERE media_id = $2 AND source = 'manual' AND $3 = 'auto'
+          )
+        "#,
+    )
+    .bind(mapping.mal_id)
+    .bind(mapping.media_id)
+    .bind(mapping.source)
+    .execute(&mut *tx)
+    .await?;
+    let saved = sqlx::query_as::<_, AnimeMapping>(
+        r#"
+        INSERT INTO anime_mappings (media_id, mal_id, tvdb_id, mal_title, source)
+        SELECT $1, $2, $3, $4, $5
+        -- still mapped elsewhere: manually, and this one is automatic
+        WHERE NOT EXISTS (SELECT 1 FROM anime_mappings WHERE mal_id = $2 AND media_id <> $1)
+        ON CONFLICT (media_id) DO UPDATE SET
+            mal_id = EXCLUDED.mal_id,
+            tvdb_id = COALESCE(EXCLUDED.tvdb_id, anime_mappings.tvdb_id),
+            mal_title = COALESCE(EXCLUDED.mal_title, anime_mappings.mal_title),
+            source = EXCLUDED.source,
+            updated_at = NOW()
+        WHERE anime_mappings.source = 'auto' OR EXCLUDED.source = 'manual'
+        RETURNING *
+        "#,
+    )
+    .bind(mapping.media_id)
+    .bind(mapping.mal_id)
+    .bind(mapping.tvdb_id)
+    .bind(mapping.mal_title)
+    .bind(mapping.source)
+    .fetch_optional(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(saved)
+}
+
+pub async fn delete_mapping(pool: &PgPool, media_id: Uuid) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM anime_mappings WHERE media_id = $1")
+        .bind(media_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Absolute numbering of a series from its stored episode list.
+pub async fn numbering(pool: &PgPool, series_id: Uuid) -> Result<AbsoluteNumbering, sqlx::Error> {
+    let episodes: Vec<(i32, i32)> = sqlx::query_as(
+        r#"
+        SELECT season_number, episode_number FROM media
+        WHERE parent_id = $1 AND season_number IS NOT NULL AND episode_number IS NOT NULL
+        "#,
+    )
+    .bind(series_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(AbsoluteNumbering::new(episodes))
+}
@@ -0,0 +1,230 @@
+use crate::{models::Media, utils::release::ReleaseDate};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Episode air date or movie release (theatrical, digital or physical).
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CalendarEntry {
+    pub media_id: Uuid,
+    /// "episode", "theatrical", "digital" or "physical"
+    pub entry_type: String,
+    pub date: NaiveDate,
+    /// Movie or series title
+    pub title: String,
+    pub episode_title: Option<String>,
+    pub season_number: Option<i32>,
+    pub episode_number: Option<i32>,
+    pub series_id: Option<Uuid>,
+    pub poster_url: Option<String>,
+    pub downloaded: bool,
+}
+
+/// Calendar of a user between `from` and `to` (inclusive): episodes of series they
+/// monitor or watchlist, and releases of watchlisted movies. Movie dates prefer
+/// `region`, falling back to the earliest country.
+pub async fn list_entries(
+    pool: &PgPool,
+    user_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+    region: &str,
+) -> Result<Vec<CalendarEntry>, sqlx::Error> {
+    sqlx::query_as::<_, CalendarEntry>(
+        r#"
+        SELECT
+            e.id AS media_id,
+            'episode' AS entry_type,
+            e.air_date AS date,
+            s.title,
+            e.title AS episode_title,
+            e.season_number,
+            e.episode_number,
+            s.id AS series_id,
+            s.poster_url,
+            EXISTS (SELECT 1 FROM media_files f WHERE f.media_id = e.id) AS downloaded
+        FROM media e
+        JOIN media s ON s.id = e.parent_id
+        WHERE e.media_type = 'episode'
+          AND e.air_date BETWEEN $2 AND $3
+          AND (EXISTS (SELECT 1 FROM monitored_series ms WHERE ms.media_id = s.id AND ms.user_id = $1)
+               OR EXISTS (SELECT 1 FROM watchlist w WHERE w.media_id = s.id AND w.user_id = $1))
+
+        UNION ALL
+
+        SELECT
+            m.id AS media_id,
+            r.release_type AS entry_type,
+            r.release_date AS date,
+            m.title,
+            NULL::TEXT AS episode_title,
+            NULL::INTEGER AS season_number,
+            NULL::INTEGER AS episode_number,
+            NULL::UUID AS series_id,
+            m.poster_url,
+            EXISTS (SELECT 1 FROM media_files f WHERE f.media_id = m.id) AS downloaded
+        FROM (
+            SELECT DISTINCT ON (rd.media_id, rd.release_type)
+                rd.media_id, rd.release_type, rd.release_date
+            FROM media_release_dates rd
+            JOIN watchlist w ON w.media_id = rd.media_id AND w.user_id = $1
+            ORDER BY rd.media_id, rd.release_type, (rd.country = $4) DESC, rd.release_date
+        ) r
+        JOIN media m ON m.id = r.media_id
+        WHERE r.release_date BETWEEN $2 AND $3
+
+        ORDER BY date, title, season_number NULLS FIRST, episode_number
+        "#,
+    )
+    .bind(user_id)
+    .bind(from)
+    .bind(to)
+    .bind(region)
+    .fetch_all(pool)
+    .await
+}
+
+/// Replace the cached release dates of a movie and mark it refreshed.
+pub async fn replace_release_dates(
+    pool: &PgPool,
+    media_id: Uuid,
+    dates: &[ReleaseDate],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM media_release_dates WHERE media_id = $1")
+        .bind(media_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for date in dates {
+        sqlx::query(
+            r#"
+            INSERT INTO media_release_dates (media_id, country, release_type, release_date)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (media_id, country, release_type) DO UPDATE SET
+                release_date = LEAST(media_release_dates.release_date, EXCLUDED.release_date)
+            "#,
+        )
+        .bind(media_id)
+        .bind(&date.country)
+        .bind(date.release_type)
+        .bind(date.release_date)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query("UPDATE media SET calendar_synced_at = NOW() WHERE id = $1")
+        .bind(media_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await
+}
+
+/// Cached release dates of a movie, all countries.
+pub async fn get_release_dates(
+    pool: &PgPool,
+    media_id: Uuid,
+) -> Result<Vec<ReleaseDate>, sqlx::Error> {
+    let rows: Vec<(String, String, NaiveDate)> = sqlx::query_as(
+        "SELECT country, release_type, release_date FROM media_release_dates WHERE media_id = $1",
+    )
+    .bind(media_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(country, release_type, release_date)| {
+            let release_type = ["theatrical", "digital", "physical"]
+                .into_iter()
+                .find(|t| *t == release_type)?;
+            Some(ReleaseDate {
+                country,
+                release_type,
+                release_date,
+            })
+        })
+        .collect())
+}
+
+pub async fn mark_calendar_synced(pool: &PgPool, media_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET calendar_synced_at = NOW() WHERE id = $1")
+        .bind(media_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Watchlisted movies and series whose calendar data is older than `max_age_secs`.
+/// Monitored series are skipped: the series worker keeps their episodes in sync.
+pub async fn list_media_to_refresh(
+    pool: &PgPool,
+    max_age_secs: f64,
+    limit: i64,
+) -> Result<Vec<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        r#"
+        SELECT m.* FROM media m
+        WHERE m.media_type IN ('movie', 'tv')
+          AND EXISTS (SELECT 1 FROM watchlist w WHERE w.media_id = m.id)
+          AND NOT EXISTS (SELECT 1 FROM monitored_series ms WHERE ms.media_id = m.id)
+          AND (m.calendar_synced_at IS NULL
+               OR m.calendar_synced_at < NOW() - make_interval(secs => $1))
+        ORDER BY m.calendar_synced_at ASC NULLS FIRST
+        LIMIT $2
+        "#,
+    )
+    .bind(max_age_secs)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Token of the user's subscribable .ics feed, if one was created.
+pub async fn get_feed_token(pool: &PgPool, user_id: Uuid) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT token FROM calendar_feeds WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Create or rotate the user's feed token; the previous URL stops working.
+pub async fn rotate_feed_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    token: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO calendar_feeds (user_id, token)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET token = EXCLUDED.token, created_at = NOW()
+        "#,
+    )
+    .bind(user_id)
+    .bind(token)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_feed_token(pool: &PgPool, user_id: Uuid) -> Result<u64, sqlx::Error> {
+    sqlx::query("DELETE FROM calendar_feeds WHERE user_id = $1")
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map(|r| r.rows_affected())
+}
+
+pub async fn find_user_by_feed_token(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar("SELECT user_id FROM calendar_feeds WHERE token = $1")
+        .bind(token)
+        .fetch_optional(pool)
+        .await
+}
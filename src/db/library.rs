@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Library file the scanner could not match to TMDB with enough confidence.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LibraryReviewItem {
+    pub id: Uuid,
+    pub file_path: String,
+    pub file_size: Option<i64>,
+    pub parsed_title: String,
+    pub parsed_year: Option<i32>,
+    pub season_number: Option<i32>,
+    pub episode_number: Option<i32>,
+    /// Best TMDB candidates: `[{tmdb_id, media_type, title, year, confidence}]`
+    pub candidates: serde_json::Value,
+    /// "pending", "resolved" or "ignored"
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct NewReviewItem<'a> {
+    pub file_path: &'a str,
+    pub file_size: Option<i64>,
+    pub parsed_title: &'a str,
+    pub parsed_year: Option<i32>,
+    pub season_number: Option<i32>,
+    pub episode_number: Option<i32>,
+    pub candidates: serde_json::Value,
+}
+
+/// Paths the scanner has already handled: imported files and queued or dismissed
+/// review items. Re-scans only look at what is not in this set.
+pub async fn known_paths(pool: &PgPool) -> Result<HashSet<String>, sqlx::Error> {
+    let paths: Vec<String> = sqlx::query_scalar(
+        "SELECT file_path FROM media_files UNION SELECT file_path FROM library_review_items",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(paths.into_iter().collect())
+}
+
+pub async fn add_review_item(
+    pool: &PgPool,
+    item: &NewReviewItem<'_>,
+) -> Result<LibraryReviewItem, sqlx::Error> {
+    sqlx::query_as::<_, LibraryReviewItem>(
+        r#"
+        INSERT INTO library_review_items
+            (file_path, file_size, parsed_title, parsed_year, season_number, episode_number, candidates)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (file_path) DO UPDATE SET
+            file_size = EXCLUDED.file_size,
+            parsed_title = EXCLUDED.parsed_title,
+            parsed_year = EXCLUDED.parsed_year,
+            season_number = EXCLUDED.season_number,
+            episode_number = EXCLUDED.episode_number,
+            candidates = EXCLUDED.candidates
+        RETURNING *
+        "#,
+    )
+    .bind(item.file_path)
+    .bind(item.file_size)
+    .bind(item.parsed_title)
+    .bind(item.parsed_year)
+    .bind(item.season_number)
+    .bind(item.episode_number)
+    .bind(&item.candidates)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn list_pending_reviews(
+    pool: &PgPool,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<LibraryReviewItem>, sqlx::Error> {
+    sqlx::query_as::<_, LibraryReviewItem>(
+        r#"
+        SELECT * FROM library_review_items
+        WHERE status = 'pending'
+        ORDER BY created_at, file_path
+        LIMIT $1 OFFSET $2
+        "#,
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_pending_reviews(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM library_review_items WHERE status = 'pending'")
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn get_review_item(pool: &PgPool, id: Uuid) -> Result<LibraryReviewItem, sqlx::Error> {
+    sqlx::query_as::<_, LibraryReviewItem>("SELECT * FROM library_review_items WHERE id = $1")
+        .bind(id)
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn set_review_status(pool: &PgPool, id: Uuid, status: &str) -> Result<u64, sqlx::Error> {
+    sqlx::query("UPDATE library_review_items SET status = $2 WHERE id = $1")
+        .bind(id)
+        .bind(status)
+        .execute(pool)
+        .await
+        .map(|r| r.rows_affected())
+}
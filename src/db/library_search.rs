@@ -0,0 +1,285 @@
+use crate::models::Media;
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+/// Filters of a library search; `None` leaves a criterion out.
+#[derive(Debug, Default)]
+pub struct LibraryFilters {
+    /// Prefix tsquery built by [`prefix_tsquery`], matched against titles,
+    /// overview and alternative titles
+    pub tsquery: Option<String>,
+    pub media_type: Option<String>,
+    /// Items must have every one of these genres
+    pub genres: Vec<String>,
+    pub year_min: Option<i32>,
+    pub year_max: Option<i32>,
+    /// Source used by `rating_min` and the rating sort
+    pub rating_source: String,
+    pub rating_min: Option<f64>,
+    pub runtime_min: Option<i32>,
+    pub runtime_max: Option<i32>,
+    pub has_file: Option<bool>,
+    /// e.g. "1080p", matched against the movie's or any episode's files
+    pub resolution: Option<String>,
+    /// Movie completed, or every episode on disk of a series completed
+    pub watched: Option<bool>,
+    pub favorite: Option<bool>,
+    pub watchlist: Option<bool>,
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibrarySort {
+    Relevance,
+    Title,
+    Year,
+    Rating,
+    Added,
+    Runtime,
+}
+
+impl LibrarySort {
+    pub const ALL: [(&'static str, LibrarySort); 6] = [
+        ("relevance", LibrarySort::Relevance),
+        ("title", LibrarySort::Title),
+        ("year", LibrarySort::Year),
+        ("rating", LibrarySort::Rating),
+        ("added", LibrarySort::Added),
+        ("runtime", LibrarySort::Runtime),
+    ];
+
+    /// Titles read A to Z; everything else best, newest or longest first.
+    pub fn default_descending(self) -> bool {
+        self != LibrarySort::Title
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+#[derive(Debug, FromRow)]
+pub struct Facets {
+    pub total: i64,
+    pub media_type: Json<Vec<FacetCount>>,
+    pub genres: Json<Vec<FacetCount>>,
+    pub decades: Json<Vec<FacetCount>>,
+    pub resolutions: Json<Vec<FacetCount>>,
+    pub has_file: Json<Vec<FacetCount>>,
+}
+
+/// Turn free text into a tsquery matching every word as a prefix: "amelie pou"
+/// gives `amelie:* & pou:*`. Anything but letters and digits separates words,
+/// so the result is always valid tsquery syntax.
+pub fn prefix_tsquery(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| format!("{}:*", w.to_lowercase()))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" & "))
+}
+
+/// Present files of a movie or of a series' episodes.
+const FILES_OF_M: &str = "SELECT 1 FROM media_files f JOIN media e ON e.id = f.media_id \
+     WHERE (e.id = m.id OR e.parent_id = m.id) AND f.missing_since IS NULL";
+
+/// `WITH filtered AS (...)`: top-level movies and series matching `filters`.
+fn push_filtered(qb: &mut QueryBuilder<'_, Postgres>, filters: &LibraryFilters) {
+    qb.push(
+        "WITH filtered AS (SELECT m.* FROM media m \
+         WHERE m.parent_id IS NULL AND m.media_type IN ('movie', 'tv')",
+    );
+    if let Some(tsquery) = &filters.tsquery {
+        qb.push(" AND (m.search_vector @@ to_tsquery('simple', sokoul_unaccent(")
+            .push_bind(tsquery.clone())
+            .push(")) OR EXISTS (SELECT 1 FROM media_titles t WHERE t.media_id = m.id AND to_tsvector('simple', sokoul_unaccent(t.title)) @@ to_tsquery('simple', sokoul_unaccent(")
+            .push_bind(tsquery.clone())
+            .push("))))");
+    }
+    if let Some(media_type) = &filters.media_type {
+        qb.push(" AND m.media_type = ")
+            .push_bind(media_type.clone());
+    }
+    if !filters.genres.is_empty() {
+        qb.push(" AND m.genres @> ")
+            .push_bind(filters.genres.clone());
+    }
+    if let Some(year) = filters.year_min {
+        qb.push(" AND m.year >= ").push_bind(year);
+    }
+    if let Some(year) = filters.year_max {
+        qb.push(" AND m.year <= ").push_bind(year);
+    }
+    if let Some(rating) = filters.rating_min {
+        qb.push(
+            " AND EXISTS (SELECT 1 FROM media_ratings r WHERE r.media_id = m.id AND r.source = ",
+        )
+        .push_bind(filters.rating_source.clone())
+        .push(" AND r.value >= ")
+        .push_bind(rating)
+        .push(")");
+    }
+    if let Some(runtime) = filters.runtime_min {
+        qb.push(" AND m.runtime_minutes >= ").push_bind(runtime);
+    }
+    if let Some(runtime) = filters.runtime_max {
+        qb.push(" AND m.runtime_minutes <= ").push_bind(runtime);
+    }
+    if let Some(has_file) = filters.has_file {
+        qb.push(if has_file {
+            " AND EXISTS ("
+        } else {
+            " AND NOT EXISTS ("
+        })
+        .push(FILES_OF_M)
+        .push(")");
+    }
+    if let Some(resolution) = &filters.resolution {
+        qb.push(" AND EXISTS (")
+            .push(FILES_OF_M)
+            .push(" AND f.resolution = ")
+            .push_bind(resolution.clone())
+            .push(")");
+    }
+    if let Some(watched) = filters.watched {
+        qb.push(if watched { " AND " } else { " AND NOT " });
+        qb.push("(CASE WHEN m.media_type = 'movie' THEN EXISTS (SELECT 1 FROM watch_history wh WHERE wh.media_id = m.id AND wh.completed AND wh.user_id = ")
+            .push_bind(filters.user_id)
+            .push(") ELSE EXISTS (SELECT 1 FROM media e JOIN watch_history wh ON wh.media_id = e.id WHERE e.parent_id = m.id AND wh.completed AND wh.user_id = ")
+            .push_bind(filters.user_id)
+            .push(") AND NOT EXISTS (SELECT 1 FROM media e JOIN media_files f ON f.media_id = e.id WHERE e.parent_id = m.id AND f.missing_since IS NULL AND NOT EXISTS (SELECT 1 FROM watch_history wh WHERE wh.media_id = e.id AND wh.completed AND wh.user_id = ")
+            .push_bind(filters.user_id)
+            .push(")) END)");
+    }
+    if let Some(favorite) = filters.favorite {
+        qb.push(if favorite {
+            " AND EXISTS"
+        } else {
+            " AND NOT EXISTS"
+        })
+        .push(" (SELECT 1 FROM favorites fv WHERE fv.media_id = m.id AND fv.user_id = ")
+        .push_bind(filters.user_id)
+        .push(")");
+    }
+    if let Some(watchlist) = filters.watchlist {
+        qb.push(if watchlist {
+            " AND EXISTS"
+        } else {
+            " AND NOT EXISTS"
+        })
+        .push(" (SELECT 1 FROM watchlist wl WHERE wl.media_id = m.id AND wl.user_id = ")
+        .push_bind(filters.user_id)
+        .push(")");
+    }
+    qb.push(") ");
+}
+
+/// One page of matching items.
+pub async fn search(
+    pool: &PgPool,
+    filters: &LibraryFilters,
+    sort: LibrarySort,
+    descending: bool,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Media>, sqlx::Error> {
+    let mut qb = QueryBuilder::new("");
+    push_filtered(&mut qb, filters);
+    qb.push(
+        "SELECT m.* FROM filtered m LEFT JOIN media_ratings r ON r.media_id = m.id AND r.source = ",
+    )
+    .push_bind(filters.rating_source.clone())
+    .push(" ORDER BY ");
+
+    let direction = if descending { "DESC" } else { "ASC" };
+    match (sort, &filters.tsquery) {
+        (LibrarySort::Relevance, Some(tsquery)) => {
+            qb.push("ts_rank(m.search_vector, to_tsquery('simple', sokoul_unaccent(")
+                .push_bind(tsquery.clone())
+                .push(format!("))) {}, ", direction));
+        }
+        // Without search terms every item is as relevant: newest first.
+        (LibrarySort::Relevance, None) | (LibrarySort::Added, _) => {
+            qb.push(format!("m.created_at {}, ", direction));
+        }
+        (LibrarySort::Title, _) => {
+            qb.push(format!("lower(sokoul_unaccent(m.title)) {}, ", direction));
+        }
+        (LibrarySort::Year, _) => {
+            qb.push(format!("m.year {} NULLS LAST, ", direction));
+        }
+        (LibrarySort::Rating, _) => {
+            qb.push(format!("r.value {} NULLS LAST, ", direction));
+        }
+        (LibrarySort::Runtime, _) => {
+            qb.push(format!("m.runtime_minutes {} NULLS LAST, ", direction));
+        }
+    }
+    qb.push("m.title, m.id LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+
+    qb.build_query_as::<Media>().fetch_all(pool).await
+}
+
+/// Total and facet counts over every matching item (not just one page).
+pub async fn facets(pool: &PgPool, filters: &LibraryFilters) -> Result<Facets, sqlx::Error> {
+    let mut qb = QueryBuilder::new("");
+    push_filtered(&mut qb, filters);
+    qb.push(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM filtered) AS total,
+            (SELECT COALESCE(json_agg(t), '[]') FROM (
+                SELECT media_type AS value, COUNT(*) AS count
+                FROM filtered GROUP BY 1 ORDER BY 2 DESC, 1
+            ) t) AS media_type,
+            (SELECT COALESCE(json_agg(t), '[]') FROM (
+                SELECT g AS value, COUNT(*) AS count
+                FROM filtered, unnest(genres) g GROUP BY 1 ORDER BY 2 DESC, 1 LIMIT 50
+            ) t) AS genres,
+            (SELECT COALESCE(json_agg(t), '[]') FROM (
+                SELECT ((year / 10) * 10)::TEXT AS value, COUNT(*) AS count
+                FROM filtered WHERE year IS NOT NULL GROUP BY 1 ORDER BY 1 DESC
+            ) t) AS decades,
+            (SELECT COALESCE(json_agg(t), '[]') FROM (
+                SELECT f.resolution AS value, COUNT(DISTINCT m.id) AS count
+                FROM filtered m
+                JOIN media e ON e.id = m.id OR e.parent_id = m.id
+                JOIN media_files f ON f.media_id = e.id
+                WHERE f.missing_since IS NULL AND f.resolution IS NOT NULL
+                GROUP BY 1 ORDER BY 2 DESC, 1
+            ) t) AS resolutions,
+            (SELECT COALESCE(json_agg(t), '[]') FROM (
+                SELECT has_file::TEXT AS value, COUNT(*) AS count FROM (
+                    SELECT EXISTS (
+                        SELECT 1 FROM media e JOIN media_files f ON f.media_id = e.id
+                        WHERE (e.id = m.id OR e.parent_id = m.id) AND f.missing_since IS NULL
+                    ) AS has_file
+                    FROM filtered m
+                ) x GROUP BY 1 ORDER BY 1 DESC
+            ) t) AS has_file
+        "#,
+    );
+    qb.build_query_as::<Facets>().fetch_one(pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_prefix_queries() {
+        assert_eq!(
+            prefix_tsquery("Le Fabuleux Destin d'Amélie").as_deref(),
+            Some("le:* & fabuleux:* & destin:* & d:* & amélie:*")
+        );
+        assert_eq!(prefix_tsquery("x' | !(y)").as_deref(), Some("x:* & y:*"));
+        assert_eq!(prefix_tsquery(" -- "), None);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::models::{CreateMediaPayload, Media, UpdateMediaPayload};
+use crate::models::{CreateMediaPayload, Media, UpdateMediaPayload, UpsertEpisodePayload};
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -89,6 +89,25 @@ pub async fn list_media_by_type(
     Ok(media_list)
 }
 
+/// Poster and backdrop URLs of media with files on disk, or with episodes that have some.
+pub async fn list_library_artwork(
+    pool: &PgPool,
+) -> Result<Vec<(Option<String>, Option<String>)>, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        SELECT m.poster_url, m.backdrop_url FROM media m
+        WHERE (m.poster_url IS NOT NULL OR m.backdrop_url IS NOT NULL)
+          AND EXISTS (
+            SELECT 1 FROM media_files f
+            JOIN media owner ON owner.id = f.media_id
+            WHERE owner.id = m.id OR owner.parent_id = m.id
+          )
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn get_episodes(pool: &PgPool, series_id: Uuid) -> Result<Vec<Media>, sqlx::Error> {
     let episodes = sqlx::query_as::<_, Media>(
         "SELECT * FROM media WHERE parent_id = $1 ORDER BY season_number ASC, episode_number ASC",
@@ -100,6 +119,43 @@ pub async fn get_episodes(pool: &PgPool, series_id: Uuid) -> Result<Vec<Media>,
     Ok(episodes)
 }
 
+/// Insert or refresh an episode row under `series_id`, keyed by season/episode number.
+pub async fn upsert_episode(
+    pool: &PgPool,
+    series_id: Uuid,
+    payload: &UpsertEpisodePayload,
+) -> Result<Media, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        r#"
+        INSERT INTO media (
+            media_type, title, tmdb_id, overview, parent_id,
+            season_number, episode_number, air_date, runtime_minutes, year
+        )
+        VALUES ('episode', $1, $2, $3, $4, $5, $6, $7, $8, EXTRACT(YEAR FROM $7::date)::int)
+        ON CONFLICT (parent_id, season_number, episode_number) WHERE parent_id IS NOT NULL
+        DO UPDATE SET
+            title = EXCLUDED.title,
+            tmdb_id = COALESCE(media.tmdb_id, EXCLUDED.tmdb_id),
+            overview = COALESCE(EXCLUDED.overview, media.overview),
+            air_date = COALESCE(EXCLUDED.air_date, media.air_date),
+            runtime_minutes = COALESCE(EXCLUDED.runtime_minutes, media.runtime_minutes),
+            year = COALESCE(EXCLUDED.year, media.year),
+            updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(&payload.title)
+    .bind(payload.tmdb_id)
+    .bind(&payload.overview)
+    .bind(series_id)
+    .bind(payload.season_number)
+    .bind(payload.episode_number)
+    .bind(payload.air_date)
+    .bind(payload.runtime_minutes)
+    .fetch_one(pool)
+    .await
+}
+
 pub async fn count_media(pool: &PgPool) -> Result<i64, sqlx::Error> {
     let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM media")
         .fetch_one(pool)
@@ -189,3 +245,249 @@ pub async fn delete_media_by_id(pool: &PgPool, id: Uuid) -> Result<u64, sqlx::Er
 
     Ok(result.rows_affected())
 }
+
+/// Record that an automated indexer search ran for this media.
+pub async fn mark_searched(pool: &PgPool, media_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET last_searched_at = NOW() WHERE id = $1")
+        .bind(media_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// TMDB statuses of titles still changing: airing series, unreleased movies.
+const ACTIVE_STATUSES: &[&str] = &[
+    "Returning Series",
+    "In Production",
+    "Planned",
+    "Pilot",
+    "Post Production",
+    "Rumored",
+];
+
+/// Movies and series whose TMDB metadata was never fetched or is stale. Active
+/// titles go stale after `active_secs`, those from this year or last year after
+/// `recent_secs`, the rest after `stale_secs`. Items whose last refresh failed
+/// wait an hour, doubling with each further failure up to `stale_secs`.
+pub async fn list_metadata_to_refresh(
+    pool: &PgPool,
+    active_secs: f64,
+    recent_secs: f64,
+    stale_secs: f64,
+    limit: i64,
+) -> Result<Vec<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        r#"
+        SELECT * FROM media
+        WHERE parent_id IS NULL
+          AND media_type IN ('movie', 'tv')
+          AND tmdb_id IS NOT NULL
+          AND (metadata_refreshed_at IS NULL
+               OR metadata_refreshed_at < NOW() - make_interval(secs => CASE
+                   WHEN status = ANY($1) THEN $2
+                   WHEN year >= EXTRACT(YEAR FROM NOW())::INT - 1 THEN $3
+                   ELSE $4
+               END))
+          AND (metadata_failed_at IS NULL
+               OR metadata_failed_at < NOW() - make_interval(secs => LEAST(
+                   3600 * POWER(2, LEAST(metadata_failures, 16) - 1), $4
+               )))
+        ORDER BY metadata_refreshed_at ASC NULLS FIRST
+        LIMIT $5
+        "#,
+    )
+    .bind(ACTIVE_STATUSES)
+    .bind(active_secs)
+    .bind(recent_secs)
+    .bind(stale_secs)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Metadata fetched from TMDB. Missing values keep what is stored; artwork and
+/// year only fill gaps so user edits survive refreshes.
+#[derive(Debug, Default)]
+pub struct MetadataUpdate {
+    pub original_title: Option<String>,
+    pub year: Option<i32>,
+    pub imdb_id: Option<String>,
+    pub overview: Option<String>,
+    pub poster_url: Option<String>,
+    pub backdrop_url: Option<String>,
+    pub genres: Option<Vec<String>>,
+    pub rating: Option<rust_decimal::Decimal>,
+    pub runtime_minutes: Option<i32>,
+    pub status: Option<String>,
+}
+
+pub async fn apply_metadata(
+    pool: &PgPool,
+    id: Uuid,
+    update: &MetadataUpdate,
+) -> Result<Media, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        r#"
+        UPDATE media SET
+            original_title        = COALESCE($2, original_title),
+            year                  = COALESCE(year, $3),
+            imdb_id               = COALESCE($4, imdb_id),
+            overview              = COALESCE($5, overview),
+            poster_url            = COALESCE(poster_url, $6),
+            backdrop_url          = COALESCE(backdrop_url, $7),
+            genres                = COALESCE($8, genres),
+            rating                = COALESCE($9, rating),
+            runtime_minutes       = COALESCE($10, runtime_minutes),
+            status                = COALESCE($11, status),
+            metadata_refreshed_at = NOW(),
+            metadata_failures     = 0,
+            metadata_failed_at    = NULL,
+            updated_at            = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&update.original_title)
+    .bind(update.year)
+    .bind(&update.imdb_id)
+    .bind(&update.overview)
+    .bind(&update.poster_url)
+    .bind(&update.backdrop_url)
+    .bind(&update.genres)
+    .bind(update.rating)
+    .bind(update.runtime_minutes)
+    .bind(&update.status)
+    .fetch_one(pool)
+    .await
+}
+
+/// Movies and series with an IMDb id whose ratings were not refreshed within
+/// `max_age_secs`, never refreshed first.
+pub async fn list_ratings_to_refresh(
+    pool: &PgPool,
+    max_age_secs: f64,
+    limit: i64,
+) -> Result<Vec<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        r#"
+        SELECT * FROM media
+        WHERE parent_id IS NULL
+          AND media_type IN ('movie', 'tv')
+          AND imdb_id IS NOT NULL
+          AND (ratings_refreshed_at IS NULL
+               OR ratings_refreshed_at < NOW() - make_interval(secs => $1))
+        ORDER BY ratings_refreshed_at ASC NULLS FIRST
+        LIMIT $2
+        "#,
+    )
+    .bind(max_age_secs)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn mark_ratings_refreshed(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET ratings_refreshed_at = NOW() WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Record a refresh that found nothing to update (e.g. the title left TMDB).
+pub async fn mark_metadata_refreshed(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE media SET
+            metadata_refreshed_at = NOW(),
+            metadata_failures = 0,
+            metadata_failed_at = NULL
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record a failed refresh; the item backs off before the next attempt.
+pub async fn mark_metadata_failed(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE media SET
+            metadata_failures = metadata_failures + 1,
+            metadata_failed_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::TestDatabase;
+
+    async fn listed(pool: &PgPool, id: Uuid) -> bool {
+        let day = 86400.0;
+        list_metadata_to_refresh(pool, day, day, 30.0 * day, 10)
+            .await
+            .unwrap()
+            .iter()
+            .any(|m| m.id == id)
+    }
+
+    async fn failed_minutes_ago(pool: &PgPool, id: Uuid, failures: i32, minutes: i32) {
+        sqlx::query(
+            r#"
+            UPDATE media SET
+                metadata_failures = $2,
+                metadata_failed_at = NOW() - make_interval(mins => $3)
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(failures)
+        .bind(minutes)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_refreshes_back_off() {
+        let Some(db) = TestDatabase::create().await else {
+            return;
+        };
+        let pool = &db.pool;
+        let id: Uuid = sqlx::query_scalar(
+            "INSERT INTO media (media_type, title, tmdb_id) VALUES ('movie', 'Heat', 949) RETURNING id",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert!(listed(pool, id).await);
+
+        mark_metadata_failed(pool, id).await.unwrap();
+        assert!(!listed(pool, id).await);
+        // One hour after the first failure, two after the second
+        failed_minutes_ago(pool, id, 1, 90).await;
+        assert!(listed(pool, id).await);
+        failed_minutes_ago(pool, id, 2, 90).await;
+        assert!(!listed(pool, id).await);
+
+        mark_metadata_refreshed(pool, id).await.unwrap();
+        let failures: i32 = sqlx::query_scalar("SELECT metadata_failures FROM media WHERE id = $1")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(failures, 0);
+        db.drop().await;
+    }
+}
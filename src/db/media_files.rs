@@ -1,30 +1,97 @@
 use crate::models::MediaFile;
-use sqlx::PgPool;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
 use uuid::Uuid;
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MediaFileUpgrade {
+    pub id: Uuid,
+    pub media_file_id: Uuid,
+    pub media_id: Uuid,
+    pub old_path: String,
+    pub old_quality_score: Option<i32>,
+    pub new_path: String,
+    pub new_quality_score: Option<i32>,
+    pub release_title: Option<String>,
+    pub upgraded_at: DateTime<Utc>,
+}
+
+/// Disk usage of the files of one media type (episodes count as their series').
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TypeUsage {
+    pub media_type: String,
+    pub files: i64,
+    pub bytes: i64,
+}
+
+/// Disk usage of a movie, or of a series with all of its episodes.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct MediaUsage {
+    pub media_id: Uuid,
+    pub title: String,
+    pub media_type: String,
+    pub year: Option<i32>,
+    pub files: i64,
+    pub bytes: i64,
+}
+
+/// Properties of a file on disk, as recorded in `media_files`.
+#[derive(Debug, Clone)]
+pub struct FileDetails<'a> {
+    pub file_path: &'a str,
+    pub file_size: Option<i64>,
+    pub resolution: Option<String>,
+    pub quality_score: Option<i32>,
+}
+
 pub async fn create_media_file(
     pool: &PgPool,
     media_id: Uuid,
-    file_path: &str,
+    details: &FileDetails<'_>,
     source: &str,
 ) -> Result<MediaFile, sqlx::Error> {
     let file = sqlx::query_as::<_, MediaFile>(
         r#"
-        INSERT INTO media_files (media_id, file_path, source)
-        VALUES ($1, $2, $3)
-        ON CONFLICT (file_path) DO UPDATE SET media_id = EXCLUDED.media_id
+        INSERT INTO media_files (media_id, file_path, file_size, resolution, quality_score, source)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (file_path) DO UPDATE SET
+            media_id = EXCLUDED.media_id,
+            file_size = EXCLUDED.file_size,
+            resolution = EXCLUDED.resolution,
+            quality_score = EXCLUDED.quality_score
         RETURNING *
         "#,
     )
     .bind(media_id)
-    .bind(file_path)
+    .bind(details.file_path)
+    .bind(details.file_size)
+    .bind(&details.resolution)
+    .bind(details.quality_score)
     .bind(source)
     .fetch_one(pool)
     .await?;
 
+    mark_listings_stale(pool, &[file.media_id]).await?;
     Ok(file)
 }
 
+/// Have the episode listing of the series of these episodes re-synced before
+/// completeness is next computed.
+async fn mark_listings_stale(pool: &PgPool, media_ids: &[Uuid]) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE media s SET episodes_synced_at = NULL
+        FROM media e
+        WHERE e.id = ANY($1) AND s.id = e.parent_id
+        "#,
+    )
+    .bind(media_ids)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
 pub async fn get_files_by_media_id(
     pool: &PgPool,
     media_id: Uuid,
@@ -47,3 +114,253 @@ pub async fn get_file_by_id(pool: &PgPool, file_id: Uuid) -> Result<MediaFile, s
 
     Ok(file)
 }
+
+/// Point an existing file row at its upgraded replacement and record the swap.
+/// The row keeps its id, so anything referencing the file follows the upgrade.
+/// Returns the previous path, which the caller removes from disk.
+pub async fn replace_with_upgrade(
+    pool: &PgPool,
+    file_id: Uuid,
+    details: &FileDetails<'_>,
+    release_title: &str,
+) -> Result<String, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let old = sqlx::query_as::<_, MediaFile>("SELECT * FROM media_files WHERE id = $1 FOR UPDATE")
+        .bind(file_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO media_file_upgrades
+            (media_file_id, media_id, old_path, old_quality_score, new_path, new_quality_score, release_title)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(old.id)
+    .bind(old.media_id)
+    .bind(&old.file_path)
+    .bind(old.quality_score)
+    .bind(details.file_path)
+    .bind(details.quality_score)
+    .bind(release_title)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE media_files SET
+            file_path = $2,
+            file_size = $3,
+            resolution = $4,
+            quality_score = $5,
+            codec_video = NULL,
+            codec_audio = NULL,
+            hash_info = NULL,
+            fingerprint = NULL,
+            file_mtime = NULL,
+            file_inode = NULL,
+            downloaded_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(file_id)
+    .bind(details.file_path)
+    .bind(details.file_size)
+    .bind(&details.resolution)
+    .bind(details.quality_score)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(old.file_path)
+}
+
+pub async fn list_upgrades(
+    pool: &PgPool,
+    media_id: Uuid,
+) -> Result<Vec<MediaFileUpgrade>, sqlx::Error> {
+    sqlx::query_as::<_, MediaFileUpgrade>(
+        "SELECT * FROM media_file_upgrades WHERE media_id = $1 ORDER BY upgraded_at DESC",
+    )
+    .bind(media_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_file_by_path(
+    pool: &PgPool,
+    file_path: &str,
+) -> Result<Option<MediaFile>, sqlx::Error> {
+    sqlx::query_as::<_, MediaFile>("SELECT * FROM media_files WHERE file_path = $1")
+        .bind(file_path)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Files stored below `dir` (a directory path without trailing slash).
+pub async fn list_files_under(pool: &PgPool, dir: &str) -> Result<Vec<MediaFile>, sqlx::Error> {
+    sqlx::query_as::<_, MediaFile>(
+        "SELECT * FROM media_files WHERE starts_with(file_path, $1 || '/') ORDER BY file_path",
+    )
+    .bind(dir)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn list_all_files(pool: &PgPool) -> Result<Vec<MediaFile>, sqlx::Error> {
+    sqlx::query_as::<_, MediaFile>("SELECT * FROM media_files ORDER BY file_path")
+        .fetch_all(pool)
+        .await
+}
+
+/// Files of exactly `file_size` bytes: the rows a moved file may belong to.
+pub async fn list_files_by_size(
+    pool: &PgPool,
+    file_size: i64,
+) -> Result<Vec<MediaFile>, sqlx::Error> {
+    sqlx::query_as::<_, MediaFile>("SELECT * FROM media_files WHERE file_size = $1")
+        .bind(file_size)
+        .fetch_all(pool)
+        .await
+}
+
+/// Point a file row at its new location; a moved file is no longer missing.
+pub async fn relocate_file(
+    pool: &PgPool,
+    file_id: Uuid,
+    new_path: &str,
+) -> Result<MediaFile, sqlx::Error> {
+    sqlx::query_as::<_, MediaFile>(
+        "UPDATE media_files SET file_path = $2, missing_since = NULL WHERE id = $1 RETURNING *",
+    )
+    .bind(file_id)
+    .bind(new_path)
+    .fetch_one(pool)
+    .await
+}
+
+/// Flag a file as gone from disk. Returns false if it was already flagged.
+pub async fn mark_missing(pool: &PgPool, file_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query(
+        "UPDATE media_files SET missing_since = NOW() WHERE id = $1 AND missing_since IS NULL",
+    )
+    .bind(file_id)
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected() > 0)
+}
+
+pub async fn clear_missing(pool: &PgPool, file_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media_files SET missing_since = NULL WHERE id = $1")
+        .bind(file_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Store the size, modification time and partial hash used to recognize the
+/// file after a move, with its inode.
+pub async fn set_fingerprint(
+    pool: &PgPool,
+    file_id: Uuid,
+    file_size: i64,
+    file_mtime: Option<DateTime<Utc>>,
+    partial_hash: &str,
+    file_inode: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE media_files SET file_size = $2, file_mtime = $3, fingerprint = $4, file_inode = $5
+        WHERE id = $1
+        "#,
+    )
+    .bind(file_id)
+    .bind(file_size)
+    .bind(file_mtime)
+    .bind(partial_hash)
+    .bind(file_inode)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn set_inode(
+    pool: &PgPool,
+    file_id: Uuid,
+    file_inode: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media_files SET file_inode = $2 WHERE id = $1")
+        .bind(file_id)
+        .bind(file_inode)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn usage_by_media_type(pool: &PgPool) -> Result<Vec<TypeUsage>, sqlx::Error> {
+    sqlx::query_as::<_, TypeUsage>(
+        r#"
+        SELECT COALESCE(p.media_type, m.media_type) AS media_type,
+               COUNT(f.id) AS files,
+               COALESCE(SUM(f.file_size), 0)::BIGINT AS bytes
+        FROM media_files f
+        JOIN media m ON m.id = f.media_id
+        LEFT JOIN media p ON p.id = m.parent_id
+        GROUP BY 1
+        ORDER BY bytes DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Largest library items first.
+pub async fn usage_by_media(
+    pool: &PgPool,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<MediaUsage>, sqlx::Error> {
+    sqlx::query_as::<_, MediaUsage>(
+        r#"
+        SELECT COALESCE(p.id, m.id) AS media_id,
+               COALESCE(p.title, m.title) AS title,
+               COALESCE(p.media_type, m.media_type) AS media_type,
+               COALESCE(p.year, m.year) AS year,
+               COUNT(f.id) AS files,
+               COALESCE(SUM(f.file_size), 0)::BIGINT AS bytes
+        FROM media_files f
+        JOIN media m ON m.id = f.media_id
+        LEFT JOIN media p ON p.id = m.parent_id
+        GROUP BY 1, 2, 3, 4
+        ORDER BY bytes DESC, title
+        LIMIT $1 OFFSET $2
+        "#,
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_media_with_files(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT COUNT(DISTINCT COALESCE(m.parent_id, m.id))
+        FROM media_files f JOIN media m ON m.id = f.media_id
+        "#,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete_files(pool: &PgPool, ids: &[Uuid]) -> Result<u64, sqlx::Error> {
+    let media_ids: Vec<Uuid> =
+        sqlx::query_scalar("DELETE FROM media_files WHERE id = ANY($1) RETURNING media_id")
+            .bind(ids)
+            .fetch_all(pool)
+            .await?;
+    mark_listings_stale(pool, &media_ids).await?;
+    Ok(media_ids.len() as u64)
+}
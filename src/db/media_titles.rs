@@ -0,0 +1,107 @@
+use crate::models::Media;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct MediaTitle {
+    /// ISO 3166-1 code, empty when unknown
+    pub country: String,
+    pub title: String,
+    pub kind: Option<String>,
+}
+
+/// Replace every stored alias of a media.
+pub async fn replace_titles(
+    pool: &PgPool,
+    media_id: Uuid,
+    titles: &[MediaTitle],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM media_titles WHERE media_id = $1")
+        .bind(media_id)
+        .execute(&mut *tx)
+        .await?;
+    for title in titles {
+        sqlx::query(
+            r#"
+            INSERT INTO media_titles (media_id, country, title, kind)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (media_id, country, title) DO NOTHING
+            "#,
+        )
+        .bind(media_id)
+        .bind(&title.country)
+        .bind(&title.title)
+        .bind(&title.kind)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// Aliases of a media, those of `region` first, plus the MyAnimeList title of
+/// an anime (kind "mal").
+pub async fn list_for_media(
+    pool: &PgPool,
+    media_id: Uuid,
+    region: &str,
+) -> Result<Vec<MediaTitle>, sqlx::Error> {
+    sqlx::query_as::<_, MediaTitle>(
+        r#"
+        SELECT country, title, kind FROM (
+            SELECT country, title, kind FROM media_titles
+            WHERE media_id = $1
+            UNION ALL
+            SELECT 'JP', mal_title, 'mal' FROM anime_mappings
+            WHERE media_id = $1 AND mal_title IS NOT NULL
+        ) aliases
+        ORDER BY country <> $2, country, title
+        "#,
+    )
+    .bind(media_id)
+    .bind(region)
+    .fetch_all(pool)
+    .await
+}
+
+/// Every name a release of `media` may use: its title, the MyAnimeList title
+/// of an anime (fansubs go by it rather than the Japanese original), its
+/// original title, then its aliases (`region` first), without case-insensitive
+/// duplicates.
+pub fn match_titles(media: &Media, aliases: &[MediaTitle]) -> Vec<String> {
+    let mut titles: Vec<String> = Vec::new();
+    let (mal, others): (Vec<&MediaTitle>, Vec<&MediaTitle>) = aliases
+        .iter()
+        .partition(|a| a.kind.as_deref() == Some("mal"));
+    let candidates = std::iter::once(media.title.as_str())
+        .chain(mal.iter().map(|a| a.title.as_str()))
+        .chain(media.original_title.as_deref())
+        .chain(others.iter().map(|a| a.title.as_str()));
+    for title in candidates.map(str::trim).filter(|t| !t.is_empty()) {
+        if !titles
+            .iter()
+            .any(|t| t.to_lowercase() == title.to_lowercase())
+        {
+            titles.push(title.to_string());
+        }
+    }
+    titles
+}
+
+/// [`match_titles`] loaded from the database. A failed lookup only loses the
+/// aliases.
+pub async fn titles_for_media(pool: &PgPool, media: &Media, region: &str) -> Vec<String> {
+    let aliases = match list_for_media(pool, media.id, region).await {
+        Ok(aliases) => aliases,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to load alternative titles of '{}': {}",
+                media.title,
+                e
+            );
+            Vec::new()
+        }
+    };
+    match_titles(media, &aliases)
+}
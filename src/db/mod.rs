@@ -1,10 +1,22 @@
+pub mod anime;
+pub mod calendar;
 pub mod collections;
 pub mod favorites;
+pub mod library;
+pub mod library_search;
 pub mod media;
 pub mod media_files;
+pub mod media_titles;
+pub mod quality_profiles;
+pub mod ratings;
+pub mod retention;
 pub mod search_results;
 pub mod security;
+pub mod series;
+pub mod subtitles;
 pub mod tasks;
+#[cfg(test)]
+pub mod testing;
 pub mod tv;
 pub mod users;
 pub mod watch_history;
@@ -0,0 +1,133 @@
+use crate::utils::quality::{parse_resolution, QualityProfile};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct QualityProfileRow {
+    pub user_id: Uuid,
+    pub min_resolution: String,
+    pub max_resolution: Option<String>,
+    pub min_seeders: i32,
+    pub max_size_bytes: Option<i64>,
+    pub rejected_terms: Vec<String>,
+    pub cutoff_score: i32,
+    pub allow_pre_digital: bool,
+    pub release_region: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Settings written by `upsert_profile`.
+#[derive(Debug, Clone)]
+pub struct UpsertQualityProfile<'a> {
+    pub min_resolution: &'a str,
+    pub max_resolution: Option<&'a str>,
+    pub min_seeders: i32,
+    pub max_size_bytes: Option<i64>,
+    pub rejected_terms: &'a [String],
+    pub cutoff_score: i32,
+    pub allow_pre_digital: bool,
+    pub release_region: Option<&'a str>,
+}
+
+impl QualityProfileRow {
+    pub fn to_profile(&self) -> QualityProfile {
+        let defaults = QualityProfile::default();
+        QualityProfile {
+            min_resolution: parse_resolution(&self.min_resolution)
+                .unwrap_or(defaults.min_resolution),
+            max_resolution: self.max_resolution.as_deref().and_then(parse_resolution),
+            min_seeders: self.min_seeders,
+            max_size_bytes: self.max_size_bytes,
+            rejected_terms: self.rejected_terms.clone(),
+            cutoff_score: self.cutoff_score,
+            allow_pre_digital: self.allow_pre_digital,
+            release_region: self.release_region.clone(),
+        }
+    }
+}
+
+pub async fn get_profile(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<QualityProfileRow>, sqlx::Error> {
+    sqlx::query_as::<_, QualityProfileRow>("SELECT * FROM quality_profiles WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// The user's profile, or the built-in defaults when none has been saved.
+pub async fn get_effective_profile(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<QualityProfile, sqlx::Error> {
+    Ok(get_profile(pool, user_id)
+        .await?
+        .map(|row| row.to_profile())
+        .unwrap_or_default())
+}
+
+pub async fn upsert_profile(
+    pool: &PgPool,
+    user_id: Uuid,
+    profile: &UpsertQualityProfile<'_>,
+) -> Result<QualityProfileRow, sqlx::Error> {
+    sqlx::query_as::<_, QualityProfileRow>(
+        r#"
+        INSERT INTO quality_profiles
+            (user_id, min_resolution, max_resolution, min_seeders, max_size_bytes,
+             rejected_terms, cutoff_score, allow_pre_digital, release_region)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (user_id) DO UPDATE SET
+            min_resolution = EXCLUDED.min_resolution,
+            max_resolution = EXCLUDED.max_resolution,
+            min_seeders = EXCLUDED.min_seeders,
+            max_size_bytes = EXCLUDED.max_size_bytes,
+            rejected_terms = EXCLUDED.rejected_terms,
+            cutoff_score = EXCLUDED.cutoff_score,
+            allow_pre_digital = EXCLUDED.allow_pre_digital,
+            release_region = EXCLUDED.release_region,
+            updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(profile.min_resolution)
+    .bind(profile.max_resolution)
+    .bind(profile.min_seeders)
+    .bind(profile.max_size_bytes)
+    .bind(profile.rejected_terms)
+    .bind(profile.cutoff_score)
+    .bind(profile.allow_pre_digital)
+    .bind(profile.release_region)
+    .fetch_one(pool)
+    .await
+}
+
+/// Profile governing automated grabs for a media: the user watching it on
+/// their watchlist, or monitoring its series; defaults otherwise.
+pub async fn get_profile_for_media(
+    pool: &PgPool,
+    media_id: Uuid,
+) -> Result<QualityProfile, sqlx::Error> {
+    let owner: Option<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT user_id FROM watchlist WHERE media_id = $1
+        UNION ALL
+        SELECT ms.user_id FROM monitored_series ms
+        JOIN media e ON e.parent_id = ms.media_id
+        WHERE e.id = $1 AND ms.user_id IS NOT NULL
+        LIMIT 1
+        "#,
+    )
+    .bind(media_id)
+    .fetch_optional(pool)
+    .await?;
+
+    match owner {
+        Some((user_id,)) => get_effective_profile(pool, user_id).await,
+        None => Ok(QualityProfile::default()),
+    }
+}
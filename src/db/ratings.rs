@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Rating sources and their best possible value.
+pub const SOURCES: &[(&str, i16)] = &[
+    ("tmdb", 10),
+    ("imdb", 10),
+    ("rotten_tomatoes", 100),
+    ("metacritic", 100),
+    ("trakt", 10),
+    ("simkl", 10),
+];
+
+pub fn is_known_source(source: &str) -> bool {
+    SOURCES.iter().any(|(s, _)| *s == source)
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct MediaRating {
+    #[serde(skip)]
+    pub media_id: Uuid,
+    pub source: String,
+    pub value: Decimal,
+    pub scale: i16,
+    pub vote_count: Option<i64>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// A score fetched from one source, on that source's scale.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewRating {
+    pub source: &'static str,
+    pub value: Decimal,
+    pub vote_count: Option<i64>,
+}
+
+impl NewRating {
+    pub fn scale(&self) -> i16 {
+        SOURCES
+            .iter()
+            .find(|(s, _)| *s == self.source)
+            .map(|(_, scale)| *scale)
+            .unwrap_or(10)
+    }
+}
+
+pub async fn upsert_ratings(
+    pool: &PgPool,
+    media_id: Uuid,
+    ratings: &[NewRating],
+) -> Result<(), sqlx::Error> {
+    for rating in ratings {
+        sqlx::query(
+            r#"
+            INSERT INTO media_ratings (media_id, source, value, scale, vote_count, fetched_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            ON CONFLICT (media_id, source) DO UPDATE SET
+                value = EXCLUDED.value,
+                scale = EXCLUDED.scale,
+                vote_count = EXCLUDED.vote_count,
+                fetched_at = NOW()
+            "#,
+        )
+        .bind(media_id)
+        .bind(rating.source)
+        .bind(rating.value)
+        .bind(rating.scale())
+        .bind(rating.vote_count)
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Ratings of several media at once, in source order.
+pub async fn list_for_media(
+    pool: &PgPool,
+    media_ids: &[Uuid],
+) -> Result<Vec<MediaRating>, sqlx::Error> {
+    sqlx::query_as::<_, MediaRating>(
+        r#"
+        SELECT r.* FROM media_ratings r
+        WHERE r.media_id = ANY($1)
+        ORDER BY r.media_id, array_position($2, r.source)
+        "#,
+    )
+    .bind(media_ids)
+    .bind(SOURCES.iter().map(|(s, _)| *s).collect::<Vec<_>>())
+    .fetch_all(pool)
+    .await
+}
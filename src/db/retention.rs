@@ -0,0 +1,316 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Rule deleting the files of media the household has finished watching.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RetentionPolicy {
+    pub id: Uuid,
+    pub name: String,
+    pub enabled: bool,
+    /// "movie" or "tv"; any type when NULL
+    pub media_type: Option<String>,
+    /// "all": every member who watches anything has finished it; "any": someone has
+    pub watched_by: String,
+    pub min_days_since_watched: i32,
+    /// Favorites (of the movie or its series) are kept forever
+    pub keep_favorites: bool,
+    pub keep_watchlisted: bool,
+    /// Only delete while the file's volume has less free space than this
+    pub free_space_below_gb: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_watched_by() -> String {
+    "all".to_string()
+}
+
+fn default_min_days() -> i32 {
+    30
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetentionPolicyPayload {
+    pub name: String,
+    /// Off until asked for, so the dry-run report can be checked first
+    #[serde(default)]
+    pub enabled: bool,
+    pub media_type: Option<String>,
+    #[serde(default = "default_watched_by")]
+    pub watched_by: String,
+    #[serde(default = "default_min_days")]
+    pub min_days_since_watched: i32,
+    #[serde(default = "default_true")]
+    pub keep_favorites: bool,
+    #[serde(default = "default_true")]
+    pub keep_watchlisted: bool,
+    pub free_space_below_gb: Option<i32>,
+}
+
+/// A file a policy would delete.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct RetentionCandidate {
+    pub file_id: Uuid,
+    pub file_path: String,
+    pub file_size: Option<i64>,
+    pub media_id: Uuid,
+    pub title: String,
+    pub media_type: String,
+    pub last_watched_at: DateTime<Utc>,
+}
+
+pub async fn list_policies(pool: &PgPool) -> Result<Vec<RetentionPolicy>, sqlx::Error> {
+    sqlx::query_as::<_, RetentionPolicy>("SELECT * FROM retention_policies ORDER BY created_at")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn create_policy(
+    pool: &PgPool,
+    payload: &RetentionPolicyPayload,
+) -> Result<RetentionPolicy, sqlx::Error> {
+    sqlx::query_as::<_, RetentionPolicy>(
+        r#"
+        INSERT INTO retention_policies
+            (name, enabled, media_type, watched_by, min_days_since_watched,
+             keep_favorites, keep_watchlisted, free_space_below_gb)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *
+        "#,
+    )
+    .bind(&payload.name)
+    .bind(payload.enabled)
+    .bind(&payload.media_type)
+    .bind(&payload.watched_by)
+    .bind(payload.min_days_since_watched)
+    .bind(payload.keep_favorites)
+    .bind(payload.keep_watchlisted)
+    .bind(payload.free_space_below_gb)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn update_policy(
+    pool: &PgPool,
+    id: Uuid,
+    payload: &RetentionPolicyPayload,
+) -> Result<RetentionPolicy, sqlx::Error> {
+    sqlx::query_as::<_, RetentionPolicy>(
+        r#"
+        UPDATE retention_policies SET
+            name = $2, enabled = $3, media_type = $4, watched_by = $5,
+            min_days_since_watched = $6, keep_favorites = $7, keep_watchlisted = $8,
+            free_space_below_gb = $9, updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&payload.name)
+    .bind(payload.enabled)
+    .bind(&payload.media_type)
+    .bind(&payload.watched_by)
+    .bind(payload.min_days_since_watched)
+    .bind(payload.keep_favorites)
+    .bind(payload.keep_watchlisted)
+    .bind(payload.free_space_below_gb)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete_policy(pool: &PgPool, id: Uuid) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM retention_policies WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Files matching a policy's watch and protection rules, longest watched first.
+/// Episodes are matched one by one; favorites and watchlist entries of their
+/// series protect them too.
+pub async fn list_candidates(
+    pool: &PgPool,
+    policy: &RetentionPolicy,
+) -> Result<Vec<RetentionCandidate>, sqlx::Error> {
+    sqlx::query_as::<_, RetentionCandidate>(
+        r#"
+        WITH members AS (
+            SELECT DISTINCT wh.user_id
+            FROM watch_history wh JOIN users u ON u.id = wh.user_id
+            WHERE u.is_active
+        )
+        SELECT f.id AS file_id, f.file_path, f.file_size,
+               m.id AS media_id, m.title,
+               COALESCE(p.media_type, m.media_type) AS media_type,
+               MAX(wh.watched_at) AS last_watched_at
+        FROM media_files f
+        JOIN media m ON m.id = f.media_id
+        LEFT JOIN media p ON p.id = m.parent_id
+        JOIN watch_history wh ON wh.media_id = m.id
+            AND wh.completed AND wh.watched_at IS NOT NULL
+            AND wh.user_id IN (SELECT user_id FROM members)
+        WHERE f.missing_since IS NULL
+          AND ($1::TEXT IS NULL OR COALESCE(p.media_type, m.media_type) = $1)
+          AND NOT ($2 AND EXISTS (
+              SELECT 1 FROM favorites fav WHERE fav.media_id IN (m.id, p.id)))
+          AND NOT ($3 AND EXISTS (
+              SELECT 1 FROM watchlist w WHERE w.media_id IN (m.id, p.id)))
+        GROUP BY f.id, f.file_path, f.file_size, m.id, m.title, p.media_type, m.media_type
+        HAVING MAX(wh.watched_at) < NOW() - make_interval(days => $4)
+           AND ($5 = 'any' OR COUNT(DISTINCT wh.user_id) >= (SELECT COUNT(*) FROM members))
+        ORDER BY last_watched_at
+        "#,
+    )
+    .bind(&policy.media_type)
+    .bind(policy.keep_favorites)
+    .bind(policy.keep_watchlisted)
+    .bind(policy.min_days_since_watched)
+    .bind(&policy.watched_by)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::TestDatabase;
+
+    async fn insert_media(pool: &PgPool, media_type: &str, parent: Option<Uuid>) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO media (media_type, title, parent_id) VALUES ($1, $1, $2) RETURNING id",
+        )
+        .bind(media_type)
+        .bind(parent)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn insert_file(pool: &PgPool, media_id: Uuid, path: &str) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO media_files (media_id, file_path, file_size) VALUES ($1, $2, 1) RETURNING id",
+        )
+        .bind(media_id)
+        .bind(path)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn insert_user(pool: &PgPool, name: &str) -> Uuid {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO users (username, email, password_hash)
+            VALUES ($1, $1 || '@test', 'x') RETURNING id
+            "#,
+        )
+        .bind(name)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn watched(pool: &PgPool, media_id: Uuid, user_id: Uuid, days_ago: i32) {
+        sqlx::query(
+            r#"
+            INSERT INTO watch_history (media_id, user_id, watched_at, completed)
+            VALUES ($1, $2, NOW() - make_interval(days => $3), TRUE)
+            "#,
+        )
+        .bind(media_id)
+        .bind(user_id)
+        .bind(days_ago)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    fn policy(watched_by: &str) -> RetentionPolicy {
+        RetentionPolicy {
+            id: Uuid::new_v4(),
+            name: "test".to_string(),
+            enabled: true,
+            media_type: None,
+            watched_by: watched_by.to_string(),
+            min_days_since_watched: 30,
+            keep_favorites: true,
+            keep_watchlisted: true,
+            free_space_below_gb: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_files_watched_long_enough() {
+        let Some(db) = TestDatabase::create().await else {
+            return;
+        };
+        let pool = &db.pool;
+        let (alice, bob) = (
+            insert_user(pool, "alice").await,
+            insert_user(pool, "bob").await,
+        );
+
+        // Watched by both, 60 then 40 days ago
+        let old = insert_media(pool, "movie", None).await;
+        insert_file(pool, old, "/m/old.mkv").await;
+        watched(pool, old, alice, 60).await;
+        watched(pool, old, bob, 60).await;
+        let older = insert_media(pool, "movie", None).await;
+        insert_file(pool, older, "/m/older.mkv").await;
+        watched(pool, older, alice, 90).await;
+        watched(pool, older, bob, 40).await;
+        // Only alice finished it
+        let half = insert_media(pool, "movie", None).await;
+        insert_file(pool, half, "/m/half.mkv").await;
+        watched(pool, half, alice, 70).await;
+        // Too recent
+        let recent = insert_media(pool, "movie", None).await;
+        insert_file(pool, recent, "/m/recent.mkv").await;
+        watched(pool, recent, alice, 5).await;
+        watched(pool, recent, bob, 5).await;
+        // Episode of a favorite series
+        let series = insert_media(pool, "tv", None).await;
+        let episode = insert_media(pool, "episode", Some(series)).await;
+        insert_file(pool, episode, "/tv/e01.mkv").await;
+        watched(pool, episode, alice, 60).await;
+        watched(pool, episode, bob, 60).await;
+        sqlx::query("INSERT INTO favorites (user_id, media_id) VALUES ($1, $2)")
+            .bind(alice)
+            .bind(series)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let paths = |candidates: Vec<RetentionCandidate>| -> Vec<String> {
+            candidates.into_iter().map(|c| c.file_path).collect()
+        };
+        let all = list_candidates(pool, &policy("all")).await.unwrap();
+        assert_eq!(paths(all), vec!["/m/old.mkv", "/m/older.mkv"]);
+
+        let any = list_candidates(pool, &policy("any")).await.unwrap();
+        assert_eq!(
+            paths(any),
+            vec!["/m/half.mkv", "/m/old.mkv", "/m/older.mkv"]
+        );
+
+        let unprotected = RetentionPolicy {
+            keep_favorites: false,
+            media_type: Some("tv".to_string()),
+            ..policy("all")
+        };
+        let tv = list_candidates(pool, &unprotected).await.unwrap();
+        assert_eq!(tv[0].media_type, "tv");
+        assert_eq!(paths(tv), vec!["/tv/e01.mkv"]);
+
+        db.drop().await;
+    }
+}
@@ -54,6 +54,38 @@ pub async fn get_results_by_media_id(
     Ok(results)
 }
 
+/// Store a result picked automatically and return its row, scored with `score`
+/// unless it was already scored.
+pub async fn store_selected(
+    pool: &PgPool,
+    media_id: Uuid,
+    result: &TorrentResult,
+    score: i32,
+) -> Result<SearchResult, sqlx::Error> {
+    create_batch(pool, media_id, std::slice::from_ref(result)).await?;
+
+    let saved = get_results_by_media_id(pool, media_id)
+        .await?
+        .into_iter()
+        .find(|r| r.guid == result.guid)
+        .ok_or(sqlx::Error::RowNotFound)?;
+
+    if saved.score.is_none() {
+        update_score(pool, saved.id, score, false).await?;
+    }
+    Ok(saved)
+}
+
+/// Record which title or alias of the media a release was accepted under.
+pub async fn set_matched_title(pool: &PgPool, id: i32, title: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE search_results SET matched_title = $1 WHERE id = $2")
+        .bind(title)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
 pub async fn update_score(
     pool: &PgPool,
     id: i32,
@@ -0,0 +1,474 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Season monitoring modes: every episode, only episodes airing after monitoring
+/// started, or nothing.
+pub const SEASON_MODES: &[&str] = &["all", "future", "none"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MonitoredSeries {
+    pub media_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub default_mode: String,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    // From the joined media row
+    pub title: String,
+    pub tmdb_id: Option<i32>,
+    pub imdb_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MonitoredSeason {
+    pub season_number: i32,
+    pub mode: String,
+    pub monitored_since: NaiveDate,
+    pub episode_count: i64,
+    pub downloaded_count: i64,
+}
+
+/// Aired episode of a monitored season that still needs a file.
+#[derive(Debug, Clone, FromRow)]
+pub struct WantedEpisode {
+    pub episode_id: Uuid,
+    pub series_id: Uuid,
+    pub series_title: String,
+    pub user_id: Option<Uuid>,
+    pub season_number: i32,
+    pub episode_number: i32,
+}
+
+const MONITORED_SERIES_SELECT: &str = r#"
+    SELECT ms.media_id, ms.user_id, ms.default_mode, ms.last_synced_at, ms.created_at,
+           m.title, m.tmdb_id, m.imdb_id
+    FROM monitored_series ms
+    JOIN media m ON m.id = ms.media_id
+"#;
+
+/// Monitor a series, or change its default mode. Seasons already registered
+/// keep their own mode; the default applies to those found by later syncs.
+pub async fn monitor_series(
+    pool: &PgPool,
+    media_id: Uuid,
+    user_id: Uuid,
+    default_mode: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO monitored_series (media_id, user_id, default_mode)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (media_id) DO UPDATE SET
+            user_id = EXCLUDED.user_id,
+            default_mode = EXCLUDED.default_mode
+        "#,
+    )
+    .bind(media_id)
+    .bind(user_id)
+    .bind(default_mode)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn unmonitor_series(pool: &PgPool, media_id: Uuid) -> Result<u64, sqlx::Error> {
+    sqlx::query("DELETE FROM monitored_series WHERE media_id = $1")
+        .bind(media_id)
+        .execute(pool)
+        .await
+        .map(|r| r.rows_affected())
+}
+
+pub async fn get_monitored_series(
+    pool: &PgPool,
+    media_id: Uuid,
+) -> Result<Option<MonitoredSeries>, sqlx::Error> {
+    sqlx::query_as::<_, MonitoredSeries>(&format!(
+        "{} WHERE ms.media_id = $1",
+        MONITORED_SERIES_SELECT
+    ))
+    .bind(media_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Monitored series whose episode list was not synced within `max_age_secs`.
+pub async fn list_series_to_sync(
+    pool: &PgPool,
+    max_age_secs: f64,
+) -> Result<Vec<MonitoredSeries>, sqlx::Error> {
+    sqlx::query_as::<_, MonitoredSeries>(&format!(
+        "{} WHERE ms.last_synced_at IS NULL OR ms.last_synced_at < NOW() - make_interval(secs => $1) ORDER BY ms.last_synced_at ASC NULLS FIRST",
+        MONITORED_SERIES_SELECT
+    ))
+    .bind(max_age_secs)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn mark_synced(pool: &PgPool, media_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE monitored_series SET last_synced_at = NOW() WHERE media_id = $1")
+        .bind(media_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Register a season discovered during sync with the series' default mode.
+pub async fn ensure_season(
+    pool: &PgPool,
+    media_id: Uuid,
+    season_number: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO monitored_seasons (media_id, season_number, mode)
+        SELECT media_id, $2, default_mode FROM monitored_series WHERE media_id = $1
+        ON CONFLICT (media_id, season_number) DO NOTHING
+        "#,
+    )
+    .bind(media_id)
+    .bind(season_number)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn set_season_mode(
+    pool: &PgPool,
+    media_id: Uuid,
+    season_number: i32,
+    mode: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO monitored_seasons (media_id, season_number, mode)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (media_id, season_number) DO UPDATE SET
+            mode = EXCLUDED.mode,
+            monitored_since = CASE
+                WHEN monitored_seasons.mode = EXCLUDED.mode THEN monitored_seasons.monitored_since
+                ELSE CURRENT_DATE
+            END
+        "#,
+    )
+    .bind(media_id)
+    .bind(season_number)
+    .bind(mode)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_seasons(
+    pool: &PgPool,
+    media_id: Uuid,
+) -> Result<Vec<MonitoredSeason>, sqlx::Error> {
+    sqlx::query_as::<_, MonitoredSeason>(
+        r#"
+        SELECT
+            s.season_number,
+            s.mode,
+            s.monitored_since,
+            COUNT(e.id) AS episode_count,
+            COUNT(e.id) FILTER (
+                WHERE EXISTS (SELECT 1 FROM media_files f WHERE f.media_id = e.id)
+            ) AS downloaded_count
+        FROM monitored_seasons s
+        LEFT JOIN media e ON e.parent_id = s.media_id AND e.season_number = s.season_number
+        WHERE s.media_id = $1
+        GROUP BY s.season_number, s.mode, s.monitored_since
+        ORDER BY s.season_number
+        "#,
+    )
+    .bind(media_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Episodes aired at least `delay_hours` ago in a monitored season, without a file,
+/// a download in flight, a search within the last `retry_secs`, or a completed
+/// viewing.
+pub async fn list_wanted_episodes(
+    pool: &PgPool,
+    delay_hours: i32,
+    retry_secs: f64,
+    limit: i64,
+) -> Result<Vec<WantedEpisode>, sqlx::Error> {
+    sqlx::query_as::<_, WantedEpisode>(
+        r#"
+        SELECT
+            e.id AS episode_id,
+            s.id AS series_id,
+            s.title AS series_title,
+            ms.user_id,
+            e.season_number,
+            e.episode_number
+        FROM media e
+        JOIN media s ON s.id = e.parent_id
+        JOIN monitored_series ms ON ms.media_id = s.id
+        JOIN monitored_seasons mss
+            ON mss.media_id = s.id AND mss.season_number = e.season_number
+        WHERE e.media_type = 'episode'
+          AND e.air_date IS NOT NULL
+          AND (mss.mode = 'all' OR (mss.mode = 'future' AND e.air_date >= mss.monitored_since))
+          AND e.air_date + make_interval(hours => $1) <= NOW()
+          AND (e.last_searched_at IS NULL
+               OR e.last_searched_at < NOW() - make_interval(secs => $2))
+          AND NOT EXISTS (SELECT 1 FROM media_files f WHERE f.media_id = e.id)
+          -- Watched then removed by retention: not wanted again
+          AND NOT EXISTS (
+              SELECT 1 FROM watch_history wh WHERE wh.media_id = e.id AND wh.completed
+          )
+          AND NOT EXISTS (
+              SELECT 1 FROM tasks t
+              WHERE t.task_type = 'download'
+                AND t.status IN ('pending', 'running')
+                AND t.payload->>'media_id' = e.id::text
+          )
+        ORDER BY e.air_date DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(delay_hours)
+    .bind(retry_secs)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn mark_episodes_synced(pool: &PgPool, series_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET episodes_synced_at = NOW() WHERE id = $1")
+        .bind(series_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Whether the stored episode listing of a series was synced within
+/// `max_age_secs` and no file of it was imported or deleted since.
+pub async fn episodes_synced_within(
+    pool: &PgPool,
+    series_id: Uuid,
+    max_age_secs: f64,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(episodes_synced_at > NOW() - make_interval(secs => $2), FALSE)
+        FROM media WHERE id = $1
+        "#,
+    )
+    .bind(series_id)
+    .bind(max_age_secs)
+    .fetch_one(pool)
+    .await
+}
+
+/// Episode counts of one season, compared against the stored TMDB listing.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SeasonCompleteness {
+    pub season_number: i32,
+    pub total: i64,
+    pub downloaded: i64,
+    /// Aired episodes without a file
+    pub missing: i64,
+    /// Episodes without a file that have not aired (or have no air date yet)
+    pub unaired: i64,
+    pub missing_episodes: Vec<i32>,
+}
+
+pub async fn season_completeness(
+    pool: &PgPool,
+    series_id: Uuid,
+) -> Result<Vec<SeasonCompleteness>, sqlx::Error> {
+    sqlx::query_as::<_, SeasonCompleteness>(
+        r#"
+        SELECT
+            season_number,
+            COUNT(*) AS total,
+            COUNT(*) FILTER (WHERE has_file) AS downloaded,
+            COUNT(*) FILTER (WHERE NOT has_file AND aired) AS missing,
+            COUNT(*) FILTER (WHERE NOT has_file AND NOT aired) AS unaired,
+            COALESCE(
+                ARRAY_AGG(episode_number ORDER BY episode_number)
+                    FILTER (WHERE NOT has_file AND aired),
+                '{}'
+            ) AS missing_episodes
+        FROM (
+            SELECT
+                e.season_number,
+                e.episode_number,
+                EXISTS (SELECT 1 FROM media_files f WHERE f.media_id = e.id) AS has_file,
+                COALESCE(e.air_date <= CURRENT_DATE, FALSE) AS aired
+            FROM media e
+            WHERE e.parent_id = $1
+              AND e.media_type = 'episode'
+              AND e.season_number > 0
+        ) episodes
+        GROUP BY season_number
+        ORDER BY season_number
+        "#,
+    )
+    .bind(series_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Aired episodes of a series without a file, a download in flight or a
+/// completed viewing, regardless of monitoring. Searches use `user_id`'s quality profile.
+pub async fn list_missing_episodes(
+    pool: &PgPool,
+    series_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<WantedEpisode>, sqlx::Error> {
+    sqlx::query_as::<_, WantedEpisode>(
+        r#"
+        SELECT
+            e.id AS episode_id,
+            s.id AS series_id,
+            s.title AS series_title,
+            $2::UUID AS user_id,
+            e.season_number,
+            e.episode_number
+        FROM media e
+        JOIN media s ON s.id = e.parent_id
+        WHERE e.parent_id = $1
+          AND e.media_type = 'episode'
+          AND e.season_number > 0
+          AND e.air_date <= CURRENT_DATE
+          AND NOT EXISTS (SELECT 1 FROM media_files f WHERE f.media_id = e.id)
+          -- Watched then removed by retention: not wanted again
+          AND NOT EXISTS (
+              SELECT 1 FROM watch_history wh WHERE wh.media_id = e.id AND wh.completed
+          )
+          AND NOT EXISTS (
+              SELECT 1 FROM tasks t
+              WHERE t.task_type = 'download'
+                AND t.status IN ('pending', 'running')
+                AND t.payload->>'media_id' = e.id::text
+          )
+        ORDER BY e.season_number, e.episode_number
+        "#,
+    )
+    .bind(series_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        media_files::{self, FileDetails},
+        testing::TestDatabase,
+    };
+
+    async fn insert_series(pool: &PgPool) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO media (media_type, title) VALUES ('tv', 'Show') RETURNING id",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    /// An episode that aired `days_ago` days ago (negative: airs later, None: no date yet).
+    async fn insert_episode(
+        pool: &PgPool,
+        series_id: Uuid,
+        season: i32,
+        episode: i32,
+        days_ago: Option<i32>,
+    ) -> Uuid {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO media (media_type, title, parent_id, season_number, episode_number, air_date)
+            VALUES ('episode', 'Episode', $1, $2, $3, CURRENT_DATE - $4)
+            RETURNING id
+            "#,
+        )
+        .bind(series_id)
+        .bind(season)
+        .bind(episode)
+        .bind(days_ago)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn add_file(pool: &PgPool, media_id: Uuid) -> Uuid {
+        let path = format!("/tv/{}.mkv", media_id);
+        let details = FileDetails {
+            file_path: &path,
+            file_size: Some(1),
+            resolution: None,
+            quality_score: None,
+        };
+        media_files::create_media_file(pool, media_id, &details, "test")
+            .await
+            .unwrap()
+            .id
+    }
+
+    #[tokio::test]
+    async fn counts_missing_and_unaired_episodes_per_season() {
+        let Some(db) = TestDatabase::create().await else {
+            return;
+        };
+        let pool = &db.pool;
+        let series = insert_series(pool).await;
+        // Specials are left out.
+        insert_episode(pool, series, 0, 1, Some(300)).await;
+        let s1e1 = insert_episode(pool, series, 1, 1, Some(30)).await;
+        insert_episode(pool, series, 1, 2, Some(23)).await;
+        insert_episode(pool, series, 1, 3, Some(-7)).await;
+        // Partial season: one aired episode downloaded, one without a date.
+        let s2e1 = insert_episode(pool, series, 2, 1, Some(0)).await;
+        insert_episode(pool, series, 2, 2, None).await;
+        add_file(pool, s1e1).await;
+        add_file(pool, s2e1).await;
+
+        let seasons = season_completeness(pool, series).await.unwrap();
+        let counts: Vec<_> = seasons
+            .iter()
+            .map(|s| {
+                (
+                    s.season_number,
+                    s.total,
+                    s.downloaded,
+                    s.missing,
+                    s.unaired,
+                    s.missing_episodes.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            counts,
+            vec![(1, 3, 1, 1, 1, vec![2]), (2, 2, 1, 0, 1, vec![])]
+        );
+        db.drop().await;
+    }
+
+    #[tokio::test]
+    async fn file_changes_make_the_listing_stale() {
+        let Some(db) = TestDatabase::create().await else {
+            return;
+        };
+        let pool = &db.pool;
+        let series = insert_series(pool).await;
+        let episode = insert_episode(pool, series, 1, 1, Some(3)).await;
+        assert!(!episodes_synced_within(pool, series, 3600.0).await.unwrap());
+
+        mark_episodes_synced(pool, series).await.unwrap();
+        assert!(episodes_synced_within(pool, series, 3600.0).await.unwrap());
+        let file = add_file(pool, episode).await;
+        assert!(!episodes_synced_within(pool, series, 3600.0).await.unwrap());
+
+        mark_episodes_synced(pool, series).await.unwrap();
+        assert_eq!(media_files::delete_files(pool, &[file]).await.unwrap(), 1);
+        assert!(!episodes_synced_within(pool, series, 3600.0).await.unwrap());
+        db.drop().await;
+    }
+}
@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Subtitle available for a media file.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Subtitle {
+    pub id: Uuid,
+    pub media_file_id: Uuid,
+    /// "sidecar" (file next to the video) or "embedded" (track inside it)
+    pub source: String,
+    pub file_path: Option<String>,
+    pub track_number: Option<i32>,
+    /// "srt", "ass" or "vtt" for text subtitles, e.g. "pgs" for bitmap tracks
+    pub format: String,
+    /// ISO 639-1 code
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub is_forced: bool,
+    /// Subtitles for the deaf and hard of hearing
+    pub is_sdh: bool,
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct NewSubtitle<'a> {
+    pub media_file_id: Uuid,
+    pub source: &'a str,
+    pub file_path: Option<&'a str>,
+    pub track_number: Option<i32>,
+    pub format: &'a str,
+    pub language: Option<&'a str>,
+    pub title: Option<&'a str>,
+    pub is_forced: bool,
+    pub is_sdh: bool,
+    pub is_default: bool,
+}
+
+/// Insert or refresh a subtitle, keyed by its file path or embedded track.
+pub async fn upsert_subtitle(
+    pool: &PgPool,
+    subtitle: &NewSubtitle<'_>,
+) -> Result<Subtitle, sqlx::Error> {
+    let conflict = if subtitle.file_path.is_some() {
+        "(media_file_id, file_path) WHERE file_path IS NOT NULL"
+    } else {
+        "(media_file_id, track_number) WHERE track_number IS NOT NULL"
+    };
+    sqlx::query_as::<_, Subtitle>(&format!(
+        r#"
+        INSERT INTO subtitles
+            (media_file_id, source, file_path, track_number, format, language, title,
+             is_forced, is_sdh, is_default)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT {} DO UPDATE SET
+            format = EXCLUDED.format,
+            language = EXCLUDED.language,
+            title = EXCLUDED.title,
+            is_forced = EXCLUDED.is_forced,
+            is_sdh = EXCLUDED.is_sdh,
+            is_default = EXCLUDED.is_default
+        RETURNING *
+        "#,
+        conflict
+    ))
+    .bind(subtitle.media_file_id)
+    .bind(subtitle.source)
+    .bind(subtitle.file_path)
+    .bind(subtitle.track_number)
+    .bind(subtitle.format)
+    .bind(subtitle.language)
+    .bind(subtitle.title)
+    .bind(subtitle.is_forced)
+    .bind(subtitle.is_sdh)
+    .bind(subtitle.is_default)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn list_for_file(
+    pool: &PgPool,
+    media_file_id: Uuid,
+) -> Result<Vec<Subtitle>, sqlx::Error> {
+    sqlx::query_as::<_, Subtitle>(
+        r#"
+        SELECT * FROM subtitles
+        WHERE media_file_id = $1
+        ORDER BY language NULLS LAST, is_forced, source DESC, track_number, file_path
+        "#,
+    )
+    .bind(media_file_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_subtitle(pool: &PgPool, id: Uuid) -> Result<Subtitle, sqlx::Error> {
+    sqlx::query_as::<_, Subtitle>("SELECT * FROM subtitles WHERE id = $1")
+        .bind(id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Drop subtitles of a file that were not found again, returning how many.
+pub async fn delete_stale(
+    pool: &PgPool,
+    media_file_id: Uuid,
+    keep: &[Uuid],
+) -> Result<u64, sqlx::Error> {
+    let result =
+        sqlx::query("DELETE FROM subtitles WHERE media_file_id = $1 AND NOT (id = ANY($2))")
+            .bind(media_file_id)
+            .bind(keep)
+            .execute(pool)
+            .await?;
+    Ok(result.rows_affected())
+}
+
+/// Subtitles downloaded from providers within the last 24 hours.
+pub async fn count_downloaded_last_day(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM subtitles WHERE source = 'downloaded' AND created_at > NOW() - INTERVAL '1 day'",
+    )
+    .fetch_one(pool)
+    .await
+}
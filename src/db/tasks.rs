@@ -103,3 +103,44 @@ pub async fn complete_task(
 
     Ok(task)
 }
+
+/// Oldest pending task of a type.
+pub async fn next_pending(pool: &PgPool, task_type: &str) -> Result<Option<Task>, sqlx::Error> {
+    sqlx::query_as::<_, Task>(
+        "SELECT * FROM tasks WHERE task_type = $1 AND status = 'pending' ORDER BY created_at LIMIT 1",
+    )
+    .bind(task_type)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Whether a pending or running task of a type has `value` under `key` in its payload.
+pub async fn has_active(
+    pool: &PgPool,
+    task_type: &str,
+    key: &str,
+    value: &str,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM tasks
+            WHERE task_type = $1 AND status IN ('pending', 'running') AND payload->>$2 = $3
+        )
+        "#,
+    )
+    .bind(task_type)
+    .bind(key)
+    .bind(value)
+    .fetch_one(pool)
+    .await
+}
+
+/// Put tasks of a type left running (by a restart) back in the queue.
+pub async fn requeue_running(pool: &PgPool, task_type: &str) -> Result<u64, sqlx::Error> {
+    sqlx::query("UPDATE tasks SET status = 'pending' WHERE task_type = $1 AND status = 'running'")
+        .bind(task_type)
+        .execute(pool)
+        .await
+        .map(|r| r.rows_affected())
+}
@@ -0,0 +1,53 @@
+//! Throwaway databases for tests of SQL queries, created on the server of
+//! `TEST_DATABASE_URL` and migrated to the latest schema. Tests using them
+//! are skipped when the variable is unset.
+
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    Executor, PgPool,
+};
+use uuid::Uuid;
+
+pub struct TestDatabase {
+    pub pool: PgPool,
+    admin: PgPool,
+    name: String,
+}
+
+impl TestDatabase {
+    pub async fn create() -> Option<Self> {
+        let url = std::env::var("TEST_DATABASE_URL").ok()?;
+        let admin = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&url)
+            .await
+            .expect("TEST_DATABASE_URL is not reachable");
+        let name = format!("sokoul_test_{}", Uuid::new_v4().simple());
+        admin
+            .execute(format!("CREATE DATABASE {}", name).as_str())
+            .await
+            .expect("failed to create the test database");
+
+        let options = url
+            .parse::<PgConnectOptions>()
+            .expect("invalid TEST_DATABASE_URL")
+            .database(&name);
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect_with(options)
+            .await
+            .expect("failed to connect to the test database");
+        crate::migrate::up(&pool)
+            .await
+            .expect("failed to migrate the test database");
+        Some(Self { pool, admin, name })
+    }
+
+    pub async fn drop(self) {
+        self.pool.close().await;
+        let _ = self
+            .admin
+            .execute(format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", self.name).as_str())
+            .await;
+    }
+}
@@ -1,6 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{PgPool, Row};
+use sqlx::{FromRow, PgPool, Row};
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -16,7 +16,7 @@ pub async fn add_to_watchlist(
     user_id: Uuid,
     media_id: Uuid,
     auto_download: bool,
-    quality_min: &str,
+    quality_min: Option<&str>,
 ) -> Result<WatchlistItem, sqlx::Error> {
     let row = sqlx::query(
         r#"
@@ -114,3 +114,250 @@ pub async fn count_watchlist(pool: &PgPool, user_id: Uuid) -> Result<i64, sqlx::
 
     Ok(row.get("count"))
 }
+
+/// Watchlist entry eligible for automatic acquisition.
+#[derive(Debug, Clone, FromRow)]
+pub struct AutoDownloadCandidate {
+    pub watchlist_id: Uuid,
+    pub user_id: Uuid,
+    pub user_email: String,
+    pub media_id: Uuid,
+    pub media_type: String,
+    pub title: String,
+    pub year: Option<i32>,
+    pub tmdb_id: Option<i32>,
+    pub quality_min: Option<String>,
+}
+
+/// Update the auto-download settings of an existing entry. `quality_min` is
+/// left unchanged when None, and cleared (back to the quality profile's
+/// minimum) when `Some(None)`.
+pub async fn update_auto_download(
+    pool: &PgPool,
+    user_id: Uuid,
+    media_id: Uuid,
+    auto_download: bool,
+    quality_min: Option<Option<&str>>,
+) -> Result<u64, sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE watchlist SET
+            auto_download = $3,
+            quality_min = CASE WHEN $4 THEN $5 ELSE quality_min END,
+            auto_status = CASE WHEN $3 THEN auto_status ELSE NULL END
+        WHERE user_id = $1 AND media_id = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(media_id)
+    .bind(auto_download)
+    .bind(quality_min.is_some())
+    .bind(quality_min.flatten())
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+/// Flagged entries whose media has no file yet and no download in flight.
+/// Entries that cannot be acquired automatically are left out, and those
+/// nothing was found for wait `not_found_retry_secs` before the next search.
+pub async fn list_auto_download_candidates(
+    pool: &PgPool,
+    limit: i64,
+    not_found_retry_secs: i64,
+) -> Result<Vec<AutoDownloadCandidate>, sqlx::Error> {
+    sqlx::query_as::<_, AutoDownloadCandidate>(
+        r#"
+        SELECT
+            w.id AS watchlist_id,
+            w.user_id,
+            u.email AS user_email,
+            m.id AS media_id,
+            m.media_type,
+            m.title,
+            m.year,
+            m.tmdb_id,
+            w.quality_min
+        FROM watchlist w
+        JOIN media m ON m.id = w.media_id
+        JOIN users u ON u.id = w.user_id
+        WHERE w.auto_download = TRUE
+          AND COALESCE(w.auto_status, '') NOT IN ('queued', 'downloaded', 'unsupported')
+          AND NOT (
+              w.auto_status = 'not_found'
+              AND w.last_searched_at > NOW() - make_interval(secs => $2)
+          )
+          AND NOT EXISTS (SELECT 1 FROM media_files f WHERE f.media_id = m.id)
+        ORDER BY w.last_searched_at ASC NULLS FIRST
+        LIMIT $1
+        "#,
+    )
+    .bind(limit)
+    .bind(not_found_retry_secs as f64)
+    .fetch_all(pool)
+    .await
+}
+
+/// Record the outcome of an automatic search on the entry.
+pub async fn record_auto_outcome(
+    pool: &PgPool,
+    watchlist_id: Uuid,
+    status: &str,
+    message: Option<&str>,
+    search_result_id: Option<i32>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE watchlist SET
+            auto_status = $2,
+            auto_message = $3,
+            last_search_result_id = COALESCE($4, last_search_result_id),
+            last_searched_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(watchlist_id)
+    .bind(status)
+    .bind(message)
+    .bind(search_result_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Send entries queued more than `timeout_secs` ago, whose download is no
+/// longer in flight, back to the search: the download failed or was never
+/// imported.
+pub async fn requeue_stale_queued(pool: &PgPool, timeout_secs: i64) -> Result<u64, sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE watchlist w SET
+            auto_status = 'pending',
+            auto_message = 'Queued download was not imported, searching again'
+        WHERE w.auto_status = 'queued'
+          AND w.last_searched_at < NOW() - make_interval(secs => $1)
+          AND NOT EXISTS (
+              SELECT 1 FROM tasks t
+              WHERE t.task_type = 'download'
+                AND t.status IN ('pending', 'running')
+                AND t.payload->>'media_id' = w.media_id::text
+          )
+        "#,
+    )
+    .bind(timeout_secs as f64)
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+/// Settle entries whose automatic download was queued for this media.
+pub async fn settle_queued_for_media(
+    pool: &PgPool,
+    media_id: Uuid,
+    status: &str,
+    message: Option<&str>,
+) -> Result<u64, sqlx::Error> {
+    sqlx::query(
+        "UPDATE watchlist SET auto_status = $2, auto_message = $3 WHERE media_id = $1 AND auto_status = 'queued'",
+    )
+    .bind(media_id)
+    .bind(status)
+    .bind(message)
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing::TestDatabase;
+
+    const USER: &str = "00000000-0000-0000-0000-000000000001";
+
+    async fn quality_min(pool: &PgPool, id: Uuid) -> Option<String> {
+        sqlx::query_scalar("SELECT quality_min FROM watchlist WHERE id = $1")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn overrides_and_requeues_entries() {
+        let Some(db) = TestDatabase::create().await else {
+            return;
+        };
+        let pool = &db.pool;
+        let user = Uuid::parse_str(USER).unwrap();
+        let media: Uuid = sqlx::query_scalar(
+            "INSERT INTO media (media_type, title) VALUES ('movie', 'Heat') RETURNING id",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+
+        let entry = add_to_watchlist(pool, user, media, true, None)
+            .await
+            .unwrap();
+        assert_eq!(quality_min(pool, entry.id).await, None);
+        update_auto_download(pool, user, media, true, Some(Some("2160p")))
+            .await
+            .unwrap();
+        update_auto_download(pool, user, media, true, None)
+            .await
+            .unwrap();
+        assert_eq!(quality_min(pool, entry.id).await.as_deref(), Some("2160p"));
+        update_auto_download(pool, user, media, true, Some(None))
+            .await
+            .unwrap();
+        assert_eq!(quality_min(pool, entry.id).await, None);
+
+        record_auto_outcome(pool, entry.id, "queued", None, None)
+            .await
+            .unwrap();
+        assert!(list_auto_download_candidates(pool, 10, 3600)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(requeue_stale_queued(pool, 3600).await.unwrap(), 0);
+        sqlx::query("UPDATE watchlist SET last_searched_at = NOW() - INTERVAL '2 hours'")
+            .execute(pool)
+            .await
+            .unwrap();
+        assert_eq!(requeue_stale_queued(pool, 3600).await.unwrap(), 1);
+        assert_eq!(
+            list_auto_download_candidates(pool, 10, 3600)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Nothing found: searched again once the retry delay has passed.
+        record_auto_outcome(pool, entry.id, "not_found", None, None)
+            .await
+            .unwrap();
+        assert!(list_auto_download_candidates(pool, 10, 3600)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            list_auto_download_candidates(pool, 10, 0)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        // Not a movie: never picked up again.
+        record_auto_outcome(pool, entry.id, "unsupported", None, None)
+            .await
+            .unwrap();
+        assert!(list_auto_download_candidates(pool, 10, 0)
+            .await
+            .unwrap()
+            .is_empty());
+
+        db.drop().await;
+    }
+}
@@ -14,6 +14,9 @@ pub struct SearchRequestedPayload {
     pub tmdb_id: Option<i32>,
     pub season: Option<i32>,
     pub episode: Option<i32>,
+    /// Skip the indexer response cache and force fresh upstream searches.
+    #[serde(default)]
+    pub refresh: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,6 +25,9 @@ pub struct DownloadRequestedPayload {
     pub search_result_id: i32,
     pub magnet_or_url: String,
     pub title: String,
+    /// Existing file this download upgrades; swapped in place once complete.
+    #[serde(default)]
+    pub replaces_file_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -68,10 +74,54 @@ pub enum WsEvent {
         #[serde(skip_serializing_if = "Option::is_none")]
         task_id: Option<String>,
     },
+    WatchlistAutoDownload {
+        media_id: String,
+        title: String,
+        status: String,
+        message: String,
+    },
     OracleValidated {
         media_id: String,
         validated_count: u32,
     },
+    LibraryScanProgress {
+        task_id: String,
+        scanned: usize,
+        total: usize,
+        imported: usize,
+        needs_review: usize,
+    },
+    LibraryScanCompleted {
+        task_id: String,
+        scanned: usize,
+        imported: usize,
+        needs_review: usize,
+        failed: usize,
+    },
+    LibraryFileImported {
+        media_id: String,
+        file_id: String,
+        file_path: String,
+    },
+    LibraryFileNeedsReview {
+        file_path: String,
+    },
+    LibraryFileMoved {
+        media_id: String,
+        file_id: String,
+        old_path: String,
+        new_path: String,
+    },
+    LibraryFileMissing {
+        media_id: String,
+        file_id: String,
+        file_path: String,
+    },
+    LibraryFileRestored {
+        media_id: String,
+        file_id: String,
+        file_path: String,
+    },
     SystemAlert {
         level: String,
         message: String,
@@ -6,10 +6,12 @@ mod db;
 mod events;
 mod metrics;
 mod middleware;
+mod migrate;
 mod models;
 mod notifications;
 use notifications::EmailService;
 mod providers;
+mod remux;
 mod scheduler;
 mod security;
 mod telegram;
@@ -18,7 +20,7 @@ mod workers;
 
 use axum::{
     middleware as axum_middleware,
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use axum_prometheus::PrometheusMetricLayer;
@@ -58,458 +60,6 @@ pub struct AppState {
     pub email_service: EmailService,
 }
 
-async fn ensure_critical_schema(pool: &sqlx::PgPool) -> anyhow::Result<()> {
-    // Extensions
-    sqlx::query(r#"CREATE EXTENSION IF NOT EXISTS "pgcrypto""#)
-        .execute(pool)
-        .await?;
-    // Required by db/media.rs similarity() search
-    sqlx::query(r#"CREATE EXTENSION IF NOT EXISTS "pg_trgm""#)
-        .execute(pool)
-        .await?;
-
-    // users
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS users (
-            id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            username      TEXT        NOT NULL UNIQUE,
-            email         TEXT        NOT NULL UNIQUE,
-            password_hash TEXT        NOT NULL,
-            role          TEXT        NOT NULL DEFAULT 'user',
-            avatar_url    TEXT,
-            is_active     BOOLEAN     NOT NULL DEFAULT TRUE,
-            created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-            updated_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // System user for API key fallback (no JWT)
-    sqlx::query(
-        r#"
-        INSERT INTO users (id, username, email, password_hash, role, is_active)
-        VALUES (
-            '00000000-0000-0000-0000-000000000001',
-            'system-api',
-            'system-api@sokoul.local',
-            'api-key-fallback-user',
-            'user',
-            TRUE
-        )
-        ON CONFLICT DO NOTHING
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // media
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS media (
-            id              UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            media_type      TEXT        NOT NULL,
-            title           TEXT        NOT NULL,
-            original_title  TEXT,
-            year            INTEGER,
-            tmdb_id         INTEGER,
-            imdb_id         TEXT,
-            overview        TEXT,
-            poster_url      TEXT,
-            backdrop_url    TEXT,
-            genres          TEXT[],
-            rating          DECIMAL(3,1),
-            runtime_minutes INTEGER,
-            status          TEXT        DEFAULT 'unknown',
-            parent_id       UUID        REFERENCES media(id) ON DELETE CASCADE,
-            season_number   INTEGER,
-            episode_number  INTEGER,
-            created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-            updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-            UNIQUE (tmdb_id, media_type)
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // media_files (models.rs::MediaFile)
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS media_files (
-            id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            media_id      UUID        NOT NULL REFERENCES media(id) ON DELETE CASCADE,
-            file_path     TEXT        NOT NULL UNIQUE,
-            file_size     BIGINT,
-            codec_video   TEXT,
-            codec_audio   TEXT,
-            resolution    TEXT,
-            quality_score INTEGER,
-            hash_info     TEXT,
-            source        TEXT,
-            downloaded_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // search_results (models.rs::SearchResult — id is SERIAL i32)
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS search_results (
-            id           SERIAL      PRIMARY KEY,
-            media_id     UUID        NOT NULL REFERENCES media(id) ON DELETE CASCADE,
-            provider     TEXT        NOT NULL,
-            title        TEXT        NOT NULL,
-            guid         TEXT        NOT NULL,
-            url          TEXT,
-            magnet_link  TEXT,
-            info_hash    TEXT,
-            protocol     TEXT        NOT NULL DEFAULT 'torrent',
-            quality      TEXT,
-            size_bytes   BIGINT      NOT NULL DEFAULT 0,
-            seeders      INTEGER     NOT NULL DEFAULT 0,
-            leechers     INTEGER     NOT NULL DEFAULT 0,
-            score        INTEGER,
-            ai_validated BOOLEAN,
-            created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-            expires_at   TIMESTAMPTZ DEFAULT (NOW() + INTERVAL '24 hours'),
-            UNIQUE (media_id, guid)
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // tasks (models.rs::Task)
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS tasks (
-            id           UUID         PRIMARY KEY DEFAULT gen_random_uuid(),
-            task_type    TEXT         NOT NULL,
-            status       TEXT         NOT NULL DEFAULT 'pending',
-            payload      JSONB,
-            result       JSONB,
-            progress     DECIMAL(5,2) DEFAULT 0,
-            error        TEXT,
-            created_at   TIMESTAMPTZ  DEFAULT NOW(),
-            started_at   TIMESTAMPTZ,
-            completed_at TIMESTAMPTZ
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // watch_history
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS watch_history (
-            id               UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            media_id         UUID        NOT NULL REFERENCES media(id) ON DELETE CASCADE,
-            user_id          UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
-            watched_at       TIMESTAMPTZ DEFAULT NOW(),
-            progress_seconds INTEGER     DEFAULT 0,
-            total_seconds    INTEGER     DEFAULT 0,
-            completed        BOOLEAN     DEFAULT FALSE,
-            UNIQUE (media_id, user_id)
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // favorites
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS favorites (
-            id       UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            user_id  UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
-            media_id UUID        NOT NULL REFERENCES media(id) ON DELETE CASCADE,
-            added_at TIMESTAMPTZ DEFAULT NOW(),
-            UNIQUE (user_id, media_id)
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // watchlist
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS watchlist (
-            id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            user_id       UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
-            media_id      UUID        NOT NULL REFERENCES media(id) ON DELETE CASCADE,
-            auto_download BOOLEAN     DEFAULT FALSE,
-            quality_min   TEXT        DEFAULT '1080p',
-            added_at      TIMESTAMPTZ DEFAULT NOW(),
-            UNIQUE (user_id, media_id)
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // collections — thematic (GoT, Breaking Bad…), NOT user playlists
-    // Schema matches db/collections.rs::Collection exactly
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS collections (
-            id              UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            name            TEXT        NOT NULL,
-            description     TEXT,
-            category        TEXT        NOT NULL,
-            api_source      TEXT        NOT NULL DEFAULT 'internal',
-            cover_image_url TEXT,
-            backdrop_url    TEXT,
-            created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // collection_items — reference external IDs (tmdb_id), not local media UUIDs
-    // Schema matches db/collections.rs::CollectionItem exactly
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS collection_items (
-            id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            collection_id UUID        NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
-            external_id   TEXT,
-            name          TEXT        NOT NULL,
-            description   TEXT,
-            image_url     TEXT,
-            item_type     TEXT,
-            data_json     JSONB,
-            created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // tv_channels
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS tv_channels (
-            id         UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            name       TEXT        NOT NULL,
-            code       TEXT        NOT NULL UNIQUE,
-            country    TEXT,
-            logo_url   TEXT,
-            category   TEXT,
-            is_free    BOOLEAN     DEFAULT TRUE,
-            is_active  BOOLEAN     DEFAULT TRUE,
-            stream_url TEXT,
-            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // tv_programs (EPG)
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS tv_programs (
-            id          UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            channel_id  UUID        NOT NULL REFERENCES tv_channels(id) ON DELETE CASCADE,
-            title       TEXT        NOT NULL,
-            description TEXT,
-            start_time  TIMESTAMPTZ NOT NULL,
-            end_time    TIMESTAMPTZ NOT NULL,
-            genre       TEXT,
-            image_url   TEXT,
-            rating      DECIMAL(3,1),
-            external_id TEXT,
-            created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // audit_logs (db/security.rs::AuditLog)
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS audit_logs (
-            id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            user_id       UUID        REFERENCES users(id) ON DELETE SET NULL,
-            action        TEXT        NOT NULL,
-            resource_type TEXT,
-            resource_id   TEXT,
-            url           TEXT,
-            ip_address    TEXT,
-            user_agent    TEXT,
-            risk_level    TEXT        NOT NULL DEFAULT 'low',
-            status        TEXT        NOT NULL DEFAULT 'success',
-            metadata      JSONB,
-            created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // url_reputation (db/security.rs::UrlReputation)
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS url_reputation (
-            id                UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            url               TEXT        NOT NULL UNIQUE,
-            domain            TEXT,
-            risk_level        TEXT        NOT NULL DEFAULT 'unknown',
-            virustotal_result JSONB,
-            urlhaus_result    JSONB,
-            malicious_count   INTEGER     NOT NULL DEFAULT 0,
-            last_checked      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-            expires_at        TIMESTAMPTZ NOT NULL DEFAULT (NOW() + INTERVAL '24 hours'),
-            created_at        TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // domain_whitelist (db/security.rs::DomainWhitelist)
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS domain_whitelist (
-            id         UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            domain     TEXT        NOT NULL UNIQUE,
-            added_by   UUID        REFERENCES users(id) ON DELETE SET NULL,
-            reason     TEXT,
-            is_active  BOOLEAN     NOT NULL DEFAULT TRUE,
-            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // domain_blacklist (db/security.rs::DomainBlacklist)
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS domain_blacklist (
-            id          UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
-            domain      TEXT        NOT NULL UNIQUE,
-            risk_level  TEXT        NOT NULL DEFAULT 'high',
-            threat_type TEXT,
-            added_by    UUID        REFERENCES users(id) ON DELETE SET NULL,
-            reason      TEXT,
-            is_active   BOOLEAN     NOT NULL DEFAULT TRUE,
-            created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-            updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // Indexes
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_media_tmdb ON media(tmdb_id, media_type)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_media_type ON media(media_type)")
-        .execute(pool)
-        .await?;
-    sqlx::query(
-        "CREATE INDEX IF NOT EXISTS idx_media_title_trgm ON media USING gin(title gin_trgm_ops)",
-    )
-    .execute(pool)
-    .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_media_files_media ON media_files(media_id)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_search_results_media ON search_results(media_id)")
-        .execute(pool)
-        .await?;
-    sqlx::query(
-        "CREATE INDEX IF NOT EXISTS idx_search_results_expires ON search_results(expires_at)",
-    )
-    .execute(pool)
-    .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_watch_history_user ON watch_history(user_id)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_watch_history_media ON watch_history(media_id)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_favorites_user ON favorites(user_id)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_watchlist_user ON watchlist(user_id)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_collections_category ON collections(category)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_collection_items_collection ON collection_items(collection_id)")
-        .execute(pool).await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_tv_channels_country ON tv_channels(country)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_tv_channels_code ON tv_channels(code)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_tv_programs_channel ON tv_programs(channel_id)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_tv_programs_start_time ON tv_programs(start_time)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_audit_logs_user ON audit_logs(user_id)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_audit_logs_risk ON audit_logs(risk_level)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_audit_logs_created ON audit_logs(created_at)")
-        .execute(pool)
-        .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_url_reputation_domain ON url_reputation(domain)")
-        .execute(pool)
-        .await?;
-    sqlx::query(
-        "CREATE INDEX IF NOT EXISTS idx_domain_whitelist_domain ON domain_whitelist(domain)",
-    )
-    .execute(pool)
-    .await?;
-    sqlx::query(
-        "CREATE INDEX IF NOT EXISTS idx_domain_blacklist_domain ON domain_blacklist(domain)",
-    )
-    .execute(pool)
-    .await?;
-
-    // Fail-fast verification: key columns expected by db/*.rs
-    sqlx::query("SELECT media_id, user_id, progress_seconds, completed, watched_at FROM watch_history LIMIT 1")
-        .fetch_optional(pool).await?;
-    sqlx::query("SELECT media_id, user_id, added_at FROM favorites LIMIT 1")
-        .fetch_optional(pool)
-        .await?;
-    sqlx::query("SELECT media_id, user_id, added_at FROM watchlist LIMIT 1")
-        .fetch_optional(pool)
-        .await?;
-    sqlx::query("SELECT category, api_source, cover_image_url FROM collections LIMIT 1")
-        .fetch_optional(pool)
-        .await?;
-    sqlx::query("SELECT external_id, name, item_type, data_json FROM collection_items LIMIT 1")
-        .fetch_optional(pool)
-        .await?;
-
-    tracing::info!("Schema ready: 16 tables, 26 indexes");
-    Ok(())
-}
-
 async fn shutdown_signal() {
     let ctrl_c = async {
         signal::ctrl_c()
@@ -546,6 +96,7 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
     tracing::info!("Starting SOKOUL v3...");
 
     config::init();
@@ -589,24 +140,15 @@ async fn main() -> anyhow::Result<()> {
         })
     };
 
-    // Run migrations (default: enabled — migrations/ is now the source of truth)
-    let skip_migrations =
-        std::env::var("SKIP_MIGRATIONS").unwrap_or_else(|_| "false".to_string()) == "true";
-    if !skip_migrations {
-        tracing::info!("Running SQL migrations...");
-        // match sqlx::migrate!("./migrations").run(&db_pool).await {
-        //     Ok(_) => {
-        //         tracing::info!("SQL migrations completed");
-        //     }
-        //     Err(e) => {
-        //         tracing::warn!("SQL migrations failed (non-blocking): {}", e);
-        //     }
-        // }
-    } else {
-        tracing::info!("SKIP_MIGRATIONS=true — skipping migrations");
+    // `sokoul migrate up|down|status` manages the schema and exits.
+    if args.first().map(String::as_str) == Some("migrate") {
+        return migrate::cli(&db_pool, &args[1..]).await;
     }
 
-    ensure_critical_schema(&db_pool).await?;
+    // Apply pending migrations (default) and refuse a schema newer than this build.
+    let skip_migrations =
+        std::env::var("SKIP_MIGRATIONS").unwrap_or_else(|_| "false".to_string()) == "true";
+    migrate::startup(&db_pool, !skip_migrations).await?;
 
     let redis_client = redis::Client::open(CONFIG.redis_url.as_str()).expect("Invalid Redis URL");
 
@@ -880,11 +422,23 @@ async fn main() -> anyhow::Result<()> {
             "/media/:id/files",
             get(api::downloads::list_media_files_handler),
         )
+        .route(
+            "/media/:id/upgrade",
+            post(api::downloads::search_upgrade_handler),
+        )
+        .route(
+            "/media/:id/upgrades",
+            get(api::downloads::list_upgrades_handler),
+        )
         .route(
             "/media/:id/results",
             get(api::search::get_search_results_handler),
         )
         .route("/media/:id/episodes", get(api::media::get_episodes_handler))
+        .route(
+            "/media/:id/refresh",
+            post(api::media::refresh_metadata_handler),
+        )
         .route(
             "/media/:id/recommendations",
             get(api::recommendations::get_recommendations_handler),
@@ -914,12 +468,49 @@ async fn main() -> anyhow::Result<()> {
         .route("/tasks/:id", get(api::tasks::get_task_handler))
         // Storage
         .route("/storage", get(api::storage::get_storage_handler))
+        .route("/storage/media", get(api::storage::media_usage_handler))
+        .route("/storage/orphans", get(api::storage::orphans_handler))
+        .route(
+            "/storage/orphans/cleanup",
+            post(api::storage::cleanup_orphans_handler),
+        )
+        .route(
+            "/retention/policies",
+            get(api::retention::list_policies_handler).post(api::retention::create_policy_handler),
+        )
+        .route(
+            "/retention/policies/:id",
+            put(api::retention::update_policy_handler)
+                .delete(api::retention::delete_policy_handler),
+        )
+        .route("/retention/report", get(api::retention::report_handler))
+        .route("/retention/run", post(api::retention::run_handler))
         // File streaming
         .route(
             "/files/:file_id/stream",
             get(api::files::stream_file_handler),
         )
+        .route(
+            "/files/:file_id/stream.mp4",
+            get(api::files::stream_mp4_handler),
+        )
+        .route(
+            "/files/:file_id/master.m3u8",
+            get(api::hls::master_playlist_handler),
+        )
+        .route(
+            "/files/:file_id/hls/:rendition/:name",
+            get(api::hls::hls_resource_handler),
+        )
         .route("/files/:file_id/info", get(api::files::file_info_handler))
+        .route(
+            "/files/:file_id/subtitles",
+            get(api::subtitles::list_subtitles_handler),
+        )
+        .route(
+            "/files/:file_id/subtitles/:subtitle_id",
+            get(api::subtitles::subtitle_vtt_handler),
+        )
         // Library (favorites)
         .route(
             "/library",
@@ -933,10 +524,37 @@ async fn main() -> anyhow::Result<()> {
             "/library/:tmdb_id/:media_type",
             delete(api::library::remove_from_library_by_tmdb_handler),
         )
+        .route(
+            "/library/search",
+            get(api::library_search::library_search_handler),
+        )
         .route(
             "/library/status/:tmdb_id/:media_type",
             get(api::library::library_status_handler),
         )
+        // Library scanner
+        .route("/library/scan", post(api::library_scan::start_scan_handler))
+        .route("/library/nfo", post(api::nfo::regenerate_nfo_handler))
+        .route(
+            "/library/duplicates",
+            get(api::duplicates::list_duplicates_handler),
+        )
+        .route(
+            "/library/duplicates/resolve",
+            post(api::duplicates::resolve_duplicates_handler),
+        )
+        .route(
+            "/library/review",
+            get(api::library_scan::list_review_handler),
+        )
+        .route(
+            "/library/review/:id",
+            delete(api::library_scan::ignore_review_handler),
+        )
+        .route(
+            "/library/review/:id/resolve",
+            post(api::library_scan::resolve_review_handler),
+        )
         // Watchlist
         .route(
             "/watchlist",
@@ -945,12 +563,19 @@ async fn main() -> anyhow::Result<()> {
         )
         .route(
             "/watchlist/:media_id",
-            delete(api::watchlist::remove_from_watchlist_handler),
+            delete(api::watchlist::remove_from_watchlist_handler)
+                .patch(api::watchlist::update_watchlist_handler),
         )
         .route(
             "/watchlist/:tmdb_id/:media_type",
             delete(api::watchlist::remove_from_watchlist_by_tmdb_handler),
         )
+        // Quality profile
+        .route(
+            "/quality-profile",
+            get(api::quality_profile::get_quality_profile_handler)
+                .put(api::quality_profile::update_quality_profile_handler),
+        )
         // Watch History
         .route(
             "/watch-history",
@@ -967,6 +592,33 @@ async fn main() -> anyhow::Result<()> {
                 .delete(api::tracking::disable_tracking_handler)
                 .get(api::tracking::get_tracking_handler),
         )
+        .route(
+            "/media/:id/tracking/seasons/:season",
+            put(api::tracking::set_season_mode_handler),
+        )
+        // Anime
+        .route(
+            "/media/:id/anime",
+            get(api::anime::get_anime_mapping_handler)
+                .put(api::anime::set_anime_mapping_handler)
+                .delete(api::anime::delete_anime_mapping_handler),
+        )
+        .route("/anime/mal/:mal_id", get(api::anime::get_by_mal_id_handler))
+        .route(
+            "/media/:id/completeness",
+            get(api::tracking::completeness_handler),
+        )
+        .route(
+            "/media/:id/missing/search",
+            post(api::tracking::search_missing_handler),
+        )
+        .route("/calendar", get(api::calendar::calendar_handler))
+        .route(
+            "/calendar/feed",
+            get(api::calendar::get_feed_handler)
+                .post(api::calendar::rotate_feed_handler)
+                .delete(api::calendar::delete_feed_handler),
+        )
         .layer(axum_middleware::from_fn(api::auth::api_key_middleware));
 
     // Public metadata routes (no auth required)
@@ -977,6 +629,12 @@ async fn main() -> anyhow::Result<()> {
         .route(
             "/streaming/embed/:media_type/:tmdb_id",
             get(api::streaming::vidfast_embed_handler),
+        )
+        // Artwork is loaded by <img> tags, which cannot send the API key
+        .route("/images", get(api::images::image_proxy_handler))
+        .route(
+            "/media/:id/artwork/:kind",
+            get(api::images::media_artwork_handler),
         );
 
     // Collections routes (nested under /api/collections)
@@ -1003,6 +661,11 @@ async fn main() -> anyhow::Result<()> {
         .route("/health", get(api::health::health_check_handler))
         .route("/metrics", get(api::metrics::metrics_handler))
         .route("/ws", get(api::ws::ws_handler))
+        // Calendar apps cannot send auth headers; the token in the URL is the credential
+        .route(
+            "/calendar/feed/:token",
+            get(api::calendar::ics_feed_handler),
+        )
         .merge(auth_routes_limited);
 
     // General API rate limit: 120 req/min per IP
@@ -42,6 +42,11 @@ lazy_static! {
         "Total number of cache misses"
     ).expect("Failed to create CACHE_MISSES_TOTAL metric");
 
+    pub static ref CACHE_BYPASSES_TOTAL: Counter = Counter::new(
+        "sokoul_cache_bypasses_total",
+        "Total number of lookups skipped to force a fresh result"
+    ).expect("Failed to create CACHE_BYPASSES_TOTAL metric");
+
     pub static ref CACHE_SIZE_BYTES: IntGauge = IntGauge::new(
         "sokoul_cache_size_bytes",
         "Current cache size in bytes"
@@ -194,6 +199,9 @@ pub fn init() {
         .ok();
     registry.register(Box::new(CACHE_HITS_TOTAL.clone())).ok();
     registry.register(Box::new(CACHE_MISSES_TOTAL.clone())).ok();
+    registry
+        .register(Box::new(CACHE_BYPASSES_TOTAL.clone()))
+        .ok();
     registry.register(Box::new(CACHE_SIZE_BYTES.clone())).ok();
     registry.register(Box::new(WORKER_JOBS_TOTAL.clone())).ok();
     registry
@@ -0,0 +1,507 @@
+//! Versioned schema migrations.
+//!
+//! Migrations live in `migrations/NNNN_name.up.sql` (with an optional
+//! `.down.sql`) and are compiled into the binary. Each applied migration is
+//! recorded in `schema_migrations` with the checksum of its up script, so an
+//! edited migration or a database migrated by a newer build is refused.
+
+use anyhow::{bail, Context};
+use sha2::{Digest, Sha256};
+use sqlx::{pool::PoolConnection, Acquire, Executor, FromRow, PgPool, Postgres};
+use std::time::Instant;
+
+#[derive(Debug)]
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static str,
+    /// None for irreversible migrations
+    pub down: Option<&'static str>,
+}
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        Sha256::digest(self.up.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Whether an applied migration recorded with `checksum` is this one.
+    fn accepts(&self, checksum: &str) -> bool {
+        checksum == self.checksum() || SUPERSEDED.contains(&(self.version, checksum))
+    }
+}
+
+/// All migrations, in version order.
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline",
+        up: include_str!("../migrations/0001_baseline.up.sql"),
+        down: None,
+    },
+    Migration {
+        version: 2,
+        name: "media_metadata_refresh",
+        up: include_str!("../migrations/0002_media_metadata_refresh.up.sql"),
+        down: Some(include_str!(
+            "../migrations/0002_media_metadata_refresh.down.sql"
+        )),
+    },
+    Migration {
+        version: 3,
+        name: "media_ratings",
+        up: include_str!("../migrations/0003_media_ratings.up.sql"),
+        down: Some(include_str!("../migrations/0003_media_ratings.down.sql")),
+    },
+    Migration {
+        version: 4,
+        name: "media_search",
+        up: include_str!("../migrations/0004_media_search.up.sql"),
+        down: Some(include_str!("../migrations/0004_media_search.down.sql")),
+    },
+    Migration {
+        version: 5,
+        name: "media_titles",
+        up: include_str!("../migrations/0005_media_titles.up.sql"),
+        down: Some(include_str!("../migrations/0005_media_titles.down.sql")),
+    },
+    Migration {
+        version: 6,
+        name: "anime_mappings",
+        up: include_str!("../migrations/0006_anime_mappings.up.sql"),
+        down: Some(include_str!("../migrations/0006_anime_mappings.down.sql")),
+    },
+    Migration {
+        version: 7,
+        name: "retention_disabled_by_default",
+        up: include_str!("../migrations/0007_retention_disabled_by_default.up.sql"),
+        down: Some(include_str!(
+            "../migrations/0007_retention_disabled_by_default.down.sql"
+        )),
+    },
+    Migration {
+        version: 8,
+        name: "watchlist_quality_override",
+        up: include_str!("../migrations/0008_watchlist_quality_override.up.sql"),
+        down: Some(include_str!(
+            "../migrations/0008_watchlist_quality_override.down.sql"
+        )),
+    },
+    Migration {
+        version: 9,
+        name: "media_ratings_refresh",
+        up: include_str!("../migrations/0009_media_ratings_refresh.up.sql"),
+        down: Some(include_str!(
+            "../migrations/0009_media_ratings_refresh.down.sql"
+        )),
+    },
+    Migration {
+        version: 10,
+        name: "media_metadata_failures",
+        up: include_str!("../migrations/0010_media_metadata_failures.up.sql"),
+        down: Some(include_str!(
+            "../migrations/0010_media_metadata_failures.down.sql"
+        )),
+    },
+    Migration {
+        version: 11,
+        name: "media_files_fingerprint",
+        up: include_str!("../migrations/0011_media_files_fingerprint.up.sql"),
+        down: Some(include_str!(
+            "../migrations/0011_media_files_fingerprint.down.sql"
+        )),
+    },
+    Migration {
+        version: 12,
+        name: "media_episodes_synced",
+        up: include_str!("../migrations/0012_media_episodes_synced.up.sql"),
+        down: Some(include_str!(
+            "../migrations/0012_media_episodes_synced.down.sql"
+        )),
+    },
+    Migration {
+        version: 13,
+        name: "media_files_inode",
+        up: include_str!("../migrations/0013_media_files_inode.up.sql"),
+        down: Some(include_str!(
+            "../migrations/0013_media_files_inode.down.sql"
+        )),
+    },
+    Migration {
+        version: 14,
+        name: "media_episode_unique",
+        up: include_str!("../migrations/0014_media_episode_unique.up.sql"),
+        down: Some(include_str!(
+            "../migrations/0014_media_episode_unique.down.sql"
+        )),
+    },
+];
+
+/// Checksums of earlier revisions of a migration, still accepted from the
+/// databases they were applied to. Only for fixes that cannot be a migration of
+/// their own or must not run as first written: the baseline lost the episode
+/// index, which must come after the dedupe of migration 14, and its leftover
+/// column checks; migration 8 no longer clears every '1080p' minimum.
+static SUPERSEDED: &[(i64, &str)] = &[
+    (
+        1,
+        "350084d290c3ad1dc5a6595996d50b21e3bc061e066fd5a66ae8ccd4cf028c7a",
+    ),
+    (
+        1,
+        "999f91f18f4bcb46da1cfcda297db78ea83403898851e50f5b4feef95688d1e6",
+    ),
+    (
+        1,
+        "38c6625b35f23e351bf13ee2f884d1d62bcd0f5b37a1fb6ab4d1575204e23074",
+    ),
+    (
+        8,
+        "eb2c4452010bac0d2203ca317f611174915f6225909201b8694cf279c47c8479",
+    ),
+];
+
+/// Serializes migration runs across instances starting at the same time.
+const LOCK_KEY: i64 = 0x736f_6b6f_756c;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: chrono::DateTime<chrono::Utc>,
+    pub execution_ms: i64,
+}
+
+/// Migrations still to apply, after making sure every applied one is known to
+/// this build and unchanged.
+fn pending<'a>(
+    applied: &[AppliedMigration],
+    known: &'a [Migration],
+) -> anyhow::Result<Vec<&'a Migration>> {
+    let latest = known.last().map(|m| m.version).unwrap_or(0);
+    for row in applied {
+        let Some(migration) = known.iter().find(|m| m.version == row.version) else {
+            bail!(
+                "Database schema has migration {} ({}) unknown to this build (latest {}); \
+                 upgrade Sokoul or run the matching `sokoul migrate down` with the newer build",
+                row.version,
+                row.name,
+                latest
+            );
+        };
+        if !migration.accepts(&row.checksum) {
+            bail!(
+                "Migration {} ({}) was modified after being applied (checksum mismatch)",
+                row.version,
+                row.name
+            );
+        }
+    }
+    Ok(known
+        .iter()
+        .filter(|m| applied.iter().all(|a| a.version != m.version))
+        .collect())
+}
+
+async fn ensure_table(conn: &mut PoolConnection<Postgres>) -> anyhow::Result<()> {
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version      BIGINT      PRIMARY KEY,
+            name         TEXT        NOT NULL,
+            checksum     TEXT        NOT NULL,
+            applied_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            execution_ms BIGINT      NOT NULL
+        )
+        "#,
+    )
+    .await?;
+    Ok(())
+}
+
+async fn list_applied(
+    conn: &mut PoolConnection<Postgres>,
+) -> anyhow::Result<Vec<AppliedMigration>> {
+    Ok(
+        sqlx::query_as::<_, AppliedMigration>("SELECT * FROM schema_migrations ORDER BY version")
+            .fetch_all(&mut **conn)
+            .await?,
+    )
+}
+
+async fn apply(conn: &mut PoolConnection<Postgres>, migration: &Migration) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let mut tx = conn.begin().await?;
+    tx.execute(migration.up).await.with_context(|| {
+        format!(
+            "Migration {} ({}) failed",
+            migration.version, migration.name
+        )
+    })?;
+    sqlx::query(
+        "INSERT INTO schema_migrations (version, name, checksum, execution_ms) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(migration.version)
+    .bind(migration.name)
+    .bind(migration.checksum())
+    .bind(started.elapsed().as_millis() as i64)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    tracing::info!(
+        "Migration {} ({}) applied in {} ms",
+        migration.version,
+        migration.name,
+        started.elapsed().as_millis()
+    );
+    Ok(())
+}
+
+async fn revert(conn: &mut PoolConnection<Postgres>, migration: &Migration) -> anyhow::Result<()> {
+    let Some(down) = migration.down else {
+        bail!(
+            "Migration {} ({}) is irreversible",
+            migration.version,
+            migration.name
+        );
+    };
+    let mut tx = conn.begin().await?;
+    tx.execute(down).await.with_context(|| {
+        format!(
+            "Reverting migration {} ({}) failed",
+            migration.version, migration.name
+        )
+    })?;
+    sqlx::query("DELETE FROM schema_migrations WHERE version = $1")
+        .bind(migration.version)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    tracing::info!(
+        "Migration {} ({}) reverted",
+        migration.version,
+        migration.name
+    );
+    Ok(())
+}
+
+/// Run `f` on a connection holding the migration lock.
+async fn locked<T>(
+    pool: &PgPool,
+    f: impl for<'c> FnOnce(
+        &'c mut PoolConnection<Postgres>,
+    ) -> futures::future::BoxFuture<'c, anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    let mut conn = pool.acquire().await?;
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(LOCK_KEY)
+        .execute(&mut *conn)
+        .await?;
+    let result = async {
+        ensure_table(&mut conn).await?;
+        f(&mut conn).await
+    }
+    .await;
+    sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(LOCK_KEY)
+        .execute(&mut *conn)
+        .await?;
+    result
+}
+
+/// Apply every pending migration. Returns the versions applied.
+pub async fn up(pool: &PgPool) -> anyhow::Result<Vec<i64>> {
+    locked(pool, |conn| {
+        Box::pin(async move {
+            let applied = list_applied(conn).await?;
+            let mut done = Vec::new();
+            for migration in pending(&applied, MIGRATIONS)? {
+                apply(conn, migration).await?;
+                done.push(migration.version);
+            }
+            Ok(done)
+        })
+    })
+    .await
+}
+
+/// Revert the `steps` most recent migrations. Returns the versions reverted.
+pub async fn down(pool: &PgPool, steps: usize) -> anyhow::Result<Vec<i64>> {
+    locked(pool, |conn| {
+        Box::pin(async move {
+            let applied = list_applied(conn).await?;
+            pending(&applied, MIGRATIONS)?;
+            let mut done = Vec::new();
+            for row in applied.iter().rev().take(steps) {
+                let migration = MIGRATIONS
+                    .iter()
+                    .find(|m| m.version == row.version)
+                    .expect("checked by pending()");
+                revert(conn, migration).await?;
+                done.push(migration.version);
+            }
+            Ok(done)
+        })
+    })
+    .await
+}
+
+/// Check the schema at startup and, unless `apply` is false, bring it up to date.
+pub async fn startup(pool: &PgPool, apply: bool) -> anyhow::Result<()> {
+    if apply {
+        up(pool).await?;
+    } else {
+        let pending = locked(pool, |conn| {
+            Box::pin(async move {
+                let applied = list_applied(conn).await?;
+                Ok(pending(&applied, MIGRATIONS)?.len())
+            })
+        })
+        .await?;
+        if pending > 0 {
+            tracing::warn!(
+                "SKIP_MIGRATIONS=true — {} migration(s) pending, run `sokoul migrate up`",
+                pending
+            );
+        }
+    }
+    tracing::info!(
+        "Schema ready: version {}",
+        MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+    );
+    Ok(())
+}
+
+async fn status(pool: &PgPool) -> anyhow::Result<()> {
+    let applied = locked(pool, |conn| Box::pin(list_applied(conn))).await?;
+    println!("{:>7}  {:<32} {:<8} APPLIED AT", "VERSION", "NAME", "STATE");
+    for migration in MIGRATIONS {
+        match applied.iter().find(|a| a.version == migration.version) {
+            Some(row) => {
+                let state = if migration.accepts(&row.checksum) {
+                    "applied"
+                } else {
+                    "modified"
+                };
+                println!(
+                    "{:>7}  {:<32} {:<8} {} ({} ms)",
+                    migration.version,
+                    migration.name,
+                    state,
+                    row.applied_at.format("%Y-%m-%d %H:%M:%S"),
+                    row.execution_ms
+                );
+            }
+            None => println!(
+                "{:>7}  {:<32} {:<8}",
+                migration.version, migration.name, "pending"
+            ),
+        }
+    }
+    for row in applied
+        .iter()
+        .filter(|a| MIGRATIONS.iter().all(|m| m.version != a.version))
+    {
+        println!(
+            "{:>7}  {:<32} {:<8} {}",
+            row.version,
+            row.name,
+            "unknown",
+            row.applied_at.format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+    Ok(())
+}
+
+/// `sokoul migrate up|down [steps]|status`
+pub async fn cli(pool: &PgPool, args: &[String]) -> anyhow::Result<()> {
+    match args.first().map(String::as_str) {
+        Some("up") => {
+            let applied = up(pool).await?;
+            println!("Applied {} migration(s) {:?}", applied.len(), applied);
+        }
+        Some("down") => {
+            let steps = match args.get(1) {
+                Some(n) => n.parse().context("steps must be a number")?,
+                None => 1,
+            };
+            let reverted = down(pool, steps).await?;
+            println!("Reverted {} migration(s) {:?}", reverted.len(), reverted);
+        }
+        Some("status") => status(pool).await?,
+        _ => bail!("Usage: sokoul migrate up | down [steps] | status"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    const KNOWN: &[Migration] = &[
+        Migration {
+            version: 1,
+            name: "baseline",
+            up: "CREATE TABLE a (id INT);",
+            down: None,
+        },
+        Migration {
+            version: 2,
+            name: "add_b",
+            up: "CREATE TABLE b (id INT);",
+            down: Some("DROP TABLE b;"),
+        },
+    ];
+
+    fn applied(migration: &Migration, checksum: Option<&str>) -> AppliedMigration {
+        AppliedMigration {
+            version: migration.version,
+            name: migration.name.to_string(),
+            checksum: checksum
+                .map(str::to_string)
+                .unwrap_or_else(|| migration.checksum()),
+            applied_at: Utc::now(),
+            execution_ms: 1,
+        }
+    }
+
+    #[test]
+    fn lists_pending_in_order() {
+        let versions = |p: Vec<&Migration>| p.iter().map(|m| m.version).collect::<Vec<_>>();
+        assert_eq!(versions(pending(&[], KNOWN).unwrap()), vec![1, 2]);
+        assert_eq!(
+            versions(pending(&[applied(&KNOWN[0], None)], KNOWN).unwrap()),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn refuses_unknown_or_modified_migrations() {
+        let newer = AppliedMigration {
+            version: 3,
+            ..applied(&KNOWN[1], None)
+        };
+        let err = pending(&[applied(&KNOWN[0], None), newer], KNOWN).unwrap_err();
+        assert!(err.to_string().contains("unknown to this build"));
+
+        let err = pending(&[applied(&KNOWN[0], Some("edited"))], KNOWN).unwrap_err();
+        assert!(err.to_string().contains("modified"));
+    }
+
+    #[test]
+    fn accepts_superseded_revisions() {
+        let (version, checksum) = SUPERSEDED[0];
+        let baseline = MIGRATIONS.iter().find(|m| m.version == version).unwrap();
+        assert!(pending(&[applied(baseline, Some(checksum))], MIGRATIONS).is_ok());
+        assert!(!KNOWN[1].accepts(checksum));
+    }
+
+    #[test]
+    fn migrations_are_ordered() {
+        assert!(MIGRATIONS.windows(2).all(|w| w[0].version < w[1].version));
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use std::collections::HashMap;
@@ -25,6 +25,7 @@ pub struct Media {
     pub parent_id: Option<Uuid>,
     pub season_number: Option<i32>,
     pub episode_number: Option<i32>,
+    pub air_date: Option<NaiveDate>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -41,6 +42,18 @@ pub struct CreateMediaPayload {
     pub rating: Option<f64>,
 }
 
+/// Episode row synced from TMDB/TVMaze under a series (`parent_id`).
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpsertEpisodePayload {
+    pub season_number: i32,
+    pub episode_number: i32,
+    pub title: String,
+    pub tmdb_id: Option<i32>,
+    pub overview: Option<String>,
+    pub air_date: Option<NaiveDate>,
+    pub runtime_minutes: Option<i32>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UpdateMediaPayload {
     pub title: Option<String>,
@@ -58,6 +71,8 @@ pub struct UpdateMediaPayload {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ApiSearchPayload {
     pub query: String,
+    #[serde(default)]
+    pub refresh: bool,
 }
 
 // ── Search Results ──
@@ -81,6 +96,8 @@ pub struct SearchResult {
     pub ai_validated: Option<bool>,
     pub created_at: DateTime<Utc>,
     pub expires_at: Option<DateTime<Utc>>,
+    /// Title or alias of the media this release was accepted under
+    pub matched_title: Option<String>,
 }
 
 // ── Tasks ──
@@ -178,12 +195,8 @@ pub struct AddWatchlistPayload {
     pub year: Option<i32>,
     #[serde(default)]
     pub auto_download: bool,
-    #[serde(default = "default_quality")]
-    pub quality_min: String,
-}
-
-fn default_quality() -> String {
-    "1080p".to_string()
+    /// Overrides the quality profile's minimum resolution when set
+    pub quality_min: Option<String>,
 }
 
 // ── Library Status ──
@@ -211,6 +224,14 @@ pub struct MediaFile {
     pub hash_info: Option<String>,
     pub source: Option<String>,
     pub downloaded_at: Option<DateTime<Utc>>,
+    /// Set while the file is gone from disk (deleted or moved outside the watched roots).
+    pub missing_since: Option<DateTime<Utc>>,
+    /// Partial-content hash recognizing the file after a move (utils/file_hash.rs)
+    pub fingerprint: Option<String>,
+    /// Modification time when `fingerprint` was computed
+    pub file_mtime: Option<DateTime<Utc>>,
+    /// "<device>:<inode>", shared by hardlinks to the same file
+    pub file_inode: Option<String>,
 }
 
 // ── Pagination ──
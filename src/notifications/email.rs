@@ -146,6 +146,34 @@ impl EmailService {
         Ok(())
     }
 
+    /// Send a plain-text notification to a user
+    pub async fn send_notification(
+        &self,
+        to: &str,
+        subject: &str,
+        body: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        if !self.enabled {
+            return Err("Email notifications are disabled".into());
+        }
+
+        let email = Message::builder()
+            .from(
+                self.smtp_user
+                    .parse()
+                    .unwrap_or_else(|_| "sokoul@example.com".parse().unwrap()),
+            )
+            .to(to.parse()?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.to_string())?;
+
+        let transport = self.create_transport()?;
+        transport.send(&email)?;
+
+        Ok(())
+    }
+
     /// Send a security digest email with multiple events
     pub async fn send_digest(
         &self,
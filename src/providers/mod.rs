@@ -1,8 +1,13 @@
 pub mod jackett;
+pub mod opensubtitles;
 pub mod prowlarr;
+pub mod search_cache;
 pub mod streaming;
+pub mod subtitles;
 
+use crate::clients::flaresolverr::FlareSolverrClient;
 use crate::config::CONFIG;
+use crate::utils::fuzzy;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
@@ -39,13 +44,47 @@ pub trait SearchProvider: Send + Sync {
     ) -> anyhow::Result<Vec<TorrentResult>>;
 }
 
+/// Most titles (main title, then aliases) tried for one search.
+const MAX_TITLE_QUERIES: usize = 3;
+
 pub struct ProviderRegistry {
     providers: Vec<Box<dyn SearchProvider>>,
+    redis_client: Option<redis::Client>,
 }
 
 impl ProviderRegistry {
-    pub fn new() -> Self {
-        Self { providers: vec![] }
+    /// Registry whose searches go through the per-provider Redis cache.
+    pub fn with_cache(redis_client: redis::Client) -> Self {
+        Self {
+            providers: vec![],
+            redis_client: Some(redis_client),
+        }
+    }
+
+    /// Cached registry with every indexer (Prowlarr, Jackett) configured in the environment.
+    pub fn with_indexers(
+        redis_client: redis::Client,
+        flaresolverr_client: Option<FlareSolverrClient>,
+    ) -> Self {
+        let mut registry = Self::with_cache(redis_client);
+
+        if !CONFIG.prowlarr_url.is_empty() && !CONFIG.prowlarr_api_key.is_empty() {
+            registry.register(Box::new(prowlarr::ProwlarrProvider::new(
+                CONFIG.prowlarr_api_key.clone(),
+                CONFIG.prowlarr_url.clone(),
+                flaresolverr_client.clone(),
+            )));
+        }
+
+        if !CONFIG.jackett_url.is_empty() && !CONFIG.jackett_api_key.is_empty() {
+            registry.register(Box::new(jackett::JackettProvider::new(
+                CONFIG.jackett_api_key.clone(),
+                CONFIG.jackett_url.clone(),
+                flaresolverr_client,
+            )));
+        }
+
+        registry
     }
 
     pub fn register(&mut self, provider: Box<dyn SearchProvider>) {
@@ -58,11 +97,10 @@ impl ProviderRegistry {
         title: &str,
         _media_type: &str,
         _tmdb_id: Option<i32>,
+        bypass_cache: bool,
     ) -> Vec<TorrentResult> {
-        use anyhow::anyhow;
         use futures::future::join_all;
         use std::time::Duration;
-        use tokio::time::timeout;
         let search_timeout = Duration::from_secs(CONFIG.indexer_search_timeout_secs.max(5));
 
         // Always use text search - public indexers don't support TMDB ID search
@@ -72,14 +110,14 @@ impl ProviderRegistry {
             async move {
                 tracing::info!("Provider '{}': text search for '{}'", name, title);
 
-                let result = match timeout(search_timeout, provider.search(&title)).await {
-                    Ok(res) => res,
-                    Err(_) => Err(anyhow!(
-                        "timeout provider '{}' after {:?}",
-                        name,
-                        search_timeout
-                    )),
-                };
+                let result = search_cache::cached_search(
+                    self.redis_client.as_ref(),
+                    provider.as_ref(),
+                    &title,
+                    search_timeout,
+                    bypass_cache,
+                )
+                .await;
 
                 (name, result)
             }
@@ -103,6 +141,42 @@ impl ProviderRegistry {
         results
     }
 
+    /// Search `query(title)` for each of `titles` (main title first, then
+    /// aliases) until a search returns releases named after one of them.
+    /// Results of every search made are merged.
+    pub async fn search_titles(
+        &self,
+        titles: &[String],
+        query: impl Fn(&str) -> String,
+        media_type: &str,
+        tmdb_id: Option<i32>,
+        bypass_cache: bool,
+    ) -> Vec<TorrentResult> {
+        let mut results: Vec<TorrentResult> = Vec::new();
+        for title in titles.iter().take(MAX_TITLE_QUERIES) {
+            let found = self
+                .search_all(
+                    &query(&fuzzy::fold_accents(title)),
+                    media_type,
+                    tmdb_id,
+                    bypass_cache,
+                )
+                .await;
+            let matched = found
+                .iter()
+                .any(|r| fuzzy::best_title_match(&r.title, titles, 0.50).is_some());
+            for result in found {
+                if !results.iter().any(|r| r.guid == result.guid) {
+                    results.push(result);
+                }
+            }
+            if matched {
+                break;
+            }
+        }
+        results
+    }
+
     pub fn list_enabled_names(&self) -> Vec<String> {
         self.providers
             .iter()
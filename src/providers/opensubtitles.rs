@@ -0,0 +1,299 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+
+use super::subtitles::{SubtitleCandidate, SubtitleProvider, SubtitleSearch};
+use crate::utils::language;
+
+/// OpenSubtitles REST API (`/api/v1`), or any server speaking the same protocol.
+pub struct OpenSubtitlesProvider {
+    client: Client,
+    base_url: String,
+    api_key: String,
+    username: String,
+    password: String,
+    /// Login token; anonymous downloads are limited to a few per day.
+    token: tokio::sync::RwLock<Option<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    data: Vec<SearchItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchItem {
+    attributes: SearchAttributes,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct SearchAttributes {
+    language: Option<String>,
+    release: Option<String>,
+    download_count: u64,
+    hearing_impaired: bool,
+    foreign_parts_only: bool,
+    from_trusted: bool,
+    moviehash_match: bool,
+    files: Vec<SearchFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchFile {
+    file_id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginResponse {
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DownloadResponse {
+    link: String,
+}
+
+impl OpenSubtitlesProvider {
+    pub fn new(base_url: String, api_key: String, username: String, password: String) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            username,
+            password,
+            token: tokio::sync::RwLock::new(None),
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, format!("{}{}", self.base_url, path))
+            .header("Api-Key", &self.api_key)
+            .header(
+                reqwest::header::USER_AGENT,
+                concat!("Sokoul v", env!("CARGO_PKG_VERSION")),
+            )
+    }
+
+    /// Token of the configured account, logging in on first use.
+    async fn token(&self) -> anyhow::Result<Option<String>> {
+        if self.username.is_empty() {
+            return Ok(None);
+        }
+        if let Some(token) = self.token.read().await.clone() {
+            return Ok(Some(token));
+        }
+
+        let response: LoginResponse = self
+            .request(reqwest::Method::POST, "/login")
+            .json(&serde_json::json!({
+                "username": self.username,
+                "password": self.password,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        *self.token.write().await = Some(response.token.clone());
+        Ok(Some(response.token))
+    }
+
+    fn query(search: &SubtitleSearch) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+        // IMDb ids are sent without their "tt" prefix.
+        let imdb_id = search.imdb_id.as_deref().map(|id| {
+            id.trim_start_matches("tt")
+                .trim_start_matches('0')
+                .to_string()
+        });
+        match search.episode {
+            Some((season, episode)) => {
+                if let Some(id) = imdb_id {
+                    query.push(("parent_imdb_id", id));
+                }
+                if let Some(id) = search.tmdb_id {
+                    query.push(("parent_tmdb_id", id.to_string()));
+                }
+                query.push(("season_number", season.to_string()));
+                query.push(("episode_number", episode.to_string()));
+            }
+            None => {
+                if let Some(id) = imdb_id {
+                    query.push(("imdb_id", id));
+                }
+                if let Some(id) = search.tmdb_id {
+                    query.push(("tmdb_id", id.to_string()));
+                }
+            }
+        }
+        if let Some(hash) = &search.movie_hash {
+            query.push(("moviehash", hash.clone()));
+        }
+        if !search.languages.is_empty() {
+            let mut languages = search.languages.clone();
+            languages.sort();
+            query.push(("languages", languages.join(",")));
+        }
+        // The API redirects requests whose parameters are not sorted.
+        query.sort_by_key(|(name, _)| *name);
+        query
+    }
+}
+
+#[async_trait]
+impl SubtitleProvider for OpenSubtitlesProvider {
+    fn name(&self) -> &str {
+        "OpenSubtitles"
+    }
+
+    async fn search(&self, search: &SubtitleSearch) -> anyhow::Result<Vec<SubtitleCandidate>> {
+        let response: SearchResponse = self
+            .request(reqwest::Method::GET, "/subtitles")
+            .query(&Self::query(search))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response
+            .data
+            .into_iter()
+            .filter_map(|item| {
+                let a = item.attributes;
+                let language = a.language.as_deref().and_then(language::normalize)?;
+                let file = a.files.first()?;
+                Some(SubtitleCandidate {
+                    provider_name: self.name().to_string(),
+                    id: file.file_id.to_string(),
+                    language: language.to_string(),
+                    release: a.release,
+                    hash_match: a.moviehash_match,
+                    forced: a.foreign_parts_only,
+                    hearing_impaired: a.hearing_impaired,
+                    trusted: a.from_trusted,
+                    downloads: a.download_count,
+                })
+            })
+            .collect())
+    }
+
+    async fn download(&self, candidate: &SubtitleCandidate) -> anyhow::Result<Vec<u8>> {
+        let file_id: i64 = candidate.id.parse()?;
+        let mut request = self
+            .request(reqwest::Method::POST, "/download")
+            .json(&serde_json::json!({ "file_id": file_id, "sub_format": "srt" }));
+        if let Some(token) = self.token().await? {
+            request = request.bearer_auth(token);
+        }
+        let response: DownloadResponse = request.send().await?.error_for_status()?.json().await?;
+
+        let bytes = self
+            .client
+            .get(&response.link)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        Ok(bytes.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{body_json, header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn searches_and_downloads_from_rest_api() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/subtitles"))
+            .and(header("Api-Key", "key"))
+            .and(query_param("parent_imdb_id", "944947"))
+            .and(query_param("season_number", "1"))
+            .and(query_param("episode_number", "2"))
+            .and(query_param("moviehash", "8e245d9679d31e12"))
+            .and(query_param("languages", "en,fr"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [
+                    {"id": "1", "attributes": {
+                        "language": "fr", "release": "Show.S01E02.1080p",
+                        "download_count": 42, "moviehash_match": true,
+                        "files": [{"file_id": 101, "file_name": "a.srt"}]
+                    }},
+                    {"id": "2", "attributes": {
+                        "language": "pt-BR", "hearing_impaired": true,
+                        "files": [{"file_id": 102}]
+                    }},
+                    {"id": "3", "attributes": {"language": "en", "files": []}}
+                ]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/login"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"token": "tok"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/download"))
+            .and(header("Authorization", "Bearer tok"))
+            .and(body_json(
+                serde_json::json!({"file_id": 101, "sub_format": "srt"}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "link": format!("{}/files/101.srt", server.uri()),
+                "remaining": 99
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/files/101.srt"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("1\n00:00:01,000 --> 00:00:02,000\nSalut\n"),
+            )
+            .mount(&server)
+            .await;
+
+        let provider = OpenSubtitlesProvider::new(
+            server.uri(),
+            "key".to_string(),
+            "user".to_string(),
+            "secret".to_string(),
+        );
+        let candidates = provider
+            .search(&SubtitleSearch {
+                languages: vec!["fr".to_string(), "en".to_string()],
+                movie_hash: Some("8e245d9679d31e12".to_string()),
+                imdb_id: Some("tt0944947".to_string()),
+                tmdb_id: None,
+                episode: Some((1, 2)),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].id, "101");
+        assert_eq!(candidates[0].language, "fr");
+        assert!(candidates[0].hash_match);
+        assert_eq!(candidates[1].language, "pt");
+        assert!(candidates[1].hearing_impaired);
+
+        for _ in 0..2 {
+            let bytes = provider.download(&candidates[0]).await.unwrap();
+            assert!(String::from_utf8(bytes).unwrap().contains("Salut"));
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use super::{SearchProvider, TorrentResult};
+use crate::{
+    cache,
+    config::CONFIG,
+    metrics::{CACHE_BYPASSES_TOTAL, CACHE_HITS_TOTAL, CACHE_MISSES_TOTAL},
+};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+type InflightCell = Arc<OnceCell<Result<Vec<TorrentResult>, String>>>;
+
+/// Upstream searches currently running, keyed like the Redis cache entries.
+/// Concurrent identical searches share a single cell instead of hitting the indexer again.
+static INFLIGHT: Lazy<Mutex<HashMap<String, InflightCell>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Normalize a free-text query so that "The.Matrix  1999" and "the matrix 1999"
+/// share the same cache entry.
+pub fn normalize_query(query: &str) -> String {
+    query
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn cache_key(provider_name: &str, query: &str) -> String {
+    format!(
+        "indexer:{}:{}",
+        provider_name.to_lowercase(),
+        normalize_query(query)
+    )
+}
+
+/// Search a single provider through the Redis cache.
+///
+/// On a hit the cached results are returned without contacting the indexer.
+/// On a miss (or when `bypass_cache` is set) the upstream search runs once per
+/// key, even if several callers ask for it concurrently, and the fresh results
+/// are written back with `INDEXER_CACHE_TTL_SECS`. Empty results are not
+/// cached: they are often a transient indexer failure, and a release posted
+/// meanwhile must not stay hidden for the whole TTL.
+pub async fn cached_search(
+    redis_client: Option<&redis::Client>,
+    provider: &dyn SearchProvider,
+    query: &str,
+    search_timeout: Duration,
+    bypass_cache: bool,
+) -> anyhow::Result<Vec<TorrentResult>> {
+    let key = cache_key(provider.name(), query);
+    let ttl = redis_client
+        .map(|_| CONFIG.indexer_cache_ttl_secs)
+        .unwrap_or(0);
+
+    match redis_client {
+        Some(_) if ttl > 0 && bypass_cache => CACHE_BYPASSES_TOTAL.inc(),
+        Some(client) if ttl > 0 => {
+            match cache::get_from_cache::<Vec<TorrentResult>>(client, &key).await {
+                Ok(Some(results)) => {
+                    CACHE_HITS_TOTAL.inc();
+                    tracing::debug!("Indexer cache hit for '{}'", key);
+                    return Ok(results);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!("Redis cache error (read): {}", e);
+                }
+            }
+            CACHE_MISSES_TOTAL.inc();
+        }
+        _ => {}
+    }
+
+    let cell = {
+        let mut inflight = INFLIGHT.lock().unwrap_or_else(|e| e.into_inner());
+        inflight.entry(key.clone()).or_default().clone()
+    };
+
+    let result = cell
+        .get_or_init(|| async {
+            let fetched = match tokio::time::timeout(search_timeout, provider.search(query)).await {
+                Ok(Ok(results)) => Ok(results),
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(_) => Err(format!(
+                    "timeout provider '{}' after {:?}",
+                    provider.name(),
+                    search_timeout
+                )),
+            };
+
+            if let (Ok(results), Some(client)) = (&fetched, redis_client) {
+                if ttl > 0 && !results.is_empty() {
+                    if let Err(e) = cache::set_to_cache_with_ttl(client, &key, results, ttl).await {
+                        tracing::warn!("Redis cache error (write): {}", e);
+                    }
+                }
+            }
+
+            fetched
+        })
+        .await
+        .clone();
+
+    {
+        let mut inflight = INFLIGHT.lock().unwrap_or_else(|e| e.into_inner());
+        if inflight
+            .get(&key)
+            .is_some_and(|current| Arc::ptr_eq(current, &cell))
+        {
+            inflight.remove(&key);
+        }
+    }
+
+    result.map_err(|e| anyhow::anyhow!(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingProvider {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl SearchProvider for CountingProvider {
+        fn name(&self) -> &str {
+            "Counting"
+        }
+
+        async fn search(&self, _query: &str) -> anyhow::Result<Vec<TorrentResult>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(vec![])
+        }
+
+        async fn search_by_tmdb_id(
+            &self,
+            _tmdb_id: i32,
+            _media_type: &str,
+        ) -> anyhow::Result<Vec<TorrentResult>> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn normalizes_separators_and_case() {
+        assert_eq!(normalize_query("  The.Matrix__1999 "), "the matrix 1999");
+        assert_eq!(normalize_query("Les Évadés"), "les évadés");
+    }
+
+    #[test]
+    fn cache_key_is_per_provider() {
+        assert_eq!(
+            cache_key("Prowlarr", "Dune: Part Two"),
+            "indexer:prowlarr:dune part two"
+        );
+        assert_ne!(cache_key("Prowlarr", "dune"), cache_key("Jackett", "dune"));
+    }
+
+    #[tokio::test]
+    async fn concurrent_identical_searches_are_coalesced() {
+        let provider = CountingProvider {
+            calls: AtomicUsize::new(0),
+        };
+        let timeout = Duration::from_secs(5);
+
+        let (a, b) = tokio::join!(
+            cached_search(None, &provider, "Inception", timeout, false),
+            cached_search(None, &provider, "inception", timeout, true),
+        );
+
+        assert!(a.is_ok() && b.is_ok());
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,124 @@
+use super::opensubtitles::OpenSubtitlesProvider;
+use crate::config::CONFIG;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// What is known about a video when looking for its subtitles.
+#[derive(Debug, Clone, Default)]
+pub struct SubtitleSearch {
+    /// ISO 639-1 codes
+    pub languages: Vec<String>,
+    /// See `utils::file_hash::movie_hash`
+    pub movie_hash: Option<String>,
+    /// IMDb id of the movie, or of the series for an episode
+    pub imdb_id: Option<String>,
+    /// TMDB id of the movie, or of the series for an episode
+    pub tmdb_id: Option<i32>,
+    /// (season, episode)
+    pub episode: Option<(i32, i32)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubtitleCandidate {
+    pub provider_name: String,
+    /// Provider-side id used to download the file
+    pub id: String,
+    /// ISO 639-1 code
+    pub language: String,
+    pub release: Option<String>,
+    /// Made for this exact file (matched by movie hash)
+    pub hash_match: bool,
+    /// Only translates foreign dialogue
+    pub forced: bool,
+    pub hearing_impaired: bool,
+    pub trusted: bool,
+    pub downloads: u64,
+}
+
+#[async_trait]
+pub trait SubtitleProvider: Send + Sync {
+    /// Unique provider name (e.g., "OpenSubtitles")
+    fn name(&self) -> &str;
+
+    async fn search(&self, search: &SubtitleSearch) -> anyhow::Result<Vec<SubtitleCandidate>>;
+
+    /// Raw subtitle file (SRT) of a search result
+    async fn download(&self, candidate: &SubtitleCandidate) -> anyhow::Result<Vec<u8>>;
+}
+
+/// Every subtitle provider configured in the environment.
+pub fn configured() -> Vec<Box<dyn SubtitleProvider>> {
+    let mut providers: Vec<Box<dyn SubtitleProvider>> = Vec::new();
+    if !CONFIG.opensubtitles_api_key.is_empty() {
+        providers.push(Box::new(OpenSubtitlesProvider::new(
+            CONFIG.opensubtitles_url.clone(),
+            CONFIG.opensubtitles_api_key.clone(),
+            CONFIG.opensubtitles_username.clone(),
+            CONFIG.opensubtitles_password.clone(),
+        )));
+    }
+    providers
+}
+
+/// Full subtitles in `language`, preferring ones synced to this exact file,
+/// then regular over SDH, then trusted uploaders, then popularity.
+pub fn best_match<'a>(
+    candidates: &'a [SubtitleCandidate],
+    language: &str,
+) -> Option<&'a SubtitleCandidate> {
+    candidates
+        .iter()
+        .filter(|c| c.language == language && !c.forced)
+        .max_by_key(|c| (c.hash_match, !c.hearing_impaired, c.trusted, c.downloads))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: &str, language: &str) -> SubtitleCandidate {
+        SubtitleCandidate {
+            provider_name: "Test".to_string(),
+            id: id.to_string(),
+            language: language.to_string(),
+            release: None,
+            hash_match: false,
+            forced: false,
+            hearing_impaired: false,
+            trusted: false,
+            downloads: 0,
+        }
+    }
+
+    #[test]
+    fn picks_best_match_per_language() {
+        let candidates = vec![
+            SubtitleCandidate {
+                downloads: 9000,
+                ..candidate("popular", "fr")
+            },
+            SubtitleCandidate {
+                hash_match: true,
+                downloads: 10,
+                ..candidate("synced", "fr")
+            },
+            SubtitleCandidate {
+                hash_match: true,
+                forced: true,
+                ..candidate("forced", "fr")
+            },
+            SubtitleCandidate {
+                hearing_impaired: true,
+                downloads: 500,
+                ..candidate("sdh", "en")
+            },
+            SubtitleCandidate {
+                downloads: 100,
+                ..candidate("plain", "en")
+            },
+        ];
+        assert_eq!(best_match(&candidates, "fr").unwrap().id, "synced");
+        assert_eq!(best_match(&candidates, "en").unwrap().id, "plain");
+        assert!(best_match(&candidates, "de").is_none());
+    }
+}
@@ -0,0 +1,150 @@
+//! Size-bounded disk cache for generated playlists and segments. Entries are
+//! evicted least recently used first, using file modification times.
+
+use crate::{config::CONFIG, utils::disk_cache};
+use once_cell::sync::Lazy;
+use std::{collections::HashMap, fs, io, path::PathBuf, sync::Mutex, time::UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Bytes stored under each cache root, counted on writes so the tree is only
+/// walked once the budget is exceeded.
+static USAGE: Lazy<Mutex<HashMap<PathBuf, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub struct SegmentCache {
+    root: PathBuf,
+    max_bytes: u64,
+}
+
+impl SegmentCache {
+    pub fn new(root: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            root: root.into(),
+            max_bytes,
+        }
+    }
+
+    /// The HLS cache configured by `HLS_CACHE_DIR` and `HLS_CACHE_MAX_MB`.
+    pub fn from_config() -> Self {
+        Self::new(&CONFIG.hls_cache_dir, CONFIG.hls_cache_max_mb * 1024 * 1024)
+    }
+
+    /// Keys are relative paths such as `<file>/<rendition>/3.m4s`.
+    fn path(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+
+    /// Read an entry, marking it as recently used.
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let path = self.path(key);
+        let data = fs::read(&path).ok()?;
+        disk_cache::touch(&path);
+        Some(data)
+    }
+
+    /// Store an entry (atomically, via a rename) then evict if over budget.
+    pub fn put(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Unique per write: concurrent requests for one segment must not share it.
+        let tmp = path.with_extension(format!("tmp-{}", Uuid::new_v4()));
+        fs::write(&tmp, data)?;
+        let replaced = fs::metadata(&path).map_or(0, |m| m.len());
+        fs::rename(&tmp, &path)?;
+        if self.record_write(data.len() as u64, replaced)? > self.max_bytes {
+            self.evict()?;
+        }
+        Ok(())
+    }
+
+    /// Add a write to the tracked size of the cache, counting what is on disk
+    /// the first time. Returns the new total.
+    fn record_write(&self, added: u64, replaced: u64) -> io::Result<u64> {
+        let mut usage = USAGE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(total) = usage.get_mut(&self.root) {
+            *total = (*total + added).saturating_sub(replaced);
+            return Ok(*total);
+        }
+        // The first walk already sees this write.
+        let total = disk_cache::usage(&self.root)?;
+        usage.insert(self.root.clone(), total);
+        Ok(total)
+    }
+
+    /// Delete the least recently used entries until the cache is back to 90%
+    /// of its budget, so the next walk is some writes away. Returns the number
+    /// of bytes freed.
+    pub fn evict(&self) -> io::Result<u64> {
+        let eviction = disk_cache::evict_lru(&self.root, self.max_bytes / 10 * 9)?;
+        USAGE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(self.root.clone(), eviction.remaining);
+        Ok(eviction.freed)
+    }
+}
+
+/// Cache namespace of a media file; it changes when the file is replaced.
+pub fn file_key(file_id: Uuid, metadata: &fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    format!("{}-{:x}-{:x}", file_id, metadata.len(), modified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let root = std::env::temp_dir().join(format!("sokoul-cache-test-{}", std::process::id()));
+        let cache = SegmentCache::new(&root, 250);
+
+        cache.put("a/video/0.m4s", &[0; 100]).unwrap();
+        cache.put("a/video/1.m4s", &[1; 100]).unwrap();
+        // Make the first entry the most recently used one.
+        let old = SystemTime::now() - Duration::from_secs(60);
+        fs::File::options()
+            .append(true)
+            .open(root.join("a/video/1.m4s"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        assert_eq!(cache.get("a/video/0.m4s"), Some(vec![0; 100]));
+
+        cache.put("b/video/0.m4s", &[2; 100]).unwrap();
+        assert!(cache.get("a/video/1.m4s").is_none());
+        assert!(cache.get("a/video/0.m4s").is_some());
+        assert!(cache.get("b/video/0.m4s").is_some());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn concurrent_writes_keep_segments_whole() {
+        let root = std::env::temp_dir().join(format!("sokoul-cache-race-{}", Uuid::new_v4()));
+        let cache = SegmentCache::new(&root, 1 << 30);
+
+        std::thread::scope(|s| {
+            for byte in [1u8, 2] {
+                let cache = &cache;
+                s.spawn(move || {
+                    for _ in 0..50 {
+                        cache.put("a/video/0.m4s", &[byte; 64 * 1024]).unwrap();
+                    }
+                });
+            }
+        });
+        let data = cache.get("a/video/0.m4s").unwrap();
+        assert_eq!(data.len(), 64 * 1024);
+        assert!(data.iter().all(|b| *b == data[0]));
+        assert_eq!(fs::read_dir(root.join("a/video")).unwrap().count(), 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,300 @@
+//! Minimal EBML reader for the Matroska Cues element (the keyframe index).
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+const EBML_HEADER: u64 = 0x1A45_DFA3;
+const SEGMENT: u64 = 0x1853_8067;
+const SEEK_HEAD: u64 = 0x114D_9B74;
+const SEEK: u64 = 0x4DBB;
+const SEEK_ID: u64 = 0x53AB;
+const SEEK_POSITION: u64 = 0x53AC;
+const CUES: u64 = 0x1C53_BB6B;
+const CUE_POINT: u64 = 0xBB;
+const CUE_TIME: u64 = 0xB3;
+const CUE_TRACK_POSITIONS: u64 = 0xB7;
+const CUE_TRACK: u64 = 0xF7;
+
+/// An indexed keyframe: `time` is in Matroska ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CuePoint {
+    pub time: u64,
+    pub track: u64,
+}
+
+/// Read every cue point of the file, following the SeekHead when present and
+/// otherwise walking the top-level elements. Files without Cues yield nothing.
+pub fn read_cues<R: Read + Seek>(r: &mut R) -> io::Result<Vec<CuePoint>> {
+    r.seek(SeekFrom::Start(0))?;
+    if read_id(r)? != EBML_HEADER {
+        return Err(invalid("not an EBML file"));
+    }
+    let header_size = read_size(r)?;
+    skip(r, header_size)?;
+    if read_id(r)? != SEGMENT {
+        return Err(invalid("missing Segment element"));
+    }
+    let segment_end = read_size(r)?;
+    let segment_start = r.stream_position()?;
+    let segment_end = segment_end
+        .map(|size| offset(segment_start, size))
+        .transpose()?;
+
+    loop {
+        if segment_end.is_some_and(|end| r.stream_position().is_ok_and(|pos| pos >= end)) {
+            return Ok(Vec::new());
+        }
+        let (id, size) = match read_header(r) {
+            Ok(header) => header,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        match id {
+            CUES => return read_cue_points(r, size),
+            SEEK_HEAD => {
+                let Some(size) = size else {
+                    return Err(invalid("SeekHead with unknown size"));
+                };
+                let end = offset(r.stream_position()?, size)?;
+                if let Some(position) = find_cues_position(r, end)? {
+                    r.seek(SeekFrom::Start(offset(segment_start, position)?))?;
+                    let (id, size) = read_header(r)?;
+                    if id == CUES {
+                        return read_cue_points(r, size);
+                    }
+                }
+                r.seek(SeekFrom::Start(end))?;
+            }
+            // Clusters of unknown size (live recordings) cannot be skipped.
+            _ => match size {
+                Some(size) => skip(r, Some(size))?,
+                None => return Ok(Vec::new()),
+            },
+        }
+    }
+}
+
+fn find_cues_position<R: Read + Seek>(r: &mut R, end: u64) -> io::Result<Option<u64>> {
+    while r.stream_position()? < end {
+        let (id, size) = read_header(r)?;
+        let size = size.ok_or_else(|| invalid("unknown size inside SeekHead"))?;
+        if id != SEEK {
+            skip(r, Some(size))?;
+            continue;
+        }
+        let seek_end = offset(r.stream_position()?, size)?;
+        let (mut target, mut position) = (None, None);
+        while r.stream_position()? < seek_end {
+            let (id, size) = read_header(r)?;
+            let size = size.ok_or_else(|| invalid("unknown size inside Seek"))?;
+            match id {
+                SEEK_ID => target = Some(read_uint(r, size)?),
+                SEEK_POSITION => position = Some(read_uint(r, size)?),
+                _ => skip(r, Some(size))?,
+            }
+        }
+        if target == Some(CUES) {
+            return Ok(position);
+        }
+    }
+    Ok(None)
+}
+
+fn read_cue_points<R: Read + Seek>(r: &mut R, size: Option<u64>) -> io::Result<Vec<CuePoint>> {
+    let size = size.ok_or_else(|| invalid("Cues with unknown size"))?;
+    let end = offset(r.stream_position()?, size)?;
+    let mut points = Vec::new();
+
+    while r.stream_position()? < end {
+        let (id, size) = read_header(r)?;
+        let size = size.ok_or_else(|| invalid("unknown size inside Cues"))?;
+        if id != CUE_POINT {
+            skip(r, Some(size))?;
+            continue;
+        }
+        let point_end = offset(r.stream_position()?, size)?;
+        let mut time = None;
+        let mut tracks = Vec::new();
+        while r.stream_position()? < point_end {
+            let (id, size) = read_header(r)?;
+            let size = size.ok_or_else(|| invalid("unknown size inside CuePoint"))?;
+            match id {
+                CUE_TIME => time = Some(read_uint(r, size)?),
+                CUE_TRACK_POSITIONS => {
+                    let positions_end = offset(r.stream_position()?, size)?;
+                    while r.stream_position()? < positions_end {
+                        let (id, size) = read_header(r)?;
+                        let size = size.ok_or_else(|| invalid("unknown size in cue"))?;
+                        if id == CUE_TRACK {
+                            tracks.push(read_uint(r, size)?);
+                        } else {
+                            skip(r, Some(size))?;
+                        }
+                    }
+                }
+                _ => skip(r, Some(size))?,
+            }
+        }
+        if let Some(time) = time {
+            points.extend(tracks.into_iter().map(|track| CuePoint { time, track }));
+        }
+    }
+    Ok(points)
+}
+
+fn read_header<R: Read>(r: &mut R) -> io::Result<(u64, Option<u64>)> {
+    Ok((read_id(r)?, read_size(r)?))
+}
+
+/// Element IDs keep their length marker bits, as written in the specification.
+fn read_id<R: Read>(r: &mut R) -> io::Result<u64> {
+    let first = read_byte(r)?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 4 {
+        return Err(invalid("invalid element ID"));
+    }
+    let mut id = u64::from(first);
+    for _ in 1..len {
+        id = (id << 8) | u64::from(read_byte(r)?);
+    }
+    Ok(id)
+}
+
+/// Data sizes drop the marker bit; all ones means "unknown".
+fn read_size<R: Read>(r: &mut R) -> io::Result<Option<u64>> {
+    let first = read_byte(r)?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return Err(invalid("invalid element size"));
+    }
+    let mut size = u64::from(first) & (0xFF >> len);
+    let mut all_ones = size == 0xFF >> len;
+    for _ in 1..len {
+        let byte = read_byte(r)?;
+        all_ones &= byte == 0xFF;
+        size = (size << 8) | u64::from(byte);
+    }
+    Ok((!all_ones).then_some(size))
+}
+
+fn read_uint<R: Read>(r: &mut R, size: u64) -> io::Result<u64> {
+    if size > 8 {
+        return Err(invalid("integer element longer than 8 bytes"));
+    }
+    let mut value = 0u64;
+    for _ in 0..size {
+        value = (value << 8) | u64::from(read_byte(r)?);
+    }
+    Ok(value)
+}
+
+fn read_byte<R: Read>(r: &mut R) -> io::Result<u8> {
+    let mut byte = [0u8];
+    r.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn skip<R: Seek>(r: &mut R, size: Option<u64>) -> io::Result<()> {
+    let size = size.ok_or_else(|| invalid("cannot skip an element of unknown size"))?;
+    r.seek(SeekFrom::Current(size as i64))?;
+    Ok(())
+}
+
+/// `base + size`, rejecting sizes that point past any possible file offset.
+fn offset(base: u64, size: u64) -> io::Result<u64> {
+    base.checked_add(size)
+        .ok_or_else(|| invalid("element size overflows the file offset"))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn element(id: u64, body: &[u8]) -> Vec<u8> {
+        let id_bytes = id.to_be_bytes();
+        let skip = id_bytes.iter().take_while(|b| **b == 0).count();
+        let mut out = id_bytes[skip..].to_vec();
+        // 8-byte size: 0x01 marker followed by 7 bytes.
+        out.push(0x01);
+        out.extend_from_slice(&(body.len() as u64).to_be_bytes()[1..]);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn uint(id: u64, value: u64) -> Vec<u8> {
+        element(id, &value.to_be_bytes())
+    }
+
+    fn cue_point(time: u64, track: u64) -> Vec<u8> {
+        let positions = element(CUE_TRACK_POSITIONS, &uint(CUE_TRACK, track));
+        element(CUE_POINT, &[uint(CUE_TIME, time), positions].concat())
+    }
+
+    fn file(with_seek_head: bool) -> Vec<u8> {
+        let info = element(0x1549_A966, &[0u8; 16]);
+        let cluster = element(0x1F43_B675, &[0u8; 64]);
+        let cues = element(
+            CUES,
+            &[cue_point(0, 1), cue_point(6000, 1), cue_point(12000, 1)].concat(),
+        );
+        let seek = |position: u64| {
+            let entry = [uint(SEEK_ID, CUES), uint(SEEK_POSITION, position)].concat();
+            element(SEEK_HEAD, &element(SEEK, &entry))
+        };
+        let mut body = Vec::new();
+        if with_seek_head {
+            let head_len = seek(0).len() as u64;
+            let cues_position = head_len + info.len() as u64 + cluster.len() as u64;
+            body.extend(seek(cues_position));
+        }
+        body.extend(info);
+        body.extend(cluster);
+        body.extend(cues);
+        [element(EBML_HEADER, &[0u8; 4]), element(SEGMENT, &body)].concat()
+    }
+
+    #[test]
+    fn reads_cues_through_seek_head() {
+        let points = read_cues(&mut Cursor::new(file(true))).unwrap();
+        let times: Vec<u64> = points.iter().map(|p| p.time).collect();
+        assert_eq!(times, vec![0, 6000, 12000]);
+        assert!(points.iter().all(|p| p.track == 1));
+    }
+
+    #[test]
+    fn reads_cues_by_walking_segment() {
+        assert_eq!(read_cues(&mut Cursor::new(file(false))).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn rejects_non_ebml() {
+        assert!(read_cues(&mut Cursor::new(b"RIFF0000".to_vec())).is_err());
+    }
+
+    #[test]
+    fn rejects_positions_past_any_offset() {
+        let entry = [uint(SEEK_ID, CUES), uint(SEEK_POSITION, u64::MAX)].concat();
+        let body = element(SEEK_HEAD, &element(SEEK, &entry));
+        let data = [element(EBML_HEADER, &[0u8; 4]), element(SEGMENT, &body)].concat();
+        let err = read_cues(&mut Cursor::new(data)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn decodes_sizes() {
+        assert_eq!(read_size(&mut Cursor::new([0x81])).unwrap(), Some(1));
+        assert_eq!(read_size(&mut Cursor::new([0x40, 0x02])).unwrap(), Some(2));
+        assert_eq!(read_size(&mut Cursor::new([0xFF])).unwrap(), None);
+        assert_eq!(
+            read_size(&mut Cursor::new([
+                0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF
+            ]))
+            .unwrap(),
+            None
+        );
+    }
+}
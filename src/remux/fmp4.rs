@@ -0,0 +1,496 @@
+//! Fragmented MP4 (ISO BMFF) writer: an init segment (`ftyp` + `moov`) followed
+//! by `moof` + `mdat` fragments, as played by browsers and MSE.
+
+/// Codec configuration of an output track.
+#[derive(Debug, Clone)]
+pub enum TrackCodec {
+    /// H.264 with its `AVCDecoderConfigurationRecord`.
+    Avc {
+        width: u16,
+        height: u16,
+        avcc: Vec<u8>,
+    },
+    /// AAC with its `AudioSpecificConfig`.
+    Aac {
+        sample_rate: u32,
+        channels: u16,
+        config: Vec<u8>,
+    },
+    /// MPEG-1/2 Layer III audio.
+    Mp3 { sample_rate: u32, channels: u16 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub id: u32,
+    pub timescale: u32,
+    pub codec: TrackCodec,
+    /// ISO 639-2 code, e.g. "eng"; "und" when unknown.
+    pub language: String,
+}
+
+impl Track {
+    fn is_video(&self) -> bool {
+        matches!(self.codec, TrackCodec::Avc { .. })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub duration: u32,
+    pub size: u32,
+    pub is_sync: bool,
+    /// Presentation minus decode time, in the track timescale.
+    pub composition_offset: i32,
+}
+
+/// Samples of one track within a fragment; `data` holds their payloads back to back.
+#[derive(Debug, Clone, Default)]
+pub struct TrackRun {
+    pub track_id: u32,
+    pub base_decode_time: u64,
+    pub samples: Vec<Sample>,
+    pub data: Vec<u8>,
+}
+
+const SYNC_SAMPLE_FLAGS: u32 = 0x0200_0000;
+const NON_SYNC_SAMPLE_FLAGS: u32 = 0x0101_0000;
+
+/// Write a box: size placeholder, type, contents, then patch the size.
+fn write_box(buf: &mut Vec<u8>, kind: &[u8; 4], contents: impl FnOnce(&mut Vec<u8>)) {
+    let start = buf.len();
+    buf.extend_from_slice(&[0, 0, 0, 0]);
+    buf.extend_from_slice(kind);
+    contents(buf);
+    let size = (buf.len() - start) as u32;
+    buf[start..start + 4].copy_from_slice(&size.to_be_bytes());
+}
+
+fn write_full_box(
+    buf: &mut Vec<u8>,
+    kind: &[u8; 4],
+    version: u8,
+    flags: u32,
+    contents: impl FnOnce(&mut Vec<u8>),
+) {
+    write_box(buf, kind, |b| {
+        b.push(version);
+        b.extend_from_slice(&flags.to_be_bytes()[1..]);
+        contents(b);
+    });
+}
+
+fn u16be(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+fn u32be(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+fn u64be(buf: &mut Vec<u8>, v: u64) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+const UNITY_MATRIX: [u32; 9] = [0x0001_0000, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000];
+
+fn matrix(buf: &mut Vec<u8>) {
+    for v in UNITY_MATRIX {
+        u32be(buf, v);
+    }
+}
+
+/// ISO 639-2/T code packed as three 5-bit letters.
+fn packed_language(code: &str) -> u16 {
+    let bytes = code.as_bytes();
+    if bytes.len() != 3 || !bytes.iter().all(|b| b.is_ascii_lowercase()) {
+        return packed_language("und");
+    }
+    bytes
+        .iter()
+        .fold(0u16, |acc, b| (acc << 5) | u16::from(b - 0x60))
+}
+
+/// `ftyp` + `moov` for the given tracks. `duration_ms` (0 if unknown) is
+/// advertised in `mehd` so players can show the length of the stream.
+pub fn init_segment(tracks: &[Track], duration_ms: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(1024);
+    write_box(&mut buf, b"ftyp", |b| {
+        b.extend_from_slice(b"isom");
+        u32be(b, 0x200);
+        for brand in [b"isom", b"iso6", b"avc1", b"mp41"] {
+            b.extend_from_slice(brand);
+        }
+    });
+
+    write_box(&mut buf, b"moov", |b| {
+        write_full_box(b, b"mvhd", 0, 0, |b| {
+            u32be(b, 0); // creation time
+            u32be(b, 0); // modification time
+            u32be(b, 1000);
+            u32be(b, 0); // duration: given by fragments
+            u32be(b, 0x0001_0000); // rate
+            u16be(b, 0x0100); // volume
+            b.extend_from_slice(&[0; 10]);
+            matrix(b);
+            b.extend_from_slice(&[0; 24]);
+            u32be(b, tracks.iter().map(|t| t.id).max().unwrap_or(0) + 1);
+        });
+
+        for track in tracks {
+            write_trak(b, track);
+        }
+
+        write_box(b, b"mvex", |b| {
+            write_full_box(b, b"mehd", 1, 0, |b| u64be(b, duration_ms));
+            for track in tracks {
+                write_full_box(b, b"trex", 0, 0, |b| {
+                    u32be(b, track.id);
+                    u32be(b, 1); // sample description index
+                    u32be(b, 0);
+                    u32be(b, 0);
+                    u32be(b, 0);
+                });
+            }
+        });
+    });
+    buf
+}
+
+fn write_trak(buf: &mut Vec<u8>, track: &Track) {
+    write_box(buf, b"trak", |b| {
+        let (width, height) = match track.codec {
+            TrackCodec::Avc { width, height, .. } => (width, height),
+            _ => (0, 0),
+        };
+        // Enabled and in movie.
+        write_full_box(b, b"tkhd", 0, 3, |b| {
+            u32be(b, 0);
+            u32be(b, 0);
+            u32be(b, track.id);
+            u32be(b, 0);
+            u32be(b, 0); // duration
+            b.extend_from_slice(&[0; 8]);
+            u16be(b, 0); // layer
+            u16be(b, 0); // alternate group
+            u16be(b, if track.is_video() { 0 } else { 0x0100 });
+            u16be(b, 0);
+            matrix(b);
+            u32be(b, u32::from(width) << 16);
+            u32be(b, u32::from(height) << 16);
+        });
+
+        write_box(b, b"mdia", |b| {
+            write_full_box(b, b"mdhd", 0, 0, |b| {
+                u32be(b, 0);
+                u32be(b, 0);
+                u32be(b, track.timescale);
+                u32be(b, 0);
+                u16be(b, packed_language(&track.language));
+                u16be(b, 0);
+            });
+            write_full_box(b, b"hdlr", 0, 0, |b| {
+                u32be(b, 0);
+                b.extend_from_slice(if track.is_video() { b"vide" } else { b"soun" });
+                b.extend_from_slice(&[0; 12]);
+                b.extend_from_slice(if track.is_video() {
+                    b"VideoHandler\0"
+                } else {
+                    b"SoundHandler\0"
+                });
+            });
+            write_box(b, b"minf", |b| {
+                if track.is_video() {
+                    write_full_box(b, b"vmhd", 0, 1, |b| b.extend_from_slice(&[0; 8]));
+                } else {
+                    write_full_box(b, b"smhd", 0, 0, |b| b.extend_from_slice(&[0; 4]));
+                }
+                write_box(b, b"dinf", |b| {
+                    write_full_box(b, b"dref", 0, 0, |b| {
+                        u32be(b, 1);
+                        // Self-contained.
+                        write_full_box(b, b"url ", 0, 1, |_| {});
+                    });
+                });
+                write_box(b, b"stbl", |b| {
+                    write_full_box(b, b"stsd", 0, 0, |b| {
+                        u32be(b, 1);
+                        write_sample_entry(b, &track.codec);
+                    });
+                    write_full_box(b, b"stts", 0, 0, |b| u32be(b, 0));
+                    write_full_box(b, b"stsc", 0, 0, |b| u32be(b, 0));
+                    write_full_box(b, b"stsz", 0, 0, |b| {
+                        u32be(b, 0);
+                        u32be(b, 0);
+                    });
+                    write_full_box(b, b"stco", 0, 0, |b| u32be(b, 0));
+                });
+            });
+        });
+    });
+}
+
+fn write_sample_entry(buf: &mut Vec<u8>, codec: &TrackCodec) {
+    match codec {
+        TrackCodec::Avc {
+            width,
+            height,
+            avcc,
+        } => write_box(buf, b"avc1", |b| {
+            b.extend_from_slice(&[0; 6]);
+            u16be(b, 1); // data reference index
+            b.extend_from_slice(&[0; 16]);
+            u16be(b, *width);
+            u16be(b, *height);
+            u32be(b, 0x0048_0000); // 72 dpi
+            u32be(b, 0x0048_0000);
+            u32be(b, 0);
+            u16be(b, 1); // frame count
+            b.extend_from_slice(&[0; 32]); // compressor name
+            u16be(b, 0x0018); // depth
+            u16be(b, 0xffff);
+            write_box(b, b"avcC", |b| b.extend_from_slice(avcc));
+        }),
+        TrackCodec::Aac {
+            sample_rate,
+            channels,
+            config,
+        } => write_audio_entry(buf, *sample_rate, *channels, 0x40, Some(config)),
+        TrackCodec::Mp3 {
+            sample_rate,
+            channels,
+        } => write_audio_entry(buf, *sample_rate, *channels, 0x6b, None),
+    }
+}
+
+fn write_audio_entry(
+    buf: &mut Vec<u8>,
+    sample_rate: u32,
+    channels: u16,
+    object_type: u8,
+    specific_info: Option<&[u8]>,
+) {
+    write_box(buf, b"mp4a", |b| {
+        b.extend_from_slice(&[0; 6]);
+        u16be(b, 1);
+        b.extend_from_slice(&[0; 8]);
+        u16be(b, channels);
+        u16be(b, 16); // sample size
+        u32be(b, 0);
+        // 16.16 fixed point; rates above 65535 Hz do not fit and are signalled as 0.
+        u32be(
+            b,
+            if sample_rate <= 0xffff {
+                sample_rate << 16
+            } else {
+                0
+            },
+        );
+
+        write_full_box(b, b"esds", 0, 0, |b| {
+            let dsi = specific_info.map(|info| descriptor(0x05, info));
+            let mut decoder_config = vec![object_type, 0x15, 0, 0, 0];
+            decoder_config.extend_from_slice(&[0; 8]); // max and average bitrate
+            if let Some(dsi) = dsi {
+                decoder_config.extend_from_slice(&dsi);
+            }
+            let mut es = vec![0, 1, 0]; // ES_ID, flags
+            es.extend_from_slice(&descriptor(0x04, &decoder_config));
+            es.extend_from_slice(&descriptor(0x06, &[0x02]));
+            b.extend_from_slice(&descriptor(0x03, &es));
+        });
+    });
+}
+
+/// MPEG-4 descriptor with a four-byte expandable length.
+fn descriptor(tag: u8, payload: &[u8]) -> Vec<u8> {
+    let len = payload.len() as u32;
+    let mut out = vec![
+        tag,
+        0x80 | ((len >> 21) & 0x7f) as u8,
+        0x80 | ((len >> 14) & 0x7f) as u8,
+        0x80 | ((len >> 7) & 0x7f) as u8,
+        (len & 0x7f) as u8,
+    ];
+    out.extend_from_slice(payload);
+    out
+}
+
+/// One `moof` + `mdat` fragment holding the given runs.
+pub fn media_segment(sequence: u32, runs: &[TrackRun]) -> Vec<u8> {
+    // The moof size does not depend on the data offsets: measure it first.
+    let moof_size = write_moof(sequence, runs, 0).len();
+    let mut buf = write_moof(sequence, runs, moof_size as u32 + 8);
+
+    let data_len: usize = runs.iter().map(|r| r.data.len()).sum();
+    buf.reserve(data_len + 8);
+    u32be(&mut buf, (data_len + 8) as u32);
+    buf.extend_from_slice(b"mdat");
+    for run in runs {
+        buf.extend_from_slice(&run.data);
+    }
+    buf
+}
+
+fn write_moof(sequence: u32, runs: &[TrackRun], first_data_offset: u32) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_box(&mut buf, b"moof", |b| {
+        write_full_box(b, b"mfhd", 0, 0, |b| u32be(b, sequence));
+
+        let mut data_offset = first_data_offset;
+        for run in runs {
+            write_box(b, b"traf", |b| {
+                // default-base-is-moof
+                write_full_box(b, b"tfhd", 0, 0x02_0000, |b| u32be(b, run.track_id));
+                write_full_box(b, b"tfdt", 1, 0, |b| u64be(b, run.base_decode_time));
+                // data offset, duration, size, flags and composition offset per sample
+                write_full_box(b, b"trun", 1, 0x000f01, |b| {
+                    u32be(b, run.samples.len() as u32);
+                    u32be(b, data_offset);
+                    for sample in &run.samples {
+                        u32be(b, sample.duration);
+                        u32be(b, sample.size);
+                        u32be(
+                            b,
+                            if sample.is_sync {
+                                SYNC_SAMPLE_FLAGS
+                            } else {
+                                NON_SYNC_SAMPLE_FLAGS
+                            },
+                        );
+                        b.extend_from_slice(&sample.composition_offset.to_be_bytes());
+                    }
+                });
+            });
+            data_offset += run.data.len() as u32;
+        }
+    });
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Top-level boxes as (type, offset, size).
+    fn boxes(data: &[u8]) -> Vec<(String, usize, usize)> {
+        let mut out = Vec::new();
+        let mut pos = 0;
+        while pos + 8 <= data.len() {
+            let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            out.push((
+                String::from_utf8_lossy(&data[pos + 4..pos + 8]).to_string(),
+                pos,
+                size,
+            ));
+            pos += size;
+        }
+        assert_eq!(pos, data.len(), "box sizes must add up");
+        out
+    }
+
+    fn find(data: &[u8], kind: &[u8; 4]) -> usize {
+        data.windows(4).position(|w| w == kind).unwrap() - 4
+    }
+
+    #[test]
+    fn writes_init_segment() {
+        let tracks = vec![
+            Track {
+                id: 1,
+                timescale: 90_000,
+                codec: TrackCodec::Avc {
+                    width: 1920,
+                    height: 1080,
+                    avcc: vec![1, 0x64, 0, 0x28, 0xff, 0xe0, 0],
+                },
+                language: "und".to_string(),
+            },
+            Track {
+                id: 2,
+                timescale: 48_000,
+                codec: TrackCodec::Aac {
+                    sample_rate: 48_000,
+                    channels: 2,
+                    config: vec![0x11, 0x90],
+                },
+                language: "fre".to_string(),
+            },
+        ];
+        let init = init_segment(&tracks, 5_400_000);
+
+        let top: Vec<String> = boxes(&init).into_iter().map(|b| b.0).collect();
+        assert_eq!(top, ["ftyp", "moov"]);
+        assert_eq!(init.windows(4).filter(|w| w == b"trak").count(), 2);
+        assert_eq!(init.windows(4).filter(|w| w == b"trex").count(), 2);
+
+        let mehd = find(&init, b"mehd");
+        assert_eq!(
+            u64::from_be_bytes(init[mehd + 12..mehd + 20].try_into().unwrap()),
+            5_400_000
+        );
+        // The AudioSpecificConfig ends up in the esds decoder specific info.
+        let esds = find(&init, b"esds");
+        assert!(init[esds..]
+            .windows(7)
+            .any(|w| w == [0x05, 0x80, 0x80, 0x80, 2, 0x11, 0x90]));
+        assert_eq!(packed_language("fre"), 0x1a45);
+        assert_eq!(packed_language("??"), packed_language("und"));
+    }
+
+    #[test]
+    fn data_offsets_point_into_mdat() {
+        let runs = vec![
+            TrackRun {
+                track_id: 1,
+                base_decode_time: 0,
+                samples: vec![
+                    Sample {
+                        duration: 3000,
+                        size: 3,
+                        is_sync: true,
+                        composition_offset: 3000,
+                    },
+                    Sample {
+                        duration: 3000,
+                        size: 2,
+                        is_sync: false,
+                        composition_offset: -3000,
+                    },
+                ],
+                data: vec![1, 1, 1, 2, 2],
+            },
+            TrackRun {
+                track_id: 2,
+                base_decode_time: 1024,
+                samples: vec![Sample {
+                    duration: 1024,
+                    size: 4,
+                    is_sync: true,
+                    composition_offset: 0,
+                }],
+                data: vec![9, 9, 9, 9],
+            },
+        ];
+        let segment = media_segment(7, &runs);
+
+        let top = boxes(&segment);
+        assert_eq!(top[0].0, "moof");
+        assert_eq!(top[1].0, "mdat");
+        assert_eq!(top[1].2, 8 + 9);
+
+        // trun: header (12) + sample count (4), then data offset.
+        let offsets: Vec<usize> = segment
+            .windows(4)
+            .enumerate()
+            .filter(|(_, w)| *w == b"trun")
+            .map(|(i, _)| {
+                let at = i + 4 + 4 + 4;
+                u32::from_be_bytes(segment[at..at + 4].try_into().unwrap()) as usize
+            })
+            .collect();
+        assert_eq!(offsets.len(), 2);
+        assert_eq!(&segment[offsets[0]..offsets[0] + 5], &[1, 1, 1, 2, 2]);
+        assert_eq!(&segment[offsets[1]..offsets[1] + 4], &[9, 9, 9, 9]);
+    }
+}
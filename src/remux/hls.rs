@@ -0,0 +1,457 @@
+//! HLS packaging of Matroska files: segment boundaries come from the keyframe
+//! index (Cues), media segments are fMP4 and text subtitles become WebVTT.
+
+use super::{
+    cues,
+    fmp4::TrackCodec,
+    mkv::{self, MkvRemuxer, SegmentTrack},
+    RemuxError,
+};
+use crate::utils::webvtt;
+use matroska_demuxer::{MatroskaFile, TrackEntry, TrackType};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    io::{Read, Seek},
+    str::FromStr,
+};
+
+/// Segments are cut at the first keyframe after this much media.
+const TARGET_SEGMENT_SECS: f64 = 6.0;
+
+/// A playlist of the presentation, as named in URLs: `video`, `audio-2`, `subs-3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rendition {
+    Video,
+    Audio(u64),
+    Subtitles(u64),
+}
+
+impl fmt::Display for Rendition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rendition::Video => write!(f, "video"),
+            Rendition::Audio(track) => write!(f, "audio-{}", track),
+            Rendition::Subtitles(track) => write!(f, "subs-{}", track),
+        }
+    }
+}
+
+impl FromStr for Rendition {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "video" {
+            return Ok(Rendition::Video);
+        }
+        if let Some(track) = s.strip_prefix("audio-") {
+            return track.parse().map(Rendition::Audio).map_err(|_| ());
+        }
+        if let Some(track) = s.strip_prefix("subs-") {
+            return track.parse().map(Rendition::Subtitles).map_err(|_| ());
+        }
+        Err(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoInfo {
+    pub codecs: String,
+    pub width: u16,
+    pub height: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioRendition {
+    pub track: u64,
+    pub codecs: String,
+    pub channels: u16,
+    pub language: Option<String>,
+    pub name: String,
+    pub default: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubtitleRendition {
+    pub track: u64,
+    pub language: Option<String>,
+    pub name: String,
+    pub forced: bool,
+}
+
+/// Everything needed to write the playlists and cut segments of one file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HlsPlan {
+    /// Nanoseconds per Matroska tick.
+    pub tick_ns: u64,
+    pub duration: u64,
+    /// Segment start ticks; each segment ends where the next one starts.
+    pub segments: Vec<u64>,
+    pub video: VideoInfo,
+    pub audio: Vec<AudioRendition>,
+    pub subtitles: Vec<SubtitleRendition>,
+}
+
+/// Inspect the tracks and keyframe index of a Matroska file.
+pub fn plan<R: Read + Seek>(reader: &mut R) -> Result<HlsPlan, RemuxError> {
+    let mkv = MatroskaFile::open(&mut *reader)?;
+    let tick_ns = mkv.info().timestamp_scale().get();
+    let duration = mkv
+        .info()
+        .duration()
+        .map(|d| d as u64)
+        .filter(|d| *d > 0)
+        .ok_or_else(|| RemuxError::Unsupported("The file does not declare its duration".into()))?;
+
+    let video_entry = mkv::find_video(&mkv)?;
+    let video_number = video_entry.track_number().get();
+    let TrackCodec::Avc {
+        width,
+        height,
+        avcc,
+    } = mkv::video_track(video_entry)?.out.codec
+    else {
+        return Err(RemuxError::Unsupported("H.264 video required".into()));
+    };
+    let video = VideoInfo {
+        codecs: format!("avc1.{:02x}{:02x}{:02x}", avcc[1], avcc[2], avcc[3]),
+        width,
+        height,
+    };
+
+    let mut audio: Vec<AudioRendition> = mkv
+        .tracks()
+        .iter()
+        .filter(|t| t.track_type() == TrackType::Audio)
+        .filter_map(|entry| {
+            let (codecs, channels) = match mkv::audio_codec(entry)? {
+                TrackCodec::Aac {
+                    channels, config, ..
+                } => (
+                    format!("mp4a.40.{}", config.first().map_or(2, |b| b >> 3)),
+                    channels,
+                ),
+                TrackCodec::Mp3 { channels, .. } => ("mp4a.40.34".to_string(), channels),
+                TrackCodec::Avc { .. } => return None,
+            };
+            Some(AudioRendition {
+                track: entry.track_number().get(),
+                codecs,
+                channels,
+                language: language(entry),
+                name: rendition_name(entry, "Audio"),
+                default: entry.flag_default(),
+            })
+        })
+        .collect();
+    let default = audio.iter().position(|a| a.default).unwrap_or(0);
+    for (i, rendition) in audio.iter_mut().enumerate() {
+        rendition.default = i == default;
+    }
+
+    let subtitles = mkv
+        .tracks()
+        .iter()
+        .filter(|t| {
+            t.track_type() == TrackType::Subtitle
+                && mkv::TEXT_SUBTITLE_CODECS.contains(&t.codec_id())
+        })
+        .map(|entry| SubtitleRendition {
+            track: entry.track_number().get(),
+            language: language(entry),
+            name: rendition_name(entry, "Subtitles"),
+            forced: entry.flag_forced(),
+        })
+        .collect();
+    drop(mkv);
+
+    let mut keyframes: Vec<u64> = cues::read_cues(reader)?
+        .into_iter()
+        .filter(|c| c.track == video_number)
+        .map(|c| c.time)
+        .collect();
+    keyframes.sort_unstable();
+    keyframes.dedup();
+    if keyframes.is_empty() {
+        return Err(RemuxError::Unsupported(
+            "The file has no keyframe index (Cues); remux it with mkvmerge to add one".into(),
+        ));
+    }
+
+    let target = (TARGET_SEGMENT_SECS * 1_000_000_000.0 / tick_ns as f64) as u64;
+    let mut segments = vec![0];
+    for time in keyframes {
+        if time < duration && time >= segments[segments.len() - 1] + target {
+            segments.push(time);
+        }
+    }
+
+    Ok(HlsPlan {
+        tick_ns,
+        duration,
+        segments,
+        video,
+        audio,
+        subtitles,
+    })
+}
+
+impl HlsPlan {
+    fn secs(&self, ticks: u64) -> f64 {
+        ticks as f64 * self.tick_ns as f64 / 1_000_000_000.0
+    }
+
+    /// Start and end ticks of a segment.
+    pub fn segment_range(&self, index: usize) -> Option<(u64, u64)> {
+        let start = *self.segments.get(index)?;
+        let end = self
+            .segments
+            .get(index + 1)
+            .copied()
+            .unwrap_or(self.duration);
+        Some((start, end))
+    }
+
+    pub fn has_rendition(&self, rendition: Rendition) -> bool {
+        match rendition {
+            Rendition::Video => true,
+            Rendition::Audio(track) => self.audio.iter().any(|a| a.track == track),
+            Rendition::Subtitles(track) => self.subtitles.iter().any(|s| s.track == track),
+        }
+    }
+
+    /// Master playlist; `file_size` gives the advertised bandwidth.
+    pub fn master_playlist(&self, file_size: u64) -> String {
+        let bandwidth = (file_size as f64 * 8.0 / self.secs(self.duration).max(1.0)) as u64;
+        let mut lines = vec![
+            "#EXTM3U".to_string(),
+            "#EXT-X-VERSION:7".to_string(),
+            "#EXT-X-INDEPENDENT-SEGMENTS".to_string(),
+        ];
+
+        for audio in &self.audio {
+            lines.push(format!(
+                "#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"audio\",NAME=\"{}\",{}DEFAULT={},AUTOSELECT=YES,CHANNELS=\"{}\",URI=\"hls/{}/index.m3u8\"",
+                quoted(&audio.name),
+                language_attribute(&audio.language),
+                yes_no(audio.default),
+                audio.channels,
+                Rendition::Audio(audio.track)
+            ));
+        }
+        for subtitle in &self.subtitles {
+            lines.push(format!(
+                "#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"subs\",NAME=\"{}\",{}DEFAULT=NO,AUTOSELECT=YES,FORCED={},URI=\"hls/{}/index.m3u8\"",
+                quoted(&subtitle.name),
+                language_attribute(&subtitle.language),
+                yes_no(subtitle.forced),
+                Rendition::Subtitles(subtitle.track)
+            ));
+        }
+
+        let mut codecs = vec![self.video.codecs.clone()];
+        for audio in &self.audio {
+            if !codecs.contains(&audio.codecs) {
+                codecs.push(audio.codecs.clone());
+            }
+        }
+        let mut stream = format!(
+            "#EXT-X-STREAM-INF:BANDWIDTH={},CODECS=\"{}\"",
+            bandwidth,
+            codecs.join(",")
+        );
+        if self.video.width > 0 && self.video.height > 0 {
+            stream.push_str(&format!(
+                ",RESOLUTION={}x{}",
+                self.video.width, self.video.height
+            ));
+        }
+        if !self.audio.is_empty() {
+            stream.push_str(",AUDIO=\"audio\"");
+        }
+        if !self.subtitles.is_empty() {
+            stream.push_str(",SUBTITLES=\"subs\"");
+        }
+        lines.push(stream);
+        lines.push(format!("hls/{}/index.m3u8", Rendition::Video));
+        lines.join("\n") + "\n"
+    }
+
+    /// Media playlist of the video or an audio rendition (fMP4 segments).
+    pub fn media_playlist(&self) -> String {
+        let durations: Vec<f64> = (0..self.segments.len())
+            .filter_map(|i| self.segment_range(i))
+            .map(|(start, end)| self.secs(end - start))
+            .collect();
+        let target = durations.iter().fold(0f64, |max, d| max.max(*d)).ceil() as u64;
+
+        let mut lines = vec![
+            "#EXTM3U".to_string(),
+            "#EXT-X-VERSION:7".to_string(),
+            format!("#EXT-X-TARGETDURATION:{}", target.max(1)),
+            "#EXT-X-MEDIA-SEQUENCE:0".to_string(),
+            "#EXT-X-PLAYLIST-TYPE:VOD".to_string(),
+            "#EXT-X-INDEPENDENT-SEGMENTS".to_string(),
+            "#EXT-X-MAP:URI=\"init.mp4\"".to_string(),
+        ];
+        for (i, duration) in durations.iter().enumerate() {
+            lines.push(format!("#EXTINF:{:.3},", duration));
+            lines.push(format!("{}.m4s", i));
+        }
+        lines.push("#EXT-X-ENDLIST".to_string());
+        lines.join("\n") + "\n"
+    }
+
+    /// Subtitle playlist: the whole track as one WebVTT document.
+    pub fn subtitle_playlist(&self) -> String {
+        let duration = self.secs(self.duration);
+        [
+            "#EXTM3U".to_string(),
+            "#EXT-X-VERSION:7".to_string(),
+            format!("#EXT-X-TARGETDURATION:{}", duration.ceil() as u64),
+            "#EXT-X-MEDIA-SEQUENCE:0".to_string(),
+            "#EXT-X-PLAYLIST-TYPE:VOD".to_string(),
+            format!("#EXTINF:{:.3},", duration),
+            "subtitles.vtt".to_string(),
+            "#EXT-X-ENDLIST".to_string(),
+        ]
+        .join("\n")
+            + "\n"
+    }
+}
+
+/// The `init.mp4` of a video or audio rendition.
+pub fn init_segment<R: Read + Seek>(reader: R, track: SegmentTrack) -> Result<Vec<u8>, RemuxError> {
+    Ok(MkvRemuxer::open_segment(reader, track, 1, 0, 0)?.init_segment())
+}
+
+/// Segment `index` of a video or audio rendition.
+pub fn media_segment<R: Read + Seek>(
+    reader: R,
+    plan: &HlsPlan,
+    track: SegmentTrack,
+    index: usize,
+) -> Result<Option<Vec<u8>>, RemuxError> {
+    let Some((start, end)) = plan.segment_range(index) else {
+        return Ok(None);
+    };
+    let mut remuxer = MkvRemuxer::open_segment(reader, track, index as u32 + 1, start, end)?;
+    let mut segment = Vec::new();
+    while let Some(fragment) = remuxer.next_fragment()? {
+        segment.extend(fragment);
+    }
+    Ok(Some(segment))
+}
+
+/// Extract a text subtitle track as WebVTT. This reads the whole file.
+pub fn subtitles<R: Read + Seek>(reader: R, track: u64) -> Result<String, RemuxError> {
+    let cues = mkv::subtitle_cues(reader, track)?;
+    Ok(webvtt::render(
+        &cues,
+        &["X-TIMESTAMP-MAP=LOCAL:00:00:00.000,MPEGTS:0"],
+    ))
+}
+
+fn language(entry: &TrackEntry) -> Option<String> {
+    entry
+        .language_bcp47()
+        .or(entry.language())
+        .filter(|l| *l != "und")
+        .map(str::to_string)
+}
+
+fn rendition_name(entry: &TrackEntry, kind: &str) -> String {
+    entry
+        .name()
+        .map(str::to_string)
+        .or_else(|| language(entry))
+        .unwrap_or_else(|| format!("{} {}", kind, entry.track_number()))
+}
+
+fn language_attribute(language: &Option<String>) -> String {
+    language
+        .as_ref()
+        .map(|l| format!("LANGUAGE=\"{}\",", quoted(l)))
+        .unwrap_or_default()
+}
+
+/// Quoted-string attributes may not contain double quotes or line breaks.
+fn quoted(value: &str) -> String {
+    value.replace('"', "'").replace(['\r', '\n'], " ")
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "YES"
+    } else {
+        "NO"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_plan() -> HlsPlan {
+        HlsPlan {
+            tick_ns: 1_000_000,
+            duration: 14_500,
+            segments: vec![0, 6_000, 12_000],
+            video: VideoInfo {
+                codecs: "avc1.640028".into(),
+                width: 1920,
+                height: 1080,
+            },
+            audio: vec![AudioRendition {
+                track: 2,
+                codecs: "mp4a.40.2".into(),
+                channels: 6,
+                language: Some("fr".into()),
+                name: "Français \"5.1\"".into(),
+                default: true,
+            }],
+            subtitles: vec![SubtitleRendition {
+                track: 3,
+                language: None,
+                name: "Subtitles 3".into(),
+                forced: true,
+            }],
+        }
+    }
+
+    #[test]
+    fn parses_rendition_names() {
+        for rendition in [
+            Rendition::Video,
+            Rendition::Audio(2),
+            Rendition::Subtitles(11),
+        ] {
+            assert_eq!(rendition.to_string().parse::<Rendition>(), Ok(rendition));
+        }
+        assert!("audio-x".parse::<Rendition>().is_err());
+        assert!("../etc".parse::<Rendition>().is_err());
+    }
+
+    #[test]
+    fn writes_master_playlist() {
+        let master = sample_plan().master_playlist(14_500_000);
+        assert!(master.contains(
+            "#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"audio\",NAME=\"Français '5.1'\",LANGUAGE=\"fr\",DEFAULT=YES,AUTOSELECT=YES,CHANNELS=\"6\",URI=\"hls/audio-2/index.m3u8\""
+        ));
+        assert!(master.contains("FORCED=YES,URI=\"hls/subs-3/index.m3u8\""));
+        assert!(master.contains(
+            "#EXT-X-STREAM-INF:BANDWIDTH=8000000,CODECS=\"avc1.640028,mp4a.40.2\",RESOLUTION=1920x1080,AUDIO=\"audio\",SUBTITLES=\"subs\"\nhls/video/index.m3u8\n"
+        ));
+    }
+
+    #[test]
+    fn writes_media_playlist() {
+        let playlist = sample_plan().media_playlist();
+        assert!(playlist.contains("#EXT-X-TARGETDURATION:6\n"));
+        assert!(playlist
+            .contains("#EXTINF:6.000,\n0.m4s\n#EXTINF:6.000,\n1.m4s\n#EXTINF:2.500,\n2.m4s\n"));
+        assert!(playlist.ends_with("#EXT-X-ENDLIST\n"));
+        assert_eq!(sample_plan().segment_range(2), Some((12_000, 14_500)));
+        assert_eq!(sample_plan().segment_range(3), None);
+    }
+}
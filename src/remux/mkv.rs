@@ -0,0 +1,677 @@
+//! Matroska to fragmented MP4: a progressive stream with one fragment per
+//! video GOP, or single-track segments for HLS.
+
+use super::{
+    fmp4::{self, Sample, Track, TrackCodec, TrackRun},
+    RemuxError,
+};
+use crate::utils::webvtt::{self, Cue};
+use matroska_demuxer::{
+    ContentCompAlgo, ContentEncodingValue, Frame, MatroskaFile, TrackEntry, TrackType,
+};
+use std::io::{Read, Seek, SeekFrom};
+
+const VIDEO_TIMESCALE: u32 = 90_000;
+/// Duration assumed for the last frame of the file (30 fps).
+const FALLBACK_FRAME_DURATION: u32 = VIDEO_TIMESCALE / 30;
+const VIDEO_TRACK_ID: u32 = 1;
+const AUDIO_TRACK_ID: u32 = 2;
+
+/// Subtitle codecs that can be converted to WebVTT (bitmap formats cannot).
+pub const TEXT_SUBTITLE_CODECS: [&str; 4] =
+    ["S_TEXT/UTF8", "S_TEXT/ASS", "S_TEXT/SSA", "S_TEXT/WEBVTT"];
+/// Cue length for subtitle blocks that carry no duration.
+const DEFAULT_SUBTITLE_MS: i64 = 4000;
+
+/// AAC sampling frequency index table (ISO 14496-3).
+const AAC_SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+/// A subtitle track stored in the file.
+#[derive(Debug, Clone)]
+pub struct SubtitleTrack {
+    pub number: u64,
+    pub codec_id: String,
+    pub language: Option<String>,
+    pub name: Option<String>,
+    pub forced: bool,
+    pub hearing_impaired: bool,
+    pub default: bool,
+}
+
+pub(super) struct SourceTrack {
+    number: u64,
+    pub(super) out: Track,
+    /// Bytes removed by Matroska header stripping, restored on every frame.
+    stripped_header: Option<Vec<u8>>,
+}
+
+struct VideoFrame {
+    pts: u64,
+    data: Vec<u8>,
+    is_sync: bool,
+}
+
+/// The track carried by an HLS rendition segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentTrack {
+    Video,
+    /// Matroska audio track number.
+    Audio(u64),
+}
+
+/// Streams a Matroska file as fragmented MP4. Only H.264 video with AAC or
+/// MP3 audio is accepted: those play natively in browsers.
+pub struct MkvRemuxer<R: Read + Seek> {
+    mkv: MatroskaFile<R>,
+    video: Option<SourceTrack>,
+    audio: Option<SourceTrack>,
+    /// Length-prefix size of H.264 NAL units, from the avcC record.
+    nal_length_size: usize,
+    /// Nanoseconds per Matroska tick.
+    tick_ns: u64,
+    duration_ms: u64,
+    /// Frames before this tick are dropped.
+    start: u64,
+    /// Segment mode: the range ends at the first keyframe (or audio frame) here.
+    end: Option<u64>,
+    /// Tick mapped to output time zero.
+    origin: u64,
+    finished: bool,
+    sequence: u32,
+    video_frames: Vec<VideoFrame>,
+    audio_frames: Vec<(u64, Vec<u8>)>,
+    last_video_dts: Option<u64>,
+    next_audio_time: Option<u64>,
+    frame: Frame,
+}
+
+impl<R: Read + Seek> MkvRemuxer<R> {
+    /// Open a Matroska stream and position it on the first keyframe at or after
+    /// `start_secs`. `audio_track` selects a Matroska track number; by default
+    /// the default (or first) browser-compatible audio track is used.
+    pub fn open(reader: R, audio_track: Option<u64>, start_secs: f64) -> Result<Self, RemuxError> {
+        let mkv = MatroskaFile::open(reader)?;
+        let video = video_track(find_video(&mkv)?)?;
+        let audio = select_audio(mkv.tracks(), audio_track)?;
+
+        let mut remuxer = Self::new(mkv, Some(video), audio);
+        if start_secs > 0.0 {
+            let target = (start_secs * 1_000_000_000.0 / remuxer.tick_ns as f64) as u64;
+            remuxer.mkv.seek(target)?;
+        }
+        remuxer.skip_to_keyframe()?;
+        remuxer.origin = remuxer.start;
+        remuxer.duration_ms = remuxer
+            .duration_ms
+            .saturating_sub(remuxer.start_secs() as u64 * 1000);
+        Ok(remuxer)
+    }
+
+    /// Open a single-track segment covering `[start, end)` ticks, keeping the
+    /// source timeline so that segments of every rendition line up. Video
+    /// ranges must start on a keyframe.
+    pub fn open_segment(
+        reader: R,
+        track: SegmentTrack,
+        sequence: u32,
+        start: u64,
+        end: u64,
+    ) -> Result<Self, RemuxError> {
+        let mkv = MatroskaFile::open(reader)?;
+        let (video, audio) = match track {
+            SegmentTrack::Video => (Some(video_track(find_video(&mkv)?)?), None),
+            SegmentTrack::Audio(number) => (None, select_audio(mkv.tracks(), Some(number))?),
+        };
+
+        let mut remuxer = Self::new(mkv, video, audio);
+        remuxer.sequence = sequence.saturating_sub(1);
+        remuxer.end = Some(end);
+        remuxer.start = start;
+        if start > 0 {
+            remuxer.mkv.seek(start)?;
+        }
+        if remuxer.video.is_some() {
+            remuxer.skip_to_keyframe()?;
+        }
+        Ok(remuxer)
+    }
+
+    fn new(mkv: MatroskaFile<R>, video: Option<SourceTrack>, audio: Option<SourceTrack>) -> Self {
+        let tick_ns = mkv.info().timestamp_scale().get();
+        let duration_ms = mkv
+            .info()
+            .duration()
+            .map(|d| (d * tick_ns as f64 / 1_000_000.0) as u64)
+            .unwrap_or(0);
+        let nal_length_size = match video.as_ref().map(|v| &v.out.codec) {
+            Some(TrackCodec::Avc { avcc, .. }) if avcc.len() > 4 => usize::from(avcc[4] & 0x03) + 1,
+            _ => 4,
+        };
+        Self {
+            mkv,
+            video,
+            audio,
+            nal_length_size,
+            tick_ns,
+            duration_ms,
+            start: 0,
+            end: None,
+            origin: 0,
+            finished: false,
+            sequence: 0,
+            video_frames: Vec::new(),
+            audio_frames: Vec::new(),
+            last_video_dts: None,
+            next_audio_time: None,
+            frame: Frame::default(),
+        }
+    }
+
+    /// Where the output starts in the source, in seconds (keyframe-aligned).
+    pub fn start_secs(&self) -> f64 {
+        (self.start * self.tick_ns) as f64 / 1_000_000_000.0
+    }
+
+    /// `ftyp` + `moov`, to send before the fragments.
+    pub fn init_segment(&self) -> Vec<u8> {
+        let tracks: Vec<Track> = self
+            .video
+            .iter()
+            .chain(self.audio.iter())
+            .map(|t| t.out.clone())
+            .collect();
+        fmp4::init_segment(&tracks, self.duration_ms)
+    }
+
+    /// The next `moof` + `mdat`, or `None` at the end of the file (or of the
+    /// segment range, which is emitted as one fragment).
+    pub fn next_fragment(&mut self) -> Result<Option<Vec<u8>>, RemuxError> {
+        if self.finished {
+            return Ok(None);
+        }
+        while self.mkv.next_frame(&mut self.frame)? {
+            let track = self.frame.track;
+            if self.video.as_ref().is_some_and(|v| v.number == track) {
+                let frame = self.take_video_frame();
+                if frame.is_sync && self.end.is_some_and(|end| frame.pts >= end) {
+                    self.finished = true;
+                    return Ok(Some(self.build_fragment(Some(frame.pts))));
+                }
+                if frame.is_sync && self.end.is_none() && !self.video_frames.is_empty() {
+                    let next_pts = frame.pts;
+                    let fragment = self.build_fragment(Some(next_pts));
+                    self.video_frames.push(frame);
+                    return Ok(Some(fragment));
+                }
+                self.video_frames.push(frame);
+            } else if self.audio.as_ref().is_some_and(|a| a.number == track)
+                && self.frame.timestamp >= self.start
+            {
+                if self.end.is_some_and(|end| self.frame.timestamp >= end) {
+                    if self.video.is_none() {
+                        break;
+                    }
+                    continue;
+                }
+                let stripped = self
+                    .audio
+                    .as_ref()
+                    .and_then(|a| a.stripped_header.as_deref());
+                let data = restore_header(stripped, std::mem::take(&mut self.frame.data));
+                self.audio_frames.push((self.frame.timestamp, data));
+            }
+        }
+
+        self.finished = true;
+        if self.video_frames.is_empty() && self.audio_frames.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.build_fragment(self.end)))
+    }
+
+    /// Drop frames until the first video keyframe, where the output starts.
+    fn skip_to_keyframe(&mut self) -> Result<(), RemuxError> {
+        let number = self.video.as_ref().map(|v| v.number);
+        while self.mkv.next_frame(&mut self.frame)? {
+            if Some(self.frame.track) != number {
+                continue;
+            }
+            let frame = self.take_video_frame();
+            if frame.is_sync {
+                self.start = frame.pts;
+                self.video_frames.push(frame);
+                return Ok(());
+            }
+        }
+        Err(RemuxError::Unsupported(
+            "No video keyframe found after the requested position".to_string(),
+        ))
+    }
+
+    fn take_video_frame(&mut self) -> VideoFrame {
+        let data = restore_header(
+            self.video
+                .as_ref()
+                .and_then(|v| v.stripped_header.as_deref()),
+            std::mem::take(&mut self.frame.data),
+        );
+        let is_sync = self
+            .frame
+            .is_keyframe
+            .unwrap_or_else(|| is_idr(&data, self.nal_length_size));
+        VideoFrame {
+            pts: self.frame.timestamp,
+            data,
+            is_sync,
+        }
+    }
+
+    /// Matroska ticks (relative to the origin) in `timescale` units.
+    fn to_timescale(&self, ticks: u64, timescale: u32) -> u64 {
+        let ns = u128::from(ticks.saturating_sub(self.origin)) * u128::from(self.tick_ns);
+        (ns * u128::from(timescale) / 1_000_000_000) as u64
+    }
+
+    fn build_fragment(&mut self, next_keyframe_pts: Option<u64>) -> Vec<u8> {
+        self.sequence += 1;
+        let mut runs = Vec::with_capacity(2);
+
+        let frames = std::mem::take(&mut self.video_frames);
+        if !frames.is_empty() {
+            runs.push(self.video_run(frames, next_keyframe_pts));
+        }
+        let audio = std::mem::take(&mut self.audio_frames);
+        if !audio.is_empty() {
+            runs.push(self.audio_run(audio));
+        }
+        fmp4::media_segment(self.sequence, &runs)
+    }
+
+    /// Matroska stores presentation times in decode order; decode times are the
+    /// same values sorted, which yields the composition offsets B-frames need.
+    fn video_run(&mut self, frames: Vec<VideoFrame>, next_keyframe_pts: Option<u64>) -> TrackRun {
+        let pts: Vec<u64> = frames
+            .iter()
+            .map(|f| self.to_timescale(f.pts, VIDEO_TIMESCALE))
+            .collect();
+        let mut dts = pts.clone();
+        dts.sort_unstable();
+        for i in 0..dts.len() {
+            let floor = match i {
+                0 => self.last_video_dts.map(|d| d + 1),
+                _ => Some(dts[i - 1] + 1),
+            };
+            if let Some(floor) = floor {
+                dts[i] = dts[i].max(floor);
+            }
+        }
+        self.last_video_dts = dts.last().copied();
+
+        let next_dts = next_keyframe_pts.map(|p| self.to_timescale(p, VIDEO_TIMESCALE));
+        let mut run = TrackRun {
+            track_id: VIDEO_TRACK_ID,
+            base_decode_time: dts[0],
+            ..TrackRun::default()
+        };
+        for (i, frame) in frames.iter().enumerate() {
+            let duration = match dts.get(i + 1).copied().or(next_dts) {
+                Some(next) if next > dts[i] => (next - dts[i]) as u32,
+                _ => FALLBACK_FRAME_DURATION,
+            };
+            run.samples.push(Sample {
+                duration,
+                size: frame.data.len() as u32,
+                is_sync: frame.is_sync,
+                composition_offset: (pts[i] as i64 - dts[i] as i64) as i32,
+            });
+            run.data.extend_from_slice(&frame.data);
+        }
+        run
+    }
+
+    /// Audio frames have a fixed length in samples; decode times follow on from
+    /// the previous fragment unless the source timestamps jump.
+    fn audio_run(&mut self, frames: Vec<(u64, Vec<u8>)>) -> TrackRun {
+        let audio = self
+            .audio
+            .as_ref()
+            .expect("audio frames without audio track");
+        let timescale = audio.out.timescale;
+        let frame_len = audio_frame_length(&audio.out.codec);
+
+        let source_time = self.to_timescale(frames[0].0, timescale);
+        let base = match self.next_audio_time {
+            Some(next) if next.abs_diff(source_time) <= u64::from(frame_len) * 2 => next,
+            _ => source_time,
+        };
+        self.next_audio_time = Some(base + frames.len() as u64 * u64::from(frame_len));
+
+        let mut run = TrackRun {
+            track_id: AUDIO_TRACK_ID,
+            base_decode_time: base,
+            ..TrackRun::default()
+        };
+        for (_, data) in &frames {
+            run.samples.push(Sample {
+                duration: frame_len,
+                size: data.len() as u32,
+                is_sync: true,
+                composition_offset: 0,
+            });
+            run.data.extend_from_slice(data);
+        }
+        run
+    }
+}
+
+/// Whether a file name has a Matroska extension (.mkv, .webm).
+pub fn is_matroska(filename: &str) -> bool {
+    let ext = filename.rsplit('.').next().unwrap_or("").to_lowercase();
+    matches!(ext.as_str(), "mkv" | "webm")
+}
+
+/// List the subtitle tracks of a Matroska file (reads the headers only).
+pub fn subtitle_tracks<R: Read + Seek>(reader: R) -> Result<Vec<SubtitleTrack>, RemuxError> {
+    let mkv = MatroskaFile::open(reader)?;
+    Ok(mkv
+        .tracks()
+        .iter()
+        .filter(|t| t.track_type() == TrackType::Subtitle)
+        .map(|t| SubtitleTrack {
+            number: t.track_number().get(),
+            codec_id: t.codec_id().to_string(),
+            language: t
+                .language_bcp47()
+                .or(t.language())
+                .filter(|l| *l != "und")
+                .map(str::to_string),
+            name: t.name().map(str::to_string),
+            forced: t.flag_forced(),
+            hearing_impaired: t.flag_hearing_impaired(),
+            default: t.flag_default(),
+        })
+        .collect())
+}
+
+/// Extract the cues of a text subtitle track. This reads the whole file.
+pub fn subtitle_cues<R: Read + Seek>(mut reader: R, track: u64) -> Result<Vec<Cue>, RemuxError> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut mkv = MatroskaFile::open(reader)?;
+    let tick_ns = mkv.info().timestamp_scale().get() as i64;
+    let codec = mkv
+        .tracks()
+        .iter()
+        .find(|t| t.track_number().get() == track)
+        .map(|t| t.codec_id().to_string())
+        .filter(|c| TEXT_SUBTITLE_CODECS.contains(&c.as_str()))
+        .ok_or_else(|| {
+            RemuxError::Unsupported(format!("Track {} is not a text subtitle", track))
+        })?;
+
+    let mut frame = Frame::default();
+    let mut cues = Vec::new();
+    while mkv.next_frame(&mut frame)? {
+        if frame.track != track {
+            continue;
+        }
+        let raw = String::from_utf8_lossy(&frame.data);
+        let text = match codec.as_str() {
+            // ReadOrder, Layer, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+            "S_TEXT/ASS" | "S_TEXT/SSA" => {
+                webvtt::ass_text(raw.splitn(9, ',').nth(8).unwrap_or(""))
+            }
+            "S_TEXT/WEBVTT" => raw.into_owned(),
+            _ => webvtt::ass_text(&raw),
+        };
+        let start_ms = frame.timestamp as i64 * tick_ns / 1_000_000;
+        let end_ms = frame
+            .duration
+            .map(|d| start_ms + d as i64 * tick_ns / 1_000_000)
+            .unwrap_or(start_ms + DEFAULT_SUBTITLE_MS);
+        cues.push(Cue {
+            start_ms,
+            end_ms,
+            text,
+        });
+    }
+    cues.sort_by_key(|c| c.start_ms);
+    Ok(cues)
+}
+
+fn restore_header(stripped: Option<&[u8]>, data: Vec<u8>) -> Vec<u8> {
+    match stripped {
+        Some(header) => [header, data.as_slice()].concat(),
+        None => data,
+    }
+}
+
+/// Whether an H.264 access unit (length-prefixed NAL units) contains an IDR slice.
+fn is_idr(data: &[u8], nal_length_size: usize) -> bool {
+    let mut pos = 0;
+    while pos + nal_length_size < data.len() {
+        let len = data[pos..pos + nal_length_size]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | usize::from(*b));
+        if data[pos + nal_length_size] & 0x1f == 5 {
+            return true;
+        }
+        pos += nal_length_size + len;
+    }
+    false
+}
+
+fn audio_frame_length(codec: &TrackCodec) -> u32 {
+    match codec {
+        TrackCodec::Mp3 { sample_rate, .. } if *sample_rate < 32000 => 576,
+        TrackCodec::Mp3 { .. } => 1152,
+        _ => 1024,
+    }
+}
+
+/// Header stripping is the only Matroska content encoding browsers' MP4 can carry.
+fn stripped_header(entry: &TrackEntry) -> Result<Option<Vec<u8>>, RemuxError> {
+    let Some(encodings) = entry.content_encodings() else {
+        return Ok(None);
+    };
+    let mut header = None;
+    for encoding in encodings {
+        match encoding.encoding() {
+            ContentEncodingValue::Compression(c) if c.algo() == ContentCompAlgo::Stripping => {
+                header = c.settings().map(<[u8]>::to_vec);
+            }
+            _ => {
+                return Err(RemuxError::Unsupported(format!(
+                    "Track {} is compressed or encrypted",
+                    entry.track_number()
+                )))
+            }
+        }
+    }
+    Ok(header)
+}
+
+pub(super) fn find_video<R: Read + Seek>(mkv: &MatroskaFile<R>) -> Result<&TrackEntry, RemuxError> {
+    mkv.tracks()
+        .iter()
+        .find(|t| t.track_type() == TrackType::Video)
+        .ok_or_else(|| RemuxError::Unsupported("The file has no video track".to_string()))
+}
+
+fn language(entry: &TrackEntry) -> String {
+    entry.language().unwrap_or("eng").to_string()
+}
+
+pub(super) fn video_track(entry: &TrackEntry) -> Result<SourceTrack, RemuxError> {
+    if entry.codec_id() != "V_MPEG4/ISO/AVC" {
+        return Err(RemuxError::Unsupported(format!(
+            "Video codec {} cannot be played by browsers without transcoding (H.264 required)",
+            entry.codec_id()
+        )));
+    }
+    let avcc = entry
+        .codec_private()
+        .filter(|p| p.len() > 6)
+        .ok_or_else(|| RemuxError::Unsupported("H.264 track has no codec configuration".into()))?
+        .to_vec();
+    let (width, height) = entry
+        .video()
+        .map(|v| (v.pixel_width().get(), v.pixel_height().get()))
+        .unwrap_or((0, 0));
+
+    Ok(SourceTrack {
+        number: entry.track_number().get(),
+        out: Track {
+            id: VIDEO_TRACK_ID,
+            timescale: VIDEO_TIMESCALE,
+            codec: TrackCodec::Avc {
+                width: width.min(u64::from(u16::MAX)) as u16,
+                height: height.min(u64::from(u16::MAX)) as u16,
+                avcc,
+            },
+            language: language(entry),
+        },
+        stripped_header: stripped_header(entry)?,
+    })
+}
+
+pub(super) fn audio_codec(entry: &TrackEntry) -> Option<TrackCodec> {
+    let audio = entry.audio()?;
+    let sample_rate = audio.sampling_frequency().round() as u32;
+    let channels = audio.channels().get().min(8) as u16;
+    let codec_id = entry.codec_id();
+
+    if codec_id == "A_MPEG/L3" {
+        return Some(TrackCodec::Mp3 {
+            sample_rate,
+            channels,
+        });
+    }
+    let profile = codec_id.strip_prefix("A_AAC")?;
+    let config = match entry.codec_private() {
+        Some(private) if !private.is_empty() => private.to_vec(),
+        // Legacy "A_AAC/MPEG4/LC"-style ids carry no AudioSpecificConfig.
+        _ => {
+            let object_type: u16 = if profile.ends_with("/MAIN") {
+                1
+            } else if profile.ends_with("/SSR") {
+                3
+            } else if profile.ends_with("/LTP") {
+                4
+            } else {
+                2
+            };
+            let index = AAC_SAMPLE_RATES
+                .iter()
+                .position(|r| *r == sample_rate)
+                .unwrap_or(4) as u16;
+            let asc = (object_type << 11) | (index << 7) | (channels.min(7) << 3);
+            asc.to_be_bytes().to_vec()
+        }
+    };
+    Some(TrackCodec::Aac {
+        sample_rate,
+        channels,
+        config,
+    })
+}
+
+fn audio_track(entry: &TrackEntry, codec: TrackCodec) -> Result<SourceTrack, RemuxError> {
+    let timescale = match codec {
+        TrackCodec::Aac { sample_rate, .. } | TrackCodec::Mp3 { sample_rate, .. } => sample_rate,
+        TrackCodec::Avc { .. } => VIDEO_TIMESCALE,
+    };
+    Ok(SourceTrack {
+        number: entry.track_number().get(),
+        out: Track {
+            id: AUDIO_TRACK_ID,
+            timescale: timescale.max(1),
+            codec,
+            language: language(entry),
+        },
+        stripped_header: stripped_header(entry)?,
+    })
+}
+
+/// The requested audio track, else the default or first browser-compatible one.
+fn select_audio(
+    tracks: &[TrackEntry],
+    requested: Option<u64>,
+) -> Result<Option<SourceTrack>, RemuxError> {
+    let audio: Vec<&TrackEntry> = tracks
+        .iter()
+        .filter(|t| t.track_type() == TrackType::Audio)
+        .collect();
+
+    if let Some(number) = requested {
+        let entry = audio
+            .iter()
+            .find(|t| t.track_number().get() == number)
+            .ok_or_else(|| RemuxError::Unsupported(format!("No audio track {}", number)))?;
+        let codec = audio_codec(entry).ok_or_else(|| {
+            RemuxError::Unsupported(format!(
+                "Audio codec {} cannot be played by browsers without transcoding (AAC or MP3 required)",
+                entry.codec_id()
+            ))
+        })?;
+        return audio_track(entry, codec).map(Some);
+    }
+
+    if audio.is_empty() {
+        return Ok(None);
+    }
+    let mut compatible: Vec<(&TrackEntry, TrackCodec)> = audio
+        .iter()
+        .filter_map(|t| audio_codec(t).map(|c| (*t, c)))
+        .collect();
+    if compatible.is_empty() {
+        let codecs: Vec<&str> = audio.iter().map(|t| t.codec_id()).collect();
+        return Err(RemuxError::Unsupported(format!(
+            "No browser-compatible audio track (found {}; AAC or MP3 required)",
+            codecs.join(", ")
+        )));
+    }
+    let index = compatible
+        .iter()
+        .position(|(t, _)| t.flag_default())
+        .unwrap_or(0);
+    let (entry, codec) = compatible.swap_remove(index);
+    audio_track(entry, codec).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_idr_access_units() {
+        // SEI (6) then IDR slice (5), with 4-byte lengths.
+        let idr = [0, 0, 0, 2, 6, 0xff, 0, 0, 0, 2, 0x65, 0x88];
+        assert!(is_idr(&idr, 4));
+        // Non-IDR slice (1).
+        let p_frame = [0, 0, 0, 2, 0x41, 0x9a];
+        assert!(!is_idr(&p_frame, 4));
+        assert!(!is_idr(&[], 4));
+    }
+
+    #[test]
+    fn audio_frame_lengths() {
+        let aac = TrackCodec::Aac {
+            sample_rate: 48000,
+            channels: 2,
+            config: vec![],
+        };
+        assert_eq!(audio_frame_length(&aac), 1024);
+        let mp3 = TrackCodec::Mp3 {
+            sample_rate: 44100,
+            channels: 2,
+        };
+        assert_eq!(audio_frame_length(&mp3), 1152);
+        let mp3_lsf = TrackCodec::Mp3 {
+            sample_rate: 22050,
+            channels: 1,
+        };
+        assert_eq!(audio_frame_length(&mp3_lsf), 576);
+    }
+}
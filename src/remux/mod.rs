@@ -0,0 +1,22 @@
+//! Container remuxing for browser playback (no re-encoding).
+
+pub mod cache;
+pub mod cues;
+pub mod fmp4;
+pub mod hls;
+pub mod mkv;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RemuxError {
+    /// The file cannot be played by browsers without transcoding.
+    #[error("{0}")]
+    Unsupported(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid Matroska file: {0}")]
+    Demux(#[from] matroska_demuxer::DemuxError),
+}
@@ -1,4 +1,4 @@
-use crate::{db, AppState};
+use crate::{db, workers::retention, AppState};
 use std::sync::Arc;
 use tokio::time::{self, Duration};
 
@@ -23,5 +23,21 @@ pub async fn run_scheduler(state: Arc<AppState>) {
                 tracing::error!("Scheduler: failed to clean expired search results: {}", e);
             }
         }
+
+        match retention::run(&state.db_pool).await {
+            Ok(run) => {
+                if run.deleted_files > 0 || !run.errors.is_empty() {
+                    tracing::info!(
+                        "Scheduler: retention deleted {} files ({} bytes), {} errors",
+                        run.deleted_files,
+                        run.freed_bytes,
+                        run.errors.len()
+                    );
+                }
+            }
+            Err(e) => {
+                tracing::error!("Scheduler: failed to evaluate retention policies: {}", e);
+            }
+        }
     }
 }
@@ -95,6 +95,7 @@ async fn handle_search(
         tmdb_id: None,
         season: None,
         episode: None,
+        refresh: false,
     };
     let event_data = serde_json::to_vec(&payload).unwrap();
 
@@ -386,6 +387,7 @@ async fn handle_callback(bot: Bot, q: CallbackQuery, state: Arc<AppState>) -> Re
             search_result_id,
             magnet_or_url,
             title: result.title.clone(),
+            replaces_file_id: None,
         };
 
         let event_data = serde_json::to_vec(&download_event).unwrap();
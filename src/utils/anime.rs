@@ -0,0 +1,153 @@
+use crate::{clients::jikan::JikanAnime, utils::fuzzy};
+use std::collections::BTreeMap;
+
+/// TMDB genre id of "Animation".
+const TMDB_ANIMATION: i32 = 16;
+
+/// Below this, a MyAnimeList entry is not taken for the series.
+const MIN_MAL_CONFIDENCE: f64 = 0.85;
+
+/// Japanese animation, the series whose releases use absolute numbering.
+pub fn is_anime(genre_ids: &[i32], original_language: Option<&str>) -> bool {
+    genre_ids.contains(&TMDB_ANIMATION) && original_language == Some("ja")
+}
+
+/// The MyAnimeList entry of a series among Jikan search results: TV entries
+/// only, best title (romaji, English or Japanese) and year match first.
+pub fn pick_mal_match<'a>(
+    title: &str,
+    year: Option<i32>,
+    results: &'a [JikanAnime],
+) -> Option<&'a JikanAnime> {
+    results
+        .iter()
+        .filter(|a| a.anime_type.as_deref().is_none_or(|t| t == "TV"))
+        .filter_map(|anime| {
+            let confidence = [
+                anime.title.as_deref(),
+                anime.title_english.as_deref(),
+                anime.title_japanese.as_deref(),
+            ]
+            .into_iter()
+            .flatten()
+            .map(|t| fuzzy::match_confidence(title, year, t, anime.year))
+            .fold(0.0, f64::max);
+            (confidence >= MIN_MAL_CONFIDENCE).then_some((anime, confidence))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(anime, _)| anime)
+}
+
+/// Absolute episode numbers of a series, derived from its TMDB episode list:
+/// regular seasons (specials excluded) numbered one after the other. Seasons
+/// TMDB already numbers continuously (season 2 starting at episode 62) keep
+/// their numbers.
+#[derive(Debug, Default)]
+pub struct AbsoluteNumbering {
+    /// season -> (offset, first episode, last episode)
+    seasons: BTreeMap<i32, (i32, i32, i32)>,
+}
+
+impl AbsoluteNumbering {
+    pub fn new(episodes: impl IntoIterator<Item = (i32, i32)>) -> Self {
+        let mut ranges: BTreeMap<i32, (i32, i32)> = BTreeMap::new();
+        for (season, episode) in episodes {
+            if season < 1 || episode < 1 {
+                continue;
+            }
+            let range = ranges.entry(season).or_insert((episode, episode));
+            range.0 = range.0.min(episode);
+            range.1 = range.1.max(episode);
+        }
+
+        let mut seasons = BTreeMap::new();
+        let mut last_absolute = 0;
+        for (season, (first, last)) in ranges {
+            let offset = if first > 1 { 0 } else { last_absolute };
+            seasons.insert(season, (offset, first, last));
+            last_absolute = offset + last;
+        }
+        Self { seasons }
+    }
+
+    pub fn to_season_episode(&self, absolute: i32) -> Option<(i32, i32)> {
+        self.seasons
+            .iter()
+            .find(|(_, (offset, first, last))| (offset + first..=offset + last).contains(&absolute))
+            .map(|(season, (offset, _, _))| (*season, absolute - offset))
+    }
+
+    pub fn to_absolute(&self, season: i32, episode: i32) -> Option<i32> {
+        let (offset, first, last) = self.seasons.get(&season)?;
+        (*first..=*last)
+            .contains(&episode)
+            .then_some(offset + episode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbering(seasons: &[(i32, std::ops::RangeInclusive<i32>)]) -> AbsoluteNumbering {
+        AbsoluteNumbering::new(
+            seasons
+                .iter()
+                .flat_map(|(s, eps)| eps.clone().map(move |e| (*s, e))),
+        )
+    }
+
+    #[test]
+    fn converts_absolute_numbers() {
+        // Per-season numbering, with specials
+        let show = numbering(&[(0, 1..=3), (1, 1..=25), (2, 1..=12), (3, 1..=22)]);
+        assert_eq!(show.to_season_episode(1), Some((1, 1)));
+        assert_eq!(show.to_season_episode(26), Some((2, 1)));
+        assert_eq!(show.to_season_episode(59), Some((3, 22)));
+        assert_eq!(show.to_season_episode(60), None);
+        assert_eq!(show.to_absolute(3, 1), Some(38));
+        assert_eq!(show.to_absolute(0, 1), None);
+
+        // Seasons already numbered continuously
+        let long = numbering(&[(1, 1..=61), (2, 62..=77), (21, 892..=1085)]);
+        assert_eq!(long.to_season_episode(1057), Some((21, 1057)));
+        assert_eq!(long.to_season_episode(70), Some((2, 70)));
+        assert_eq!(long.to_absolute(2, 62), Some(62));
+    }
+
+    #[test]
+    fn picks_the_matching_mal_entry() {
+        let anime = |mal_id, title: &str, english: Option<&str>, kind: &str, year| JikanAnime {
+            mal_id,
+            title: Some(title.to_string()),
+            title_english: english.map(str::to_string),
+            anime_type: Some(kind.to_string()),
+            year: Some(year),
+            ..serde_json::from_value(serde_json::json!({"mal_id": mal_id})).unwrap()
+        };
+        let results = vec![
+            anime(
+                16498,
+                "Shingeki no Kyojin",
+                Some("Attack on Titan"),
+                "TV",
+                2013,
+            ),
+            anime(18397, "Shingeki no Kyojin OVA", None, "OVA", 2013),
+            anime(
+                25777,
+                "Shingeki no Kyojin Season 2",
+                Some("Attack on Titan Season 2"),
+                "TV",
+                2017,
+            ),
+        ];
+        assert_eq!(
+            pick_mal_match("Attack on Titan", Some(2013), &results).map(|a| a.mal_id),
+            Some(16498)
+        );
+        assert!(pick_mal_match("Frieren", Some(2023), &results).is_none());
+        assert!(is_anime(&[16, 10759], Some("ja")));
+        assert!(!is_anime(&[16], Some("en")));
+    }
+}
@@ -0,0 +1,330 @@
+//! On-disk artwork cache: originals are fetched once from the metadata image
+//! hosts and kept, resized variants are rendered from them on demand. The
+//! artwork worker keeps the whole cache within `ARTWORK_CACHE_MAX_MB`.
+
+use crate::{config::CONFIG, utils::disk_cache};
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+/// Hosts artwork may be fetched from; anything else would make the proxy an open relay.
+const ALLOWED_HOSTS: &[&str] = &[
+    "image.tmdb.org",
+    "assets.fanart.tv",
+    "artworks.thetvdb.com",
+    "static.tvmaze.com",
+];
+
+/// Widths variants are rendered at, requests are rounded up to one of them.
+const WIDTHS: &[u32] = &[92, 154, 185, 342, 500, 780, 1280, 1920];
+
+const JPEG_QUALITY: u8 = 85;
+/// Lossy: lossless WebP of a photographic poster is larger than the JPEG source.
+const WEBP_QUALITY: f32 = 80.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Webp,
+    Jpeg,
+}
+
+impl ImageFormat {
+    /// Explicit `format=` first, else WebP for clients that accept it.
+    pub fn negotiate(requested: Option<&str>, accept: Option<&str>) -> Option<Self> {
+        match requested.map(|f| f.to_lowercase()).as_deref() {
+            Some("webp") => Some(ImageFormat::Webp),
+            Some("jpeg" | "jpg") => Some(ImageFormat::Jpeg),
+            Some(_) => None,
+            None if accept.is_some_and(|a| a.contains("image/webp")) => Some(ImageFormat::Webp),
+            None => Some(ImageFormat::Jpeg),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Jpeg => "image/jpeg",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Webp => "webp",
+            ImageFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+/// Whether `url` is https artwork from one of the known image hosts.
+pub fn is_allowed_source(url: &str) -> bool {
+    url::Url::parse(url).is_ok_and(|u| {
+        u.scheme() == "https" && u.host_str().is_some_and(|h| ALLOWED_HOSTS.contains(&h))
+    })
+}
+
+/// Smallest standard width at least `requested`; the largest one past that.
+pub fn variant_width(requested: u32) -> u32 {
+    WIDTHS
+        .iter()
+        .copied()
+        .find(|w| *w >= requested)
+        .unwrap_or(WIDTHS[WIDTHS.len() - 1])
+}
+
+/// Resize (never enlarge) to `width` and encode.
+pub fn render(original: &[u8], width: u32, format: ImageFormat) -> image::ImageResult<Vec<u8>> {
+    let mut img = image::load_from_memory(original)?;
+    if img.width() > width {
+        let height = (u64::from(img.height()) * u64::from(width) / u64::from(img.width())).max(1);
+        img = img.resize_exact(width, height as u32, FilterType::Lanczos3);
+    }
+
+    let mut out = Vec::new();
+    match format {
+        ImageFormat::Webp => {
+            let rgba = img.to_rgba8();
+            out = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+                .encode(WEBP_QUALITY)
+                .to_vec();
+        }
+        // JPEG has no alpha channel.
+        ImageFormat::Jpeg => img
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY))?,
+    }
+    Ok(out)
+}
+
+/// The image as JPEG, re-encoded only when it is in another format.
+pub fn to_jpeg(original: &[u8]) -> image::ImageResult<Vec<u8>> {
+    if image::guess_format(original)? == image::ImageFormat::Jpeg {
+        return Ok(original.to_vec());
+    }
+    render(original, u32::MAX, ImageFormat::Jpeg)
+}
+
+/// Redirects are followed only to allowed sources, so an image host cannot
+/// bounce the proxy to another host or to plain http.
+fn redirect_policy() -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= 5 {
+            attempt.error("too many redirects")
+        } else if is_allowed_source(attempt.url().as_str()) {
+            attempt.follow()
+        } else {
+            let target = attempt.url().to_string();
+            attempt.error(format!("redirect to {} is not allowed", target))
+        }
+    })
+}
+
+pub struct ArtworkCache {
+    root: PathBuf,
+    max_bytes: u64,
+    client: reqwest::Client,
+}
+
+impl ArtworkCache {
+    pub fn new(root: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            root: root.into(),
+            max_bytes,
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(30))
+                .redirect(redirect_policy())
+                .build()
+                .expect("artwork HTTP client"),
+        }
+    }
+
+    /// The cache configured by `ARTWORK_CACHE_DIR` and `ARTWORK_CACHE_MAX_MB`.
+    pub fn from_config() -> Self {
+        Self::new(
+            &CONFIG.artwork_cache_dir,
+            CONFIG.artwork_cache_max_mb * 1024 * 1024,
+        )
+    }
+
+    /// Delete the least recently used images until the cache fits its budget.
+    /// Returns the number of bytes freed.
+    pub async fn evict(&self) -> std::io::Result<u64> {
+        let (root, max_bytes) = (self.root.clone(), self.max_bytes);
+        let eviction =
+            tokio::task::spawn_blocking(move || disk_cache::evict_lru(&root, max_bytes)).await??;
+        Ok(eviction.freed)
+    }
+
+    fn key(url: &str) -> String {
+        Sha256::digest(url.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn original_path(&self, key: &str) -> PathBuf {
+        self.root.join("originals").join(&key[..2]).join(key)
+    }
+
+    fn variant_path(&self, key: &str, width: u32, format: ImageFormat) -> PathBuf {
+        self.root.join("variants").join(&key[..2]).join(format!(
+            "{}-{}.{}",
+            key,
+            width,
+            format.extension()
+        ))
+    }
+
+    /// The original image, downloaded only the first time it is asked for.
+    pub async fn original(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+        let path = self.original_path(&Self::key(url));
+        if let Ok(data) = tokio::fs::read(&path).await {
+            disk_cache::touch(&path);
+            return Ok(data);
+        }
+
+        let data = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?
+            .to_vec();
+        // Keep error pages and truncated downloads out of the cache.
+        image::guess_format(&data)?;
+        write_atomically(&path, &data).await?;
+        Ok(data)
+    }
+
+    /// A resized variant, rendered from the cached original and kept.
+    pub async fn variant(
+        &self,
+        url: &str,
+        width: u32,
+        format: ImageFormat,
+    ) -> anyhow::Result<Vec<u8>> {
+        let width = variant_width(width);
+        let path = self.variant_path(&Self::key(url), width, format);
+        if let Ok(data) = tokio::fs::read(&path).await {
+            disk_cache::touch(&path);
+            return Ok(data);
+        }
+
+        let original = self.original(url).await?;
+        let data = tokio::task::spawn_blocking(move || render(&original, width, format)).await??;
+        write_atomically(&path, &data).await?;
+        Ok(data)
+    }
+}
+
+async fn write_atomically(path: &std::path::Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let tmp = path.with_extension(format!("tmp-{}", uuid::Uuid::new_v4()));
+    tokio::fs::write(&tmp, data).await?;
+    tokio::fs::rename(&tmp, path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_proxies_known_hosts() {
+        assert!(is_allowed_source("https://image.tmdb.org/t/p/w500/abc.jpg"));
+        assert!(is_allowed_source(
+            "https://assets.fanart.tv/fanart/movies/1/hdmovielogo/x.png"
+        ));
+        assert!(!is_allowed_source("http://image.tmdb.org/t/p/w500/abc.jpg"));
+        assert!(!is_allowed_source("https://image.tmdb.org.evil.com/a.jpg"));
+        assert!(!is_allowed_source("https://127.0.0.1/a.jpg"));
+        assert!(!is_allowed_source("not a url"));
+    }
+
+    #[tokio::test]
+    async fn refuses_redirects_to_other_hosts() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await;
+            let response = format!(
+                "HTTP/1.1 302 Found\r\nLocation: http://{}/internal\r\nContent-Length: 0\r\n\r\n",
+                addr
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+
+        let client = reqwest::Client::builder()
+            .redirect(redirect_policy())
+            .build()
+            .unwrap();
+        let err = client
+            .get(format!("http://{}/poster.jpg", addr))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(err.is_redirect());
+    }
+
+    #[test]
+    fn negotiates_format_and_width() {
+        assert_eq!(
+            ImageFormat::negotiate(None, Some("image/avif,image/webp,*/*")),
+            Some(ImageFormat::Webp)
+        );
+        assert_eq!(ImageFormat::negotiate(None, None), Some(ImageFormat::Jpeg));
+        assert_eq!(
+            ImageFormat::negotiate(Some("JPG"), Some("image/webp")),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(ImageFormat::negotiate(Some("gif"), None), None);
+        assert_eq!(variant_width(300), 342);
+        assert_eq!(variant_width(342), 342);
+        assert_eq!(variant_width(10_000), 1920);
+    }
+
+    #[test]
+    fn renders_resized_variants() {
+        let mut png = Vec::new();
+        image::RgbaImage::from_pixel(400, 600, image::Rgba([200, 30, 30, 255]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let webp = render(&png, 154, ImageFormat::Webp).unwrap();
+        let img = image::load_from_memory(&webp).unwrap();
+        assert_eq!((img.width(), img.height()), (154, 231));
+
+        // Never enlarged.
+        let jpeg = render(&png, 1280, ImageFormat::Jpeg).unwrap();
+        assert_eq!(
+            image::guess_format(&jpeg).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+        assert_eq!(image::load_from_memory(&jpeg).unwrap().width(), 400);
+
+        // A photo-like image comes out smaller than its source, not larger.
+        let photo = image::RgbImage::from_fn(500, 750, |x, y| {
+            // Smooth gradient plus sensor-like noise
+            let noise = (x.wrapping_mul(2_654_435_761) ^ y.wrapping_mul(40_503)) % 24;
+            let shade = ((x + y) / 5 + noise) as u8;
+            image::Rgb([shade, shade / 2 + 40, 255 - shade])
+        });
+        let mut source = Vec::new();
+        JpegEncoder::new_with_quality(&mut source, 90)
+            .encode_image(&photo)
+            .unwrap();
+        let webp = render(&source, 1920, ImageFormat::Webp).unwrap();
+        assert!(
+            webp.len() < source.len(),
+            "{} >= {}",
+            webp.len(),
+            source.len()
+        );
+    }
+}
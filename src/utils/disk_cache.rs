@@ -0,0 +1,87 @@
+//! Size budget shared by the on-disk caches (HLS segments, artwork): entries
+//! are evicted least recently used first, using file modification times.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Mark a cache entry as recently used.
+pub fn touch(path: &Path) {
+    if let Ok(file) = fs::File::options().append(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Eviction {
+    pub freed: u64,
+    /// Bytes left in the cache
+    pub remaining: u64,
+}
+
+/// Delete the least recently used files under `root` until they fit in
+/// `max_bytes`.
+pub fn evict_lru(root: &Path, max_bytes: u64) -> io::Result<Eviction> {
+    let mut entries = Vec::new();
+    collect_files(root, &mut entries)?;
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    if total <= max_bytes {
+        return Ok(Eviction {
+            freed: 0,
+            remaining: total,
+        });
+    }
+
+    entries.sort_by_key(|(_, _, modified)| *modified);
+    let mut freed = 0;
+    for (path, size, _) in entries {
+        if total <= max_bytes {
+            break;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                total -= size;
+                freed += size;
+                if let Some(parent) = path.parent() {
+                    // Only succeeds once the directory is empty.
+                    let _ = fs::remove_dir(parent);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => total -= size,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(Eviction {
+        freed,
+        remaining: total,
+    })
+}
+
+/// Bytes stored under `root`.
+pub fn usage(root: &Path) -> io::Result<u64> {
+    let mut entries = Vec::new();
+    collect_files(root, &mut entries)?;
+    Ok(entries.iter().map(|(_, size, _)| size).sum())
+}
+
+fn collect_files(dir: &Path, out: &mut Vec<(PathBuf, u64, SystemTime)>) -> io::Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            collect_files(&entry.path(), out)?;
+        } else {
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            out.push((entry.path(), metadata.len(), modified));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,253 @@
+use crate::models::MediaFile;
+use serde::Serialize;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
+use uuid::Uuid;
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroup {
+    /// "media": several files for one movie or episode;
+    /// "content": the same file recorded under different media
+    pub kind: &'static str,
+    /// Media id or `<size>:<partial hash>`
+    pub key: String,
+    pub files: Vec<MediaFile>,
+    /// Recommended copy to keep
+    pub keep: Uuid,
+    /// Freed by deleting every other copy; hardlinks to a kept file free nothing
+    pub reclaimable_bytes: i64,
+}
+
+/// Same size and same sampled hash.
+fn fingerprint(file: &MediaFile) -> Option<(i64, &str)> {
+    Some((file.file_size?, file.fingerprint.as_deref()?))
+}
+
+/// Whether two rows are hardlinks to the same file on disk.
+fn same_inode(a: &MediaFile, b: &MediaFile) -> bool {
+    a.file_inode.is_some() && a.file_inode == b.file_inode
+}
+
+/// Whether `a` is a better copy to keep than `b`: higher quality score, then
+/// larger file, then the one already in the library the longest.
+fn better(a: &MediaFile, b: &MediaFile) -> Ordering {
+    a.quality_score
+        .cmp(&b.quality_score)
+        .then(a.file_size.cmp(&b.file_size))
+        .then_with(|| match (a.downloaded_at, b.downloaded_at) {
+            (Some(x), Some(y)) => y.cmp(&x),
+            (x, y) => x.is_some().cmp(&y.is_some()),
+        })
+        .then_with(|| b.file_path.cmp(&a.file_path))
+}
+
+pub fn recommend_keep(files: &[MediaFile]) -> Option<&MediaFile> {
+    files.iter().max_by(|a, b| better(a, b))
+}
+
+/// Whether two present files are copies of each other.
+pub fn are_duplicates(a: &MediaFile, b: &MediaFile) -> bool {
+    a.id != b.id
+        && a.missing_since.is_none()
+        && b.missing_since.is_none()
+        && !same_inode(a, b)
+        && (a.media_id == b.media_id || fingerprint(a).is_some_and(|f| Some(f) == fingerprint(b)))
+}
+
+/// Copies of `keep` that may be deleted without the user naming them: only
+/// identical content (same size and partial hash). Other files of the same
+/// media may be another part (CD1/CD2) or an edition kept on purpose.
+pub fn identical_copies<'a>(keep: &MediaFile, files: &'a [MediaFile]) -> Vec<&'a MediaFile> {
+    files
+        .iter()
+        .filter(|f| are_duplicates(keep, f))
+        .filter(|f| fingerprint(keep).is_some_and(|k| Some(k) == fingerprint(f)))
+        .collect()
+}
+
+/// A group of rows, unless they are all hardlinks to one file.
+fn into_group(kind: &'static str, key: String, files: Vec<MediaFile>) -> Option<DuplicateGroup> {
+    let keep = recommend_keep(&files)?;
+    let keep_id = keep.id;
+    // Each file on disk counts once, and not at all when it is the kept one.
+    let mut inodes: HashSet<&str> = keep.file_inode.as_deref().into_iter().collect();
+    let copies: Vec<&MediaFile> = files
+        .iter()
+        .filter(|f| f.id != keep_id)
+        .filter(|f| {
+            f.file_inode
+                .as_deref()
+                .is_none_or(|inode| inodes.insert(inode))
+        })
+        .collect();
+    if copies.is_empty() {
+        return None;
+    }
+    let reclaimable_bytes = copies.iter().map(|f| f.file_size.unwrap_or(0)).sum();
+    Some(DuplicateGroup {
+        kind,
+        key,
+        files,
+        keep: keep_id,
+        reclaimable_bytes,
+    })
+}
+
+/// Duplicate groups among the present files: per media, then identical content
+/// spread over several media (one of which is usually a wrong match). Copies of
+/// one media with identical content only appear in the media group; hardlinks
+/// to one file are not copies.
+pub fn find_groups(files: &[MediaFile]) -> Vec<DuplicateGroup> {
+    let present: Vec<&MediaFile> = files.iter().filter(|f| f.missing_since.is_none()).collect();
+
+    let mut by_media: HashMap<Uuid, Vec<MediaFile>> = HashMap::new();
+    let mut by_content: HashMap<(i64, &str), Vec<MediaFile>> = HashMap::new();
+    for file in &present {
+        by_media
+            .entry(file.media_id)
+            .or_default()
+            .push((*file).clone());
+        if let Some(fingerprint) = fingerprint(file) {
+            by_content
+                .entry(fingerprint)
+                .or_default()
+                .push((*file).clone());
+        }
+    }
+
+    let mut groups: Vec<DuplicateGroup> = by_media
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .filter_map(|(media_id, files)| into_group("media", media_id.to_string(), files))
+        .collect();
+    groups.extend(
+        by_content
+            .into_iter()
+            .filter(|(_, files)| files.iter().any(|f| f.media_id != files[0].media_id))
+            .filter_map(|((size, hash), files)| {
+                into_group("content", format!("{}:{}", size, hash), files)
+            }),
+    );
+    groups.sort_by(|a, b| {
+        b.reclaimable_bytes
+            .cmp(&a.reclaimable_bytes)
+            .then_with(|| a.key.cmp(&b.key))
+    });
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn file(media_id: Uuid, path: &str, size: i64, score: Option<i32>) -> MediaFile {
+        MediaFile {
+            id: Uuid::new_v4(),
+            media_id,
+            file_path: path.to_string(),
+            file_size: Some(size),
+            codec_video: None,
+            codec_audio: None,
+            resolution: None,
+            quality_score: score,
+            hash_info: None,
+            source: None,
+            downloaded_at: Some(Utc::now()),
+            fingerprint: None,
+            file_mtime: None,
+            file_inode: None,
+            missing_since: None,
+        }
+    }
+
+    #[test]
+    fn keeps_best_quality_then_oldest() {
+        let media = Uuid::new_v4();
+        let hd = file(media, "/a/Movie.1080p.mkv", 8, Some(80));
+        let mut uhd = file(media, "/a/Movie.2160p.mkv", 20, Some(95));
+        let sd = file(media, "/a/Movie.480p.avi", 2, None);
+        assert_eq!(
+            recommend_keep(&[hd.clone(), uhd.clone(), sd.clone()]).map(|f| f.id),
+            Some(uhd.id)
+        );
+
+        let mut older = uhd.clone();
+        older.id = Uuid::new_v4();
+        older.file_path = "/b/Movie.2160p.mkv".to_string();
+        older.downloaded_at = Some(Utc::now() - Duration::days(3));
+        uhd.downloaded_at = Some(Utc::now());
+        assert_eq!(
+            recommend_keep(&[uhd, older.clone()]).map(|f| f.id),
+            Some(older.id)
+        );
+    }
+
+    #[test]
+    fn groups_by_media_and_content() {
+        let (movie, wrong_match, other) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let a = file(movie, "/a/Movie.mkv", 10, Some(90));
+        let b = file(movie, "/b/Movie.mkv", 5, Some(70));
+        let mut c = file(other, "/c/Other.mkv", 10, None);
+        let mut d = file(wrong_match, "/d/Other (copy).mkv", 10, None);
+        c.fingerprint = Some("h".to_string());
+        d.fingerprint = Some("h".to_string());
+        let mut gone = file(other, "/e/Other.mkv", 10, None);
+        gone.missing_since = Some(Utc::now());
+
+        let groups = find_groups(&[a.clone(), b.clone(), c.clone(), d.clone(), gone]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].kind, "content");
+        assert_eq!(groups[0].key, "10:h");
+        assert_eq!(groups[0].files.len(), 2);
+        assert_eq!(groups[1].kind, "media");
+        assert_eq!(groups[1].keep, a.id);
+        assert_eq!(groups[1].reclaimable_bytes, 5);
+
+        assert!(are_duplicates(&a, &b));
+        assert!(are_duplicates(&c, &d));
+        assert!(!are_duplicates(&a, &c));
+    }
+
+    #[test]
+    fn only_identical_copies_are_removed_by_default() {
+        let movie = Uuid::new_v4();
+        let mut cd1 = file(movie, "/a/Movie.CD1.avi", 700, Some(60));
+        let mut cd2 = file(movie, "/a/Movie.CD2.avi", 698, Some(60));
+        cd1.fingerprint = Some("part1".to_string());
+        cd2.fingerprint = Some("part2".to_string());
+        let files = vec![cd1.clone(), cd2.clone()];
+        assert!(are_duplicates(&cd1, &cd2));
+        assert!(identical_copies(&cd1, &files).is_empty());
+
+        let mut copy = file(movie, "/b/Movie.CD1.avi", 700, Some(60));
+        copy.fingerprint = Some("part1".to_string());
+        let files = vec![cd1.clone(), cd2, copy.clone()];
+        let removed: Vec<Uuid> = identical_copies(&cd1, &files)
+            .iter()
+            .map(|f| f.id)
+            .collect();
+        assert_eq!(removed, vec![copy.id]);
+    }
+
+    #[test]
+    fn hardlinks_are_not_duplicates() {
+        let movie = Uuid::new_v4();
+        let mut imported = file(movie, "/media/Movie.mkv", 10, Some(90));
+        let mut seeding = file(movie, "/downloads/Movie.mkv", 10, Some(90));
+        imported.file_inode = Some("1:42".to_string());
+        seeding.file_inode = Some("1:42".to_string());
+        assert!(!are_duplicates(&imported, &seeding));
+        assert!(find_groups(&[imported.clone(), seeding.clone()]).is_empty());
+
+        // A real copy next to them frees its own size only.
+        let mut copy = file(movie, "/other/Movie.mkv", 10, Some(50));
+        copy.file_inode = Some("2:7".to_string());
+        let groups = find_groups(&[imported, seeding, copy.clone()]);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].files.len(), 3);
+        assert_eq!(groups[0].reclaimable_bytes, 10);
+    }
+}
@@ -0,0 +1,123 @@
+use chrono::{DateTime, SubsecRound, Utc};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{File, Metadata},
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Bytes read from each end of a file.
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Fingerprint of a file from its size and its first and last megabyte. Cheap on
+/// multi-gigabyte videos and unchanged by renames or moves across disks.
+pub fn partial_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+
+    let mut hasher = Sha256::new();
+    hasher.update(size.to_le_bytes());
+
+    let mut buf = vec![0u8; CHUNK_SIZE.min(size) as usize];
+    file.read_exact(&mut buf)?;
+    hasher.update(&buf);
+
+    if size > CHUNK_SIZE {
+        let tail = CHUNK_SIZE.min(size - CHUNK_SIZE);
+        file.seek(SeekFrom::End(-(tail as i64)))?;
+        buf.resize(tail as usize, 0);
+        file.read_exact(&mut buf)?;
+        hasher.update(&buf);
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Modification time of a file, at the microsecond precision the database keeps.
+pub fn modified_at(metadata: &Metadata) -> Option<DateTime<Utc>> {
+    let modified: DateTime<Utc> = metadata.modified().ok()?.into();
+    Some(modified.trunc_subsecs(6))
+}
+
+/// Device and inode of a file, "<dev>:<ino>": equal for hardlinks to it.
+#[cfg(unix)]
+pub fn inode(metadata: &Metadata) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    Some(format!("{}:{}", metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub fn inode(_metadata: &Metadata) -> Option<String> {
+    None
+}
+
+/// Bytes summed from each end of a file by [`movie_hash`].
+const MOVIE_HASH_CHUNK: u64 = 64 * 1024;
+
+/// The "movie hash" subtitle sites index files by: the file size plus the
+/// 64-bit little-endian words of its first and last 64 KiB, wrapping.
+pub fn movie_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    if size < MOVIE_HASH_CHUNK {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "file too small for a movie hash",
+        ));
+    }
+
+    let mut hash = size;
+    let mut buf = vec![0u8; MOVIE_HASH_CHUNK as usize];
+    for offset in [0, size - MOVIE_HASH_CHUNK] {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buf)?;
+        for word in buf.chunks_exact(8) {
+            hash = hash.wrapping_add(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+    }
+    Ok(format!("{:016x}", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_ends_of_file() {
+        let dir = std::env::temp_dir().join(format!("sokoul-hash-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, data: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, data).unwrap();
+            path
+        };
+
+        let mut data = vec![7u8; 3 * CHUNK_SIZE as usize];
+        let original = write("a.mkv", &data);
+        let copy = write("b.mkv", &data);
+        // The middle is not sampled; the tail is.
+        data[CHUNK_SIZE as usize + 10] = 1;
+        let middle_changed = write("c.mkv", &data);
+        data[3 * CHUNK_SIZE as usize - 1] = 1;
+        let tail_changed = write("d.mkv", &data);
+        let small = write("e.srt", b"1\n00:00:01,000 --> 00:00:02,000\nHi\n");
+
+        let hash = partial_hash(&original).unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(partial_hash(&copy).unwrap(), hash);
+        assert_eq!(partial_hash(&middle_changed).unwrap(), hash);
+        assert_ne!(partial_hash(&tail_changed).unwrap(), hash);
+        assert!(partial_hash(&small).is_ok());
+
+        // Size 0x30_0000, plus 0x0707..07 for each of the 2 * 8192 sampled words.
+        let expected = (3 * CHUNK_SIZE).wrapping_add(0x0707_0707_0707_0707u64.wrapping_mul(16_384));
+        assert_eq!(movie_hash(&original).unwrap(), format!("{:016x}", expected));
+        assert!(movie_hash(&small).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,4 +1,10 @@
 use strsim::jaro_winkler;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Lowest similarity accepted for an alias. Aliases are numerous and often
+/// short or generic, so a loose match on one is much more likely to be wrong
+/// than on the main title.
+const ALIAS_THRESHOLD: f64 = 0.85;
 
 /// Clean a torrent title by removing technical specs, year, and release groups.
 pub fn normalize_torrent_title(title: &str) -> String {
@@ -99,11 +105,38 @@ pub fn normalize_torrent_title(title: &str) -> String {
         .to_string()
 }
 
+/// Strip the accents release names usually drop: "Les Évadés" -> "Les Evades".
+/// Letters without a decomposition are spelled out: "Æon Flux" -> "AEon Flux".
+pub fn fold_accents(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.nfkd().filter(|c| !is_combining_mark(*c)) {
+        match c {
+            'æ' => folded.push_str("ae"),
+            'Æ' => folded.push_str("AE"),
+            'œ' => folded.push_str("oe"),
+            'Œ' => folded.push_str("OE"),
+            'ß' => folded.push_str("ss"),
+            'ẞ' => folded.push_str("SS"),
+            'þ' => folded.push_str("th"),
+            'Þ' => folded.push_str("TH"),
+            'ø' => folded.push('o'),
+            'Ø' => folded.push('O'),
+            'đ' | 'ð' => folded.push('d'),
+            'Đ' | 'Ð' => folded.push('D'),
+            'ł' => folded.push('l'),
+            'Ł' => folded.push('L'),
+            'ı' => folded.push('i'),
+            c => folded.push(c),
+        }
+    }
+    folded
+}
+
 /// Compare a torrent title to the expected media title.
 /// Returns a score between 0.0 and 1.0.
 pub fn title_similarity(torrent_title: &str, media_title: &str) -> f64 {
-    let torrent_clean = normalize_torrent_title(torrent_title);
-    let media_clean = media_title.to_lowercase().trim().to_string();
+    let torrent_clean = normalize_torrent_title(&fold_accents(torrent_title));
+    let media_clean = fold_accents(media_title).to_lowercase().trim().to_string();
 
     // Exact match
     if torrent_clean == media_clean {
@@ -132,14 +165,90 @@ pub fn title_similarity(torrent_title: &str, media_title: &str) -> f64 {
 }
 
 /// Check if a torrent title matches the media with a given threshold.
+#[allow(dead_code)]
 pub fn is_title_match(torrent_title: &str, media_title: &str, threshold: f64) -> bool {
     title_similarity(torrent_title, media_title) >= threshold
 }
 
+/// The title (or alias) of `titles` a torrent title matches best, with its
+/// score. The first of `titles` is the main title and must reach `threshold`;
+/// the aliases after it must also reach [`ALIAS_THRESHOLD`].
+pub fn best_title_match<'a>(
+    torrent_title: &str,
+    titles: &'a [String],
+    threshold: f64,
+) -> Option<(&'a str, f64)> {
+    titles
+        .iter()
+        .enumerate()
+        .map(|(i, title)| {
+            let required = if i == 0 {
+                threshold
+            } else {
+                threshold.max(ALIAS_THRESHOLD)
+            };
+            (
+                title.as_str(),
+                title_similarity(torrent_title, title),
+                required,
+            )
+        })
+        .filter(|(_, score, required)| score >= required)
+        .map(|(title, score, _)| (title, score))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Confidence (0.0 to 1.0) that a title and year parsed from a file name refer to a
+/// catalog entry. A differing year weighs heavily; a missing one slightly.
+pub fn match_confidence(
+    title: &str,
+    year: Option<i32>,
+    candidate_title: &str,
+    candidate_year: Option<i32>,
+) -> f64 {
+    let simplify = |s: &str| {
+        s.to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let (title, candidate_title) = (simplify(title), simplify(candidate_title));
+    let similarity = if title == candidate_title {
+        1.0
+    } else {
+        jaro_winkler(&title, &candidate_title)
+    };
+
+    let year_factor = match (year, candidate_year) {
+        (Some(a), Some(b)) if a == b => 1.0,
+        (Some(a), Some(b)) if (a - b).abs() == 1 => 0.95,
+        (Some(_), Some(_)) => 0.6,
+        _ => 0.9,
+    };
+    similarity * year_factor
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_match_confidence() {
+        assert_eq!(
+            match_confidence(
+                "Blade Runner 2049",
+                Some(2017),
+                "Blade Runner 2049",
+                Some(2017)
+            ),
+            1.0
+        );
+        assert!(match_confidence("Heat", None, "Heat", Some(1995)) >= 0.85);
+        assert!(match_confidence("Alien", Some(1979), "Aliens", Some(1986)) < 0.85);
+        assert!(match_confidence("Bounty", Some(2009), "Perrier's Bounty", Some(2009)) < 0.85);
+    }
+
     #[test]
     fn test_exact_match() {
         assert!(is_title_match("Inception", "Inception", 0.65));
@@ -170,6 +279,51 @@ mod tests {
         assert!(score >= 0.90);
     }
 
+    #[test]
+    fn matches_any_alias() {
+        let titles = vec![
+            "Les Évadés".to_string(),
+            "The Shawshank Redemption".to_string(),
+        ];
+        assert_eq!(
+            best_title_match(
+                "The.Shawshank.Redemption.1994.1080p.BluRay.x264",
+                &titles,
+                0.65
+            )
+            .map(|(title, _)| title),
+            Some("The Shawshank Redemption")
+        );
+        assert_eq!(
+            best_title_match("Les.Evades.1994.FRENCH.720p", &titles, 0.65).map(|(t, _)| t),
+            Some("Les Évadés")
+        );
+        assert_eq!(best_title_match("Frozen.2013.720p", &titles, 0.65), None);
+    }
+
+    #[test]
+    fn aliases_need_a_closer_match() {
+        let titles = vec!["Heat".to_string(), "Hitze".to_string()];
+        let score = title_similarity("Hitman.2007.720p", "Hitze");
+        assert!((0.65..ALIAS_THRESHOLD).contains(&score));
+        assert_eq!(best_title_match("Hitman.2007.720p", &titles, 0.65), None);
+        assert_eq!(
+            best_title_match("Hitze.1995.GERMAN.720p", &titles, 0.65).map(|(t, _)| t),
+            Some("Hitze")
+        );
+    }
+
+    #[test]
+    fn folds_every_accent() {
+        assert_eq!(fold_accents("Les Évadés"), "Les Evades");
+        assert_eq!(fold_accents("Amélie Poulain"), "Amelie Poulain");
+        assert_eq!(fold_accents("Cœur de pirate"), "Coeur de pirate");
+        assert_eq!(fold_accents("Æon Flux"), "AEon Flux");
+        assert_eq!(fold_accents("Die Straße"), "Die Strasse");
+        assert_eq!(fold_accents("Kjærlighet på ø"), "Kjaerlighet pa o");
+        assert_eq!(fold_accents("Łódź"), "Lodz");
+    }
+
     #[test]
     fn test_completely_different() {
         let score = title_similarity("The.Matrix.1999.720p.BluRay", "Frozen");
@@ -0,0 +1,473 @@
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use std::{io::SeekFrom, path::Path, time::UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+/// Requests asking for more ranges than this get the whole file instead.
+const MAX_RANGES: usize = 32;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Outcome of a `Range` header against a file of known size.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// No usable Range header: send the whole file.
+    Full,
+    /// Inclusive byte ranges, sorted and merged.
+    Partial(Vec<(u64, u64)>),
+    /// Syntactically valid, but no range overlaps the file.
+    Unsatisfiable,
+}
+
+/// Parse a `Range: bytes=…` header (RFC 9110 §14.1.2). Invalid headers are
+/// ignored, as the RFC allows, and overlapping ranges are merged.
+pub fn parse_range(value: &str, size: u64) -> RangeRequest {
+    let Some((unit, specs)) = value.split_once('=') else {
+        return RangeRequest::Full;
+    };
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return RangeRequest::Full;
+    }
+
+    let mut ranges = Vec::new();
+    for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let Some((start, end)) = spec.split_once('-') else {
+            return RangeRequest::Full;
+        };
+        let (start, end) = (start.trim(), end.trim());
+        let range = if start.is_empty() {
+            // Suffix range: the last N bytes.
+            let Ok(len) = end.parse::<u64>() else {
+                return RangeRequest::Full;
+            };
+            (len > 0 && size > 0).then(|| (size - len.min(size), size - 1))
+        } else {
+            let Ok(start) = start.parse::<u64>() else {
+                return RangeRequest::Full;
+            };
+            let end = if end.is_empty() {
+                u64::MAX
+            } else {
+                match end.parse::<u64>() {
+                    Ok(end) if end >= start => end,
+                    _ => return RangeRequest::Full,
+                }
+            };
+            (start < size).then(|| (start, end.min(size - 1)))
+        };
+        ranges.extend(range);
+    }
+
+    if ranges.len() > MAX_RANGES {
+        return RangeRequest::Full;
+    }
+    if ranges.is_empty() {
+        return RangeRequest::Unsatisfiable;
+    }
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    RangeRequest::Partial(merged)
+}
+
+/// Strong entity tag derived from the file's size and modification time.
+pub fn entity_tag(size: u64, modified: Option<DateTime<Utc>>) -> String {
+    let mtime = modified
+        .and_then(|m| m.timestamp_nanos_opt())
+        .unwrap_or_default();
+    format!("\"{:x}-{:x}\"", size, mtime)
+}
+
+fn http_date(date: DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+/// Weak comparison of an `If-None-Match` list against the current tag.
+fn none_match(header: &str, etag: &str) -> bool {
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag.trim_start_matches("W/"))
+}
+
+/// Whether `If-Range` still designates the current file, so the range applies.
+fn if_range_matches(value: &str, etag: &str, last_modified: Option<DateTime<Utc>>) -> bool {
+    let value = value.trim();
+    if value.starts_with('"') || value.starts_with("W/") {
+        // Strong comparison: weak tags never match.
+        return value == etag;
+    }
+    match (parse_http_date(value), last_modified) {
+        (Some(date), Some(modified)) => date.timestamp() == modified.timestamp(),
+        _ => false,
+    }
+}
+
+/// Serve a file from disk with byte ranges (single and multipart), `If-Range`,
+/// `ETag`/`Last-Modified` validators, `If-None-Match`/`If-Modified-Since` and HEAD.
+pub async fn serve_file(
+    path: &Path,
+    method: &Method,
+    headers: &HeaderMap,
+    content_type: &str,
+) -> std::io::Result<Response> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let metadata = file.metadata().await?;
+    let size = metadata.len();
+    let modified: Option<DateTime<Utc>> = metadata
+        .modified()
+        .ok()
+        .filter(|m| *m > UNIX_EPOCH)
+        .map(DateTime::from);
+    let etag = entity_tag(size, modified);
+
+    let header_str = |name: header::HeaderName| headers.get(name).and_then(|v| v.to_str().ok());
+    let mut builder = Response::builder()
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, &etag);
+    if let Some(modified) = modified {
+        builder = builder.header(header::LAST_MODIFIED, http_date(modified));
+    }
+
+    // If-None-Match takes precedence over If-Modified-Since (RFC 9110 §13.2.2).
+    let not_modified = match header_str(header::IF_NONE_MATCH) {
+        Some(tags) => none_match(tags, &etag),
+        None => match (
+            header_str(header::IF_MODIFIED_SINCE).and_then(parse_http_date),
+            modified,
+        ) {
+            (Some(since), Some(modified)) => modified.timestamp() <= since.timestamp(),
+            _ => false,
+        },
+    };
+    if not_modified {
+        return Ok(builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap());
+    }
+
+    // Range only applies to GET, and only while If-Range still matches.
+    let range = match header_str(header::RANGE) {
+        Some(range) if method == Method::GET => match header_str(header::IF_RANGE) {
+            Some(if_range) if !if_range_matches(if_range, &etag, modified) => RangeRequest::Full,
+            _ => parse_range(range, size),
+        },
+        _ => RangeRequest::Full,
+    };
+    let is_head = method == Method::HEAD;
+
+    match range {
+        RangeRequest::Full => {
+            let body = if is_head {
+                Body::empty()
+            } else {
+                Body::from_stream(ReaderStream::with_capacity(file, CHUNK_SIZE))
+            };
+            Ok(builder
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, content_type)
+                .header(header::CONTENT_LENGTH, size)
+                .body(body)
+                .unwrap())
+        }
+        RangeRequest::Unsatisfiable => Ok(builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", size))
+            .body(Body::empty())
+            .unwrap()),
+        RangeRequest::Partial(ranges) if ranges.len() == 1 => {
+            let (start, end) = ranges[0];
+            file.seek(SeekFrom::Start(start)).await?;
+            let reader = file.take(end - start + 1);
+            Ok(builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_TYPE, content_type)
+                .header(header::CONTENT_LENGTH, end - start + 1)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, size),
+                )
+                .body(Body::from_stream(ReaderStream::with_capacity(
+                    reader, CHUNK_SIZE,
+                )))
+                .unwrap())
+        }
+        RangeRequest::Partial(ranges) => {
+            let boundary = uuid::Uuid::new_v4().simple().to_string();
+            let part_headers: Vec<Bytes> = ranges
+                .iter()
+                .map(|(start, end)| {
+                    Bytes::from(format!(
+                        "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                        boundary, content_type, start, end, size
+                    ))
+                })
+                .collect();
+            let closing = Bytes::from(format!("\r\n--{}--\r\n", boundary));
+            let length = part_headers.iter().map(|h| h.len() as u64).sum::<u64>()
+                + ranges.iter().map(|(s, e)| e - s + 1).sum::<u64>()
+                + closing.len() as u64;
+
+            let parts: Vec<_> = ranges.into_iter().zip(part_headers).collect();
+            let stream = multipart_stream(file, parts, closing);
+
+            let content_type = format!("multipart/byteranges; boundary={}", boundary);
+            Ok(builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_str(&content_type).unwrap(),
+                )
+                .header(header::CONTENT_LENGTH, length)
+                .body(Body::from_stream(stream))
+                .unwrap())
+        }
+    }
+}
+
+/// Body of a `multipart/byteranges` response: each part header followed by its bytes.
+fn multipart_stream(
+    mut file: tokio::fs::File,
+    parts: Vec<((u64, u64), Bytes)>,
+    closing: Bytes,
+) -> impl futures::Stream<Item = std::io::Result<Bytes>> + Send {
+    async_stream::try_stream! {
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        for ((start, end), part_header) in parts {
+            yield part_header;
+            file.seek(SeekFrom::Start(start)).await?;
+            let mut remaining = end - start + 1;
+            while remaining > 0 {
+                let want = remaining.min(CHUNK_SIZE as u64) as usize;
+                let read = file.read(&mut buf[..want]).await?;
+                if read == 0 {
+                    Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?;
+                }
+                remaining -= read as u64;
+                yield Bytes::copy_from_slice(&buf[..read]);
+            }
+        }
+        yield closing;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    struct TestFile(std::path::PathBuf);
+
+    impl TestFile {
+        fn new(name: &str, data: &[u8]) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("sokoul-http-{}-{}", std::process::id(), name));
+            std::fs::write(&path, data).unwrap();
+            TestFile(path)
+        }
+    }
+
+    impl Drop for TestFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn data() -> Vec<u8> {
+        (0..200_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    async fn request(file: &TestFile, method: Method, pairs: &[(&str, &str)]) -> Response {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(
+                header::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        serve_file(&file.0, &method, &headers, "video/x-matroska")
+            .await
+            .unwrap()
+    }
+
+    fn header_of(response: &Response, name: header::HeaderName) -> String {
+        response.headers()[name].to_str().unwrap().to_string()
+    }
+
+    async fn body_of(response: Response) -> Vec<u8> {
+        to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    #[test]
+    fn parses_range_headers() {
+        use RangeRequest::*;
+        assert_eq!(parse_range("bytes=0-99", 1000), Partial(vec![(0, 99)]));
+        assert_eq!(parse_range("bytes=900-", 1000), Partial(vec![(900, 999)]));
+        assert_eq!(parse_range("bytes=-100", 1000), Partial(vec![(900, 999)]));
+        assert_eq!(parse_range("bytes=-5000", 1000), Partial(vec![(0, 999)]));
+        assert_eq!(
+            parse_range("bytes=990-2000", 1000),
+            Partial(vec![(990, 999)])
+        );
+        assert_eq!(
+            parse_range("bytes=500-599, 0-99, 50-149", 1000),
+            Partial(vec![(0, 149), (500, 599)])
+        );
+        assert_eq!(parse_range("bytes=1000-", 1000), Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), Unsatisfiable);
+        assert_eq!(parse_range("bytes=5-1", 1000), Full);
+        assert_eq!(parse_range("items=0-1", 1000), Full);
+        assert_eq!(parse_range("bytes=abc", 1000), Full);
+    }
+
+    #[tokio::test]
+    async fn serves_full_file_with_validators() {
+        let data = data();
+        let file = TestFile::new("full.mkv", &data);
+
+        let response = request(&file, Method::GET, &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(header_of(&response, header::ACCEPT_RANGES), "bytes");
+        assert_eq!(header_of(&response, header::CONTENT_LENGTH), "200000");
+        assert!(header_of(&response, header::ETAG).starts_with('"'));
+        assert!(header_of(&response, header::LAST_MODIFIED).ends_with(" GMT"));
+        assert_eq!(body_of(response).await, data);
+
+        let head = request(&file, Method::HEAD, &[("range", "bytes=0-9")]).await;
+        assert_eq!(head.status(), StatusCode::OK);
+        assert_eq!(header_of(&head, header::CONTENT_LENGTH), "200000");
+        assert!(body_of(head).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn serves_single_ranges() {
+        let data = data();
+        let file = TestFile::new("single.mkv", &data);
+
+        let response = request(&file, Method::GET, &[("range", "bytes=100-199")]).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            header_of(&response, header::CONTENT_RANGE),
+            "bytes 100-199/200000"
+        );
+        assert_eq!(header_of(&response, header::CONTENT_LENGTH), "100");
+        assert_eq!(body_of(response).await, &data[100..200]);
+
+        let suffix = request(&file, Method::GET, &[("range", "bytes=-70000")]).await;
+        assert_eq!(
+            header_of(&suffix, header::CONTENT_RANGE),
+            "bytes 130000-199999/200000"
+        );
+        assert_eq!(body_of(suffix).await, &data[130_000..]);
+
+        let unsatisfiable = request(&file, Method::GET, &[("range", "bytes=200000-")]).await;
+        assert_eq!(unsatisfiable.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            header_of(&unsatisfiable, header::CONTENT_RANGE),
+            "bytes */200000"
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_multipart_ranges() {
+        let data = data();
+        let file = TestFile::new("multi.mkv", &data);
+
+        let response = request(&file, Method::GET, &[("range", "bytes=0-9,100000-100009")]).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = header_of(&response, header::CONTENT_TYPE);
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap()
+            .to_string();
+        let length: usize = header_of(&response, header::CONTENT_LENGTH)
+            .parse()
+            .unwrap();
+
+        let body = body_of(response).await;
+        assert_eq!(body.len(), length);
+
+        let mut expected = Vec::new();
+        for (start, end) in [(0usize, 9usize), (100_000, 100_009)] {
+            expected.extend_from_slice(
+                format!(
+                    "\r\n--{}\r\nContent-Type: video/x-matroska\r\nContent-Range: bytes {}-{}/200000\r\n\r\n",
+                    boundary, start, end
+                )
+                .as_bytes(),
+            );
+            expected.extend_from_slice(&data[start..=end]);
+        }
+        expected.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+        assert_eq!(body, expected);
+    }
+
+    #[tokio::test]
+    async fn honors_conditional_headers() {
+        let data = data();
+        let file = TestFile::new("conditional.mkv", &data);
+        let first = request(&file, Method::GET, &[]).await;
+        let etag = header_of(&first, header::ETAG);
+        let last_modified = header_of(&first, header::LAST_MODIFIED);
+
+        let cached = request(&file, Method::GET, &[("if-none-match", &etag)]).await;
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(header_of(&cached, header::ETAG), etag);
+        assert!(body_of(cached).await.is_empty());
+
+        let weak = format!("\"other\", W/{}", etag);
+        let cached = request(&file, Method::GET, &[("if-none-match", &weak)]).await;
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+
+        let since = request(&file, Method::GET, &[("if-modified-since", &last_modified)]).await;
+        assert_eq!(since.status(), StatusCode::NOT_MODIFIED);
+
+        let changed = request(&file, Method::GET, &[("if-none-match", "\"stale\"")]).await;
+        assert_eq!(changed.status(), StatusCode::OK);
+
+        let same = request(
+            &file,
+            Method::GET,
+            &[("range", "bytes=0-9"), ("if-range", &etag)],
+        )
+        .await;
+        assert_eq!(same.status(), StatusCode::PARTIAL_CONTENT);
+
+        let by_date = request(
+            &file,
+            Method::GET,
+            &[("range", "bytes=0-9"), ("if-range", &last_modified)],
+        )
+        .await;
+        assert_eq!(by_date.status(), StatusCode::PARTIAL_CONTENT);
+
+        let stale = request(
+            &file,
+            Method::GET,
+            &[("range", "bytes=0-9"), ("if-range", "\"stale\"")],
+        )
+        .await;
+        assert_eq!(stale.status(), StatusCode::OK);
+        assert_eq!(body_of(stale).await, data);
+    }
+}
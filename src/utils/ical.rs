@@ -0,0 +1,121 @@
+use chrono::{DateTime, Days, NaiveDate, Utc};
+
+/// All-day calendar event.
+#[derive(Debug, Clone)]
+pub struct IcsEvent {
+    pub uid: String,
+    pub date: NaiveDate,
+    pub summary: String,
+    pub description: String,
+}
+
+/// Render an iCalendar (RFC 5545) document with all-day events.
+pub fn render_calendar(name: &str, events: &[IcsEvent], generated_at: DateTime<Utc>) -> String {
+    let stamp = generated_at.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Sokoul//Release Calendar//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+
+    for event in events {
+        let end = event
+            .date
+            .checked_add_days(Days::new(1))
+            .unwrap_or(event.date);
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", event.uid));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!(
+            "DTSTART;VALUE=DATE:{}",
+            event.date.format("%Y%m%d")
+        ));
+        lines.push(format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")));
+        lines.push(format!("SUMMARY:{}", escape_text(&event.summary)));
+        lines.push(format!("DESCRIPTION:{}", escape_text(&event.description)));
+        lines.push("TRANSP:TRANSPARENT".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold_line(line))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+/// Escape TEXT property values (backslash, semicolon, comma, newline).
+fn escape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Fold content lines longer than 75 octets, without splitting UTF-8 characters.
+fn fold_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / 74 * 3);
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            // The leading space counts towards the continuation line.
+            octets = 1;
+        }
+        out.push(c);
+        octets += c.len_utf8();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn escapes_and_folds_text() {
+        assert_eq!(
+            escape_text("Dune; Part Two, 2024\n"),
+            "Dune\\; Part Two\\, 2024\\n"
+        );
+
+        let folded = fold_line(&format!("SUMMARY:{}", "é".repeat(60)));
+        let lines: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.len() <= 75));
+        assert!(lines[1].starts_with(' '));
+    }
+
+    #[test]
+    fn renders_all_day_events() {
+        let events = vec![IcsEvent {
+            uid: "episode-1@sokoul".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 6, 26).unwrap(),
+            summary: "The Bear S03E01".to_string(),
+            description: "Downloaded".to_string(),
+        }];
+        let generated_at = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let ics = render_calendar("Sokoul", &events, generated_at);
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240626\r\n"));
+        assert!(ics.contains("DTEND;VALUE=DATE:20240627\r\n"));
+        assert!(ics.contains("DTSTAMP:20240601T120000Z\r\n"));
+        assert!(ics.contains("SUMMARY:The Bear S03E01\r\n"));
+    }
+}
@@ -0,0 +1,89 @@
+/// (ISO 639-1, ISO 639-2/B, ISO 639-2/T, English name, native name) for the
+/// languages commonly found in release and subtitle file names.
+const LANGUAGES: &[(&str, &str, &str, &str, &str)] = &[
+    ("ar", "ara", "ara", "arabic", "العربية"),
+    ("bg", "bul", "bul", "bulgarian", "български"),
+    ("cs", "cze", "ces", "czech", "čeština"),
+    ("da", "dan", "dan", "danish", "dansk"),
+    ("de", "ger", "deu", "german", "deutsch"),
+    ("el", "gre", "ell", "greek", "ελληνικά"),
+    ("en", "eng", "eng", "english", "english"),
+    ("es", "spa", "spa", "spanish", "español"),
+    ("et", "est", "est", "estonian", "eesti"),
+    ("fa", "per", "fas", "persian", "فارسی"),
+    ("fi", "fin", "fin", "finnish", "suomi"),
+    ("fr", "fre", "fra", "french", "français"),
+    ("he", "heb", "heb", "hebrew", "עברית"),
+    ("hi", "hin", "hin", "hindi", "हिन्दी"),
+    ("hr", "hrv", "hrv", "croatian", "hrvatski"),
+    ("hu", "hun", "hun", "hungarian", "magyar"),
+    ("id", "ind", "ind", "indonesian", "bahasa indonesia"),
+    ("is", "ice", "isl", "icelandic", "íslenska"),
+    ("it", "ita", "ita", "italian", "italiano"),
+    ("ja", "jpn", "jpn", "japanese", "日本語"),
+    ("ko", "kor", "kor", "korean", "한국어"),
+    ("lt", "lit", "lit", "lithuanian", "lietuvių"),
+    ("lv", "lav", "lav", "latvian", "latviešu"),
+    ("ms", "may", "msa", "malay", "bahasa melayu"),
+    ("nl", "dut", "nld", "dutch", "nederlands"),
+    ("no", "nor", "nor", "norwegian", "norsk"),
+    ("pl", "pol", "pol", "polish", "polski"),
+    ("pt", "por", "por", "portuguese", "português"),
+    ("ro", "rum", "ron", "romanian", "română"),
+    ("ru", "rus", "rus", "russian", "русский"),
+    ("sk", "slo", "slk", "slovak", "slovenčina"),
+    ("sl", "slv", "slv", "slovenian", "slovenščina"),
+    ("sr", "srp", "srp", "serbian", "српски"),
+    ("sv", "swe", "swe", "swedish", "svenska"),
+    ("th", "tha", "tha", "thai", "ไทย"),
+    ("tr", "tur", "tur", "turkish", "türkçe"),
+    ("uk", "ukr", "ukr", "ukrainian", "українська"),
+    ("vi", "vie", "vie", "vietnamese", "tiếng việt"),
+    ("zh", "chi", "zho", "chinese", "中文"),
+];
+
+/// Normalize a language code or name ("fre", "fra", "fr-FR", "French",
+/// "français") to its ISO 639-1 code.
+pub fn normalize(value: &str) -> Option<&'static str> {
+    let value = value.trim().to_lowercase();
+    // Region or script subtags: "pt-BR", "zh_Hans".
+    let primary = value.split(['-', '_']).next().unwrap_or("");
+    LANGUAGES
+        .iter()
+        .find(|(iso1, iso2b, iso2t, english, native)| {
+            [*iso1, *iso2b, *iso2t].contains(&primary) || value == *english || value == *native
+        })
+        .map(|(iso1, ..)| *iso1)
+}
+
+/// English name of an ISO 639-1 code, capitalized ("fr" -> "French").
+pub fn display_name(iso1: &str) -> Option<String> {
+    LANGUAGES
+        .iter()
+        .find(|(code, ..)| *code == iso1)
+        .map(|(_, _, _, english, _)| {
+            let mut chars = english.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_codes_and_names() {
+        assert_eq!(normalize("fre"), Some("fr"));
+        assert_eq!(normalize("fra"), Some("fr"));
+        assert_eq!(normalize("fr-CA"), Some("fr"));
+        assert_eq!(normalize("Français"), Some("fr"));
+        assert_eq!(normalize("ENGLISH"), Some("en"));
+        assert_eq!(normalize("pt_BR"), Some("pt"));
+        assert_eq!(normalize("und"), None);
+        assert_eq!(normalize("forced"), None);
+        assert_eq!(display_name("de").as_deref(), Some("German"));
+    }
+}
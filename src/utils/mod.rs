@@ -1,4 +1,18 @@
+pub mod anime;
+pub mod artwork;
+pub mod disk_cache;
+pub mod duplicates;
+pub mod file_hash;
 pub mod fuzzy;
+pub mod http_file;
+pub mod ical;
+pub mod language;
+pub mod nfo;
+pub mod quality;
+pub mod release;
 pub mod resilience;
 pub mod retry;
 pub mod scoring;
+pub mod storage;
+pub mod subtitles;
+pub mod webvtt;
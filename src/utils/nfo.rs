@@ -0,0 +1,268 @@
+//! Kodi / Jellyfin `.nfo` documents (`<movie>`, `<tvshow>`, `<episodedetails>`).
+
+use crate::models::Media;
+
+/// First line after the XML declaration; files carrying it may be overwritten,
+/// others were written by hand or by another tool and are left alone.
+pub const MARKER: &str = "<!-- Generated by Sokoul -->";
+
+#[derive(Debug, Clone, Default)]
+pub struct Actor {
+    pub name: String,
+    pub role: Option<String>,
+    pub thumb: Option<String>,
+}
+
+/// TMDB details the `media` row does not keep.
+#[derive(Debug, Clone, Default)]
+pub struct Details {
+    pub tagline: Option<String>,
+    /// YYYY-MM-DD
+    pub premiered: Option<String>,
+    pub certification: Option<String>,
+    pub directors: Vec<String>,
+    pub actors: Vec<Actor>,
+}
+
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Control characters are not allowed in XML 1.0.
+            c if c.is_control() && !matches!(c, '\n' | '\t' | '\r') => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+struct Doc(String);
+
+impl Doc {
+    fn new(root: &str) -> Self {
+        Self(format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n{}\n<{}>\n",
+            MARKER, root
+        ))
+    }
+
+    fn tag(&mut self, name: &str, value: Option<impl ToString>) {
+        if let Some(value) = value {
+            let value = value.to_string();
+            if !value.trim().is_empty() {
+                self.0
+                    .push_str(&format!("  <{0}>{1}</{0}>\n", name, escape(value.trim())));
+            }
+        }
+    }
+
+    fn raw(&mut self, xml: &str) {
+        self.0.push_str(xml);
+    }
+
+    fn ids(&mut self, tmdb_id: Option<i32>, imdb_id: Option<&str>) {
+        if let Some(id) = tmdb_id {
+            self.raw(&format!(
+                "  <uniqueid type=\"tmdb\" default=\"true\">{}</uniqueid>\n",
+                id
+            ));
+            self.tag("tmdbid", Some(id));
+        }
+        if let Some(id) = imdb_id.filter(|id| !id.is_empty()) {
+            self.raw(&format!(
+                "  <uniqueid type=\"imdb\">{}</uniqueid>\n",
+                escape(id)
+            ));
+            self.tag("imdbid", Some(id));
+        }
+    }
+
+    fn rating(&mut self, media: &Media) {
+        if let Some(rating) = media.rating {
+            self.raw(&format!(
+                "  <ratings>\n    <rating name=\"themoviedb\" max=\"10\" default=\"true\">\n      <value>{}</value>\n    </rating>\n  </ratings>\n",
+                rating.round_dp(1)
+            ));
+        }
+    }
+
+    fn common(&mut self, media: &Media, details: &Details) {
+        self.tag("title", Some(&media.title));
+        self.tag("originaltitle", media.original_title.as_ref());
+        self.tag("year", media.year);
+        self.rating(media);
+        self.tag("plot", media.overview.as_ref());
+        self.tag("tagline", details.tagline.as_ref());
+        self.tag("runtime", media.runtime_minutes);
+        self.tag("mpaa", details.certification.as_ref());
+        self.ids(media.tmdb_id, media.imdb_id.as_deref());
+        for genre in media.genres.iter().flatten() {
+            self.tag("genre", Some(genre));
+        }
+        for director in &details.directors {
+            self.tag("director", Some(director));
+        }
+        self.tag("premiered", details.premiered.as_ref());
+    }
+
+    fn artwork(&mut self, media: &Media) {
+        if let Some(poster) = &media.poster_url {
+            self.raw(&format!(
+                "  <thumb aspect=\"poster\">{}</thumb>\n",
+                escape(poster)
+            ));
+        }
+        if let Some(backdrop) = &media.backdrop_url {
+            self.raw(&format!(
+                "  <fanart>\n    <thumb>{}</thumb>\n  </fanart>\n",
+                escape(backdrop)
+            ));
+        }
+    }
+
+    fn actors(&mut self, actors: &[Actor]) {
+        for (order, actor) in actors.iter().enumerate() {
+            self.raw("  <actor>\n");
+            self.raw(&format!("    <name>{}</name>\n", escape(&actor.name)));
+            if let Some(role) = actor.role.as_deref().filter(|r| !r.is_empty()) {
+                self.raw(&format!("    <role>{}</role>\n", escape(role)));
+            }
+            self.raw(&format!("    <order>{}</order>\n", order));
+            if let Some(thumb) = &actor.thumb {
+                self.raw(&format!("    <thumb>{}</thumb>\n", escape(thumb)));
+            }
+            self.raw("  </actor>\n");
+        }
+    }
+
+    fn finish(mut self, root: &str) -> String {
+        self.raw(&format!("</{}>\n", root));
+        self.0
+    }
+}
+
+pub fn movie(media: &Media, details: &Details) -> String {
+    let mut doc = Doc::new("movie");
+    doc.common(media, details);
+    doc.artwork(media);
+    doc.actors(&details.actors);
+    doc.finish("movie")
+}
+
+/// `seasons` are (season number, poster URL) pairs.
+pub fn tvshow(series: &Media, details: &Details, seasons: &[(i32, String)]) -> String {
+    let mut doc = Doc::new("tvshow");
+    doc.common(series, details);
+    doc.tag("status", series.status.as_ref());
+    doc.artwork(series);
+    for (season, poster) in seasons {
+        doc.raw(&format!(
+            "  <thumb aspect=\"poster\" type=\"season\" season=\"{}\">{}</thumb>\n",
+            season,
+            escape(poster)
+        ));
+    }
+    doc.actors(&details.actors);
+    doc.finish("tvshow")
+}
+
+pub fn episode(episode: &Media, series: &Media, details: &Details) -> String {
+    let mut doc = Doc::new("episodedetails");
+    doc.tag("title", Some(&episode.title));
+    doc.tag("showtitle", Some(&series.title));
+    doc.tag("season", episode.season_number);
+    doc.tag("episode", episode.episode_number);
+    doc.rating(episode);
+    doc.tag("plot", episode.overview.as_ref());
+    doc.tag("runtime", episode.runtime_minutes);
+    doc.ids(episode.tmdb_id, episode.imdb_id.as_deref());
+    doc.tag("aired", episode.air_date);
+    for director in &details.directors {
+        doc.tag("director", Some(director));
+    }
+    if let Some(still) = &episode.poster_url {
+        doc.raw(&format!("  <thumb>{}</thumb>\n", escape(still)));
+    }
+    doc.actors(&details.actors);
+    doc.finish("episodedetails")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn media(title: &str) -> Media {
+        Media {
+            id: Uuid::new_v4(),
+            media_type: "movie".to_string(),
+            title: title.to_string(),
+            original_title: None,
+            year: Some(2004),
+            tmdb_id: Some(1402),
+            imdb_id: Some("tt0338564".to_string()),
+            overview: Some("Un flic & un gangster <infiltrés>".to_string()),
+            poster_url: Some("https://image.tmdb.org/t/p/w500/p.jpg".to_string()),
+            backdrop_url: None,
+            genres: Some(vec!["Thriller".to_string()]),
+            rating: Some(rust_decimal::Decimal::new(784, 2)),
+            runtime_minutes: Some(101),
+            status: None,
+            parent_id: None,
+            season_number: None,
+            episode_number: None,
+            air_date: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn writes_movie_nfo() {
+        let xml = movie(
+            &media("Infernal Affairs"),
+            &Details {
+                directors: vec!["Andrew Lau".to_string()],
+                actors: vec![Actor {
+                    name: "Andy Lau".to_string(),
+                    role: Some("Lau Kin-ming".to_string()),
+                    thumb: None,
+                }],
+                ..Default::default()
+            },
+        );
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains(MARKER));
+        assert!(xml.contains("<plot>Un flic &amp; un gangster &lt;infiltrés&gt;</plot>"));
+        assert!(xml.contains("<uniqueid type=\"tmdb\" default=\"true\">1402</uniqueid>"));
+        assert!(xml.contains("<uniqueid type=\"imdb\">tt0338564</uniqueid>"));
+        assert!(xml.contains("<value>7.8</value>"));
+        assert!(xml.contains("<genre>Thriller</genre>"));
+        assert!(xml.contains("<role>Lau Kin-ming</role>\n    <order>0</order>"));
+        assert!(!xml.contains("<originaltitle>"));
+        assert!(xml.ends_with("</movie>\n"));
+    }
+
+    #[test]
+    fn writes_episode_nfo() {
+        let series = media("Show");
+        let ep = Media {
+            title: "Pilot".to_string(),
+            season_number: Some(1),
+            episode_number: Some(2),
+            air_date: chrono::NaiveDate::from_ymd_opt(2020, 1, 5),
+            ..media("Pilot")
+        };
+        let xml = episode(&ep, &series, &Details::default());
+        assert!(xml.contains("<episodedetails>"));
+        assert!(xml.contains("<showtitle>Show</showtitle>"));
+        assert!(xml.contains("<season>1</season>\n  <episode>2</episode>"));
+        assert!(xml.contains("<aired>2020-01-05</aired>"));
+    }
+}
@@ -0,0 +1,240 @@
+use crate::providers::TorrentResult;
+use crate::utils::{fuzzy, release, scoring};
+
+/// Vertical resolution advertised in a release title, e.g. `1080` for "1080p".
+/// Titles without a recognizable tag are treated as SD (480).
+pub fn detect_resolution(title: &str) -> u32 {
+    let lower = title.to_lowercase();
+    if lower.contains("2160p") || lower.contains("4k") || lower.contains("uhd") {
+        2160
+    } else if lower.contains("1080p") || lower.contains("1080i") {
+        1080
+    } else if lower.contains("720p") {
+        720
+    } else {
+        480
+    }
+}
+
+/// Parse a profile resolution setting ("720p", "1080p", "4k", "sd"…).
+pub fn parse_resolution(value: &str) -> Option<u32> {
+    match value.trim().to_lowercase().as_str() {
+        "2160p" | "2160" | "4k" | "uhd" => Some(2160),
+        "1080p" | "1080" => Some(1080),
+        "720p" | "720" => Some(720),
+        "480p" | "480" | "sd" => Some(480),
+        _ => None,
+    }
+}
+
+/// Release filters applied before picking a result automatically.
+#[derive(Debug, Clone)]
+pub struct QualityProfile {
+    pub min_resolution: u32,
+    pub max_resolution: Option<u32>,
+    pub min_seeders: i32,
+    pub max_size_bytes: Option<i64>,
+    pub rejected_terms: Vec<String>,
+    /// Files at or above this `scoring::quality_score` are not upgraded (0 disables upgrades).
+    pub cutoff_score: i32,
+    /// Accept CAM/TS/screener sources and grab movies before their digital release.
+    pub allow_pre_digital: bool,
+    /// Country whose digital release date gates movie searches (server default if unset).
+    pub release_region: Option<String>,
+}
+
+impl Default for QualityProfile {
+    fn default() -> Self {
+        Self {
+            min_resolution: 1080,
+            max_resolution: None,
+            min_seeders: 1,
+            max_size_bytes: None,
+            rejected_terms: vec![
+                "cam".to_string(),
+                "hdts".to_string(),
+                "telesync".to_string(),
+                "screener".to_string(),
+            ],
+            cutoff_score: 70,
+            allow_pre_digital: false,
+            release_region: None,
+        }
+    }
+}
+
+impl QualityProfile {
+    /// Returns the reason a result is rejected, or `None` if it is acceptable.
+    pub fn rejection_reason(&self, result: &TorrentResult) -> Option<String> {
+        let resolution = detect_resolution(&result.title);
+        if resolution < self.min_resolution {
+            return Some(format!(
+                "{}p below minimum {}p",
+                resolution, self.min_resolution
+            ));
+        }
+        if let Some(max) = self.max_resolution {
+            if resolution > max {
+                return Some(format!("{}p above maximum {}p", resolution, max));
+            }
+        }
+        if result.seeders.unwrap_or(0) < self.min_seeders {
+            return Some(format!("fewer than {} seeders", self.min_seeders));
+        }
+        if let Some(max_size) = self.max_size_bytes {
+            if result.size_bytes > max_size {
+                return Some("larger than maximum size".to_string());
+            }
+        }
+
+        if !self.allow_pre_digital && release::is_pre_digital(&result.title) {
+            return Some("pre-digital source".to_string());
+        }
+
+        let lower = result.title.to_lowercase();
+        let words: Vec<&str> = lower
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+        self.rejected_terms
+            .iter()
+            .find(|term| words.contains(&term.to_lowercase().as_str()))
+            .map(|term| format!("contains rejected term '{}'", term))
+    }
+}
+
+/// Pick the highest scoring result that matches one of `expected_titles` (title
+/// and aliases) and passes the profile.
+pub fn pick_best<'a>(
+    profile: &QualityProfile,
+    expected_titles: &[String],
+    results: &'a [TorrentResult],
+) -> Option<(&'a TorrentResult, i32)> {
+    results
+        .iter()
+        .filter(|r| fuzzy::best_title_match(&r.title, expected_titles, 0.50).is_some())
+        .filter(|r| profile.rejection_reason(r).is_none())
+        .map(|r| (r, scoring::compute_score(r)))
+        .max_by_key(|(_, score)| *score)
+}
+
+/// Pick a release that improves on a file scored `current_score`, as long as
+/// that file is still under the profile cutoff. Returns the release and its quality score.
+pub fn pick_upgrade<'a>(
+    profile: &QualityProfile,
+    expected_titles: &[String],
+    current_score: i32,
+    results: &'a [TorrentResult],
+) -> Option<(&'a TorrentResult, i32)> {
+    if current_score >= profile.cutoff_score {
+        return None;
+    }
+
+    results
+        .iter()
+        .filter(|r| fuzzy::best_title_match(&r.title, expected_titles, 0.50).is_some())
+        .filter(|r| profile.rejection_reason(r).is_none())
+        .map(|r| (r, scoring::quality_score(&r.title)))
+        .filter(|(_, quality)| *quality > current_score)
+        .max_by_key(|(r, quality)| (*quality, scoring::compute_score(r)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(title: &str, seeders: i32, size_gb: i64) -> TorrentResult {
+        TorrentResult {
+            title: title.to_string(),
+            guid: title.to_string(),
+            size_bytes: size_gb * 1024 * 1024 * 1024,
+            indexer: "test".to_string(),
+            info_url: None,
+            download_url: None,
+            magnet_url: Some("magnet:?xt=urn:btih:test".to_string()),
+            info_hash: None,
+            seeders: Some(seeders),
+            leechers: Some(0),
+            protocol: Some("torrent".to_string()),
+            provider_name: "Test".to_string(),
+        }
+    }
+
+    #[test]
+    fn detects_resolution_tags() {
+        assert_eq!(detect_resolution("Dune.2021.2160p.WEB-DL"), 2160);
+        assert_eq!(detect_resolution("Dune 2021 1080p BluRay x264"), 1080);
+        assert_eq!(detect_resolution("Dune.2021.720p.HDTV"), 720);
+        assert_eq!(detect_resolution("Dune 2021 DVDRip"), 480);
+        assert_eq!(parse_resolution("4K"), Some(2160));
+        assert_eq!(parse_resolution("best"), None);
+    }
+
+    #[test]
+    fn rejects_below_minimum_and_rejected_terms() {
+        let profile = QualityProfile::default();
+        assert!(profile
+            .rejection_reason(&result("Dune 2021 720p WEB", 50, 2))
+            .is_some());
+        assert!(profile
+            .rejection_reason(&result("Dune 2021 1080p HDTS", 50, 2))
+            .is_some());
+        assert!(profile
+            .rejection_reason(&result("Dune 2021 1080p WEB-DL", 50, 2))
+            .is_none());
+
+        let permissive = QualityProfile {
+            rejected_terms: Vec::new(),
+            ..QualityProfile::default()
+        };
+        assert!(permissive
+            .rejection_reason(&result("Dune 2021 1080p HDTC", 50, 2))
+            .is_some());
+        let pre_digital = QualityProfile {
+            allow_pre_digital: true,
+            ..permissive
+        };
+        assert!(pre_digital
+            .rejection_reason(&result("Dune 2021 1080p HDTC", 50, 2))
+            .is_none());
+    }
+
+    #[test]
+    fn picks_best_matching_release() {
+        let profile = QualityProfile {
+            max_resolution: Some(1080),
+            ..QualityProfile::default()
+        };
+        let results = vec![
+            result("Dune 2021 2160p BluRay REMUX", 200, 60),
+            result("Dune 2021 1080p WEB-DL x264", 20, 4),
+            result("Dune 2021 1080p BluRay x265", 150, 6),
+            result("Arrival 2016 1080p BluRay x265", 900, 6),
+        ];
+
+        let (best, _) = pick_best(&profile, &["Dune".to_string()], &results).unwrap();
+        assert_eq!(best.title, "Dune 2021 1080p BluRay x265");
+        assert!(pick_best(&profile, &["Oppenheimer".to_string()], &results).is_none());
+    }
+
+    #[test]
+    fn upgrades_only_below_cutoff() {
+        let profile = QualityProfile {
+            min_resolution: 720,
+            ..QualityProfile::default()
+        };
+        let results = vec![
+            result("Dune 2021 720p WEBRip", 500, 1),
+            result("Dune 2021 1080p BluRay x265", 40, 6),
+        ];
+        let current = scoring::quality_score("Dune.2021.720p.WEBRip");
+        let dune = ["Dune".to_string()];
+
+        let (upgrade, quality) = pick_upgrade(&profile, &dune, current, &results).unwrap();
+        assert_eq!(upgrade.title, "Dune 2021 1080p BluRay x265");
+        assert!(quality > current);
+
+        assert!(pick_upgrade(&profile, &dune, profile.cutoff_score, &results).is_none());
+        assert!(pick_upgrade(&profile, &dune, quality, &results).is_none());
+    }
+}
@@ -0,0 +1,361 @@
+use chrono::{Days, NaiveDate};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::path::Path;
+
+static SXXEXX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\bs(\d{1,2})[ ._-]?e(\d{1,4})\b").unwrap());
+static NXNN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\b(\d{1,2})x(\d{2,3})\b").unwrap());
+/// Anime absolute numbering: "[Group] Show - 1057 [1080p]", "Show - 07v2", "Show Ep 12".
+static ABSOLUTE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(?:\s-\s|[ ._](?:ep?|episode)[ ._]?)(\d{1,4})(?:v\d)?(?:[\s\[(._]|$)").unwrap()
+});
+static YEAR: Lazy<Regex> = Lazy::new(|| Regex::new(r"[\[(. _-]((?:19|20)\d{2})").unwrap());
+static RELEASE_TAG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)[\[(. _-](2160p|1080[pi]|720p|576p|480p|4k|uhd|blu-?ray|bdrip|brrip|web-?dl|webrip|web|hdtv|dvdrip|hdrip|remux|x26[45]|h\.?26[45]|hevc|xvid|proper|repack|multi|vostfr|truefrench|french|complete|integrale)(?:[\]). _-]|$)",
+    )
+    .unwrap()
+});
+static LEADING_GROUP: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[[^\]]*\]\s*").unwrap());
+static SEASON_FOLDER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(season|saison|series|staffel|s)[ ._-]?\d{1,2}$|^specials?$").unwrap()
+});
+
+/// Season and episode numbers of a release name ("Show.S01E02", "Show 1x02").
+pub fn parse_episode(name: &str) -> Option<(i32, i32)> {
+    let caps = SXXEXX.captures(name).or_else(|| NXNN.captures(name))?;
+    let season = caps.get(1)?.as_str().parse().ok()?;
+    let episode = caps.get(2)?.as_str().parse().ok()?;
+    Some((season, episode))
+}
+
+/// Absolute episode number of an anime release name without season and
+/// episode tags. Years are not taken for episode numbers.
+pub fn parse_absolute_episode(name: &str) -> Option<i32> {
+    if parse_episode(name).is_some() {
+        return None;
+    }
+    let stripped = LEADING_GROUP.replace(name.trim(), "");
+    ABSOLUTE
+        .captures_iter(&stripped)
+        .filter_map(|c| c.get(1)?.as_str().parse().ok())
+        .find(|n: &i32| *n > 0 && !(1900..=2099).contains(n))
+}
+
+/// Title, year and episode numbers parsed from a release or file name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedRelease {
+    pub title: String,
+    pub year: Option<i32>,
+    pub season: Option<i32>,
+    pub episode: Option<i32>,
+    /// Anime episode number counted from the first season, when the name has
+    /// no season and episode tags
+    pub absolute_episode: Option<i32>,
+}
+
+/// Parse a release name ("Blade.Runner.2049.2017.1080p.BluRay", "The Bear S02E05 720p").
+/// The title is everything before the year, episode tag or first technical tag.
+pub fn parse_release(name: &str) -> ParsedRelease {
+    let stripped = LEADING_GROUP.replace(name.trim(), "");
+    let name = stripped.as_ref();
+    let absolute_episode = parse_absolute_episode(name);
+    let episode_at = SXXEXX
+        .find(name)
+        .or_else(|| NXNN.find(name))
+        .or_else(|| absolute_episode.and_then(|_| ABSOLUTE.find(name)))
+        .map(|m| m.start());
+    let tag_at = RELEASE_TAG.find(name).map(|m| m.start());
+    let markers_at = [episode_at, tag_at].into_iter().flatten().min();
+
+    // The last year before the markers: "Blade Runner 2049 (2017)" is from 2017.
+    let year = YEAR
+        .captures_iter(name)
+        .filter_map(|c| c.get(1))
+        // Matched without the closing separator so "2049.2017" yields both.
+        .filter(|m| {
+            name[m.end()..]
+                .chars()
+                .next()
+                .is_none_or(|c| "]). _-".contains(c))
+        })
+        .filter(|m| m.start() > 1 && markers_at.is_none_or(|at| m.start() < at))
+        .last();
+
+    let title_end = [year.map(|m| m.start() - 1), markers_at]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(name.len());
+    let title = name[..title_end]
+        .replace(['.', '_'], " ")
+        .trim_matches(|c: char| c.is_whitespace() || "-([".contains(c))
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let (season, episode) = match parse_episode(name) {
+        Some((s, e)) => (Some(s), Some(e)),
+        None => (None, None),
+    };
+
+    ParsedRelease {
+        title,
+        year: year.and_then(|m| m.as_str().parse().ok()),
+        season,
+        episode,
+        absolute_episode,
+    }
+}
+
+/// Parse a library file path, using parent folders (below `root`) for what the
+/// file name lacks: "Show/Season 1/S01E02.mkv", "Movie (1999)/movie.mkv".
+pub fn parse_library_path(path: &Path, root: &Path) -> ParsedRelease {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut parsed = parse_release(&stem);
+
+    let folders = path
+        .ancestors()
+        .skip(1)
+        .take_while(|p| p.starts_with(root) && *p != root)
+        .filter_map(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .filter(|n| !SEASON_FOLDER.is_match(n));
+
+    for folder in folders {
+        let from_folder = parse_release(&folder);
+        if from_folder.title.is_empty() {
+            continue;
+        }
+        // Episodes are named after their show folder; movies take the folder
+        // when it carries the year the file name lacks.
+        if parsed.title.is_empty() || (parsed.year.is_none() && from_folder.year.is_some()) {
+            parsed.title = from_folder.title;
+            parsed.year = parsed.year.or(from_folder.year);
+        }
+        break;
+    }
+
+    parsed
+}
+
+/// Search query used for a single episode, e.g. "The Bear S02E05".
+pub fn episode_query(series_title: &str, season: i32, episode: i32) -> String {
+    format!("{} S{:02}E{:02}", series_title, season, episode)
+}
+
+/// Search query for an anime episode by absolute number, e.g. "One Piece 1057".
+pub fn absolute_episode_query(series_title: &str, absolute: i32) -> String {
+    format!("{} {:02}", series_title, absolute)
+}
+
+/// Whether a release name is the given episode, tagged by season and episode
+/// or, for anime, by `absolute` number.
+pub fn is_episode_release(name: &str, season: i32, episode: i32, absolute: Option<i32>) -> bool {
+    parse_episode(name) == Some((season, episode))
+        || absolute.is_some_and(|n| parse_absolute_episode(name) == Some(n))
+}
+
+/// Source tags of releases captured before a movie is out on digital or disc.
+const PRE_DIGITAL_TAGS: &[&str] = &[
+    "cam",
+    "camrip",
+    "hdcam",
+    "ts",
+    "hdts",
+    "telesync",
+    "tc",
+    "hdtc",
+    "telecine",
+    "scr",
+    "screener",
+    "dvdscr",
+    "r5",
+    "workprint",
+];
+
+/// Theatrical-to-digital window assumed when TMDB reports no digital date.
+const THEATRICAL_WINDOW_DAYS: u64 = 120;
+
+/// Whether a release name advertises a pre-digital source (CAM, TS, TC, screener…).
+pub fn is_pre_digital(name: &str) -> bool {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| PRE_DIGITAL_TAGS.contains(&word))
+}
+
+/// A movie release in one country, as reported by TMDB.
+#[derive(Debug, Clone)]
+pub struct ReleaseDate {
+    pub country: String,
+    /// "theatrical", "digital" or "physical"
+    pub release_type: &'static str,
+    pub release_date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigitalRelease {
+    Available,
+    /// Not out on digital or disc yet; the expected date when known.
+    Pending(Option<NaiveDate>),
+}
+
+/// Whether a movie is out on digital or physical media in `region`. Without
+/// such a date there, it is expected the usual window after the regional
+/// theatrical release; dates from other countries are not used.
+pub fn digital_release(dates: &[ReleaseDate], region: &str, today: NaiveDate) -> DigitalRelease {
+    let earliest = |types: &[&str]| {
+        dates
+            .iter()
+            .filter(|d| types.contains(&d.release_type))
+            .filter(|d| d.country.eq_ignore_ascii_case(region))
+            .map(|d| d.release_date)
+            .min()
+    };
+
+    if let Some(date) = earliest(&["digital", "physical"]) {
+        return if date <= today {
+            DigitalRelease::Available
+        } else {
+            DigitalRelease::Pending(Some(date))
+        };
+    }
+
+    let theatrical = earliest(&["theatrical"]);
+    match theatrical.and_then(|d| d.checked_add_days(Days::new(THEATRICAL_WINDOW_DAYS))) {
+        Some(expected) if expected <= today => DigitalRelease::Available,
+        expected => DigitalRelease::Pending(expected),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_episode_tags() {
+        assert_eq!(
+            parse_episode("The.Bear.S02E05.1080p.WEB.h264"),
+            Some((2, 5))
+        );
+        assert_eq!(parse_episode("the bear s2e5 720p"), Some((2, 5)));
+        assert_eq!(parse_episode("Show 1x02 HDTV"), Some((1, 2)));
+        assert_eq!(parse_episode("Show.S01.COMPLETE.1080p"), None);
+        assert_eq!(parse_episode("Movie 1920x1080"), None);
+    }
+
+    #[test]
+    fn builds_episode_query() {
+        assert_eq!(episode_query("The Bear", 2, 5), "The Bear S02E05");
+    }
+
+    #[test]
+    fn parses_release_names() {
+        let parsed = parse_release("Blade.Runner.2049.2017.1080p.BluRay.x264-SPARKS");
+        assert_eq!(parsed.title, "Blade Runner 2049");
+        assert_eq!(parsed.year, Some(2017));
+
+        assert_eq!(parse_release("1917 (2019) [1080p]").title, "1917");
+        assert_eq!(parse_release("1917 (2019) [1080p]").year, Some(2019));
+
+        let episode = parse_release("[Group] The.Bear.S02E05.720p.WEB");
+        assert_eq!(episode.title, "The Bear");
+        assert_eq!((episode.season, episode.episode), (Some(2), Some(5)));
+
+        let anime = parse_release("[SubsPlease] One Piece - 1057 (1080p) [A1B2C3D4]");
+        assert_eq!(anime.title, "One Piece");
+        assert_eq!(anime.absolute_episode, Some(1057));
+        assert_eq!(anime.episode, None);
+        assert_eq!(parse_absolute_episode("Frieren.Ep.07v2.1080p.WEB"), Some(7));
+        assert_eq!(parse_absolute_episode("Show - S01E07 - Title"), None);
+        assert_eq!(parse_absolute_episode("Heat - 1995 - 1080p"), None);
+        assert!(is_episode_release(
+            "[Erai-raws] One Piece - 1057 [1080p]",
+            21,
+            1057,
+            Some(1057)
+        ));
+        assert!(is_episode_release(
+            "One.Piece.S21E1057.1080p",
+            21,
+            1057,
+            Some(1057)
+        ));
+        assert!(!is_episode_release(
+            "[Erai-raws] One Piece - 1057 [1080p]",
+            21,
+            1057,
+            None
+        ));
+
+        assert_eq!(parse_release("Arrival").title, "Arrival");
+        assert_eq!(parse_release("Arrival").year, None);
+    }
+
+    #[test]
+    fn parses_library_paths() {
+        let root = Path::new("/media/tv");
+        let parsed = parse_library_path(
+            Path::new("/media/tv/The Bear (2022)/Season 2/S02E05.mkv"),
+            root,
+        );
+        assert_eq!(parsed.title, "The Bear");
+        assert_eq!(parsed.year, Some(2022));
+        assert_eq!((parsed.season, parsed.episode), (Some(2), Some(5)));
+
+        let root = Path::new("/media/movies");
+        let parsed = parse_library_path(
+            Path::new("/media/movies/Heat (1995)/heat.1080p.bluray.mkv"),
+            root,
+        );
+        assert_eq!(parsed.title, "Heat");
+        assert_eq!(parsed.year, Some(1995));
+    }
+
+    #[test]
+    fn detects_pre_digital_sources() {
+        assert!(is_pre_digital("Dune.Part.Two.2024.HDCAM.x264"));
+        assert!(is_pre_digital("Dune Part Two 2024 TS 720p"));
+        assert!(!is_pre_digital("Dune.Part.Two.2024.1080p.WEB-DL.DTS"));
+    }
+
+    #[test]
+    fn waits_for_regional_digital_release() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let release = |country: &str, release_type, day: &str| ReleaseDate {
+            country: country.to_string(),
+            release_type,
+            release_date: date(day),
+        };
+        let dates = vec![
+            release("US", "theatrical", "2024-03-01"),
+            release("US", "digital", "2024-04-16"),
+            release("FR", "theatrical", "2024-02-28"),
+            release("FR", "digital", "2024-07-01"),
+        ];
+        let today = date("2024-05-01");
+
+        assert_eq!(
+            digital_release(&dates, "FR", today),
+            DigitalRelease::Pending(Some(date("2024-07-01")))
+        );
+        assert_eq!(
+            digital_release(&dates, "US", today),
+            DigitalRelease::Available
+        );
+        // Out on digital in the US only: not available in Germany.
+        assert_eq!(
+            digital_release(&dates, "DE", today),
+            DigitalRelease::Pending(None)
+        );
+        // Theatrical only: assume the usual window.
+        assert_eq!(
+            digital_release(&dates[..1], "US", today),
+            DigitalRelease::Pending(Some(date("2024-06-29")))
+        );
+    }
+}
@@ -18,23 +18,39 @@ pub fn compute_score(result: &TorrentResult) -> i32 {
         score += 5.0;
     }
 
+    score += release_quality_points(&title_lower);
+
+    score.clamp(0.0, 100.0) as i32
+}
+
+/// Quality of a release judged from its name only (0-100), independent of
+/// seeders and size. Stored as `media_files.quality_score` and compared when
+/// looking for upgrades.
+pub fn quality_score(title: &str) -> i32 {
+    (release_quality_points(&title.to_lowercase()) * 2.0).clamp(0.0, 100.0) as i32
+}
+
+/// Resolution, codec, HDR, audio and source points (max 50), CAM/TS penalty included.
+fn release_quality_points(title_lower: &str) -> f64 {
+    let mut points: f64 = 0.0;
+
     // Quality score (0-25 points)
     if title_lower.contains("2160p") || title_lower.contains("4k") {
-        score += 25.0;
+        points += 25.0;
     } else if title_lower.contains("1080p") {
-        score += 20.0;
+        points += 20.0;
     } else if title_lower.contains("720p") {
-        score += 10.0;
+        points += 10.0;
     } else if title_lower.contains("480p") {
-        score += 3.0;
+        points += 3.0;
     }
 
     // Codec bonus (0-10 points)
     if title_lower.contains("x265") || title_lower.contains("hevc") || title_lower.contains("h265")
     {
-        score += 10.0;
+        points += 10.0;
     } else if title_lower.contains("x264") || title_lower.contains("h264") {
-        score += 5.0;
+        points += 5.0;
     }
 
     // HDR bonus (0-5 points)
@@ -42,7 +58,7 @@ pub fn compute_score(result: &TorrentResult) -> i32 {
         || title_lower.contains("dolby vision")
         || title_lower.contains("dv")
     {
-        score += 5.0;
+        points += 5.0;
     }
 
     // Audio bonus (0-5 points)
@@ -50,9 +66,9 @@ pub fn compute_score(result: &TorrentResult) -> i32 {
         || title_lower.contains("truehd")
         || title_lower.contains("dts-hd")
     {
-        score += 5.0;
+        points += 5.0;
     } else if title_lower.contains("aac") || title_lower.contains("ac3") {
-        score += 2.0;
+        points += 2.0;
     }
 
     // Source quality bonus
@@ -60,11 +76,11 @@ pub fn compute_score(result: &TorrentResult) -> i32 {
         || title_lower.contains("blu-ray")
         || title_lower.contains("remux")
     {
-        score += 5.0;
+        points += 5.0;
     } else if title_lower.contains("web-dl") || title_lower.contains("webdl") {
-        score += 3.0;
+        points += 3.0;
     } else if title_lower.contains("webrip") {
-        score += 2.0;
+        points += 2.0;
     }
 
     // Penalty: CAM/TS/screener
@@ -73,10 +89,10 @@ pub fn compute_score(result: &TorrentResult) -> i32 {
         || title_lower.contains("screener")
         || title_lower.contains("telecine")
     {
-        score -= 30.0;
+        points -= 30.0;
     }
 
-    score.clamp(0.0, 100.0) as i32
+    points
 }
 
 #[cfg(test)]
@@ -113,4 +129,16 @@ mod tests {
         let web = make_result("Movie.2024.1080p.WEB-DL.x264", 50, 2.0);
         assert!(compute_score(&cam) < compute_score(&web));
     }
+
+    #[test]
+    fn quality_score_ignores_availability() {
+        assert_eq!(
+            quality_score("Movie.2024.1080p.WEB-DL.x264"),
+            quality_score("movie 2024 1080p web-dl x264")
+        );
+        assert!(
+            quality_score("Movie.2024.2160p.BluRay.x265") > quality_score("Movie.2024.720p.WEBRip")
+        );
+        assert_eq!(quality_score("Movie.2024.CAM"), 0);
+    }
 }
@@ -0,0 +1,192 @@
+use crate::config::CONFIG;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DirUsage {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Files and bytes below `root`, recursively. Symlinks are not followed, so
+/// linked folders are neither counted twice nor walked in loops.
+pub fn dir_usage(root: &Path) -> DirUsage {
+    let mut usage = DirUsage::default();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() {
+                usage.files += 1;
+                usage.bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            }
+        }
+    }
+    usage
+}
+
+/// `path` made absolute against the working directory (without touching the disk).
+pub fn absolute(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+/// Download directory first, then library roots, without duplicates.
+pub fn roots() -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for root in std::iter::once(&CONFIG.download_dir).chain(&CONFIG.library_roots) {
+        let root = absolute(Path::new(root));
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    }
+    roots
+}
+
+/// The root holding `path`, if any. Paths with `..` are refused outright: lexically
+/// they may look inside a root while resolving outside it.
+pub fn root_of<'a>(path: &Path, roots: &'a [PathBuf]) -> Option<&'a PathBuf> {
+    if path
+        .components()
+        .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        return None;
+    }
+    roots.iter().find(|root| path.starts_with(root))
+}
+
+/// Index of the mount point holding `path`: the longest one it starts with.
+pub fn volume_of(path: &Path, mount_points: &[PathBuf]) -> Option<usize> {
+    let path = absolute(path);
+    mount_points
+        .iter()
+        .enumerate()
+        .filter(|(_, mount)| path.starts_with(mount))
+        .max_by_key(|(_, mount)| mount.as_os_str().len())
+        .map(|(i, _)| i)
+}
+
+/// Fingerprint of an orphan report. A cleanup must quote it, which proves the
+/// caller reviewed the current list and not one that has changed since.
+pub fn confirm_token<'a>(
+    files: impl IntoIterator<Item = &'a str>,
+    rows: impl IntoIterator<Item = String>,
+) -> String {
+    let mut entries: Vec<String> = files
+        .into_iter()
+        .map(|f| format!("file:{}", f))
+        .chain(rows.into_iter().map(|r| format!("row:{}", r)))
+        .collect();
+    entries.sort();
+    let mut hasher = Sha256::new();
+    for entry in &entries {
+        hasher.update(entry.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.finalize()[..12]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Remove the directories left empty between `path` and `root` (exclusive).
+pub fn remove_empty_parents(path: &Path, root: &Path) {
+    let mut dir = path.parent();
+    while let Some(current) = dir {
+        if current == root || !current.starts_with(root) || std::fs::remove_dir(current).is_err() {
+            break;
+        }
+        dir = current.parent();
+    }
+}
+
+/// Move a file, copying it when `to` is on another filesystem. The copy is
+/// written next to `to` and renamed over it, so `to` is never left half written.
+pub async fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if tokio::fs::rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+    let name = to.file_name().unwrap_or_default().to_string_lossy();
+    let partial = to.with_file_name(format!(".{}.part", name));
+    if let Err(e) = tokio::fs::copy(from, &partial).await {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(e);
+    }
+    tokio::fs::rename(&partial, to).await?;
+    tokio::fs::remove_file(from).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_paths_escaping_the_roots() {
+        let roots = vec![PathBuf::from("/media/movies"), PathBuf::from("/downloads")];
+        assert_eq!(
+            root_of(Path::new("/media/movies/Dune (2021)/Dune.mkv"), &roots),
+            Some(&roots[0])
+        );
+        assert_eq!(
+            root_of(Path::new("/media/movies/../../etc/passwd"), &roots),
+            None
+        );
+        assert_eq!(root_of(Path::new("/media/movies/a/../b.mkv"), &roots), None);
+        assert_eq!(root_of(Path::new("/media/moviesx/a.mkv"), &roots), None);
+    }
+
+    #[test]
+    fn counts_nested_files() {
+        let root = std::env::temp_dir().join(format!("sokoul-storage-{}", std::process::id()));
+        let nested = root.join("Movie.2020.1080p/Subs");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(root.join("a.mkv"), [0; 10]).unwrap();
+        std::fs::write(root.join("Movie.2020.1080p/movie.mkv"), [0; 100]).unwrap();
+        std::fs::write(nested.join("fr.srt"), [0; 5]).unwrap();
+
+        assert_eq!(
+            dir_usage(&root),
+            DirUsage {
+                files: 3,
+                bytes: 115
+            }
+        );
+
+        std::fs::remove_file(nested.join("fr.srt")).unwrap();
+        remove_empty_parents(&nested.join("fr.srt"), &root);
+        assert!(!nested.exists());
+        assert!(root.join("Movie.2020.1080p").exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn picks_deepest_volume() {
+        let mounts = vec![PathBuf::from("/"), PathBuf::from("/mnt/media")];
+        assert_eq!(volume_of(Path::new("/mnt/media/a.mkv"), &mounts), Some(1));
+        assert_eq!(volume_of(Path::new("/mnt/mediaX/a.mkv"), &mounts), Some(0));
+        assert_eq!(volume_of(Path::new("/srv/a.mkv"), &mounts[1..]), None);
+    }
+
+    #[test]
+    fn confirm_token_ignores_order() {
+        let a = confirm_token(["/a", "/b"], vec!["1".to_string()]);
+        let b = confirm_token(["/b", "/a"], vec!["1".to_string()]);
+        assert_eq!(a, b);
+        assert_ne!(a, confirm_token(["/a"], vec!["1".to_string()]));
+        assert_ne!(a, confirm_token(["/a", "/b"], Vec::new()));
+    }
+}
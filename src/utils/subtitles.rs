@@ -0,0 +1,311 @@
+use crate::utils::{
+    language,
+    webvtt::{self, Cue},
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// `00:01:02,345 --> 00:01:04,000` (SRT) or `01:02.345 --> 01:04.000` (WebVTT).
+static TIMING: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^\s*(?:(\d+):)?(\d{1,2}):(\d{2})[,.](\d{1,3})\s*-->\s*(?:(\d+):)?(\d{1,2}):(\d{2})[,.](\d{1,3})",
+    )
+    .unwrap()
+});
+/// ASS `H:MM:SS.cc`
+static ASS_TIME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\d+):(\d{2}):(\d{2})[.:](\d{1,3})$").unwrap());
+
+/// Windows-1252 characters for 0x80..=0x9F; other bytes map like Latin-1.
+const CP1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    Ass,
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "ass" | "ssa" => Some(SubtitleFormat::Ass),
+            "vtt" => Some(SubtitleFormat::Vtt),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "srt" => Some(SubtitleFormat::Srt),
+            "ass" => Some(SubtitleFormat::Ass),
+            "vtt" => Some(SubtitleFormat::Vtt),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Ass => "ass",
+            SubtitleFormat::Vtt => "vtt",
+        }
+    }
+}
+
+/// Decode a subtitle file: UTF-8 or UTF-16 with BOM, UTF-8, else Windows-1252
+/// (still the usual encoding of older French and European subtitles).
+pub fn decode_text(bytes: &[u8]) -> String {
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(rest).into_owned();
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return decode_utf16(rest, u16::from_le_bytes);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return decode_utf16(rest, u16::from_be_bytes);
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes
+            .iter()
+            .map(|&b| match b {
+                0x80..=0x9F => CP1252_HIGH[usize::from(b - 0x80)],
+                _ => char::from(b),
+            })
+            .collect(),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Milliseconds from regex captures `hours?, minutes, seconds, fraction`.
+fn capture_ms(caps: &regex::Captures, first: usize) -> i64 {
+    let number = |i: usize| {
+        caps.get(i)
+            .map_or(0, |m| m.as_str().parse::<i64>().unwrap_or(0))
+    };
+    let fraction = caps.get(first + 3).map_or("0", |m| m.as_str());
+    // "5" is half a second, "05" five hundredths.
+    let fraction_ms = fraction.parse::<i64>().unwrap_or(0) * 10i64.pow(3 - fraction.len() as u32);
+    ((number(first) * 60 + number(first + 1)) * 60 + number(first + 2)) * 1000 + fraction_ms
+}
+
+/// SRT and WebVTT share the "timing line, text lines, blank line" layout.
+fn parse_timed_blocks(text: &str, clean: fn(&str) -> String) -> Vec<Cue> {
+    let mut cues = Vec::new();
+    let mut current: Option<(i64, i64, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        if let Some(caps) = TIMING.captures(line) {
+            if let Some((start, end, lines)) = current.take() {
+                cues.push(finish_cue(start, end, &lines, clean));
+            }
+            current = Some((capture_ms(&caps, 1), capture_ms(&caps, 5), Vec::new()));
+        } else if line.trim().is_empty() {
+            if let Some((start, end, lines)) = current.take() {
+                cues.push(finish_cue(start, end, &lines, clean));
+            }
+        } else if let Some((_, _, lines)) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+    if let Some((start, end, lines)) = current {
+        cues.push(finish_cue(start, end, &lines, clean));
+    }
+    cues.retain(|c| !c.text.trim().is_empty());
+    cues
+}
+
+fn finish_cue(start: i64, end: i64, lines: &[&str], clean: fn(&str) -> String) -> Cue {
+    Cue {
+        start_ms: start,
+        end_ms: end,
+        text: clean(lines.join("\n").trim()),
+    }
+}
+
+pub fn parse_srt(text: &str) -> Vec<Cue> {
+    // SRT files in the wild carry ASS-style position tags such as {\an8}.
+    parse_timed_blocks(text, webvtt::ass_text)
+}
+
+pub fn parse_vtt(text: &str) -> Vec<Cue> {
+    parse_timed_blocks(text, |t| t.to_string())
+}
+
+/// Dialogue lines of the `[Events]` section, laid out by its `Format:` line.
+pub fn parse_ass(text: &str) -> Vec<Cue> {
+    let mut in_events = false;
+    let mut fields: Vec<String> = Vec::new();
+    let mut cues = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_events = line.eq_ignore_ascii_case("[events]");
+            continue;
+        }
+        if !in_events {
+            continue;
+        }
+        if let Some(format) = line.strip_prefix("Format:") {
+            fields = format.split(',').map(|f| f.trim().to_lowercase()).collect();
+        } else if let Some(dialogue) = line.strip_prefix("Dialogue:") {
+            if fields.is_empty() {
+                fields = [
+                    "layer", "start", "end", "style", "name", "marginl", "marginr", "marginv",
+                    "effect", "text",
+                ]
+                .iter()
+                .map(|f| f.to_string())
+                .collect();
+            }
+            let values: Vec<&str> = dialogue.splitn(fields.len(), ',').collect();
+            let field = |name: &str| {
+                fields
+                    .iter()
+                    .position(|f| f == name)
+                    .and_then(|i| values.get(i))
+                    .map(|v| v.trim())
+            };
+            let time = |name: &str| {
+                field(name)
+                    .and_then(|v| ASS_TIME.captures(v))
+                    .map(|caps| capture_ms(&caps, 1))
+            };
+            if let (Some(start), Some(end), Some(text)) =
+                (time("start"), time("end"), field("text"))
+            {
+                cues.push(Cue {
+                    start_ms: start,
+                    end_ms: end,
+                    text: webvtt::ass_text(text),
+                });
+            }
+        }
+    }
+    cues.sort_by_key(|c| c.start_ms);
+    cues
+}
+
+pub fn parse(format: SubtitleFormat, text: &str) -> Vec<Cue> {
+    match format {
+        SubtitleFormat::Srt => parse_srt(text),
+        SubtitleFormat::Ass => parse_ass(text),
+        SubtitleFormat::Vtt => parse_vtt(text),
+    }
+}
+
+/// Delay (positive) or advance (negative) every cue.
+pub fn shift(cues: &mut [Cue], offset_ms: i64) {
+    for cue in cues {
+        cue.start_ms += offset_ms;
+        cue.end_ms += offset_ms;
+    }
+}
+
+/// Convert a subtitle file to WebVTT, shifted by `offset_ms`.
+pub fn to_webvtt(format: SubtitleFormat, bytes: &[u8], offset_ms: i64) -> String {
+    let mut cues = parse(format, &decode_text(bytes));
+    shift(&mut cues, offset_ms);
+    webvtt::render(&cues, &[])
+}
+
+/// Flags carried by a sidecar file name after the video name, e.g.
+/// `Movie.fr.forced.srt` or `Subs/3_French_SDH.srt`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SidecarTags {
+    pub language: Option<&'static str>,
+    pub forced: bool,
+    pub sdh: bool,
+}
+
+pub fn sidecar_tags(tags: &str) -> SidecarTags {
+    let mut result = SidecarTags::default();
+    for token in tags
+        .split(['.', '_', '-', ' ', '[', ']', '(', ')'])
+        .filter(|t| !t.is_empty())
+    {
+        match token.to_lowercase().as_str() {
+            "forced" | "foreign" => result.forced = true,
+            "sdh" | "cc" => result.sdh = true,
+            // "hi" after a language means hearing impaired, not Hindi.
+            "hi" if result.language.is_some() => result.sdh = true,
+            _ => {
+                if result.language.is_none() {
+                    result.language = language::normalize(token);
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_srt() {
+        let srt = "1\r\n00:00:01,500 --> 00:00:03,000\r\n{\\an8}<i>Bonjour</i>\r\n\r\n2\r\n00:01:02,05 --> 00:01:04,000\r\nLigne 1\r\nLigne 2\r\n";
+        let cues = parse_srt(srt);
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].start_ms, 1500);
+        assert_eq!(cues[0].text, "<i>Bonjour</i>");
+        assert_eq!(cues[1].start_ms, 62_050);
+        assert_eq!(cues[1].text, "Ligne 1\nLigne 2");
+    }
+
+    #[test]
+    fn parses_ass() {
+        let ass = "[Script Info]\nTitle: x\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:05.10,0:00:07.00,Default,,0,0,0,,{\\i1}Hello, world{\\i0}\\Nbye\nComment: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,hidden\n";
+        let cues = parse_ass(ass);
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].start_ms, 5100);
+        assert_eq!(cues[0].end_ms, 7000);
+        assert_eq!(cues[0].text, "Hello, world\nbye");
+    }
+
+    #[test]
+    fn converts_with_offset() {
+        let vtt = to_webvtt(
+            SubtitleFormat::Vtt,
+            b"WEBVTT\n\nNOTE hi\n\n00:02.000 --> 00:04.000 line:0\nHey\n",
+            -1000,
+        );
+        assert_eq!(vtt, "WEBVTT\n\n00:00:01.000 --> 00:00:03.000\nHey\n");
+    }
+
+    #[test]
+    fn decodes_legacy_encodings() {
+        assert_eq!(decode_text(b"\xEF\xBB\xBFd\xC3\xA9j\xC3\xA0"), "déjà");
+        assert_eq!(decode_text(b"d\xE9j\xE0 \x93vu\x94"), "déjà “vu”");
+        assert_eq!(decode_text(&[0xFF, 0xFE, b'o', 0, b'k', 0]), "ok");
+    }
+
+    #[test]
+    fn reads_sidecar_tags() {
+        assert_eq!(
+            sidecar_tags("fr.forced"),
+            SidecarTags {
+                language: Some("fr"),
+                forced: true,
+                sdh: false
+            }
+        );
+        assert_eq!(sidecar_tags("3_English_SDH").language, Some("en"));
+        assert!(sidecar_tags("eng.hi").sdh);
+        assert_eq!(sidecar_tags("").language, None);
+    }
+}
@@ -0,0 +1,116 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static ASS_OVERRIDE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{[^}]*\}").unwrap());
+/// Any markup tag; only `<b>`, `<i>` and `<u>` survive in WebVTT output.
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"</?([A-Za-z]+)[^>]*>").unwrap());
+
+/// A subtitle cue, times in milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+}
+
+/// Render a WebVTT document. `header` lines (e.g. `X-TIMESTAMP-MAP`) go right
+/// after the signature. Cues ending before zero are dropped.
+pub fn render(cues: &[Cue], header: &[&str]) -> String {
+    let mut out = String::from("WEBVTT\n");
+    for line in header {
+        out.push_str(line);
+        out.push('\n');
+    }
+    for cue in cues
+        .iter()
+        .filter(|c| c.end_ms > 0 && !c.text.trim().is_empty())
+    {
+        out.push('\n');
+        out.push_str(&format!(
+            "{} --> {}\n",
+            format_timestamp(cue.start_ms.max(0)),
+            format_timestamp(cue.end_ms)
+        ));
+        // A blank line would end the cue early.
+        for line in cue.text.lines().filter(|l| !l.trim().is_empty()) {
+            out.push_str(&line.replace("-->", "->"));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// `HH:MM:SS.mmm`
+pub fn format_timestamp(ms: i64) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Keep the tags WebVTT understands, drop the rest (`<font>`...), escape `&`.
+pub fn clean_markup(text: &str) -> String {
+    let text = text.replace('&', "&amp;");
+    TAG.replace_all(&text, |caps: &regex::Captures| {
+        match caps[1].to_lowercase().as_str() {
+            "b" | "i" | "u" => caps[0].to_lowercase(),
+            _ => String::new(),
+        }
+    })
+    .into_owned()
+}
+
+/// Text of an ASS/SSA dialogue: override blocks removed, `\N` line breaks.
+pub fn ass_text(text: &str) -> String {
+    let text = ASS_OVERRIDE.replace_all(text, "");
+    let text = text
+        .replace("\\N", "\n")
+        .replace("\\n", "\n")
+        .replace("\\h", " ");
+    clean_markup(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_timestamps() {
+        assert_eq!(format_timestamp(0), "00:00:00.000");
+        assert_eq!(format_timestamp(3_723_045), "01:02:03.045");
+        assert_eq!(format_timestamp(-5), "00:00:00.000");
+    }
+
+    #[test]
+    fn cleans_markup() {
+        assert_eq!(
+            clean_markup("<font color=\"red\"><I>Tom & Jerry</I></font>"),
+            "<i>Tom &amp; Jerry</i>"
+        );
+        assert_eq!(ass_text("{\\an8}{\\i1}Hello{\\i0}\\NWorld"), "Hello\nWorld");
+    }
+
+    #[test]
+    fn renders_document() {
+        let cues = vec![
+            Cue {
+                start_ms: -500,
+                end_ms: -100,
+                text: "gone".into(),
+            },
+            Cue {
+                start_ms: 1000,
+                end_ms: 2500,
+                text: "Line one\n\nLine two".into(),
+            },
+        ];
+        assert_eq!(
+            render(&cues, &["X-TIMESTAMP-MAP=LOCAL:00:00:00.000,MPEGTS:0"]),
+            "WEBVTT\nX-TIMESTAMP-MAP=LOCAL:00:00:00.000,MPEGTS:0\n\n00:00:01.000 --> 00:00:02.500\nLine one\nLine two\n"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{
+    db::{
+        self,
+        anime::{AnimeMapping, NewAnimeMapping},
+    },
+    models::Media,
+    utils::anime,
+    AppState,
+};
+use once_cell::sync::Lazy;
+use std::time::Duration;
+use tokio::{sync::Mutex, time::Instant};
+use uuid::Uuid;
+
+/// Jikan allows 3 requests a second and 60 a minute; lookups stay well below.
+const JIKAN_INTERVAL: Duration = Duration::from_millis(1500);
+
+static JIKAN_NEXT_CALL: Lazy<Mutex<Instant>> = Lazy::new(|| Mutex::new(Instant::now()));
+
+/// Wait for the next Jikan call slot, shared by every caller.
+pub async fn throttle_jikan() {
+    let mut next = JIKAN_NEXT_CALL.lock().await;
+    tokio::time::sleep_until(*next).await;
+    *next = Instant::now() + JIKAN_INTERVAL;
+}
+
+/// Absolute number of an episode of a series mapped to MyAnimeList; None for
+/// other series, whose releases use season and episode tags.
+pub async fn absolute_number(
+    state: &AppState,
+    series_id: Uuid,
+    season: i32,
+    episode: i32,
+) -> Result<Option<i32>, sqlx::Error> {
+    if db::anime::get_mapping(&state.db_pool, series_id)
+        .await?
+        .is_none()
+    {
+        return Ok(None);
+    }
+    Ok(db::anime::numbering(&state.db_pool, series_id)
+        .await?
+        .to_absolute(season, episode))
+}
+
+/// Map an anime series to MyAnimeList through Jikan, by its title then its
+/// original (Japanese) title. Series already mapped keep their mapping, only
+/// gaining the TVDB id when it was missing.
+pub async fn ensure_mapping(
+    state: &AppState,
+    series: &Media,
+    tvdb_id: Option<i32>,
+) -> Option<AnimeMapping> {
+    match db::anime::get_mapping(&state.db_pool, series.id).await {
+        Ok(Some(mapping)) if mapping.tvdb_id.is_some() || tvdb_id.is_none() => {
+            return Some(mapping)
+        }
+        Ok(Some(mapping)) => {
+            return save(
+                state,
+                &NewAnimeMapping {
+                    media_id: series.id,
+                    mal_id: mapping.mal_id,
+                    tvdb_id,
+                    mal_title: None,
+                    source: if mapping.source == "manual" {
+                        "manual"
+                    } else {
+                        "auto"
+                    },
+                },
+            )
+            .await
+            .or(Some(mapping));
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::warn!("Anime: failed to load mapping of '{}': {}", series.title, e);
+            return None;
+        }
+    }
+
+    let titles = std::iter::once(series.title.as_str()).chain(
+        series
+            .original_title
+            .as_deref()
+            .filter(|t| *t != series.title),
+    );
+    for title in titles {
+        throttle_jikan().await;
+        let results = match state.jikan_client.search_anime(title, 1).await {
+            Ok(response) => response.data,
+            Err(e) => {
+                tracing::warn!("Anime: Jikan search failed for '{}': {}", title, e);
+                return None;
+            }
+        };
+        if let Some(found) = anime::pick_mal_match(title, series.year, &results) {
+            tracing::info!(
+                "Anime: '{}' is MyAnimeList {} ({})",
+                series.title,
+                found.mal_id,
+                found.title.as_deref().unwrap_or("?")
+            );
+            return save(
+                state,
+                &NewAnimeMapping {
+                    media_id: series.id,
+                    mal_id: found.mal_id,
+                    tvdb_id,
+                    mal_title: found.title.as_deref(),
+                    source: "auto",
+                },
+            )
+            .await;
+        }
+    }
+    tracing::debug!("Anime: no MyAnimeList entry for '{}'", series.title);
+    None
+}
+
+async fn save(state: &AppState, mapping: &NewAnimeMapping<'_>) -> Option<AnimeMapping> {
+    match db::anime::upsert_mapping(&state.db_pool, mapping).await {
+        Ok(saved) => saved,
+        Err(e) => {
+            tracing::warn!(
+                "Anime: failed to save mapping to MyAnimeList {}: {}",
+                mapping.mal_id,
+                e
+            );
+            None
+        }
+    }
+}
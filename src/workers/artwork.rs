@@ -0,0 +1,61 @@
+use crate::{
+    config::CONFIG,
+    db,
+    utils::artwork::{self, ArtworkCache, ImageFormat},
+    AppState,
+};
+use std::{sync::Arc, time::Duration};
+use tokio::time;
+
+/// Variants clients ask for most: poster grids and detail page backdrops.
+const POSTER_WIDTH: u32 = 342;
+const BACKDROP_WIDTH: u32 = 1280;
+
+/// Downloads the artwork of library items ahead of time so pages render from
+/// the local cache, including while the image hosts are unreachable.
+pub async fn artwork_worker(state: Arc<AppState>) -> anyhow::Result<()> {
+    tracing::info!("Artwork worker starting...");
+    let cache = ArtworkCache::from_config();
+    let mut interval = time::interval(Duration::from_secs(
+        CONFIG.artwork_prewarm_interval_secs.max(60),
+    ));
+
+    loop {
+        interval.tick().await;
+
+        let items = match db::media::list_library_artwork(&state.db_pool).await {
+            Ok(items) => items,
+            Err(e) => {
+                tracing::error!("Artwork: failed to list library artwork: {}", e);
+                continue;
+            }
+        };
+
+        let mut failed = 0;
+        for (poster, backdrop) in &items {
+            let wanted = [(poster, POSTER_WIDTH), (backdrop, BACKDROP_WIDTH)];
+            for (url, width) in wanted {
+                let Some(url) = url.as_deref().filter(|u| artwork::is_allowed_source(u)) else {
+                    continue;
+                };
+                for format in [ImageFormat::Webp, ImageFormat::Jpeg] {
+                    if let Err(e) = cache.variant(url, width, format).await {
+                        tracing::debug!("Artwork: unable to cache {}: {}", url, e);
+                        failed += 1;
+                        break;
+                    }
+                }
+            }
+        }
+        tracing::info!(
+            "Artwork: {} library item(s) checked, {} image(s) unavailable",
+            items.len(),
+            failed
+        );
+        match cache.evict().await {
+            Ok(0) => {}
+            Ok(freed) => tracing::info!("Artwork: evicted {} MB from the cache", freed >> 20),
+            Err(e) => tracing::warn!("Artwork: cache eviction failed: {}", e),
+        }
+    }
+}
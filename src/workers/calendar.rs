@@ -0,0 +1,111 @@
+use crate::{
+    config::CONFIG, db, models::Media, utils::release::ReleaseDate, workers::series, AppState,
+};
+use chrono::NaiveDate;
+use std::{sync::Arc, time::Duration};
+use tokio::time;
+use uuid::Uuid;
+
+/// Caches release dates of watchlisted movies and episode lists of watchlisted
+/// series (monitored series are handled by the series worker) for the calendar.
+pub async fn calendar_worker(state: Arc<AppState>) -> anyhow::Result<()> {
+    tracing::info!("Calendar worker starting...");
+
+    // Wake up at least hourly; each item is refreshed once per refresh interval.
+    let mut interval = time::interval(Duration::from_secs(
+        CONFIG.calendar_refresh_interval_secs.clamp(60, 3600),
+    ));
+
+    loop {
+        interval.tick().await;
+
+        let stale = match db::calendar::list_media_to_refresh(
+            &state.db_pool,
+            CONFIG.calendar_refresh_interval_secs as f64,
+            50,
+        )
+        .await
+        {
+            Ok(media) => media,
+            Err(e) => {
+                tracing::error!("Calendar: failed to load media to refresh: {}", e);
+                continue;
+            }
+        };
+
+        for media in stale {
+            if let Err(e) = refresh_media(&state, &media).await {
+                tracing::warn!("Calendar: refresh failed for '{}': {}", media.title, e);
+            }
+        }
+    }
+}
+
+async fn refresh_media(state: &AppState, media: &Media) -> anyhow::Result<()> {
+    match media.media_type.as_str() {
+        "movie" => {
+            let Some(tmdb_id) = media.tmdb_id else {
+                return Ok(db::calendar::mark_calendar_synced(&state.db_pool, media.id).await?);
+            };
+            refresh_release_dates(state, media.id, tmdb_id).await?;
+        }
+        _ => {
+            series::sync_episodes(
+                state,
+                media.id,
+                &media.title,
+                media.tmdb_id,
+                media.imdb_id.as_deref(),
+            )
+            .await?;
+            db::calendar::mark_calendar_synced(&state.db_pool, media.id).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Fetch a movie's per-country release dates from TMDB and cache them.
+pub async fn refresh_release_dates(
+    state: &AppState,
+    media_id: Uuid,
+    tmdb_id: i32,
+) -> anyhow::Result<Vec<ReleaseDate>> {
+    let dates = movie_release_dates(state, tmdb_id).await?;
+    db::calendar::replace_release_dates(&state.db_pool, media_id, &dates).await?;
+    Ok(dates)
+}
+
+async fn movie_release_dates(state: &AppState, tmdb_id: i32) -> anyhow::Result<Vec<ReleaseDate>> {
+    let countries = state.tmdb_client.movie_release_dates(tmdb_id).await?;
+
+    Ok(countries
+        .into_iter()
+        .flat_map(|country| {
+            let code = country.iso_3166_1;
+            country.release_dates.into_iter().filter_map(move |entry| {
+                let release_type = release_kind(entry.release_type)?;
+                // "2024-03-01T00:00:00.000Z" → date part only
+                let release_date = entry
+                    .release_date
+                    .as_deref()
+                    .and_then(|d| d.get(..10))
+                    .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())?;
+                Some(ReleaseDate {
+                    country: code.clone(),
+                    release_type,
+                    release_date,
+                })
+            })
+        })
+        .collect())
+}
+
+/// Calendar kind of a TMDB release type; premieres and TV airings are skipped.
+fn release_kind(tmdb_type: i32) -> Option<&'static str> {
+    match tmdb_type {
+        2 | 3 => Some("theatrical"),
+        4 => Some("digital"),
+        5 => Some("physical"),
+        _ => None,
+    }
+}
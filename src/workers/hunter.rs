@@ -1,18 +1,21 @@
 use crate::{
     config::CONFIG,
     db,
+    db::media_files::FileDetails,
     events::{self, DownloadRequestedPayload, WsEvent},
     utils::{
-        fuzzy,
+        fuzzy, quality,
         retry::{self, RetryConfig},
+        scoring, storage,
     },
+    workers::{nfo, subtitles},
     AppState,
 };
 use futures::StreamExt;
-use librqbit::{AddTorrent, Session};
+use librqbit::{AddTorrent, AddTorrentOptions, Session};
 use reqwest::header::LOCATION;
 use rust_decimal::Decimal;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::{broadcast, Semaphore};
 use url::Url;
@@ -64,7 +67,7 @@ pub async fn hunter_worker(state: Arc<AppState>) -> anyhow::Result<()> {
             payload.media_id
         );
 
-        let media = match db::media::get_media_by_id(&state.db_pool, payload.media_id).await {
+        let mut media = match db::media::get_media_by_id(&state.db_pool, payload.media_id).await {
             Ok(m) => Some(m),
             Err(e) => {
                 tracing::warn!("Hunter: failed to load media for fuzzy validation: {}", e);
@@ -72,33 +75,62 @@ pub async fn hunter_worker(state: Arc<AppState>) -> anyhow::Result<()> {
             }
         };
 
-        if let Some(ref media) = media {
-            let similarity = fuzzy::title_similarity(&payload.title, &media.title);
-            tracing::info!(
-                "Hunter: title similarity '{}' vs '{}' = {:.2}%",
-                payload.title,
-                media.title,
-                similarity * 100.0
-            );
+        // Episode releases are named after the series, not the episode.
+        if let Some(parent_id) = media.as_ref().and_then(|m| m.parent_id) {
+            media = db::media::get_media_by_id(&state.db_pool, parent_id)
+                .await
+                .ok()
+                .or(media);
+        }
 
-            if !fuzzy::is_title_match(&payload.title, &media.title, 0.50) {
-                tracing::warn!(
-                    "Hunter: title '{}' does not match '{}' (score: {:.2}%), skipping.",
-                    payload.title,
-                    media.title,
-                    similarity * 100.0
-                );
-                message
-                    .ack()
+        if let Some(ref media) = media {
+            let titles =
+                db::media_titles::titles_for_media(&state.db_pool, media, &CONFIG.release_region)
+                    .await;
+            match fuzzy::best_title_match(&payload.title, &titles, 0.50) {
+                Some((matched, similarity)) => {
+                    tracing::info!(
+                        "Hunter: '{}' matches '{}' ({:.2}%)",
+                        payload.title,
+                        matched,
+                        similarity * 100.0
+                    );
+                    if let Err(e) = db::search_results::set_matched_title(
+                        &state.db_pool,
+                        payload.search_result_id,
+                        matched,
+                    )
                     .await
-                    .map_err(|e| anyhow::anyhow!("Ack failed: {}", e))?;
-                continue;
+                    {
+                        tracing::warn!("Hunter: failed to record matched title: {}", e);
+                    }
+                }
+                None => {
+                    tracing::warn!(
+                        "Hunter: title '{}' matches none of {:?}, skipping.",
+                        payload.title,
+                        titles
+                    );
+                    let _ = db::watchlist::settle_queued_for_media(
+                        &state.db_pool,
+                        payload.media_id,
+                        "failed",
+                        Some("Release title does not match the media"),
+                    )
+                    .await;
+                    message
+                        .ack()
+                        .await
+                        .map_err(|e| anyhow::anyhow!("Ack failed: {}", e))?;
+                    continue;
+                }
             }
         }
 
         let session_clone = session.clone();
         let db_pool = state.db_pool.clone();
         let event_tx = state.event_tx.clone();
+        let import_state = state.clone();
         let permit = semaphore.clone().acquire_owned().await?;
         let media_id = payload.media_id;
 
@@ -110,6 +142,7 @@ pub async fn hunter_worker(state: Arc<AppState>) -> anyhow::Result<()> {
                     "media_id": media_id.to_string(),
                     "title": payload.title,
                     "search_result_id": payload.search_result_id,
+                    "replaces_file_id": payload.replaces_file_id,
                 })),
             },
         )
@@ -148,6 +181,8 @@ pub async fn hunter_worker(state: Arc<AppState>) -> anyhow::Result<()> {
             };
 
             let magnet = payload.magnet_or_url.clone();
+            // One folder per grab so an upgrade never overwrites the file it replaces.
+            let sub_folder = format!("{}-{}", media_id, payload.search_result_id);
             let title_for_progress = payload.title.clone();
             let event_tx_for_progress = event_tx.clone();
             let db_pool_for_progress = db_pool.clone();
@@ -161,11 +196,13 @@ pub async fn hunter_worker(state: Arc<AppState>) -> anyhow::Result<()> {
                     let media_id_ref = media_id.to_string();
                     let tx = event_tx_for_progress.clone();
                     let pool = db_pool_for_progress.clone();
+                    let sub_folder_ref = sub_folder.clone();
                     let tid = task_id;
                     async move {
                         download_torrent_with_progress(
                             &session_ref,
                             &magnet_ref,
+                            &sub_folder_ref,
                             &title_ref,
                             &media_id_ref,
                             tid,
@@ -179,18 +216,51 @@ pub async fn hunter_worker(state: Arc<AppState>) -> anyhow::Result<()> {
             .await;
 
             match result {
-                Ok(output_name) => {
+                Ok((output_path, file_size)) => {
+                    let output_name = output_path.to_string_lossy().to_string();
                     tracing::info!(
                         "Download completed for '{}': {}",
                         payload.title,
                         output_name
                     );
 
-                    let _ = db::media_files::create_media_file(
+                    let details = FileDetails {
+                        file_path: &output_name,
+                        file_size: i64::try_from(file_size).ok(),
+                        resolution: Some(format!(
+                            "{}p",
+                            quality::detect_resolution(&payload.title)
+                        )),
+                        quality_score: Some(scoring::quality_score(&payload.title)),
+                    };
+                    match payload.replaces_file_id {
+                        Some(file_id) => {
+                            finish_upgrade(&db_pool, file_id, &details, &payload.title).await
+                        }
+                        None => {
+                            match db::media_files::create_media_file(
+                                &db_pool, media_id, &details, "torrent",
+                            )
+                            .await
+                            {
+                                Ok(file) => {
+                                    subtitles::after_import(&db_pool, &file).await;
+                                    nfo::after_import(&import_state, &file).await;
+                                }
+                                Err(e) => tracing::error!(
+                                    "Failed to record file '{}': {}",
+                                    output_name,
+                                    e
+                                ),
+                            }
+                        }
+                    }
+
+                    let _ = db::watchlist::settle_queued_for_media(
                         &db_pool,
                         media_id,
-                        &output_name,
-                        "torrent",
+                        "downloaded",
+                        Some(&output_name),
                     )
                     .await;
 
@@ -216,6 +286,14 @@ pub async fn hunter_worker(state: Arc<AppState>) -> anyhow::Result<()> {
                 Err(e) => {
                     tracing::error!("Download failed for '{}': {}", payload.title, e);
 
+                    let _ = db::watchlist::settle_queued_for_media(
+                        &db_pool,
+                        media_id,
+                        "failed",
+                        Some(&e.to_string()),
+                    )
+                    .await;
+
                     if let Some(tid) = task_id {
                         let _ = db::tasks::update_task_status(
                             &db_pool,
@@ -248,6 +326,90 @@ pub async fn hunter_worker(state: Arc<AppState>) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Move an upgraded download next to the file it replaces and point the file
+/// row at it, then remove the old file. Until the new file is in place the old
+/// one is left alone, so a failed import keeps the library playable.
+async fn finish_upgrade(
+    db_pool: &sqlx::PgPool,
+    file_id: Uuid,
+    details: &FileDetails<'_>,
+    release_title: &str,
+) {
+    let old = match db::media_files::get_file_by_id(db_pool, file_id).await {
+        Ok(file) => file,
+        Err(e) => {
+            tracing::error!("Failed to load file {} for its upgrade: {}", file_id, e);
+            return;
+        }
+    };
+
+    let downloaded = Path::new(details.file_path);
+    let mut target = match (Path::new(&old.file_path).parent(), downloaded.file_name()) {
+        (Some(dir), Some(name)) => dir.join(name),
+        _ => downloaded.to_path_buf(),
+    };
+    // Never overwrite another file that happens to have the release's name.
+    if target != Path::new(&old.file_path) && target.exists() {
+        tracing::warn!(
+            "Upgrade of file {}: '{}' already exists, keeping the download in place",
+            file_id,
+            target.display()
+        );
+        target = downloaded.to_path_buf();
+    }
+    if target != downloaded {
+        if let Err(e) = storage::move_file(downloaded, &target).await {
+            tracing::error!(
+                "Upgrade of file {}: cannot move '{}' to '{}': {}",
+                file_id,
+                details.file_path,
+                target.display(),
+                e
+            );
+            return;
+        }
+        storage::remove_empty_parents(
+            downloaded,
+            &storage::absolute(Path::new(&CONFIG.download_dir)),
+        );
+    }
+
+    let target_path = target.to_string_lossy().to_string();
+    let details = FileDetails {
+        file_path: &target_path,
+        file_size: details.file_size,
+        resolution: details.resolution.clone(),
+        quality_score: details.quality_score,
+    };
+    let old_path = match db::media_files::replace_with_upgrade(
+        db_pool,
+        file_id,
+        &details,
+        release_title,
+    )
+    .await
+    {
+        Ok(path) => path,
+        Err(e) => {
+            tracing::error!("Failed to record upgrade of file {}: {}", file_id, e);
+            return;
+        }
+    };
+
+    tracing::info!(
+        "Upgraded file {}: '{}' -> '{}'",
+        file_id,
+        old_path,
+        target_path
+    );
+
+    if old_path != target_path && Path::new(&old_path).is_file() {
+        if let Err(e) = tokio::fs::remove_file(&old_path).await {
+            tracing::warn!("Failed to remove replaced file '{}': {}", old_path, e);
+        }
+    }
+}
+
 async fn resolve_magnet_or_url(magnet_or_url: &str) -> anyhow::Result<String> {
     let mut current = magnet_or_url.to_string();
 
@@ -300,20 +462,27 @@ async fn resolve_magnet_or_url(magnet_or_url: &str) -> anyhow::Result<String> {
     Ok(current)
 }
 
-/// Add torrent and poll stats() every 2s, emitting DownloadProgress via WS
+/// Add torrent and poll stats() every 2s, emitting DownloadProgress via WS.
+/// Returns the path and size of the main (largest) file once complete.
+#[allow(clippy::too_many_arguments)]
 async fn download_torrent_with_progress(
     session: &Arc<Session>,
     magnet_or_url: &str,
+    sub_folder: &str,
     title: &str,
     media_id: &str,
     task_id: Option<Uuid>,
     event_tx: &broadcast::Sender<String>,
     db_pool: &sqlx::PgPool,
-) -> anyhow::Result<String> {
+) -> anyhow::Result<(PathBuf, u64)> {
     let current = resolve_magnet_or_url(magnet_or_url).await?;
 
+    let options = AddTorrentOptions {
+        sub_folder: Some(sub_folder.to_string()),
+        ..Default::default()
+    };
     let handle = session
-        .add_torrent(AddTorrent::from_url(&current), None)
+        .add_torrent(AddTorrent::from_url(&current), Some(options))
         .await?
         .into_handle()
         .ok_or_else(|| anyhow::anyhow!("Torrent already managed or failed to add: {}", current))?;
@@ -361,5 +530,21 @@ async fn download_torrent_with_progress(
         }
     }
 
-    Ok(current)
+    let (relative, len) = handle
+        .with_metadata(|metadata| {
+            metadata
+                .file_infos
+                .iter()
+                .filter(|f| !f.attrs.padding)
+                .max_by_key(|f| f.len)
+                .map(|f| (f.relative_filename.clone(), f.len))
+        })?
+        .ok_or_else(|| anyhow::anyhow!("Torrent has no files: {}", current))?;
+
+    Ok((
+        PathBuf::from(&CONFIG.download_dir)
+            .join(sub_folder)
+            .join(relative),
+        len,
+    ))
 }
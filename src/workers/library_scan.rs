@@ -0,0 +1,558 @@
+use crate::{
+    clients::tmdb::TmdbSearchResult,
+    config::CONFIG,
+    db::{self, library::NewReviewItem, media_files::FileDetails},
+    events::WsEvent,
+    models::{CreateMediaPayload, CreateTaskPayload, Media, MediaFile, UpsertEpisodePayload},
+    utils::{
+        anime, fuzzy, quality,
+        release::{self, ParsedRelease},
+        scoring,
+    },
+    workers::{nfo, series, subtitles},
+    AppState,
+};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use uuid::Uuid;
+
+/// Extensions treated as video files when walking library folders.
+pub const VIDEO_EXTENSIONS: &[&str] = &[
+    "mkv", "mp4", "m4v", "avi", "mov", "wmv", "webm", "ts", "m2ts", "mpg", "mpeg",
+];
+
+/// Candidates kept on a review item.
+const MAX_CANDIDATES: usize = 5;
+/// Below this lead over the runner-up, a match is ambiguous ("Heat" without a year).
+const MIN_LEAD: f64 = 0.05;
+/// Progress is reported every this many files.
+const PROGRESS_EVERY: usize = 10;
+
+static SCAN_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Marks a scan as running until dropped, even when the scan task panics.
+struct ScanGuard;
+
+impl ScanGuard {
+    /// `None` when a scan is already running.
+    fn acquire() -> Option<Self> {
+        SCAN_RUNNING
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()
+            .map(|_| ScanGuard)
+    }
+}
+
+impl Drop for ScanGuard {
+    fn drop(&mut self) {
+        SCAN_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// A video file found under a library root.
+#[derive(Debug, Clone)]
+pub struct ScannedFile {
+    pub path: PathBuf,
+    pub root: PathBuf,
+    pub size: u64,
+}
+
+/// TMDB entry a scanned file may belong to.
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchCandidate {
+    pub tmdb_id: i32,
+    pub media_type: String,
+    pub title: String,
+    pub year: Option<i32>,
+    pub confidence: f64,
+    #[serde(skip)]
+    result: TmdbSearchResult,
+}
+
+#[derive(Debug)]
+pub enum ImportOutcome {
+    Imported(Box<MediaFile>),
+    NeedsReview,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ScanSummary {
+    pub scanned: usize,
+    pub imported: usize,
+    pub needs_review: usize,
+    pub failed: usize,
+}
+
+/// Matches scanned files to TMDB, caching searches for the duration of a scan so
+/// a season folder costs one lookup rather than one per episode.
+#[derive(Default)]
+pub struct LibraryMatcher {
+    searches: HashMap<(String, &'static str), Vec<TmdbSearchResult>>,
+}
+
+impl LibraryMatcher {
+    /// Ranked TMDB candidates for a parsed file, best first.
+    pub async fn candidates(
+        &mut self,
+        state: &AppState,
+        parsed: &ParsedRelease,
+    ) -> anyhow::Result<Vec<MatchCandidate>> {
+        // "Title - 12" is an anime episode numbered from the first season, or a
+        // movie sequel: other series are not numbered that way.
+        let wanted = match (parsed.season, parsed.episode, parsed.absolute_episode) {
+            (Some(_), Some(_), _) => "tv",
+            (_, _, Some(_)) => "anime",
+            _ => "movie",
+        };
+        let key = (parsed.title.to_lowercase(), wanted);
+        if !self.searches.contains_key(&key) {
+            let mut results: Vec<_> = state
+                .tmdb_client
+                .search_multi(&parsed.title)
+                .await?
+                .into_iter()
+                .filter(|r| match wanted {
+                    "anime" => r.media_type == "tv" || r.media_type == "movie",
+                    _ => r.media_type == wanted,
+                })
+                .collect();
+            if wanted == "anime" {
+                let series_ids: Vec<i32> = results
+                    .iter()
+                    .filter(|r| r.media_type == "tv")
+                    .map(|r| r.id)
+                    .collect();
+                let mapped = db::anime::mapped_tmdb_ids(&state.db_pool, &series_ids).await?;
+                results.retain(|r| {
+                    r.media_type == "movie"
+                        || mapped.contains(&r.id)
+                        || anime::is_anime(
+                            r.genre_ids.as_deref().unwrap_or_default(),
+                            r.original_language.as_deref(),
+                        )
+                });
+            }
+            self.searches.insert(key.clone(), results);
+        }
+
+        let mut candidates: Vec<MatchCandidate> = self.searches[&key]
+            .iter()
+            .map(|r| MatchCandidate {
+                tmdb_id: r.id,
+                media_type: r.media_type.clone(),
+                title: r.get_title(),
+                year: r.get_year(),
+                confidence: fuzzy::match_confidence(
+                    &parsed.title,
+                    parsed.year,
+                    &r.get_title(),
+                    r.get_year(),
+                ),
+                result: r.clone(),
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        candidates.truncate(MAX_CANDIDATES);
+        Ok(candidates)
+    }
+}
+
+/// The candidate to import without review, if it is both confident and unambiguous.
+fn confident_match(candidates: &[MatchCandidate]) -> Option<&MatchCandidate> {
+    let best = candidates.first()?;
+    if best.confidence < CONFIG.library_match_threshold {
+        return None;
+    }
+    match candidates.get(1) {
+        Some(second) if best.confidence - second.confidence < MIN_LEAD => None,
+        _ => Some(best),
+    }
+}
+
+/// Whether a path looks like a library video (samples and trailers excluded).
+pub fn is_video_file(path: &Path) -> bool {
+    let is_video = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    is_video && !stem.contains("sample") && !stem.ends_with("-trailer")
+}
+
+/// Recursively list video files under `root`, skipping hidden and sample folders.
+pub fn collect_video_files(root: &Path) -> Vec<ScannedFile> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!("Library scan: cannot read {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if !name.starts_with('.') && name != "sample" && name != "samples" {
+                    dirs.push(path);
+                }
+            } else if file_type.is_file() && is_video_file(&path) {
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                files.push(ScannedFile {
+                    path,
+                    root: root.to_path_buf(),
+                    size,
+                });
+            }
+        }
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+/// Match one file to TMDB and import it, or queue it for manual review.
+pub async fn import_file(
+    state: &AppState,
+    matcher: &mut LibraryMatcher,
+    file: &ScannedFile,
+) -> anyhow::Result<ImportOutcome> {
+    let parsed = release::parse_library_path(&file.path, &file.root);
+    let candidates = if parsed.title.is_empty() {
+        Vec::new()
+    } else {
+        matcher.candidates(state, &parsed).await?
+    };
+
+    if let Some(best) = confident_match(&candidates) {
+        let media = db::media::create_media(&state.db_pool, &media_payload(&best.result)).await?;
+        if let Some(imported) = attach_file(state, &media, &parsed, file).await? {
+            return Ok(ImportOutcome::Imported(Box::new(imported)));
+        }
+        tracing::info!(
+            "Library scan: episode {} of '{}' is not in its episode list, queued for review",
+            parsed.absolute_episode.unwrap_or_default(),
+            media.title
+        );
+    }
+
+    let path = file.path.to_string_lossy();
+    db::library::add_review_item(
+        &state.db_pool,
+        &NewReviewItem {
+            file_path: &path,
+            file_size: i64::try_from(file.size).ok(),
+            parsed_title: &parsed.title,
+            parsed_year: parsed.year,
+            season_number: parsed.season,
+            episode_number: parsed.episode,
+            candidates: serde_json::to_value(&candidates)?,
+        },
+    )
+    .await?;
+    Ok(ImportOutcome::NeedsReview)
+}
+
+fn media_payload(result: &TmdbSearchResult) -> CreateMediaPayload {
+    CreateMediaPayload {
+        title: result.get_title(),
+        media_type: result.media_type.clone(),
+        tmdb_id: Some(result.id),
+        year: result.get_year(),
+        overview: result.overview.clone(),
+        poster_url: result.poster_url(),
+        genres: None,
+        rating: result.vote_average,
+    }
+}
+
+/// Record `file` under `media`, or under its episode when the file is one.
+/// None for an episode numbered from the first season that is not in the
+/// series' episode list.
+pub async fn attach_file(
+    state: &AppState,
+    media: &Media,
+    parsed: &ParsedRelease,
+    file: &ScannedFile,
+) -> anyhow::Result<Option<MediaFile>> {
+    let target_id = match (media.media_type.as_str(), parsed.season, parsed.episode) {
+        ("tv", Some(season), Some(episode)) => {
+            find_or_create_episode(state, media, season, episode).await?
+        }
+        ("tv", _, _) if parsed.absolute_episode.is_some() => {
+            let absolute = parsed.absolute_episode.unwrap_or_default();
+            let Some((season, episode)) =
+                absolute_to_season_episode(state, media, absolute).await?
+            else {
+                return Ok(None);
+            };
+            find_or_create_episode(state, media, season, episode).await?
+        }
+        _ => media.id,
+    };
+
+    // Quality tags often sit on the release folder rather than the file name.
+    let file_name = file
+        .path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let folder_name = file
+        .path
+        .parent()
+        .filter(|p| *p != file.root)
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let release_name = format!("{} {}", folder_name, file_name);
+
+    let path = file.path.to_string_lossy();
+    let media_file = db::media_files::create_media_file(
+        &state.db_pool,
+        target_id,
+        &FileDetails {
+            file_path: &path,
+            file_size: i64::try_from(file.size).ok(),
+            resolution: Some(format!("{}p", quality::detect_resolution(&release_name))),
+            quality_score: Some(scoring::quality_score(&release_name)),
+        },
+        "library",
+    )
+    .await?;
+    subtitles::after_import(&state.db_pool, &media_file).await;
+    nfo::after_import(state, &media_file).await;
+    Ok(Some(media_file))
+}
+
+/// Season and episode of an anime file numbered from the first season, from
+/// the series' episode list (synced first when it lacks the episode).
+async fn absolute_to_season_episode(
+    state: &AppState,
+    series_media: &Media,
+    absolute: i32,
+) -> anyhow::Result<Option<(i32, i32)>> {
+    let mut numbering = db::anime::numbering(&state.db_pool, series_media.id).await?;
+    if numbering.to_season_episode(absolute).is_none() {
+        if let Err(e) = series::sync_episodes(
+            state,
+            series_media.id,
+            &series_media.title,
+            series_media.tmdb_id,
+            series_media.imdb_id.as_deref(),
+        )
+        .await
+        {
+            tracing::warn!(
+                "Library scan: episode sync failed for '{}': {}",
+                series_media.title,
+                e
+            );
+        }
+        numbering = db::anime::numbering(&state.db_pool, series_media.id).await?;
+    }
+    Ok(numbering.to_season_episode(absolute))
+}
+
+async fn find_or_create_episode(
+    state: &AppState,
+    series_media: &Media,
+    season: i32,
+    episode: i32,
+) -> anyhow::Result<Uuid> {
+    let find = |episodes: Vec<Media>| {
+        episodes
+            .into_iter()
+            .find(|e| e.season_number == Some(season) && e.episode_number == Some(episode))
+            .map(|e| e.id)
+    };
+
+    if let Some(id) = find(db::media::get_episodes(&state.db_pool, series_media.id).await?) {
+        return Ok(id);
+    }
+
+    // First file of this series: pull its episode list.
+    if let Err(e) = series::sync_episodes(
+        state,
+        series_media.id,
+        &series_media.title,
+        series_media.tmdb_id,
+        series_media.imdb_id.as_deref(),
+    )
+    .await
+    {
+        tracing::warn!(
+            "Library scan: episode sync failed for '{}': {}",
+            series_media.title,
+            e
+        );
+    }
+    if let Some(id) = find(db::media::get_episodes(&state.db_pool, series_media.id).await?) {
+        return Ok(id);
+    }
+
+    // Unknown to TMDB and TVMaze (specials, extras): keep the file anyway.
+    db::series::ensure_season(&state.db_pool, series_media.id, season).await?;
+    let created = db::media::upsert_episode(
+        &state.db_pool,
+        series_media.id,
+        &UpsertEpisodePayload {
+            season_number: season,
+            episode_number: episode,
+            title: format!("Episode {}", episode),
+            tmdb_id: None,
+            overview: None,
+            air_date: None,
+            runtime_minutes: None,
+        },
+    )
+    .await?;
+    Ok(created.id)
+}
+
+/// Start a scan of the configured library roots as a tracked task. Returns `None`
+/// when a scan is already running.
+pub async fn start_scan(state: &Arc<AppState>) -> anyhow::Result<Option<Uuid>> {
+    let Some(guard) = ScanGuard::acquire() else {
+        return Ok(None);
+    };
+
+    let task = db::tasks::create_task(
+        &state.db_pool,
+        &CreateTaskPayload {
+            task_type: "library_scan".to_string(),
+            payload: Some(serde_json::json!({ "roots": CONFIG.library_roots })),
+        },
+    )
+    .await?;
+
+    let task_id = task.id;
+    let state = state.clone();
+    tokio::spawn(async move {
+        let _guard = guard;
+        let _ = db::tasks::update_task_status(&state.db_pool, task_id, "running", None).await;
+        match run_scan(&state, task_id).await {
+            Ok(summary) => {
+                tracing::info!(
+                    "Library scan: {} files, {} imported, {} to review, {} failed",
+                    summary.scanned,
+                    summary.imported,
+                    summary.needs_review,
+                    summary.failed
+                );
+                let _ = state.event_tx.send(
+                    WsEvent::LibraryScanCompleted {
+                        task_id: task_id.to_string(),
+                        scanned: summary.scanned,
+                        imported: summary.imported,
+                        needs_review: summary.needs_review,
+                        failed: summary.failed,
+                    }
+                    .to_json(),
+                );
+                let _ = db::tasks::complete_task(
+                    &state.db_pool,
+                    task_id,
+                    serde_json::to_value(&summary).ok(),
+                )
+                .await;
+            }
+            Err(e) => {
+                tracing::error!("Library scan failed: {}", e);
+                let _ = db::tasks::update_task_status(
+                    &state.db_pool,
+                    task_id,
+                    "failed",
+                    Some(&e.to_string()),
+                )
+                .await;
+            }
+        }
+    });
+
+    Ok(Some(task_id))
+}
+
+/// Walk every library root and import the files not seen by a previous scan.
+async fn run_scan(state: &AppState, task_id: Uuid) -> anyhow::Result<ScanSummary> {
+    let known: HashSet<String> = db::library::known_paths(&state.db_pool).await?;
+    let roots: Vec<PathBuf> = CONFIG.library_roots.iter().map(PathBuf::from).collect();
+
+    let files: Vec<ScannedFile> = tokio::task::spawn_blocking(move || {
+        roots
+            .iter()
+            .flat_map(|root| collect_video_files(root))
+            .filter(|f| !known.contains(f.path.to_string_lossy().as_ref()))
+            .collect()
+    })
+    .await?;
+
+    let total = files.len();
+    let mut summary = ScanSummary::default();
+    let mut matcher = LibraryMatcher::default();
+
+    for file in &files {
+        match import_file(state, &mut matcher, file).await {
+            Ok(ImportOutcome::Imported(imported)) => {
+                summary.imported += 1;
+                tracing::debug!("Library scan: imported {}", imported.file_path);
+            }
+            Ok(ImportOutcome::NeedsReview) => summary.needs_review += 1,
+            Err(e) => {
+                summary.failed += 1;
+                tracing::warn!("Library scan: {}: {}", file.path.display(), e);
+            }
+        }
+        summary.scanned += 1;
+
+        if summary.scanned % PROGRESS_EVERY == 0 || summary.scanned == total {
+            let progress = Decimal::from(summary.scanned * 100) / Decimal::from(total);
+            let _ = db::tasks::update_task_progress(&state.db_pool, task_id, progress).await;
+            let _ = state.event_tx.send(
+                WsEvent::LibraryScanProgress {
+                    task_id: task_id.to_string(),
+                    scanned: summary.scanned,
+                    total,
+                    imported: summary.imported,
+                    needs_review: summary.needs_review,
+                }
+                .to_json(),
+            );
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn scan_flag_is_cleared_when_the_scan_panics() {
+        let guard = ScanGuard::acquire().unwrap();
+        assert!(ScanGuard::acquire().is_none());
+        let scan = tokio::spawn(async move {
+            let _guard = guard;
+            panic!("scan failed");
+        });
+        assert!(scan.await.is_err());
+        assert!(ScanGuard::acquire().is_some());
+    }
+}
@@ -0,0 +1,409 @@
+use crate::{
+    config::CONFIG,
+    db,
+    events::WsEvent,
+    models::MediaFile,
+    utils::file_hash,
+    workers::library_scan::{self, ImportOutcome, LibraryMatcher, ScannedFile},
+    AppState,
+};
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Event, EventKind, RecursiveMode, Watcher,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::mpsc, time};
+
+/// Watches the library roots and the download directory (inotify on Linux):
+/// new files in library roots are imported, moved files keep their row, and
+/// deleted files are flagged as missing. A periodic pass reconciles what
+/// happened while Sokoul was not running.
+pub async fn library_watch_worker(state: Arc<AppState>) -> anyhow::Result<()> {
+    let library_roots: Vec<PathBuf> = CONFIG.library_roots.iter().map(PathBuf::from).collect();
+    let download_dir = PathBuf::from(&CONFIG.download_dir);
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let _ = tx.send(res);
+    })?;
+
+    let mut watched = 0;
+    for root in library_roots.iter().chain(std::iter::once(&download_dir)) {
+        if !root.is_dir() {
+            continue;
+        }
+        match watcher.watch(root, RecursiveMode::Recursive) {
+            Ok(()) => watched += 1,
+            Err(e) => tracing::warn!("Library watch: cannot watch {}: {}", root.display(), e),
+        }
+    }
+    tracing::info!("Library watcher starting ({} directories)...", watched);
+
+    let mut watch = LibraryWatch {
+        state,
+        library_roots,
+        download_dir,
+        pending: HashMap::new(),
+        matcher: LibraryMatcher::default(),
+    };
+
+    let settle = Duration::from_secs(CONFIG.library_watch_settle_secs.max(1));
+    let mut settle_tick = time::interval(Duration::from_secs(5));
+    let mut reconcile_tick = time::interval(Duration::from_secs(
+        CONFIG.library_reconcile_interval_secs.max(60),
+    ));
+
+    loop {
+        tokio::select! {
+            Some(res) = rx.recv() => match res {
+                Ok(event) => watch.handle_event(event).await,
+                Err(e) => tracing::warn!("Library watch: {}", e),
+            },
+            _ = settle_tick.tick() => watch.process_settled(settle).await,
+            _ = reconcile_tick.tick() => {
+                if let Err(e) = watch.reconcile().await {
+                    tracing::error!("Library watch: reconcile failed: {}", e);
+                }
+                // Keep the TMDB search cache from growing for the lifetime of the process.
+                watch.matcher = LibraryMatcher::default();
+            }
+        }
+    }
+}
+
+struct LibraryWatch {
+    state: Arc<AppState>,
+    library_roots: Vec<PathBuf>,
+    download_dir: PathBuf,
+    /// New or changed video files, with the time of their last event. They are
+    /// handled once writes have stopped for the settle delay.
+    pending: HashMap<PathBuf, Instant>,
+    matcher: LibraryMatcher,
+}
+
+impl LibraryWatch {
+    async fn handle_event(&mut self, event: Event) {
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                self.handle_rename(&event.paths[0], &event.paths[1]).await
+            }
+            EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                for path in &event.paths {
+                    self.handle_removed(path).await;
+                }
+            }
+            EventKind::Create(_) | EventKind::Modify(_) => {
+                // Attribute changes on a directory must not re-walk it.
+                let walk_dirs = matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_))
+                );
+                for path in event.paths {
+                    if path.is_dir() && !walk_dirs {
+                        continue;
+                    }
+                    if path.exists() {
+                        self.track(path);
+                    } else {
+                        // Renames reported without a direction (RenameMode::Any).
+                        self.handle_removed(&path).await;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Queue a new or changed path; a directory moved in queues its videos.
+    fn track(&mut self, path: PathBuf) {
+        if path.is_dir() {
+            let root = path.clone();
+            for file in library_scan::collect_video_files(&root) {
+                self.pending.insert(file.path, Instant::now());
+            }
+        } else if library_scan::is_video_file(&path) {
+            self.pending.insert(path, Instant::now());
+        }
+    }
+
+    async fn handle_rename(&mut self, from: &Path, to: &Path) {
+        self.pending.remove(from);
+        let pool = &self.state.db_pool;
+
+        if to.is_dir() {
+            let from_dir = from.to_string_lossy();
+            let files = match db::media_files::list_files_under(pool, &from_dir).await {
+                Ok(files) => files,
+                Err(e) => {
+                    tracing::warn!("Library watch: {}", e);
+                    return;
+                }
+            };
+            for file in files {
+                let Ok(relative) = Path::new(&file.file_path).strip_prefix(from) else {
+                    continue;
+                };
+                self.relocate(&file, &to.join(relative)).await;
+            }
+            // Videos not known yet are picked up like new files.
+            self.track(to.to_path_buf());
+            return;
+        }
+
+        match db::media_files::get_file_by_path(pool, &from.to_string_lossy()).await {
+            Ok(Some(file)) => self.relocate(&file, to).await,
+            Ok(None) => self.track(to.to_path_buf()),
+            Err(e) => tracing::warn!("Library watch: {}", e),
+        }
+    }
+
+    async fn handle_removed(&mut self, path: &Path) {
+        self.pending.remove(path);
+        let pool = &self.state.db_pool;
+        let path_str = path.to_string_lossy();
+
+        let files = match db::media_files::get_file_by_path(pool, &path_str).await {
+            Ok(Some(file)) => vec![file],
+            // Not a file row: possibly a directory that held some.
+            Ok(None) => db::media_files::list_files_under(pool, &path_str)
+                .await
+                .unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!("Library watch: {}", e);
+                return;
+            }
+        };
+
+        for file in files {
+            if Path::new(&file.file_path).exists() {
+                continue;
+            }
+            self.mark_missing(&file).await;
+        }
+    }
+
+    /// Handle tracked files whose writes have settled.
+    async fn process_settled(&mut self, settle: Duration) {
+        let ready: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, seen)| seen.elapsed() >= settle)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        for path in ready {
+            self.pending.remove(&path);
+            if let Err(e) = self.handle_new_file(&path).await {
+                tracing::warn!("Library watch: {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    async fn handle_new_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        let pool = &self.state.db_pool;
+        let path_str = path.to_string_lossy().to_string();
+        let Ok(metadata) = tokio::fs::metadata(path).await else {
+            return Ok(());
+        };
+        let size = metadata.len();
+        let mtime = file_hash::modified_at(&metadata);
+        let inode = file_hash::inode(&metadata);
+
+        // Already known: a finished download, or a file replaced in place.
+        if let Some(file) = db::media_files::get_file_by_path(pool, &path_str).await? {
+            if file.missing_since.is_some() {
+                db::media_files::clear_missing(pool, file.id).await?;
+                self.notify_restored(&file);
+            }
+            return Ok(());
+        }
+
+        let hash = fingerprint(path).await?;
+        if let Some(file) = self.find_moved(size, &hash).await? {
+            self.relocate(&file, path).await;
+            db::media_files::set_fingerprint(
+                pool,
+                file.id,
+                size as i64,
+                mtime,
+                &hash,
+                inode.as_deref(),
+            )
+            .await?;
+            return Ok(());
+        }
+
+        // Downloads are recorded by the Hunter; only library roots are imported.
+        let Some(root) = self.library_root_of(path) else {
+            return Ok(());
+        };
+        if db::library::known_paths(pool).await?.contains(&path_str) {
+            return Ok(());
+        }
+
+        let scanned = ScannedFile {
+            path: path.to_path_buf(),
+            root,
+            size,
+        };
+        match library_scan::import_file(&self.state, &mut self.matcher, &scanned).await? {
+            ImportOutcome::Imported(file) => {
+                db::media_files::set_fingerprint(
+                    pool,
+                    file.id,
+                    size as i64,
+                    mtime,
+                    &hash,
+                    inode.as_deref(),
+                )
+                .await?;
+                tracing::info!("Library watch: imported {}", path_str);
+                let _ = self.state.event_tx.send(
+                    WsEvent::LibraryFileImported {
+                        media_id: file.media_id.to_string(),
+                        file_id: file.id.to_string(),
+                        file_path: file.file_path.clone(),
+                    }
+                    .to_json(),
+                );
+            }
+            ImportOutcome::NeedsReview => {
+                let _ = self.state.event_tx.send(
+                    WsEvent::LibraryFileNeedsReview {
+                        file_path: path_str,
+                    }
+                    .to_json(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The row of a file that disappeared and now shows up as `size`/`hash`.
+    /// Rows never fingerprinted are left alone: a file of the same size may be
+    /// unrelated, and it is imported as a new file instead.
+    async fn find_moved(&self, size: u64, hash: &str) -> anyhow::Result<Option<MediaFile>> {
+        Ok(
+            db::media_files::list_files_by_size(&self.state.db_pool, size as i64)
+                .await?
+                .into_iter()
+                .find(|f| {
+                    f.fingerprint.as_deref() == Some(hash) && !Path::new(&f.file_path).exists()
+                }),
+        )
+    }
+
+    fn library_root_of(&self, path: &Path) -> Option<PathBuf> {
+        if path.starts_with(&self.download_dir) {
+            return None;
+        }
+        self.library_roots
+            .iter()
+            .find(|root| path.starts_with(root))
+            .cloned()
+    }
+
+    async fn relocate(&self, file: &MediaFile, new_path: &Path) {
+        let new_path = new_path.to_string_lossy().to_string();
+        match db::media_files::relocate_file(&self.state.db_pool, file.id, &new_path).await {
+            Ok(_) => {
+                tracing::info!("Library watch: {} moved to {}", file.file_path, new_path);
+                let _ = self.state.event_tx.send(
+                    WsEvent::LibraryFileMoved {
+                        media_id: file.media_id.to_string(),
+                        file_id: file.id.to_string(),
+                        old_path: file.file_path.clone(),
+                        new_path,
+                    }
+                    .to_json(),
+                );
+            }
+            Err(e) => tracing::warn!("Library watch: cannot relocate {}: {}", file.file_path, e),
+        }
+    }
+
+    async fn mark_missing(&self, file: &MediaFile) {
+        match db::media_files::mark_missing(&self.state.db_pool, file.id).await {
+            Ok(true) => {
+                tracing::info!("Library watch: {} is missing", file.file_path);
+                let _ = self.state.event_tx.send(
+                    WsEvent::LibraryFileMissing {
+                        media_id: file.media_id.to_string(),
+                        file_id: file.id.to_string(),
+                        file_path: file.file_path.clone(),
+                    }
+                    .to_json(),
+                );
+            }
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Library watch: {}", e),
+        }
+    }
+
+    fn notify_restored(&self, file: &MediaFile) {
+        let _ = self.state.event_tx.send(
+            WsEvent::LibraryFileRestored {
+                media_id: file.media_id.to_string(),
+                file_id: file.id.to_string(),
+                file_path: file.file_path.clone(),
+            }
+            .to_json(),
+        );
+    }
+
+    /// Check every file row against the disk: flag missing files, clear restored
+    /// ones and fingerprint files that are new or changed since the last pass,
+    /// keeping their inode current.
+    async fn reconcile(&self) -> anyhow::Result<()> {
+        let pool = &self.state.db_pool;
+        for file in db::media_files::list_all_files(pool).await? {
+            let path = PathBuf::from(&file.file_path);
+            let Ok(metadata) = tokio::fs::metadata(&path).await else {
+                self.mark_missing(&file).await;
+                continue;
+            };
+
+            if file.missing_since.is_some() {
+                db::media_files::clear_missing(pool, file.id).await?;
+                self.notify_restored(&file);
+            }
+            let size = metadata.len() as i64;
+            let mtime = file_hash::modified_at(&metadata);
+            let inode = file_hash::inode(&metadata);
+            if file.fingerprint.is_none()
+                || file.file_size != Some(size)
+                || file.file_mtime != mtime
+            {
+                match fingerprint(&path).await {
+                    Ok(hash) => {
+                        db::media_files::set_fingerprint(
+                            pool,
+                            file.id,
+                            size,
+                            mtime,
+                            &hash,
+                            inode.as_deref(),
+                        )
+                        .await?
+                    }
+                    Err(e) => {
+                        tracing::warn!("Library watch: cannot hash {}: {}", path.display(), e)
+                    }
+                }
+            } else if file.file_inode != inode {
+                db::media_files::set_inode(pool, file.id, inode.as_deref()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn fingerprint(path: &Path) -> anyhow::Result<String> {
+    let path = path.to_path_buf();
+    Ok(tokio::task::spawn_blocking(move || file_hash::partial_hash(&path)).await??)
+}
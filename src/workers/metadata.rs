@@ -0,0 +1,578 @@
+use crate::{
+    cache,
+    clients::{
+        omdb::OmdbResponse,
+        tmdb::{TmdbAlternativeTitles, TmdbGenre, TMDB_IMAGE_BASE},
+    },
+    config::CONFIG,
+    db::{self, media::MetadataUpdate, media_titles::MediaTitle, ratings::NewRating},
+    models::Media,
+    utils::anime,
+    workers, AppState,
+};
+use chrono::{NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time;
+
+/// Gap between two TMDB calls, well under its ~40 requests per second limit so
+/// interactive requests keep their share.
+const REQUEST_SPACING: Duration = Duration::from_millis(250);
+
+/// How often the worker looks for stale items.
+const TICK: Duration = Duration::from_secs(300);
+
+/// OMDb calls made today (UTC) by the worker and manual refreshes.
+#[derive(Debug)]
+struct OmdbBudget {
+    day: NaiveDate,
+    used: u32,
+    /// OMDb refused a call (limit reached or bad key)
+    refused: bool,
+}
+
+impl OmdbBudget {
+    fn new(day: NaiveDate) -> Self {
+        Self {
+            day,
+            used: 0,
+            refused: false,
+        }
+    }
+
+    /// Take one call from the budget of `today`; false once `limit` calls were
+    /// made or OMDb refused one.
+    fn take(&mut self, today: NaiveDate, limit: u32) -> bool {
+        if self.day != today {
+            *self = Self::new(today);
+        }
+        if self.refused || self.used >= limit {
+            return false;
+        }
+        self.used += 1;
+        true
+    }
+}
+
+static OMDB_BUDGET: Lazy<Mutex<OmdbBudget>> =
+    Lazy::new(|| Mutex::new(OmdbBudget::new(Utc::now().date_naive())));
+
+#[derive(Debug)]
+pub enum RefreshError {
+    /// TMDB throttled us; retry later.
+    RateLimited,
+    Tmdb(reqwest::Error),
+    Database(sqlx::Error),
+}
+
+impl std::fmt::Display for RefreshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RateLimited => write!(f, "TMDB rate limit reached"),
+            Self::Tmdb(e) => write!(f, "TMDB error: {}", e),
+            Self::Database(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for RefreshError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.status() == Some(StatusCode::TOO_MANY_REQUESTS) {
+            Self::RateLimited
+        } else {
+            Self::Tmdb(e)
+        }
+    }
+}
+
+impl From<sqlx::Error> for RefreshError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+/// Fills the TMDB metadata scout leaves empty (genres, rating, IMDb id, runtime,
+/// backdrop, status) and keeps it current, airing series most often. The
+/// ratings of the other sources follow on a much longer schedule, within the
+/// OMDb daily budget.
+pub async fn metadata_worker(state: Arc<AppState>) -> anyhow::Result<()> {
+    tracing::info!("Metadata worker starting...");
+    let mut interval = time::interval(TICK);
+
+    loop {
+        interval.tick().await;
+        refresh_details_batch(&state).await;
+        refresh_ratings_batch(&state).await;
+    }
+}
+
+async fn refresh_details_batch(state: &AppState) {
+    let stale = match db::media::list_metadata_to_refresh(
+        &state.db_pool,
+        CONFIG.metadata_refresh_active_secs as f64,
+        CONFIG.metadata_refresh_recent_secs as f64,
+        CONFIG.metadata_refresh_secs as f64,
+        CONFIG.metadata_refresh_batch_size,
+    )
+    .await
+    {
+        Ok(media) => media,
+        Err(e) => {
+            tracing::error!("Metadata: failed to load media to refresh: {}", e);
+            return;
+        }
+    };
+
+    let mut refreshed = 0;
+    // The query only returns items with a TMDB id
+    for (tmdb_id, media) in stale.into_iter().filter_map(|m| Some((m.tmdb_id?, m))) {
+        match refresh(state, &media, tmdb_id).await {
+            Ok(_) => refreshed += 1,
+            Err(RefreshError::RateLimited) => {
+                tracing::warn!("Metadata: TMDB rate limit reached, pausing until next run");
+                break;
+            }
+            Err(e) => {
+                tracing::warn!("Metadata: refresh failed for '{}': {}", media.title, e);
+                if let Err(e) = db::media::mark_metadata_failed(&state.db_pool, media.id).await {
+                    tracing::error!("Metadata: failed to record the failure: {}", e);
+                }
+            }
+        }
+        time::sleep(REQUEST_SPACING).await;
+    }
+    if refreshed > 0 {
+        tracing::info!("Metadata: refreshed {} item(s)", refreshed);
+    }
+}
+
+async fn refresh_ratings_batch(state: &AppState) {
+    let due = match db::media::list_ratings_to_refresh(
+        &state.db_pool,
+        CONFIG.ratings_refresh_secs as f64,
+        CONFIG.metadata_refresh_batch_size,
+    )
+    .await
+    {
+        Ok(media) => media,
+        Err(e) => {
+            tracing::error!("Metadata: failed to load media to rate: {}", e);
+            return;
+        }
+    };
+
+    let mut rated = 0;
+    for media in due {
+        match refresh_ratings(state, &media).await {
+            Ok(true) => rated += 1,
+            Ok(false) => {
+                tracing::info!("Metadata: OMDb daily budget spent, ratings resume tomorrow");
+                break;
+            }
+            Err(e) => tracing::warn!("Metadata: ratings failed for '{}': {}", media.title, e),
+        }
+        time::sleep(REQUEST_SPACING).await;
+    }
+    if rated > 0 {
+        tracing::info!("Metadata: refreshed the ratings of {} item(s)", rated);
+    }
+}
+
+/// What a TMDB details call yields besides the media columns.
+struct Fetched {
+    vote_count: Option<i32>,
+    aliases: Option<Vec<MediaTitle>>,
+    /// Japanese animation series, mapped to MyAnimeList
+    anime: bool,
+    tvdb_id: Option<i32>,
+}
+
+fn genre_names(genres: &[TmdbGenre]) -> Option<Vec<String>> {
+    (!genres.is_empty()).then(|| genres.iter().map(|g| g.name.clone()).collect())
+}
+
+fn rating(vote_average: Option<f64>, vote_count: Option<i32>) -> Option<Decimal> {
+    if vote_count.unwrap_or(0) == 0 {
+        return None;
+    }
+    Decimal::try_from(vote_average?).ok().map(|r| r.round_dp(1))
+}
+
+/// "1,234,567" -> 1234567; "N/A" -> None
+fn parse_votes(votes: Option<&str>) -> Option<i64> {
+    votes?.replace(',', "").parse().ok()
+}
+
+/// Leading number of "7.8/10", "87%", "74/100" or "7.8"; None for "N/A".
+fn parse_score(value: &str) -> Option<Decimal> {
+    let number = value.split(['/', '%']).next()?.trim();
+    number.parse().ok()
+}
+
+/// IMDb, Rotten Tomatoes and Metacritic scores of an OMDb response.
+fn omdb_ratings(omdb: &OmdbResponse) -> Vec<NewRating> {
+    let mut ratings = Vec::new();
+    let imdb = omdb.imdb_rating_field.as_deref().and_then(parse_score);
+    if let Some(value) = imdb {
+        ratings.push(NewRating {
+            source: "imdb",
+            value,
+            vote_count: parse_votes(omdb.imdb_votes.as_deref()),
+        });
+    }
+    for rating in &omdb.ratings {
+        let source = match rating.source.as_str() {
+            "Internet Movie Database" if imdb.is_none() => "imdb",
+            "Rotten Tomatoes" => "rotten_tomatoes",
+            "Metacritic" => "metacritic",
+            _ => continue,
+        };
+        if let Some(value) = parse_score(&rating.value) {
+            ratings.push(NewRating {
+                source,
+                value,
+                vote_count: None,
+            });
+        }
+    }
+    ratings
+}
+
+/// OMDb answers over-quota and bad-key calls with an error message rather than
+/// a failure status.
+fn omdb_refused(response: &OmdbResponse) -> bool {
+    response
+        .error
+        .as_deref()
+        .is_some_and(|e| e.contains("limit reached") || e.contains("API key"))
+}
+
+/// Scores from OMDb, Trakt and Simkl (whichever are configured), all looked up
+/// by IMDb id. A failing source is skipped and keeps its previous score. None
+/// when OMDb is out of calls for today, so nothing is fetched.
+async fn external_ratings(state: &AppState, media: &Media) -> Option<Vec<NewRating>> {
+    let Some(imdb_id) = media.imdb_id.as_deref() else {
+        return Some(Vec::new());
+    };
+    let mut ratings = Vec::new();
+
+    if let Some(omdb) = &state.omdb_client {
+        let allowed = OMDB_BUDGET
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take(Utc::now().date_naive(), CONFIG.omdb_daily_limit);
+        if !allowed {
+            return None;
+        }
+        match omdb.get_by_imdb_id(imdb_id).await {
+            Ok(response) if omdb_refused(&response) => {
+                tracing::warn!(
+                    "Metadata: OMDb refused the lookup of {} ({}), pausing it until tomorrow",
+                    imdb_id,
+                    response.error.as_deref().unwrap_or_default()
+                );
+                OMDB_BUDGET
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .refused = true;
+                return None;
+            }
+            Ok(response) => ratings.extend(omdb_ratings(&response)),
+            Err(e) => tracing::debug!("Metadata: OMDb lookup failed for {}: {}", imdb_id, e),
+        }
+    }
+
+    if let Some(trakt) = &state.trakt_client {
+        let score = if media.media_type == "movie" {
+            trakt
+                .movie_details(imdb_id)
+                .await
+                .map(|d| d.map(|d| (d.rating, d.votes)))
+        } else {
+            trakt
+                .show_details(imdb_id)
+                .await
+                .map(|d| d.map(|d| (d.rating, d.votes)))
+        };
+        match score {
+            Ok(Some((Some(rating), votes))) if votes.unwrap_or(0) > 0 => {
+                if let Ok(value) = Decimal::try_from(rating) {
+                    ratings.push(NewRating {
+                        source: "trakt",
+                        value: value.round_dp(1),
+                        vote_count: votes,
+                    });
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::debug!("Metadata: Trakt lookup failed for {}: {}", imdb_id, e),
+        }
+    }
+
+    if let Some(simkl) = &state.simkl_client {
+        match simkl.ratings(imdb_id).await {
+            Ok(Some(found)) => {
+                let score = found.simkl.and_then(|s| Some((s.rating?, s.votes)));
+                if let Some((value, votes)) =
+                    score.and_then(|(r, v)| Some((Decimal::try_from(r).ok()?, v)))
+                {
+                    ratings.push(NewRating {
+                        source: "simkl",
+                        value: value.round_dp(1),
+                        vote_count: votes,
+                    });
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::debug!("Metadata: Simkl lookup failed for {}: {}", imdb_id, e),
+        }
+    }
+
+    Some(ratings)
+}
+
+/// Fetch the OMDb, Trakt and Simkl ratings of a movie or series. Returns false,
+/// leaving the item due, when the OMDb budget of the day is spent.
+pub async fn refresh_ratings(state: &AppState, media: &Media) -> Result<bool, sqlx::Error> {
+    let Some(ratings) = external_ratings(state, media).await else {
+        return Ok(false);
+    };
+    db::ratings::upsert_ratings(&state.db_pool, media.id, &ratings).await?;
+    db::media::mark_ratings_refreshed(&state.db_pool, media.id).await?;
+    invalidate_cache(state, media).await;
+    Ok(true)
+}
+
+async fn invalidate_cache(state: &AppState, media: &Media) {
+    let cache_key = format!("media:{}", media.id);
+    if let Err(e) = cache::delete_from_cache(&state.redis_client, &cache_key).await {
+        tracing::warn!("Redis cache error (delete): {}", e);
+    }
+}
+
+/// Aliases per country; None when TMDB did not send the list, so the stored
+/// ones are kept.
+fn aliases(titles: Option<TmdbAlternativeTitles>) -> Option<Vec<MediaTitle>> {
+    let titles = titles?.titles;
+    Some(
+        titles
+            .into_iter()
+            .filter(|t| !t.title.trim().is_empty())
+            .map(|t| MediaTitle {
+                country: t.iso_3166_1.to_uppercase(),
+                title: t.title.trim().to_string(),
+                kind: non_empty(Some(t.kind)),
+            })
+            .collect(),
+    )
+}
+
+fn year_of(date: Option<&str>) -> Option<i32> {
+    date?.get(..4)?.parse().ok()
+}
+
+fn image(path: Option<&str>, size: &str) -> Option<String> {
+    path.filter(|p| !p.is_empty())
+        .map(|p| format!("{}/{}{}", TMDB_IMAGE_BASE, size, p))
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+/// Fetch a movie or series from TMDB by its `tmdb_id` and store its metadata.
+/// Items unknown to TMDB are marked refreshed so they are not retried before
+/// they go stale.
+pub async fn refresh(state: &AppState, media: &Media, tmdb_id: i32) -> Result<Media, RefreshError> {
+    let details = match media.media_type.as_str() {
+        "movie" => state.tmdb_client.movie_details(tmdb_id).await.map(|m| {
+            (
+                Fetched {
+                    vote_count: m.vote_count,
+                    aliases: aliases(m.alternative_titles),
+                    anime: false,
+                    tvdb_id: None,
+                },
+                MetadataUpdate {
+                    original_title: non_empty(m.original_title),
+                    year: year_of(m.release_date.as_deref()),
+                    imdb_id: non_empty(m.imdb_id),
+                    overview: non_empty(m.overview),
+                    poster_url: image(m.poster_path.as_deref(), "w500"),
+                    backdrop_url: image(m.backdrop_path.as_deref(), "w1280"),
+                    genres: genre_names(&m.genres),
+                    rating: rating(m.vote_average, m.vote_count),
+                    runtime_minutes: m.runtime.filter(|r| *r > 0),
+                    status: non_empty(m.status),
+                },
+            )
+        }),
+        _ => state.tmdb_client.tv_details(tmdb_id).await.map(|t| {
+            let genre_ids: Vec<i32> = t.genres.iter().map(|g| g.id).collect();
+            (
+                Fetched {
+                    vote_count: t.vote_count,
+                    aliases: aliases(t.alternative_titles),
+                    anime: anime::is_anime(&genre_ids, t.original_language.as_deref()),
+                    tvdb_id: t.external_ids.as_ref().and_then(|ids| ids.tvdb_id),
+                },
+                MetadataUpdate {
+                    original_title: non_empty(t.original_name),
+                    year: year_of(t.first_air_date.as_deref()),
+                    imdb_id: non_empty(t.external_ids.and_then(|ids| ids.imdb_id)),
+                    overview: non_empty(t.overview),
+                    poster_url: image(t.poster_path.as_deref(), "w500"),
+                    backdrop_url: image(t.backdrop_path.as_deref(), "w1280"),
+                    genres: genre_names(&t.genres),
+                    rating: rating(t.vote_average, t.vote_count),
+                    runtime_minutes: t
+                        .episode_run_time
+                        .and_then(|times| times.into_iter().find(|r| *r > 0)),
+                    status: non_empty(t.status),
+                },
+            )
+        }),
+    };
+
+    let (fetched, update) = match details {
+        Ok(details) => details,
+        Err(e) if e.status() == Some(StatusCode::NOT_FOUND) => {
+            tracing::warn!(
+                "Metadata: '{}' (TMDB {}) no longer exists on TMDB",
+                media.title,
+                tmdb_id
+            );
+            db::media::mark_metadata_refreshed(&state.db_pool, media.id).await?;
+            return Ok(media.clone());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let updated = db::media::apply_metadata(&state.db_pool, media.id, &update).await?;
+    if let Some(aliases) = fetched.aliases {
+        db::media_titles::replace_titles(&state.db_pool, media.id, &aliases).await?;
+    }
+    if fetched.anime {
+        workers::anime::ensure_mapping(state, &updated, fetched.tvdb_id).await;
+    }
+
+    let tmdb_rating: Vec<NewRating> = update
+        .rating
+        .map(|value| NewRating {
+            source: "tmdb",
+            value,
+            vote_count: fetched.vote_count.map(i64::from),
+        })
+        .into_iter()
+        .collect();
+    db::ratings::upsert_ratings(&state.db_pool, media.id, &tmdb_rating).await?;
+
+    invalidate_cache(state, media).await;
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_unrated_titles() {
+        assert_eq!(rating(Some(7.456), Some(1200)), Some(Decimal::new(75, 1)));
+        assert_eq!(rating(Some(0.0), Some(0)), None);
+        assert_eq!(rating(None, Some(3)), None);
+        assert_eq!(year_of(Some("2019-05-02")), Some(2019));
+        assert_eq!(year_of(Some("")), None);
+        assert_eq!(image(Some(""), "w500"), None);
+    }
+
+    #[test]
+    fn keeps_alternative_titles_per_country() {
+        // Series list their aliases under `results`, movies under `titles`.
+        let titles: TmdbAlternativeTitles = serde_json::from_value(serde_json::json!({
+            "results": [
+                {"iso_3166_1": "ca", "title": " À l'ombre de Shawshank ", "type": ""},
+                {"iso_3166_1": "US", "title": "Rita Hayworth and Shawshank Redemption", "type": "working title"},
+                {"iso_3166_1": "DE", "title": "", "type": ""}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            aliases(Some(titles)).unwrap(),
+            vec![
+                MediaTitle {
+                    country: "CA".to_string(),
+                    title: "À l'ombre de Shawshank".to_string(),
+                    kind: None,
+                },
+                MediaTitle {
+                    country: "US".to_string(),
+                    title: "Rita Hayworth and Shawshank Redemption".to_string(),
+                    kind: Some("working title".to_string()),
+                },
+            ]
+        );
+        assert_eq!(aliases(None), None);
+    }
+
+    #[test]
+    fn spends_the_omdb_budget_per_day() {
+        let monday = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        let tuesday = monday.succ_opt().unwrap();
+        let mut budget = OmdbBudget::new(monday);
+        assert!(budget.take(monday, 2));
+        assert!(budget.take(monday, 2));
+        assert!(!budget.take(monday, 2));
+        assert!(budget.take(tuesday, 2));
+
+        budget.refused = true;
+        assert!(!budget.take(tuesday, 2));
+        assert!(budget.take(tuesday.succ_opt().unwrap(), 2));
+
+        let limited: OmdbResponse = serde_json::from_value(serde_json::json!({
+            "Response": "False",
+            "Error": "Request limit reached!"
+        }))
+        .unwrap();
+        assert!(omdb_refused(&limited));
+        let unknown: OmdbResponse = serde_json::from_value(serde_json::json!({
+            "Response": "False",
+            "Error": "Incorrect IMDb ID."
+        }))
+        .unwrap();
+        assert!(!omdb_refused(&unknown));
+    }
+
+    #[test]
+    fn parses_omdb_scores() {
+        let omdb: OmdbResponse = serde_json::from_value(serde_json::json!({
+            "imdbRating": "7.8",
+            "imdbVotes": "1,234,567",
+            "Ratings": [
+                {"Source": "Internet Movie Database", "Value": "7.8/10"},
+                {"Source": "Rotten Tomatoes", "Value": "87%"},
+                {"Source": "Metacritic", "Value": "N/A"}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            omdb_ratings(&omdb),
+            vec![
+                NewRating {
+                    source: "imdb",
+                    value: Decimal::new(78, 1),
+                    vote_count: Some(1234567),
+                },
+                NewRating {
+                    source: "rotten_tomatoes",
+                    value: Decimal::new(87, 0),
+                    vote_count: None,
+                },
+            ]
+        );
+    }
+}
@@ -1,11 +1,23 @@
 use crate::AppState;
 use std::sync::Arc;
 
+pub mod anime;
+pub mod artwork;
+pub mod calendar;
 pub mod hunter;
+pub mod library_scan;
+pub mod library_watch;
+pub mod metadata;
 pub mod metrics;
+pub mod nfo;
 pub mod oracle;
+pub mod retention;
 pub mod scout;
 pub mod sentinel;
+pub mod series;
+pub mod subtitles;
+pub mod upgrades;
+pub mod watchlist;
 
 /// Entry point to launch all workers in parallel.
 pub async fn run_workers(state: Arc<AppState>) {
@@ -15,6 +27,14 @@ pub async fn run_workers(state: Arc<AppState>) {
         tokio::spawn(hunter::hunter_worker(state.clone())),
         tokio::spawn(oracle::oracle_worker(state.clone())),
         tokio::spawn(sentinel::sentinel_worker(state.clone())),
+        tokio::spawn(watchlist::watchlist_worker(state.clone())),
+        tokio::spawn(series::series_worker(state.clone())),
+        tokio::spawn(upgrades::upgrades_worker(state.clone())),
+        tokio::spawn(calendar::calendar_worker(state.clone())),
+        tokio::spawn(library_watch::library_watch_worker(state.clone())),
+        tokio::spawn(artwork::artwork_worker(state.clone())),
+        tokio::spawn(metadata::metadata_worker(state.clone())),
+        tokio::spawn(subtitles::subtitle_worker(state.clone())),
     ];
 
     for worker in workers {
@@ -0,0 +1,556 @@
+use crate::{
+    clients::tmdb::{TmdbCreditsResponse, TmdbEpisode, TMDB_IMAGE_BASE},
+    config::CONFIG,
+    db,
+    models::{CreateTaskPayload, Media, MediaFile},
+    utils::{
+        artwork::{self, ArtworkCache},
+        nfo::{self, Actor, Details},
+    },
+    AppState,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use uuid::Uuid;
+
+/// Season folders inside a show folder: "Season 1", "Saison 02", "S03", "Specials".
+static SEASON_DIR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(?:(?:season|saison|staffel|series)[ ._-]*\d+|s\d{1,2}|specials)$").unwrap()
+});
+
+/// Cast members written to an NFO.
+const MAX_ACTORS: usize = 15;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ExportSummary {
+    pub files: usize,
+    pub nfo_written: usize,
+    pub artwork_written: usize,
+    /// Existing files not written by Sokoul, left untouched without `force`
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// The show folder of an episode: its directory, or the parent of a season folder.
+pub fn show_dir(video: &Path) -> Option<&Path> {
+    let dir = video.parent()?;
+    let is_season = dir
+        .file_name()
+        .is_some_and(|name| SEASON_DIR.is_match(&name.to_string_lossy()));
+    match dir.parent() {
+        Some(parent) if is_season => Some(parent),
+        _ => Some(dir),
+    }
+}
+
+/// The show folder of an episode when the series has one of its own. Episodes
+/// lying directly in a library root or the download directory have none, so
+/// series-level files would be shared with every other title there.
+pub fn series_dir<'a>(video: &'a Path, shared_roots: &[PathBuf]) -> Option<&'a Path> {
+    let dir = show_dir(video)?;
+    let same = |root: &PathBuf| {
+        dir == root || matches!((dir.canonicalize(), root.canonicalize()), (Ok(a), Ok(b)) if a == b)
+    };
+    (!shared_roots.iter().any(same)).then_some(dir)
+}
+
+/// Kodi's name for a season poster in the show folder.
+pub fn season_poster_name(season: i32) -> String {
+    match season {
+        0 => "season-specials-poster.jpg".to_string(),
+        n => format!("season{:02}-poster.jpg", n),
+    }
+}
+
+fn credits_details(credits: TmdbCreditsResponse) -> (Vec<String>, Vec<Actor>) {
+    let directors = credits
+        .crew
+        .into_iter()
+        .filter(|c| c.job.as_deref() == Some("Director"))
+        .map(|c| c.name)
+        .collect();
+    let mut cast = credits.cast;
+    cast.sort_by_key(|c| c.order.unwrap_or(i32::MAX));
+    let actors = cast
+        .into_iter()
+        .take(MAX_ACTORS)
+        .map(|c| Actor {
+            name: c.name,
+            role: c.character,
+            thumb: c
+                .profile_path
+                .map(|p| format!("{}/w185{}", TMDB_IMAGE_BASE, p)),
+        })
+        .collect();
+    (directors, actors)
+}
+
+/// TMDB details of a series, shared by all of its episodes during a run.
+struct SeriesInfo {
+    details: Details,
+    /// (season number, poster URL)
+    seasons: Vec<(i32, String)>,
+    /// Episodes of the seasons fetched so far
+    episodes: HashMap<i32, Vec<TmdbEpisode>>,
+}
+
+/// An episode row with the gaps filled from TMDB, and its director and guest
+/// stars.
+fn episode_details(episode: &Media, tmdb: Option<&TmdbEpisode>) -> (Media, Details) {
+    let mut filled = episode.clone();
+    let Some(tmdb) = tmdb else {
+        return (filled, Details::default());
+    };
+    if filled.overview.is_none() {
+        filled.overview = tmdb.overview.clone().filter(|o| !o.trim().is_empty());
+    }
+    if filled.air_date.is_none() {
+        filled.air_date = tmdb.air_date.as_deref().and_then(|d| d.parse().ok());
+    }
+    if filled.rating.is_none() {
+        filled.rating = tmdb
+            .vote_average
+            .filter(|r| *r > 0.0)
+            .and_then(|r| Decimal::try_from(r).ok())
+            .map(|r| r.round_dp(1));
+    }
+    if filled.runtime_minutes.is_none() {
+        filled.runtime_minutes = tmdb.runtime.filter(|r| *r > 0);
+    }
+    if filled.poster_url.is_none() {
+        filled.poster_url = tmdb
+            .still_path
+            .as_ref()
+            .map(|p| format!("{}/w300{}", TMDB_IMAGE_BASE, p));
+    }
+    let (directors, actors) = credits_details(TmdbCreditsResponse {
+        cast: tmdb.guest_stars.clone(),
+        crew: tmdb.crew.clone(),
+    });
+    let details = Details {
+        premiered: tmdb.air_date.clone(),
+        directors,
+        actors,
+        ..Details::default()
+    };
+    (filled, details)
+}
+
+/// Writes NFO files and artwork next to media files. TMDB is optional: without
+/// it the documents are built from the `media` rows alone.
+pub struct Exporter<'a> {
+    state: &'a AppState,
+    force: bool,
+    artwork: ArtworkCache,
+    series: HashMap<Uuid, SeriesInfo>,
+    /// Show folders whose series-level files were written during this run
+    show_dirs: HashSet<PathBuf>,
+    /// Library roots and the download directory, never a show folder
+    shared_roots: Vec<PathBuf>,
+    pub summary: ExportSummary,
+}
+
+impl<'a> Exporter<'a> {
+    pub fn new(state: &'a AppState, force: bool) -> Self {
+        Self {
+            state,
+            force,
+            artwork: ArtworkCache::from_config(),
+            series: HashMap::new(),
+            show_dirs: HashSet::new(),
+            shared_roots: CONFIG
+                .library_roots
+                .iter()
+                .chain(std::iter::once(&CONFIG.download_dir))
+                .map(PathBuf::from)
+                .collect(),
+            summary: ExportSummary::default(),
+        }
+    }
+
+    pub async fn export_file(&mut self, file: &MediaFile) -> anyhow::Result<()> {
+        self.summary.files += 1;
+        let media = db::media::get_media_by_id(&self.state.db_pool, file.media_id).await?;
+        let video = PathBuf::from(&file.file_path);
+
+        match (media.parent_id, media.season_number) {
+            (Some(parent_id), Some(season)) => {
+                let series = db::media::get_media_by_id(&self.state.db_pool, parent_id).await?;
+                self.export_episode(&video, &media, &series, season).await
+            }
+            _ => self.export_movie(&video, &media).await,
+        }
+    }
+
+    async fn export_movie(&mut self, video: &Path, media: &Media) -> anyhow::Result<()> {
+        let Some(dir) = video.parent() else {
+            return Ok(());
+        };
+        let stem = video
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // `movie.nfo` and `poster.jpg` only name the movie when it has the folder to itself.
+        let alone = std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|e| crate::workers::library_scan::is_video_file(&e.path()))
+                    .count()
+                    <= 1
+            })
+            .unwrap_or(false);
+        let (nfo_name, poster_name, fanart_name) = if alone {
+            (
+                "movie.nfo".to_string(),
+                "poster.jpg".to_string(),
+                "fanart.jpg".to_string(),
+            )
+        } else {
+            (
+                format!("{}.nfo", stem),
+                format!("{}-poster.jpg", stem),
+                format!("{}-fanart.jpg", stem),
+            )
+        };
+
+        let details = self.movie_details(media).await;
+        self.write_nfo(&dir.join(nfo_name), &nfo::movie(media, &details))
+            .await?;
+        self.write_artwork(&dir.join(poster_name), media.poster_url.as_deref())
+            .await;
+        self.write_artwork(&dir.join(fanart_name), media.backdrop_url.as_deref())
+            .await;
+        Ok(())
+    }
+
+    async fn export_episode(
+        &mut self,
+        video: &Path,
+        episode: &Media,
+        series: &Media,
+        season: i32,
+    ) -> anyhow::Result<()> {
+        if !self.series.contains_key(&series.id) {
+            let info = self.series_info(series).await;
+            self.series.insert(series.id, info);
+        }
+        self.load_season(series, season).await;
+        let Some(info) = self.series.get(&series.id) else {
+            return Ok(());
+        };
+        let tvshow = nfo::tvshow(series, &info.details, &info.seasons);
+        let season_poster = info
+            .seasons
+            .iter()
+            .find(|(number, _)| *number == season)
+            .map(|(_, url)| url.clone());
+        let tmdb_episode = info.episodes.get(&season).and_then(|episodes| {
+            episodes
+                .iter()
+                .find(|e| Some(e.episode_number) == episode.episode_number)
+        });
+        let (episode, details) = episode_details(episode, tmdb_episode);
+
+        // Series-level files only go in a folder of the series' own.
+        if let Some(show_dir) = series_dir(video, &self.shared_roots) {
+            if self.show_dirs.insert(show_dir.to_path_buf()) {
+                self.write_nfo(&show_dir.join("tvshow.nfo"), &tvshow)
+                    .await?;
+                self.write_artwork(&show_dir.join("poster.jpg"), series.poster_url.as_deref())
+                    .await;
+                self.write_artwork(&show_dir.join("fanart.jpg"), series.backdrop_url.as_deref())
+                    .await;
+            }
+            self.write_artwork(
+                &show_dir.join(season_poster_name(season)),
+                season_poster.as_deref(),
+            )
+            .await;
+        }
+
+        let nfo_path = video.with_extension("nfo");
+        self.write_nfo(&nfo_path, &nfo::episode(&episode, series, &details))
+            .await
+    }
+
+    /// Fetch the episodes of a season once per run.
+    async fn load_season(&mut self, series: &Media, season: i32) {
+        let Some(tmdb_id) = series.tmdb_id else {
+            return;
+        };
+        let Some(info) = self.series.get_mut(&series.id) else {
+            return;
+        };
+        if info.episodes.contains_key(&season) {
+            return;
+        }
+        let episodes = match self.state.tmdb_client.season_details(tmdb_id, season).await {
+            Ok(detail) => detail.episodes,
+            Err(e) => {
+                tracing::debug!(
+                    "NFO: TMDB season {} unavailable for tv {}: {}",
+                    season,
+                    tmdb_id,
+                    e
+                );
+                Vec::new()
+            }
+        };
+        info.episodes.insert(season, episodes);
+    }
+
+    async fn movie_details(&self, media: &Media) -> Details {
+        let Some(tmdb_id) = media.tmdb_id else {
+            return Details::default();
+        };
+        let tmdb = &self.state.tmdb_client;
+        let mut details = Details::default();
+        match tmdb.movie_details(tmdb_id).await {
+            Ok(movie) => {
+                details.tagline = movie.tagline;
+                details.premiered = movie.release_date;
+            }
+            Err(e) => tracing::debug!("NFO: TMDB details unavailable for {}: {}", tmdb_id, e),
+        }
+        if let Ok(credits) = tmdb.credits("movie", tmdb_id).await {
+            (details.directors, details.actors) = credits_details(credits);
+        }
+        details.certification = tmdb.movie_certification(tmdb_id).await.ok().flatten();
+        details
+    }
+
+    async fn series_info(&self, series: &Media) -> SeriesInfo {
+        let mut info = SeriesInfo {
+            details: Details::default(),
+            seasons: Vec::new(),
+            episodes: HashMap::new(),
+        };
+        let Some(tmdb_id) = series.tmdb_id else {
+            return info;
+        };
+        let tmdb = &self.state.tmdb_client;
+        match tmdb.tv_details(tmdb_id).await {
+            Ok(tv) => {
+                info.details.premiered = tv.first_air_date;
+                info.seasons = tv
+                    .seasons
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|s| {
+                        s.poster_path
+                            .map(|p| (s.season_number, format!("{}/w500{}", TMDB_IMAGE_BASE, p)))
+                    })
+                    .collect();
+            }
+            Err(e) => tracing::debug!("NFO: TMDB details unavailable for tv {}: {}", tmdb_id, e),
+        }
+        if let Ok(credits) = tmdb.credits("tv", tmdb_id).await {
+            (_, info.details.actors) = credits_details(credits);
+        }
+        info.details.certification = tmdb.tv_certification(tmdb_id).await.ok().flatten();
+        info
+    }
+
+    /// Write an NFO unless a hand-made one (without our marker) is already there.
+    async fn write_nfo(&mut self, path: &Path, xml: &str) -> anyhow::Result<()> {
+        if !self.force {
+            if let Ok(existing) = tokio::fs::read_to_string(path).await {
+                if !existing.contains(nfo::MARKER) {
+                    self.summary.skipped += 1;
+                    return Ok(());
+                }
+                if existing == xml {
+                    return Ok(());
+                }
+            }
+        }
+        tokio::fs::write(path, xml).await?;
+        self.summary.nfo_written += 1;
+        Ok(())
+    }
+
+    /// Save artwork as JPEG, keeping existing images unless forced.
+    async fn write_artwork(&mut self, path: &Path, url: Option<&str>) {
+        let Some(url) = url.filter(|u| artwork::is_allowed_source(u)) else {
+            return;
+        };
+        if !self.force && tokio::fs::try_exists(path).await.unwrap_or(false) {
+            return;
+        }
+        let result = async {
+            let original = self.artwork.original(url).await?;
+            let jpeg = tokio::task::spawn_blocking(move || artwork::to_jpeg(&original)).await??;
+            tokio::fs::write(path, jpeg).await?;
+            anyhow::Ok(())
+        }
+        .await;
+        match result {
+            Ok(()) => self.summary.artwork_written += 1,
+            Err(e) => tracing::warn!("NFO: unable to save {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Export after an import, when `NFO_EXPORT_ON_IMPORT` is enabled.
+pub async fn after_import(state: &AppState, file: &MediaFile) {
+    if !CONFIG.nfo_export_on_import {
+        return;
+    }
+    let mut exporter = Exporter::new(state, false);
+    if let Err(e) = exporter.export_file(file).await {
+        tracing::warn!("NFO export failed for {}: {}", file.file_path, e);
+    }
+}
+
+/// Regenerate the NFO files of one media item (and its episodes) or of the whole
+/// library, as a background task. Returns the task id.
+pub async fn start_export(
+    state: &Arc<AppState>,
+    media_id: Option<Uuid>,
+    force: bool,
+) -> anyhow::Result<Uuid> {
+    let files = match media_id {
+        Some(id) => {
+            let mut files = db::media_files::get_files_by_media_id(&state.db_pool, id).await?;
+            for episode in db::media::get_episodes(&state.db_pool, id).await? {
+                files.extend(
+                    db::media_files::get_files_by_media_id(&state.db_pool, episode.id).await?,
+                );
+            }
+            files
+        }
+        None => db::media_files::list_all_files(&state.db_pool).await?,
+    };
+
+    let task = db::tasks::create_task(
+        &state.db_pool,
+        &CreateTaskPayload {
+            task_type: "nfo_export".to_string(),
+            payload: Some(serde_json::json!({
+                "media_id": media_id,
+                "force": force,
+                "files": files.len(),
+            })),
+        },
+    )
+    .await?;
+
+    let task_id = task.id;
+    let state = state.clone();
+    tokio::spawn(async move {
+        let _ = db::tasks::update_task_status(&state.db_pool, task_id, "running", None).await;
+        let mut exporter = Exporter::new(&state, force);
+        let total = files.len().max(1);
+        for (i, file) in files.iter().enumerate() {
+            if file.missing_since.is_some() {
+                continue;
+            }
+            if let Err(e) = exporter.export_file(file).await {
+                tracing::warn!("NFO export failed for {}: {}", file.file_path, e);
+                exporter.summary.failed += 1;
+            }
+            let progress = Decimal::from((i + 1) * 100) / Decimal::from(total);
+            let _ = db::tasks::update_task_progress(&state.db_pool, task_id, progress).await;
+        }
+        tracing::info!(
+            "NFO export: {} files, {} NFO and {} images written, {} skipped, {} failed",
+            exporter.summary.files,
+            exporter.summary.nfo_written,
+            exporter.summary.artwork_written,
+            exporter.summary.skipped,
+            exporter.summary.failed
+        );
+        let _ = db::tasks::complete_task(
+            &state.db_pool,
+            task_id,
+            serde_json::to_value(&exporter.summary).ok(),
+        )
+        .await;
+    });
+
+    Ok(task_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_show_folders() {
+        assert_eq!(
+            show_dir(Path::new("/tv/Show/Season 01/Show.S01E01.mkv")),
+            Some(Path::new("/tv/Show"))
+        );
+        assert_eq!(
+            show_dir(Path::new("/tv/Show/S2/e.mkv")),
+            Some(Path::new("/tv/Show"))
+        );
+        assert_eq!(
+            show_dir(Path::new("/tv/Show/Show.S01E01.mkv")),
+            Some(Path::new("/tv/Show"))
+        );
+        assert_eq!(season_poster_name(3), "season03-poster.jpg");
+        assert_eq!(season_poster_name(0), "season-specials-poster.jpg");
+    }
+
+    #[test]
+    fn skips_series_files_in_shared_folders() {
+        let roots = vec![PathBuf::from("/tv"), PathBuf::from("/downloads")];
+        assert_eq!(
+            series_dir(Path::new("/tv/Show/Season 01/Show.S01E01.mkv"), &roots),
+            Some(Path::new("/tv/Show"))
+        );
+        assert_eq!(
+            series_dir(Path::new("/downloads/Show.S01E01.mkv"), &roots),
+            None
+        );
+        assert_eq!(
+            series_dir(Path::new("/tv/Season 2/Show.S02E01.mkv"), &roots),
+            None
+        );
+    }
+
+    #[test]
+    fn fills_episode_details_from_tmdb() {
+        let episode: Media = serde_json::from_value(serde_json::json!({
+            "id": Uuid::new_v4(),
+            "media_type": "episode",
+            "title": "Pilot",
+            "overview": "Written by hand",
+            "season_number": 1,
+            "episode_number": 1,
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        let tmdb: TmdbEpisode = serde_json::from_value(serde_json::json!({
+            "id": 62085,
+            "episode_number": 1,
+            "overview": "From TMDB",
+            "air_date": "2008-01-20",
+            "vote_average": 8.24,
+            "crew": [{"id": 1, "name": "Vince Gilligan", "job": "Director"}],
+            "guest_stars": [{"id": 2, "name": "Max Arciniega", "character": "Krazy-8", "order": 0}]
+        }))
+        .unwrap();
+
+        let (filled, details) = episode_details(&episode, Some(&tmdb));
+        assert_eq!(filled.overview.as_deref(), Some("Written by hand"));
+        assert_eq!(filled.air_date, "2008-01-20".parse().ok());
+        assert_eq!(filled.rating, Some(Decimal::new(82, 1)));
+        assert_eq!(details.directors, vec!["Vince Gilligan".to_string()]);
+        assert_eq!(details.actors[0].role.as_deref(), Some("Krazy-8"));
+
+        let (unchanged, details) = episode_details(&episode, None);
+        assert_eq!(unchanged.air_date, None);
+        assert!(details.actors.is_empty());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{
+    db::{
+        self,
+        retention::{RetentionCandidate, RetentionPolicy},
+    },
+    utils::storage,
+};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedDeletion {
+    pub policy_id: Uuid,
+    pub policy_name: String,
+    #[serde(flatten)]
+    pub file: RetentionCandidate,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RetentionReport {
+    pub deletions: Vec<PlannedDeletion>,
+    pub reclaimable_bytes: u64,
+    /// Matching files left alone because their volume has enough free space
+    pub kept_for_free_space: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RetentionRun {
+    pub deleted_files: usize,
+    pub freed_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+/// Whether deleting `size` more bytes is still needed to bring the volume above
+/// `target` free bytes. Counts the deletion as done when it is.
+fn needs_space(free: &mut [u64], volume: Option<usize>, size: u64, target: u64) -> bool {
+    // Unknown volume: the rule cannot be checked, so it does not apply.
+    let Some(free) = volume.and_then(|v| free.get_mut(v)) else {
+        return false;
+    };
+    if *free >= target {
+        return false;
+    }
+    *free += size;
+    true
+}
+
+/// What the enabled policies would delete now, in policy order. A file matched
+/// by several policies is listed once, under the first one.
+pub async fn evaluate(pool: &PgPool) -> Result<RetentionReport, sqlx::Error> {
+    let mut matches = Vec::new();
+    for policy in db::retention::list_policies(pool).await? {
+        if policy.enabled {
+            let candidates = db::retention::list_candidates(pool, &policy).await?;
+            matches.push((policy, candidates));
+        }
+    }
+
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    let mount_points: Vec<PathBuf> = disks
+        .iter()
+        .map(|d| d.mount_point().to_path_buf())
+        .collect();
+    let mut free: Vec<u64> = disks.iter().map(|d| d.available_space()).collect();
+    Ok(plan(matches, &mut free, |path| {
+        storage::volume_of(Path::new(path), &mount_points)
+    }))
+}
+
+/// Deletions from each policy's candidates, `free` holding the free space of
+/// each volume as it will be once the deletions planned so far are done.
+fn plan(
+    matches: Vec<(RetentionPolicy, Vec<RetentionCandidate>)>,
+    free: &mut [u64],
+    volume_of: impl Fn(&str) -> Option<usize>,
+) -> RetentionReport {
+    let mut report = RetentionReport::default();
+    let mut planned = HashSet::new();
+    for (policy, candidates) in matches {
+        for file in candidates {
+            if planned.contains(&file.file_id) {
+                continue;
+            }
+            let size = file.file_size.unwrap_or(0).max(0) as u64;
+            if let Some(gb) = policy.free_space_below_gb {
+                let volume = volume_of(&file.file_path);
+                if !needs_space(free, volume, size, gb.max(0) as u64 * GIB) {
+                    report.kept_for_free_space += 1;
+                    continue;
+                }
+            }
+            planned.insert(file.file_id);
+            report.reclaimable_bytes += size;
+            report.deletions.push(PlannedDeletion {
+                policy_id: policy.id,
+                policy_name: policy.name.clone(),
+                file,
+            });
+        }
+    }
+    report
+}
+
+/// Delete the planned files and their rows, recording each one in the audit log.
+/// Only files inside the download directory or a library root are touched.
+pub async fn apply(pool: &PgPool, report: &RetentionReport, actor: Option<Uuid>) -> RetentionRun {
+    let roots = storage::roots();
+    let mut run = RetentionRun::default();
+
+    for planned in &report.deletions {
+        let file = &planned.file;
+        let path = storage::absolute(Path::new(&file.file_path));
+        let result = match storage::root_of(&path, &roots) {
+            None => Err("outside the storage roots".to_string()),
+            Some(root) => match tokio::fs::remove_file(&path).await {
+                Ok(()) => {
+                    storage::remove_empty_parents(&path, root);
+                    db::media_files::delete_files(pool, &[file.file_id])
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                }
+                Err(e) => Err(e.to_string()),
+            },
+        };
+
+        let (status, risk) = match &result {
+            Ok(()) => {
+                run.deleted_files += 1;
+                run.freed_bytes += file.file_size.unwrap_or(0).max(0) as u64;
+                tracing::info!(
+                    "Retention: deleted {} ({}, policy \"{}\")",
+                    file.file_path,
+                    file.title,
+                    planned.policy_name
+                );
+                ("success", "medium")
+            }
+            Err(e) => {
+                tracing::warn!("Retention: could not delete {}: {}", file.file_path, e);
+                run.errors.push(format!("{}: {}", file.file_path, e));
+                ("failed", "low")
+            }
+        };
+
+        let file_id = file.file_id.to_string();
+        if let Err(e) = db::security::insert_audit_log(
+            pool,
+            actor,
+            "retention_delete",
+            Some("media_file"),
+            Some(&file_id),
+            None,
+            None,
+            None,
+            risk,
+            status,
+            Some(serde_json::json!({
+                "policy_id": planned.policy_id,
+                "policy_name": planned.policy_name,
+                "path": file.file_path,
+                "size_bytes": file.file_size,
+                "media_id": file.media_id,
+                "title": file.title,
+                "last_watched_at": file.last_watched_at,
+                "error": result.err(),
+            })),
+        )
+        .await
+        {
+            tracing::error!("Retention: failed to write audit log: {}", e);
+        }
+    }
+    run
+}
+
+/// Scheduled pass: evaluate the policies and apply them.
+pub async fn run(pool: &PgPool) -> Result<RetentionRun, sqlx::Error> {
+    let report = evaluate(pool).await?;
+    if report.deletions.is_empty() {
+        return Ok(RetentionRun::default());
+    }
+    Ok(apply(pool, &report, None).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deletes_until_free_space_target() {
+        let mut free = vec![5 * GIB, 50 * GIB];
+        let target = 10 * GIB;
+        assert!(needs_space(&mut free, Some(0), 3 * GIB, target));
+        assert!(needs_space(&mut free, Some(0), 3 * GIB, target));
+        // 11 GiB free once both are gone: enough.
+        assert!(!needs_space(&mut free, Some(0), 3 * GIB, target));
+        assert!(!needs_space(&mut free, Some(1), 3 * GIB, target));
+        assert!(!needs_space(&mut free, None, 3 * GIB, target));
+        assert_eq!(free, vec![11 * GIB, 50 * GIB]);
+    }
+
+    fn policy(name: &str, free_space_below_gb: Option<i32>) -> RetentionPolicy {
+        RetentionPolicy {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            enabled: true,
+            media_type: None,
+            watched_by: "all".to_string(),
+            min_days_since_watched: 30,
+            keep_favorites: true,
+            keep_watchlisted: true,
+            free_space_below_gb,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    fn candidate(path: &str, gib: i64) -> RetentionCandidate {
+        RetentionCandidate {
+            file_id: Uuid::new_v4(),
+            file_path: path.to_string(),
+            file_size: Some(gib * GIB as i64),
+            media_id: Uuid::new_v4(),
+            title: path.to_string(),
+            media_type: "movie".to_string(),
+            last_watched_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn plans_policies_in_order() {
+        let (a, b, c) = (
+            candidate("/fast/a.mkv", 4),
+            candidate("/fast/b.mkv", 4),
+            candidate("/slow/c.mkv", 4),
+        );
+        let space = policy("space", Some(10));
+        let old = policy("old", None);
+        let (space_id, old_id) = (space.id, old.id);
+        let matches = vec![
+            (space, vec![a.clone(), b.clone(), c.clone()]),
+            (old, vec![b.clone(), c.clone()]),
+        ];
+        // 7 GiB free on /fast, plenty on /slow
+        let mut free = vec![7 * GIB, 100 * GIB];
+        let report = plan(matches, &mut free, |path| {
+            Some(if path.starts_with("/fast") { 0 } else { 1 })
+        });
+
+        let planned: Vec<(Uuid, &str)> = report
+            .deletions
+            .iter()
+            .map(|d| (d.policy_id, d.file.file_path.as_str()))
+            .collect();
+        // a frees enough on /fast; b and c are left to the next policy, which
+        // lists them in its own order.
+        assert_eq!(
+            planned,
+            vec![
+                (space_id, "/fast/a.mkv"),
+                (old_id, "/fast/b.mkv"),
+                (old_id, "/slow/c.mkv"),
+            ]
+        );
+        assert_eq!(report.kept_for_free_space, 2);
+        assert_eq!(report.reclaimable_bytes, 12 * GIB);
+
+        // A file planned by a policy is not listed again by the next ones.
+        let first = policy("first", None);
+        let second = policy("second", None);
+        let first_id = first.id;
+        let report = plan(
+            vec![(first, vec![a.clone()]), (second, vec![a])],
+            &mut free,
+            |_| None,
+        );
+        assert_eq!(report.deletions.len(), 1);
+        assert_eq!(report.deletions[0].policy_id, first_id);
+    }
+}
@@ -4,10 +4,7 @@ use crate::{
     db,
     events::{self, SearchRequestedPayload, SearchResultsFoundPayload, WsEvent},
     models::CreateMediaPayload,
-    providers::{
-        jackett::JackettProvider, prowlarr::ProwlarrProvider, streaming::StreamingProvider,
-        ProviderRegistry,
-    },
+    providers::{streaming::StreamingProvider, ProviderRegistry},
     utils::scoring,
     AppState,
 };
@@ -19,23 +16,10 @@ pub async fn scout_worker(state: Arc<AppState>) -> anyhow::Result<()> {
 
     let tmdb_client = TmdbClient::new(CONFIG.tmdb_api_key.clone());
 
-    let mut registry = ProviderRegistry::new();
-
-    if !CONFIG.prowlarr_url.is_empty() && !CONFIG.prowlarr_api_key.is_empty() {
-        registry.register(Box::new(ProwlarrProvider::new(
-            CONFIG.prowlarr_api_key.clone(),
-            CONFIG.prowlarr_url.clone(),
-            state.flaresolverr_client.clone(),
-        )));
-    }
-
-    if !CONFIG.jackett_url.is_empty() && !CONFIG.jackett_api_key.is_empty() {
-        registry.register(Box::new(JackettProvider::new(
-            CONFIG.jackett_api_key.clone(),
-            CONFIG.jackett_url.clone(),
-            state.flaresolverr_client.clone(),
-        )));
-    }
+    let mut registry = ProviderRegistry::with_indexers(
+        state.redis_client.clone(),
+        state.flaresolverr_client.clone(),
+    );
 
     if CONFIG.streaming_enabled {
         if let Some(browser) = &state.browser {
@@ -107,6 +91,7 @@ pub async fn scout_worker(state: Arc<AppState>) -> anyhow::Result<()> {
         );
 
         let event_tx_clone = state.event_tx.clone();
+        let refresh = payload.refresh;
 
         futures::stream::iter(tmdb_results)
             .for_each_concurrent(5, |result| {
@@ -131,8 +116,9 @@ pub async fn scout_worker(state: Arc<AppState>) -> anyhow::Result<()> {
                         Ok(media) => {
                             tracing::info!("Media '{}' (ID: {}) saved to database.", media.title, media.id);
 
+                            let titles = db::media_titles::titles_for_media(&db_pool_clone, &media, &CONFIG.release_region).await;
                             let sources = registry_clone
-                                .search_all(&media.title, &media.media_type, media.tmdb_id)
+                                .search_titles(&titles, str::to_string, &media.media_type, media.tmdb_id, refresh)
                                 .await;
 
                             if sources.is_empty() {
@@ -0,0 +1,311 @@
+use crate::{
+    api::downloads,
+    config::CONFIG,
+    db::{
+        self,
+        series::{MonitoredSeries, WantedEpisode},
+    },
+    models::UpsertEpisodePayload,
+    providers::ProviderRegistry,
+    utils::{quality, release},
+    workers::anime,
+    AppState,
+};
+use chrono::NaiveDate;
+use std::{sync::Arc, time::Duration};
+use tokio::time;
+use uuid::Uuid;
+
+/// Keeps monitored series' episode lists in sync and downloads newly aired episodes.
+pub async fn series_worker(state: Arc<AppState>) -> anyhow::Result<()> {
+    tracing::info!("Series worker starting...");
+
+    let registry = ProviderRegistry::with_indexers(
+        state.redis_client.clone(),
+        state.flaresolverr_client.clone(),
+    );
+
+    let mut interval = time::interval(Duration::from_secs(
+        CONFIG.series_check_interval_secs.max(60),
+    ));
+
+    loop {
+        interval.tick().await;
+
+        // 1. Refresh episode lists that are older than the sync interval
+        match db::series::list_series_to_sync(
+            &state.db_pool,
+            CONFIG.series_sync_interval_secs as f64,
+        )
+        .await
+        {
+            Ok(series_list) => {
+                for series in series_list {
+                    match sync_series(&state, &series).await {
+                        Ok(count) => tracing::info!(
+                            "Series: synced {} episode(s) for '{}'",
+                            count,
+                            series.title
+                        ),
+                        Err(e) => {
+                            tracing::warn!("Series: sync failed for '{}': {}", series.title, e)
+                        }
+                    }
+                }
+            }
+            Err(e) => tracing::error!("Series: failed to load monitored series: {}", e),
+        }
+
+        // 2. Search aired episodes that are still missing
+        if registry.list_enabled_names().is_empty() {
+            continue;
+        }
+        let wanted = match db::series::list_wanted_episodes(
+            &state.db_pool,
+            CONFIG.episode_search_delay_hours,
+            CONFIG.series_sync_interval_secs as f64,
+            50,
+        )
+        .await
+        {
+            Ok(w) => w,
+            Err(e) => {
+                tracing::error!("Series: failed to load wanted episodes: {}", e);
+                continue;
+            }
+        };
+
+        for episode in wanted {
+            if let Err(e) = search_episode(&state, &registry, &episode).await {
+                tracing::warn!(
+                    "Series: '{}' S{:02}E{:02}: {}",
+                    episode.series_title,
+                    episode.season_number,
+                    episode.episode_number,
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Pull the episode list of a monitored series from TMDB (TVMaze as fallback)
+/// into child `media` rows. Returns the number of episodes synced.
+pub async fn sync_series(state: &AppState, series: &MonitoredSeries) -> anyhow::Result<usize> {
+    let synced = sync_episodes(
+        state,
+        series.media_id,
+        &series.title,
+        series.tmdb_id,
+        series.imdb_id.as_deref(),
+    )
+    .await?;
+
+    db::series::mark_synced(&state.db_pool, series.media_id).await?;
+    Ok(synced)
+}
+
+/// Store the episode list of any series, monitored or not (seasons are only
+/// registered for monitored ones).
+pub async fn sync_episodes(
+    state: &AppState,
+    series_id: Uuid,
+    title: &str,
+    tmdb_id: Option<i32>,
+    imdb_id: Option<&str>,
+) -> anyhow::Result<usize> {
+    let episodes = match tmdb_id {
+        Some(tmdb_id) => match tmdb_episodes(state, tmdb_id).await {
+            Ok(episodes) => episodes,
+            Err(e) => {
+                tracing::warn!(
+                    "Series: TMDB episodes failed for '{}': {}, trying TVMaze",
+                    title,
+                    e
+                );
+                tvmaze_episodes(state, title, imdb_id).await?
+            }
+        },
+        None => tvmaze_episodes(state, title, imdb_id).await?,
+    };
+
+    let mut synced = 0;
+    for episode in &episodes {
+        db::series::ensure_season(&state.db_pool, series_id, episode.season_number).await?;
+        match db::media::upsert_episode(&state.db_pool, series_id, episode).await {
+            Ok(_) => synced += 1,
+            Err(e) => tracing::warn!(
+                "Series: could not save '{}' S{:02}E{:02}: {}",
+                title,
+                episode.season_number,
+                episode.episode_number,
+                e
+            ),
+        }
+    }
+    db::series::mark_episodes_synced(&state.db_pool, series_id).await?;
+
+    Ok(synced)
+}
+
+fn parse_air_date(value: Option<&str>) -> Option<NaiveDate> {
+    value.and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+}
+
+async fn tmdb_episodes(
+    state: &AppState,
+    tmdb_id: i32,
+) -> anyhow::Result<Vec<UpsertEpisodePayload>> {
+    let details = state.tmdb_client.tv_details(tmdb_id).await?;
+    let mut episodes = Vec::new();
+
+    // Season 0 holds specials, which indexers rarely name consistently.
+    for season in details
+        .seasons
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|s| s.season_number > 0)
+    {
+        let season_detail = state
+            .tmdb_client
+            .season_details(tmdb_id, season.season_number)
+            .await?;
+        episodes.extend(season_detail.episodes.into_iter().map(|e| {
+            UpsertEpisodePayload {
+                season_number: season.season_number,
+                episode_number: e.episode_number,
+                title: e
+                    .name
+                    .unwrap_or_else(|| format!("Episode {}", e.episode_number)),
+                tmdb_id: Some(e.id),
+                overview: e.overview.filter(|o| !o.is_empty()),
+                air_date: parse_air_date(e.air_date.as_deref()),
+                runtime_minutes: e.runtime,
+            }
+        }));
+    }
+
+    Ok(episodes)
+}
+
+async fn tvmaze_episodes(
+    state: &AppState,
+    title: &str,
+    imdb_id: Option<&str>,
+) -> anyhow::Result<Vec<UpsertEpisodePayload>> {
+    let show_id = match imdb_id {
+        Some(imdb_id) => state
+            .tvmaze_client
+            .lookup_by_imdb(imdb_id)
+            .await?
+            .map(|show| show.id),
+        None => None,
+    };
+    let show_id = match show_id {
+        Some(id) => id,
+        None => state
+            .tvmaze_client
+            .search(title)
+            .await?
+            .into_iter()
+            .next()
+            .map(|r| r.show.id)
+            .ok_or_else(|| anyhow::anyhow!("show not found on TVMaze"))?,
+    };
+
+    Ok(state
+        .tvmaze_client
+        .episodes(show_id)
+        .await?
+        .into_iter()
+        .filter_map(|e| {
+            let season_number = e.season.filter(|s| *s > 0)?;
+            let episode_number = e.number?;
+            Some(UpsertEpisodePayload {
+                season_number,
+                episode_number,
+                title: e
+                    .name
+                    .unwrap_or_else(|| format!("Episode {}", episode_number)),
+                tmdb_id: None,
+                overview: None,
+                air_date: parse_air_date(e.airdate.as_deref()),
+                runtime_minutes: e.runtime,
+            })
+        })
+        .collect())
+}
+
+/// Search the indexers for one episode and queue the best release for the profile.
+pub async fn search_episode(
+    state: &Arc<AppState>,
+    registry: &ProviderRegistry,
+    episode: &WantedEpisode,
+) -> anyhow::Result<()> {
+    db::media::mark_searched(&state.db_pool, episode.episode_id).await?;
+
+    let titles = match db::media::get_media_by_id(&state.db_pool, episode.series_id).await {
+        Ok(series) => {
+            db::media_titles::titles_for_media(&state.db_pool, &series, &CONFIG.release_region)
+                .await
+        }
+        Err(_) => vec![episode.series_title.clone()],
+    };
+    // Anime releases number episodes from the first season: "Show - 1057".
+    let absolute = anime::absolute_number(
+        state,
+        episode.series_id,
+        episode.season_number,
+        episode.episode_number,
+    )
+    .await?;
+    let results: Vec<_> = registry
+        .search_titles(
+            &titles,
+            |title| match absolute {
+                Some(absolute) => release::absolute_episode_query(title, absolute),
+                None => {
+                    release::episode_query(title, episode.season_number, episode.episode_number)
+                }
+            },
+            "episode",
+            None,
+            false,
+        )
+        .await
+        .into_iter()
+        .filter(|r| {
+            release::is_episode_release(
+                &r.title,
+                episode.season_number,
+                episode.episode_number,
+                absolute,
+            )
+        })
+        .collect();
+
+    let profile = match episode.user_id {
+        Some(user_id) => {
+            db::quality_profiles::get_effective_profile(&state.db_pool, user_id).await?
+        }
+        None => quality::QualityProfile::default(),
+    };
+    let (best, score) = quality::pick_best(&profile, &titles, &results)
+        .ok_or_else(|| anyhow::anyhow!("no release matching the quality profile"))?;
+
+    let saved =
+        db::search_results::store_selected(&state.db_pool, episode.episode_id, best, score).await?;
+
+    downloads::queue_download(state, episode.user_id, episode.episode_id, &saved, None)
+        .await
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+
+    tracing::info!(
+        "Series: queued '{}' for '{}' S{:02}E{:02}",
+        saved.title,
+        episode.series_title,
+        episode.season_number,
+        episode.episode_number
+    );
+    Ok(())
+}
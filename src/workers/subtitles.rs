@@ -0,0 +1,594 @@
+use crate::{
+    config::CONFIG,
+    db::{
+        self,
+        subtitles::{NewSubtitle, Subtitle},
+    },
+    models::{CreateTaskPayload, MediaFile, Task},
+    providers::subtitles::{self as subtitle_providers, SubtitleProvider, SubtitleSearch},
+    remux::mkv::{self, SubtitleTrack},
+    utils::{
+        file_hash, language,
+        subtitles::{self, SidecarTags, SubtitleFormat},
+    },
+    workers::library_scan::is_video_file,
+    AppState,
+};
+use once_cell::sync::Lazy;
+use reqwest::StatusCode;
+use sqlx::PgPool;
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use uuid::Uuid;
+
+static PROVIDERS: Lazy<Vec<Box<dyn SubtitleProvider>>> = Lazy::new(subtitle_providers::configured);
+
+/// Task queued for each imported file missing subtitles in a preferred language.
+const DOWNLOAD_TASK: &str = "subtitle_download";
+/// Wait when no download is queued.
+const IDLE_POLL: Duration = Duration::from_secs(60);
+/// Wait once the daily quota is used, ours or the provider's.
+const QUOTA_PAUSE: Duration = Duration::from_secs(3600);
+/// Wait after the provider asked to slow down.
+const RATE_LIMIT_PAUSE: Duration = Duration::from_secs(60);
+
+/// Folders release groups put subtitles in, next to the video.
+const SUBTITLE_DIRS: &[&str] = &["subs", "subtitles", "sub"];
+
+/// A subtitle file found next to a video.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sidecar {
+    pub path: PathBuf,
+    pub format: SubtitleFormat,
+    pub tags: SidecarTags,
+}
+
+fn subtitle_format(path: &Path) -> Option<SubtitleFormat> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(SubtitleFormat::from_extension)
+}
+
+fn stem_of(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Tags after the video name: `Movie.fr.srt` -> ".fr", but `Movie2.srt` is another video's.
+fn tags_after<'a>(name: &'a str, stem: &str) -> Option<&'a str> {
+    name.strip_prefix(stem)
+        .filter(|rest| rest.is_empty() || rest.starts_with(['.', '_', '-', ' ']))
+}
+
+fn files_in(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default()
+}
+
+/// Subtitle files belonging to `video`: `<video name>[.tags].srt` in the same
+/// directory, and files of a `Subs/` folder (`Subs/<video name>/` for season
+/// packs, or the whole folder when the video is alone in its directory).
+pub fn find_sidecars(video: &Path) -> Vec<Sidecar> {
+    let Some(dir) = video.parent() else {
+        return Vec::new();
+    };
+    let stem = stem_of(video);
+    let entries = files_in(dir);
+    let alone = entries.iter().filter(|p| is_video_file(p)).count() <= 1;
+    let mut found = Vec::new();
+
+    let mut add = |path: &Path, tags: &str| {
+        if let Some(format) = subtitle_format(path) {
+            found.push(Sidecar {
+                path: path.to_path_buf(),
+                format,
+                tags: subtitles::sidecar_tags(tags),
+            });
+        }
+    };
+
+    for path in &entries {
+        if path.is_dir() {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            if !SUBTITLE_DIRS.contains(&name.as_str()) {
+                continue;
+            }
+            for sub in files_in(path) {
+                let sub_stem = stem_of(&sub);
+                if sub.is_dir() {
+                    // Directory names have no extension: compare them whole.
+                    if sub.file_name().map(|n| n.to_string_lossy().to_lowercase())
+                        == Some(stem.clone())
+                    {
+                        for nested in files_in(&sub) {
+                            add(&nested, &stem_of(&nested));
+                        }
+                    }
+                } else if let Some(tags) = tags_after(&sub_stem, &stem) {
+                    add(&sub, tags);
+                } else if alone && sub.is_file() {
+                    add(&sub, &sub_stem);
+                }
+            }
+        } else if let Some(tags) = tags_after(&stem_of(path), &stem) {
+            add(path, tags);
+        }
+    }
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found
+}
+
+/// Format name stored for an embedded track.
+fn embedded_format(codec_id: &str) -> String {
+    match codec_id {
+        "S_TEXT/UTF8" => "srt".to_string(),
+        "S_TEXT/ASS" | "S_TEXT/SSA" => "ass".to_string(),
+        "S_TEXT/WEBVTT" => "vtt".to_string(),
+        "S_HDMV/PGS" => "pgs".to_string(),
+        "S_VOBSUB" => "vobsub".to_string(),
+        other => other.trim_start_matches("S_").to_lowercase(),
+    }
+}
+
+fn probe_embedded(path: &Path) -> Vec<SubtitleTrack> {
+    if !mkv::is_matroska(&path.to_string_lossy()) {
+        return Vec::new();
+    }
+    match File::open(path).map(BufReader::new) {
+        Ok(reader) => mkv::subtitle_tracks(reader).unwrap_or_else(|e| {
+            tracing::debug!("Unable to probe subtitles of {}: {}", path.display(), e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Refresh the subtitles recorded for a file from what is on disk and return them.
+pub async fn discover(pool: &PgPool, file: &MediaFile) -> anyhow::Result<Vec<Subtitle>> {
+    let path = PathBuf::from(&file.file_path);
+    if !path.exists() {
+        return Ok(db::subtitles::list_for_file(pool, file.id).await?);
+    }
+
+    let (sidecars, embedded) =
+        tokio::task::spawn_blocking(move || (find_sidecars(&path), probe_embedded(&path))).await?;
+
+    let mut keep = Vec::with_capacity(sidecars.len() + embedded.len());
+    for sidecar in &sidecars {
+        let file_path = sidecar.path.to_string_lossy();
+        let subtitle = db::subtitles::upsert_subtitle(
+            pool,
+            &NewSubtitle {
+                media_file_id: file.id,
+                source: "sidecar",
+                file_path: Some(&file_path),
+                track_number: None,
+                format: sidecar.format.as_str(),
+                language: sidecar.tags.language,
+                title: None,
+                is_forced: sidecar.tags.forced,
+                is_sdh: sidecar.tags.sdh,
+                is_default: false,
+            },
+        )
+        .await?;
+        keep.push(subtitle.id);
+    }
+    for track in &embedded {
+        let name = track.name.as_deref().unwrap_or("").to_lowercase();
+        let subtitle = db::subtitles::upsert_subtitle(
+            pool,
+            &NewSubtitle {
+                media_file_id: file.id,
+                source: "embedded",
+                file_path: None,
+                track_number: i32::try_from(track.number).ok(),
+                format: &embedded_format(&track.codec_id),
+                language: track.language.as_deref().and_then(language::normalize),
+                title: track.name.as_deref(),
+                is_forced: track.forced || name.contains("forced"),
+                is_sdh: track.hearing_impaired || name.contains("sdh"),
+                is_default: track.default,
+            },
+        )
+        .await?;
+        keep.push(subtitle.id);
+    }
+    db::subtitles::delete_stale(pool, file.id, &keep).await?;
+
+    Ok(db::subtitles::list_for_file(pool, file.id).await?)
+}
+
+/// Preferred languages (`SUBTITLE_LANGUAGES`) without full subtitles yet;
+/// forced subtitles only cover foreign dialogue and do not count.
+fn missing_languages(existing: &[Subtitle]) -> Vec<&'static str> {
+    missing_among(&CONFIG.subtitle_languages, existing)
+}
+
+fn missing_among(preferred: &[String], existing: &[Subtitle]) -> Vec<&'static str> {
+    let mut seen = HashSet::new();
+    preferred
+        .iter()
+        .filter_map(|l| language::normalize(l))
+        .filter(|l| seen.insert(*l))
+        .filter(|l| {
+            !existing
+                .iter()
+                .any(|s| !s.is_forced && s.language.as_deref() == Some(*l))
+        })
+        .collect()
+}
+
+/// Provider refusals meaning no more requests for now: 406 is OpenSubtitles'
+/// exhausted download quota, 429 its rate limit.
+fn limit_status(error: &anyhow::Error) -> Option<StatusCode> {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+        .filter(|s| {
+            matches!(
+                *s,
+                StatusCode::NOT_ACCEPTABLE | StatusCode::TOO_MANY_REQUESTS
+            )
+        })
+}
+
+#[derive(Debug, Default)]
+pub struct DownloadRun {
+    pub saved: Vec<Subtitle>,
+    /// Set when a provider refused more requests
+    pub limited: Option<StatusCode>,
+}
+
+/// Search terms for a file: its movie hash, and the ids of its movie or,
+/// for an episode, of its series.
+async fn search_terms(
+    pool: &PgPool,
+    file: &MediaFile,
+    languages: &[&str],
+) -> anyhow::Result<SubtitleSearch> {
+    let media = db::media::get_media_by_id(pool, file.media_id).await?;
+    let path = PathBuf::from(&file.file_path);
+    let movie_hash = tokio::task::spawn_blocking(move || file_hash::movie_hash(&path))
+        .await?
+        .ok();
+
+    let (ids_from, episode) = match (media.parent_id, media.season_number, media.episode_number) {
+        (Some(parent_id), Some(season), Some(episode)) => (
+            db::media::get_media_by_id(pool, parent_id).await?,
+            Some((season, episode)),
+        ),
+        _ => (media, None),
+    };
+    Ok(SubtitleSearch {
+        languages: languages.iter().map(|l| l.to_string()).collect(),
+        movie_hash,
+        imdb_id: ids_from.imdb_id,
+        tmdb_id: ids_from.tmdb_id,
+        episode,
+    })
+}
+
+/// Download the best subtitle of each preferred language the file lacks, at
+/// most `budget` of them, save it as `<video name>.<language>.srt` next to it
+/// and record it. Stops at the first provider refusing more requests.
+pub async fn download_missing(
+    pool: &PgPool,
+    file: &MediaFile,
+    existing: &[Subtitle],
+    providers: &[Box<dyn SubtitleProvider>],
+    budget: usize,
+) -> anyhow::Result<DownloadRun> {
+    let mut run = DownloadRun::default();
+    let mut missing = missing_languages(existing);
+    if missing.is_empty() || providers.is_empty() {
+        return Ok(run);
+    }
+    let video = PathBuf::from(&file.file_path);
+    let (Some(dir), Some(stem)) = (video.parent(), video.file_stem()) else {
+        return Ok(run);
+    };
+    let search = search_terms(pool, file, &missing).await?;
+
+    for provider in providers {
+        if missing.is_empty() || run.saved.len() >= budget {
+            break;
+        }
+        let candidates = match provider.search(&search).await {
+            Ok(candidates) => candidates,
+            Err(e) if limit_status(&e).is_some() => {
+                run.limited = limit_status(&e);
+                return Ok(run);
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Subtitle provider '{}' search failed for {}: {}",
+                    provider.name(),
+                    file.file_path,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let mut found = Vec::new();
+        for lang in &missing {
+            if run.saved.len() >= budget {
+                break;
+            }
+            let Some(candidate) = subtitle_providers::best_match(&candidates, lang) else {
+                continue;
+            };
+            let bytes = match provider.download(candidate).await {
+                Ok(bytes) => bytes,
+                Err(e) if limit_status(&e).is_some() => {
+                    run.limited = limit_status(&e);
+                    return Ok(run);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Subtitle provider '{}' download of {} failed: {}",
+                        provider.name(),
+                        candidate.id,
+                        e
+                    );
+                    continue;
+                }
+            };
+            if subtitles::parse_srt(&subtitles::decode_text(&bytes)).is_empty() {
+                tracing::warn!(
+                    "Subtitle {} from '{}' is not a valid SRT file",
+                    candidate.id,
+                    provider.name()
+                );
+                continue;
+            }
+
+            let target = dir.join(format!("{}.{}.srt", stem.to_string_lossy(), lang));
+            if target.exists() {
+                continue;
+            }
+            tokio::fs::write(&target, &bytes).await?;
+            let file_path = target.to_string_lossy();
+            let subtitle = db::subtitles::upsert_subtitle(
+                pool,
+                &NewSubtitle {
+                    media_file_id: file.id,
+                    source: "downloaded",
+                    file_path: Some(&file_path),
+                    track_number: None,
+                    format: SubtitleFormat::Srt.as_str(),
+                    language: Some(lang),
+                    title: candidate.release.as_deref(),
+                    is_forced: false,
+                    is_sdh: candidate.hearing_impaired,
+                    is_default: false,
+                },
+            )
+            .await?;
+            tracing::info!(
+                "Downloaded {} subtitles for {} from '{}'",
+                lang,
+                file.file_path,
+                provider.name()
+            );
+            run.saved.push(subtitle);
+            found.push(*lang);
+        }
+        missing.retain(|l| !found.contains(l));
+    }
+    Ok(run)
+}
+
+/// Subtitle work after an import: discovery now, and a download task for the
+/// subtitle worker when preferred languages are missing. Failures only cost
+/// the subtitles.
+pub async fn after_import(pool: &PgPool, file: &MediaFile) {
+    let existing = match discover(pool, file).await {
+        Ok(existing) => existing,
+        Err(e) => {
+            tracing::warn!("Subtitle discovery failed for {}: {}", file.file_path, e);
+            return;
+        }
+    };
+    if PROVIDERS.is_empty() || missing_languages(&existing).is_empty() {
+        return;
+    }
+    if let Err(e) = queue_download(pool, file).await {
+        tracing::warn!(
+            "Failed to queue subtitle download for {}: {}",
+            file.file_path,
+            e
+        );
+    }
+}
+
+async fn queue_download(pool: &PgPool, file: &MediaFile) -> Result<(), sqlx::Error> {
+    let file_id = file.id.to_string();
+    if db::tasks::has_active(pool, DOWNLOAD_TASK, "media_file_id", &file_id).await? {
+        return Ok(());
+    }
+    db::tasks::create_task(
+        pool,
+        &CreateTaskPayload {
+            task_type: DOWNLOAD_TASK.to_string(),
+            payload: Some(serde_json::json!({
+                "media_file_id": file_id,
+                "path": file.file_path,
+            })),
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+/// Works through the queued subtitle downloads one file at a time, within the
+/// daily download quota, pausing when a provider refuses more requests.
+pub async fn subtitle_worker(state: Arc<AppState>) -> anyhow::Result<()> {
+    if PROVIDERS.is_empty() {
+        tracing::info!("Subtitles: no provider configured, downloads disabled");
+        return Ok(());
+    }
+    tracing::info!("Subtitle worker starting...");
+    db::tasks::requeue_running(&state.db_pool, DOWNLOAD_TASK).await?;
+
+    loop {
+        let pause = match next_download(&state.db_pool).await {
+            Ok(pause) => pause,
+            Err(e) => {
+                tracing::error!("Subtitles: download queue failed: {}", e);
+                IDLE_POLL
+            }
+        };
+        tokio::time::sleep(pause).await;
+    }
+}
+
+/// Handle the oldest queued download, returning how long to wait before the next.
+async fn next_download(pool: &PgPool) -> anyhow::Result<Duration> {
+    let used = db::subtitles::count_downloaded_last_day(pool).await?;
+    let budget = CONFIG.subtitle_daily_downloads - used;
+    if budget <= 0 {
+        tracing::debug!("Subtitles: daily quota of {} used", used);
+        return Ok(QUOTA_PAUSE);
+    }
+    let Some(task) = db::tasks::next_pending(pool, DOWNLOAD_TASK).await? else {
+        return Ok(IDLE_POLL);
+    };
+
+    db::tasks::update_task_status(pool, task.id, "running", None).await?;
+    match download_for_task(pool, &task, budget as usize).await {
+        Ok(run) if run.limited.is_some() => {
+            let status = run.limited.unwrap_or_default();
+            tracing::warn!("Subtitles: provider refused more downloads ({})", status);
+            // Back in the queue for the next window.
+            db::tasks::update_task_status(pool, task.id, "pending", None).await?;
+            Ok(if status == StatusCode::TOO_MANY_REQUESTS {
+                RATE_LIMIT_PAUSE
+            } else {
+                QUOTA_PAUSE
+            })
+        }
+        Ok(run) => {
+            let languages: Vec<&str> = run
+                .saved
+                .iter()
+                .filter_map(|s| s.language.as_deref())
+                .collect();
+            db::tasks::complete_task(
+                pool,
+                task.id,
+                Some(serde_json::json!({ "downloaded": languages })),
+            )
+            .await?;
+            Ok(Duration::from_secs(CONFIG.subtitle_download_interval_secs))
+        }
+        Err(e) => {
+            tracing::warn!("Subtitles: download task {} failed: {}", task.id, e);
+            db::tasks::update_task_status(pool, task.id, "failed", Some(&e.to_string())).await?;
+            Ok(Duration::from_secs(CONFIG.subtitle_download_interval_secs))
+        }
+    }
+}
+
+async fn download_for_task(
+    pool: &PgPool,
+    task: &Task,
+    budget: usize,
+) -> anyhow::Result<DownloadRun> {
+    let file_id: Uuid = task
+        .payload
+        .as_ref()
+        .and_then(|p| p.get("media_file_id"))
+        .and_then(|id| id.as_str())
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("task has no media_file_id"))?;
+    let file = match db::media_files::get_file_by_id(pool, file_id).await {
+        Ok(file) => file,
+        // Deleted since it was queued
+        Err(sqlx::Error::RowNotFound) => return Ok(DownloadRun::default()),
+        Err(e) => return Err(e.into()),
+    };
+    let existing = discover(pool, &file).await?;
+    download_missing(pool, &file, &existing, &PROVIDERS, budget).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_sidecars() {
+        let root = std::env::temp_dir().join(format!("sokoul-subs-test-{}", std::process::id()));
+        let season = root.join("Show.S01");
+        fs::create_dir_all(season.join("Subs/Show.S01E01")).unwrap();
+        for name in [
+            "Show.S01E01.mkv",
+            "Show.S01E02.mkv",
+            "Show.S01E01.fr.forced.srt",
+            "Show.S01E01.ass",
+            "Show.S01E02.en.srt",
+            "Show.S01E010.srt",
+            "Subs/Show.S01E01/2_English_SDH.srt",
+            "Subs/unrelated.srt",
+        ] {
+            fs::write(season.join(name), b"").unwrap();
+        }
+
+        let found = find_sidecars(&season.join("Show.S01E01.mkv"));
+        let names: Vec<String> = found
+            .iter()
+            .map(|s| s.path.strip_prefix(&season).unwrap().display().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "Show.S01E01.ass",
+                "Show.S01E01.fr.forced.srt",
+                "Subs/Show.S01E01/2_English_SDH.srt",
+            ]
+        );
+        assert_eq!(found[0].tags.language, None);
+        assert_eq!(found[1].tags.language, Some("fr"));
+        assert!(found[1].tags.forced);
+        assert_eq!(found[2].format, SubtitleFormat::Srt);
+        assert!(found[2].tags.sdh);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn lists_each_missing_language_once() {
+        let subtitle = |language: &str, is_forced| Subtitle {
+            id: Uuid::new_v4(),
+            media_file_id: Uuid::new_v4(),
+            source: "sidecar".to_string(),
+            file_path: Some(format!("/m/movie.{}.srt", language)),
+            track_number: None,
+            format: "srt".to_string(),
+            language: Some(language.to_string()),
+            title: None,
+            is_forced,
+            is_sdh: false,
+            is_default: false,
+            created_at: chrono::Utc::now(),
+        };
+        let preferred: Vec<String> = ["fr", "en", "fre", "de", "eng"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        let existing = vec![subtitle("de", false), subtitle("fr", true)];
+        assert_eq!(missing_among(&preferred, &existing), vec!["fr", "en"]);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::{
+    api::downloads,
+    config::CONFIG,
+    db,
+    events::{self, SearchResultsFoundPayload},
+    models::{MediaFile, SearchResult},
+    providers::{ProviderRegistry, TorrentResult},
+    utils::{quality, release, scoring},
+    workers::anime,
+    AppState,
+};
+use futures::StreamExt;
+use serde::Serialize;
+use std::{path::Path, sync::Arc};
+use uuid::Uuid;
+
+/// Result of looking for a better release of an existing file.
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum UpgradeOutcome {
+    /// The file already meets the profile cutoff.
+    CutoffMet {
+        current_score: i32,
+        cutoff_score: i32,
+    },
+    /// No release scores above the current file.
+    NoUpgrade { current_score: i32 },
+    /// A better release was handed to the Hunter.
+    Queued {
+        current_score: i32,
+        new_score: i32,
+        title: String,
+        search_result_id: i32,
+    },
+}
+
+/// Checks the releases found by searches (the scout's results stream) for
+/// upgrades of files already in the library. No indexer is queried for this;
+/// `search_upgrade` does that for one file on request.
+pub async fn upgrades_worker(state: Arc<AppState>) -> anyhow::Result<()> {
+    tracing::info!("Upgrade worker starting...");
+
+    let stream = state
+        .jetstream_context
+        .get_or_create_stream(async_nats::jetstream::stream::Config {
+            name: "sokoul_analysis".to_string(),
+            subjects: vec![events::SEARCH_RESULTS_FOUND_SUBJECT.to_string()],
+            ..Default::default()
+        })
+        .await?;
+
+    let consumer = stream
+        .create_consumer(async_nats::jetstream::consumer::pull::Config {
+            durable_name: Some("upgrades_worker".to_string()),
+            ..Default::default()
+        })
+        .await?;
+
+    let mut messages = consumer.messages().await?;
+    while let Some(Ok(message)) = messages.next().await {
+        match serde_json::from_slice::<SearchResultsFoundPayload>(&message.payload) {
+            Ok(payload) => match upgrade_from_results(&state, payload.media_id).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!(
+                    "Upgrades: {} upgrade(s) queued from the results for media {}",
+                    n,
+                    payload.media_id
+                ),
+                Err(e) => tracing::warn!("Upgrades: media {}: {}", payload.media_id, e),
+            },
+            Err(e) => tracing::error!("Invalid payload for the upgrade worker: {}", e),
+        }
+        message
+            .ack()
+            .await
+            .map_err(|e| anyhow::anyhow!("Ack failed: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Queue upgrades from the stored search results of `media_id`: of its own
+/// file for a movie, of its episodes' files for a series. Returns how many
+/// were queued.
+pub async fn upgrade_from_results(state: &Arc<AppState>, media_id: Uuid) -> anyhow::Result<usize> {
+    let pool = &state.db_pool;
+    let releases: Vec<TorrentResult> = db::search_results::get_results_by_media_id(pool, media_id)
+        .await?
+        .iter()
+        .map(as_release)
+        .collect();
+    if releases.is_empty() {
+        return Ok(0);
+    }
+
+    let named = db::media::get_media_by_id(pool, media_id).await?;
+    let targets = match named.media_type.as_str() {
+        "movie" => vec![named.clone()],
+        "tv" => db::media::get_episodes(pool, media_id).await?,
+        _ => return Ok(0),
+    };
+    let titles = db::media_titles::titles_for_media(pool, &named, &CONFIG.release_region).await;
+
+    let mut queued = 0;
+    for media in targets {
+        let files = db::media_files::get_files_by_media_id(pool, media.id).await?;
+        let Some(file) = files
+            .iter()
+            .filter(|f| f.missing_since.is_none())
+            .max_by_key(|f| f.quality_score)
+        else {
+            continue;
+        };
+        if db::tasks::has_active(pool, "download", "media_id", &media.id.to_string()).await? {
+            continue;
+        }
+        let profile = db::quality_profiles::get_profile_for_media(pool, media.id).await?;
+        let current_score = file_score(file);
+        if current_score >= profile.cutoff_score {
+            continue;
+        }
+
+        let candidates: Vec<TorrentResult> = match (media.season_number, media.episode_number) {
+            (Some(season), Some(number)) => {
+                let absolute = anime::absolute_number(state, named.id, season, number).await?;
+                releases
+                    .iter()
+                    .filter(|r| release::is_episode_release(&r.title, season, number, absolute))
+                    .cloned()
+                    .collect()
+            }
+            _ => releases.clone(),
+        };
+        if let Some((best, new_score)) =
+            quality::pick_upgrade(&profile, &titles, current_score, &candidates)
+        {
+            let outcome =
+                queue_upgrade(state, media.id, file, best, (current_score, new_score)).await?;
+            if let UpgradeOutcome::Queued { title, .. } = outcome {
+                tracing::info!(
+                    "Upgrades: queued '{}' ({} -> {}) for {}",
+                    title,
+                    current_score,
+                    new_score,
+                    file.file_path
+                );
+                queued += 1;
+            }
+        }
+    }
+    Ok(queued)
+}
+
+/// A stored search result as the release it was found as.
+fn as_release(result: &SearchResult) -> TorrentResult {
+    TorrentResult {
+        title: result.title.clone(),
+        guid: result.guid.clone(),
+        size_bytes: result.size_bytes,
+        indexer: result.provider.clone(),
+        info_url: None,
+        download_url: result.url.clone(),
+        magnet_url: result.magnet_link.clone(),
+        info_hash: result.info_hash.clone(),
+        seeders: Some(result.seeders),
+        leechers: Some(result.leechers),
+        protocol: Some(result.protocol.clone()),
+        provider_name: result.provider.clone(),
+    }
+}
+
+/// Quality score of a file; files recorded before scores were stored are
+/// rated from their name.
+fn file_score(file: &MediaFile) -> i32 {
+    file.quality_score.unwrap_or_else(|| {
+        let name = Path::new(&file.file_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| file.file_path.clone());
+        scoring::quality_score(&name)
+    })
+}
+
+/// Store `best` for `media_id` and hand it to the Hunter as a replacement of `file`.
+async fn queue_upgrade(
+    state: &Arc<AppState>,
+    media_id: Uuid,
+    file: &MediaFile,
+    best: &TorrentResult,
+    (current_score, new_score): (i32, i32),
+) -> anyhow::Result<UpgradeOutcome> {
+    let saved = db::search_results::store_selected(
+        &state.db_pool,
+        media_id,
+        best,
+        scoring::compute_score(best),
+    )
+    .await?;
+
+    downloads::queue_download(state, None, media_id, &saved, Some(file.id))
+        .await
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+
+    Ok(UpgradeOutcome::Queued {
+        current_score,
+        new_score,
+        title: saved.title,
+        search_result_id: saved.id,
+    })
+}
+
+/// Search for a release that beats `file` under its media's quality profile and
+/// queue it as an in-place upgrade.
+pub async fn search_upgrade(
+    state: &Arc<AppState>,
+    registry: &ProviderRegistry,
+    file: &MediaFile,
+) -> anyhow::Result<UpgradeOutcome> {
+    let media = db::media::get_media_by_id(&state.db_pool, file.media_id).await?;
+    let profile = db::quality_profiles::get_profile_for_media(&state.db_pool, media.id).await?;
+
+    let current_score = file_score(file);
+    if current_score >= profile.cutoff_score {
+        return Ok(UpgradeOutcome::CutoffMet {
+            current_score,
+            cutoff_score: profile.cutoff_score,
+        });
+    }
+
+    db::media::mark_searched(&state.db_pool, media.id).await?;
+
+    // Episode releases are named after the series.
+    let (named, episode) = match (media.parent_id, media.season_number, media.episode_number) {
+        (Some(parent_id), Some(season), Some(episode)) => (
+            db::media::get_media_by_id(&state.db_pool, parent_id).await?,
+            Some((season, episode)),
+        ),
+        _ => (media.clone(), None),
+    };
+    let titles =
+        db::media_titles::titles_for_media(&state.db_pool, &named, &CONFIG.release_region).await;
+    let absolute = match episode {
+        Some((season, number)) => anime::absolute_number(state, named.id, season, number).await?,
+        None => None,
+    };
+
+    let results: Vec<_> = registry
+        .search_titles(
+            &titles,
+            |title| match (absolute, episode, media.year) {
+                (Some(absolute), _, _) => release::absolute_episode_query(title, absolute),
+                (None, Some((season, number)), _) => release::episode_query(title, season, number),
+                (None, None, Some(year)) => format!("{} {}", title, year),
+                (None, None, None) => title.to_string(),
+            },
+            &media.media_type,
+            media.tmdb_id,
+            false,
+        )
+        .await
+        .into_iter()
+        .filter(|r| {
+            episode.is_none_or(|(season, number)| {
+                release::is_episode_release(&r.title, season, number, absolute)
+            })
+        })
+        .collect();
+
+    let Some((best, new_score)) = quality::pick_upgrade(&profile, &titles, current_score, &results)
+    else {
+        return Ok(UpgradeOutcome::NoUpgrade { current_score });
+    };
+    queue_upgrade(state, media.id, file, best, (current_score, new_score)).await
+}
@@ -0,0 +1,333 @@
+use crate::{
+    api::{downloads, error::ApiError},
+    config::CONFIG,
+    db::{self, watchlist::AutoDownloadCandidate},
+    events::WsEvent,
+    providers::ProviderRegistry,
+    utils::{
+        quality::{self, QualityProfile},
+        release::{self, DigitalRelease, ReleaseDate},
+    },
+    workers::calendar,
+    AppState,
+};
+use chrono::{Datelike, NaiveDate, Utc};
+use std::{sync::Arc, time::Duration};
+use tokio::time;
+
+/// Result of one automatic acquisition attempt, stored on the watchlist entry.
+struct Outcome {
+    status: &'static str,
+    message: String,
+    search_result_id: Option<i32>,
+}
+
+impl Outcome {
+    fn new(status: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            search_result_id: None,
+        }
+    }
+
+    /// Statuses worth a notification; the others are retried silently next cycle.
+    fn should_notify(&self) -> bool {
+        matches!(self.status, "queued" | "blocked" | "failed")
+    }
+}
+
+/// Periodically searches flagged watchlist entries that are released but not in the library.
+pub async fn watchlist_worker(state: Arc<AppState>) -> anyhow::Result<()> {
+    tracing::info!("Watchlist worker starting...");
+
+    let registry = ProviderRegistry::with_indexers(
+        state.redis_client.clone(),
+        state.flaresolverr_client.clone(),
+    );
+    if registry.list_enabled_names().is_empty() {
+        tracing::warn!("Watchlist: no indexer configured, auto-acquisition disabled");
+        return Ok(());
+    }
+
+    let mut interval = time::interval(Duration::from_secs(
+        CONFIG.watchlist_auto_interval_secs.max(60),
+    ));
+
+    loop {
+        interval.tick().await;
+
+        match db::watchlist::requeue_stale_queued(
+            &state.db_pool,
+            CONFIG.watchlist_queued_timeout_secs,
+        )
+        .await
+        {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("Watchlist: {} stale queued entrie(s) searched again", n),
+            Err(e) => tracing::error!("Watchlist: failed to requeue stale entries: {}", e),
+        }
+
+        let candidates = match db::watchlist::list_auto_download_candidates(
+            &state.db_pool,
+            CONFIG.watchlist_auto_batch_size,
+            CONFIG.watchlist_not_found_retry_secs,
+        )
+        .await
+        {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::error!("Watchlist: failed to load auto-download entries: {}", e);
+                continue;
+            }
+        };
+
+        if !candidates.is_empty() {
+            tracing::info!(
+                "Watchlist: {} auto-download entrie(s) to check",
+                candidates.len()
+            );
+        }
+
+        for candidate in candidates {
+            let outcome = acquire(&state, &registry, &candidate).await;
+            tracing::info!(
+                "Watchlist: '{}' -> {} ({})",
+                candidate.title,
+                outcome.status,
+                outcome.message
+            );
+
+            if let Err(e) = db::watchlist::record_auto_outcome(
+                &state.db_pool,
+                candidate.watchlist_id,
+                outcome.status,
+                Some(&outcome.message),
+                outcome.search_result_id,
+            )
+            .await
+            {
+                tracing::error!("Watchlist: failed to record outcome: {}", e);
+            }
+
+            if outcome.should_notify() {
+                notify(&state, &candidate, &outcome).await;
+            }
+        }
+    }
+}
+
+async fn acquire(
+    state: &Arc<AppState>,
+    registry: &ProviderRegistry,
+    candidate: &AutoDownloadCandidate,
+) -> Outcome {
+    if candidate.media_type != "movie" {
+        return Outcome::new("unsupported", "Automatic acquisition only handles movies");
+    }
+
+    let mut profile = match db::quality_profiles::get_effective_profile(
+        &state.db_pool,
+        candidate.user_id,
+    )
+    .await
+    {
+        Ok(p) => p,
+        Err(e) => return Outcome::new("failed", format!("Quality profile: {}", e)),
+    };
+    // A minimum set on the entry takes precedence over the profile's.
+    if let Some(min) = candidate
+        .quality_min
+        .as_deref()
+        .and_then(quality::parse_resolution)
+    {
+        profile.min_resolution = min;
+    }
+
+    if let Some(reason) = release_hold(state, candidate, &profile).await {
+        return Outcome::new("not_released", reason);
+    }
+
+    let titles = match db::media::get_media_by_id(&state.db_pool, candidate.media_id).await {
+        Ok(media) => {
+            db::media_titles::titles_for_media(&state.db_pool, &media, &CONFIG.release_region).await
+        }
+        Err(_) => vec![candidate.title.clone()],
+    };
+    let results = registry
+        .search_titles(
+            &titles,
+            |title| match candidate.year {
+                Some(year) => format!("{} {}", title, year),
+                None => title.to_string(),
+            },
+            &candidate.media_type,
+            candidate.tmdb_id,
+            false,
+        )
+        .await;
+    if results.is_empty() {
+        return Outcome::new("not_found", "No results from indexers");
+    }
+
+    let Some((best, score)) = quality::pick_best(&profile, &titles, &results) else {
+        return Outcome::new(
+            "not_found",
+            format!(
+                "{} result(s), none matched the quality profile",
+                results.len()
+            ),
+        );
+    };
+
+    let saved =
+        match db::search_results::store_selected(&state.db_pool, candidate.media_id, best, score)
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => return Outcome::new("failed", format!("Could not store result: {}", e)),
+        };
+
+    match downloads::queue_download(
+        state,
+        Some(candidate.user_id),
+        candidate.media_id,
+        &saved,
+        None,
+    )
+    .await
+    {
+        Ok(warning) => Outcome {
+            status: "queued",
+            message: match warning {
+                Some(reason) => format!("Queued '{}' (security warning: {})", saved.title, reason),
+                None => format!("Queued '{}'", saved.title),
+            },
+            search_result_id: Some(saved.id),
+        },
+        Err(ApiError::Forbidden(reason)) => Outcome {
+            status: "blocked",
+            message: reason,
+            search_result_id: Some(saved.id),
+        },
+        Err(e) => Outcome {
+            status: "failed",
+            message: e.to_string(),
+            search_result_id: Some(saved.id),
+        },
+    }
+}
+
+/// Why a movie is not searched yet, `None` once it can be: it must be out in
+/// theaters and, unless the profile allows pre-digital releases, on digital or
+/// disc in the user's region.
+async fn release_hold(
+    state: &AppState,
+    candidate: &AutoDownloadCandidate,
+    profile: &QualityProfile,
+) -> Option<String> {
+    if let Some(release_date) = upcoming_release(state, candidate).await {
+        return Some(format!("Not released yet ({})", release_date));
+    }
+    if profile.allow_pre_digital {
+        return None;
+    }
+
+    let dates = release_dates(state, candidate).await;
+    // TMDB lists no dates for many older titles; the theatrical check above applies.
+    if dates.is_empty() {
+        return None;
+    }
+
+    let region = profile
+        .release_region
+        .as_deref()
+        .unwrap_or(&CONFIG.release_region);
+    match release::digital_release(&dates, region, Utc::now().date_naive()) {
+        DigitalRelease::Available => None,
+        DigitalRelease::Pending(Some(date)) => Some(format!(
+            "Waiting for the digital release in {} ({})",
+            region, date
+        )),
+        DigitalRelease::Pending(None) => {
+            Some(format!("Waiting for the digital release in {}", region))
+        }
+    }
+}
+
+/// Cached release dates, fetched from TMDB the first time.
+async fn release_dates(state: &AppState, candidate: &AutoDownloadCandidate) -> Vec<ReleaseDate> {
+    match db::calendar::get_release_dates(&state.db_pool, candidate.media_id).await {
+        Ok(dates) if !dates.is_empty() => return dates,
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Watchlist: failed to load release dates: {}", e),
+    }
+
+    let Some(tmdb_id) = candidate.tmdb_id else {
+        return Vec::new();
+    };
+    calendar::refresh_release_dates(state, candidate.media_id, tmdb_id)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!(
+                "Watchlist: TMDB release dates failed for {}: {}",
+                tmdb_id,
+                e
+            );
+            Vec::new()
+        })
+}
+
+/// Release date of a movie that is not out yet, `None` once it is released.
+/// Without a date from TMDB, only a future year holds the movie back.
+async fn upcoming_release(state: &AppState, candidate: &AutoDownloadCandidate) -> Option<String> {
+    let today = Utc::now().date_naive();
+
+    if let Some(tmdb_id) = candidate.tmdb_id {
+        match state.tmdb_client.movie_details(tmdb_id).await {
+            Ok(details) => {
+                if details.status.as_deref() == Some("Released") {
+                    return None;
+                }
+                let release = details
+                    .release_date
+                    .as_deref()
+                    .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+                match release {
+                    Some(date) if date <= today => return None,
+                    Some(date) => return Some(date.to_string()),
+                    None => {}
+                }
+            }
+            Err(e) => tracing::warn!("Watchlist: TMDB details failed for {}: {}", tmdb_id, e),
+        }
+    }
+
+    candidate
+        .year
+        .filter(|year| *year > today.year())
+        .map(|year| year.to_string())
+}
+
+async fn notify(state: &AppState, candidate: &AutoDownloadCandidate, outcome: &Outcome) {
+    let _ = state.event_tx.send(
+        WsEvent::WatchlistAutoDownload {
+            media_id: candidate.media_id.to_string(),
+            title: candidate.title.clone(),
+            status: outcome.status.to_string(),
+            message: outcome.message.clone(),
+        }
+        .to_json(),
+    );
+
+    if state.email_service.enabled {
+        let subject = format!("Sokoul watchlist: {} ({})", candidate.title, outcome.status);
+        if let Err(e) = state
+            .email_service
+            .send_notification(&candidate.user_email, &subject, &outcome.message)
+            .await
+        {
+            tracing::warn!("Watchlist: email notification failed: {}", e);
+        }
+    }
+}